@@ -1,15 +1,19 @@
+use golem_llm::batch::parse_jsonl;
 use golem_llm::error::{error_code_from_status, from_event_source_error, from_reqwest_error};
 use golem_llm::event_source::EventSource;
 use golem_llm::golem::llm::llm::Error;
+use golem_llm::retry::SendWithRetry;
 use log::trace;
 use reqwest::header::HeaderValue;
-use reqwest::{Client, Method, Response};
+use reqwest::{Client, Method, RequestBuilder, Response};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fmt::Debug;
 
 const BASE_URL: &str = "https://api.anthropic.com";
+const EXTENDED_CACHE_TTL_BETA: &str = "extended-cache-ttl-2025-04-11";
+const CODE_EXECUTION_BETA: &str = "code-execution-2025-05-22";
 
 /// The Anthropic API client for creating model responses.
 pub struct MessagesApi {
@@ -29,12 +33,9 @@ impl MessagesApi {
         trace!("Sending request to Anthropic API: {request:?}");
 
         let response: Response = self
-            .client
-            .request(Method::POST, format!("{BASE_URL}/v1/messages"))
-            .header("anthropic-version", "2023-06-01")
-            .header("x-api-key", &self.api_key)
+            .messages_request("/v1/messages", &request)
             .json(&request)
-            .send()
+            .send_with_retry()
             .map_err(|err| from_reqwest_error("Request failed", err))?;
 
         parse_response(response)
@@ -44,16 +45,13 @@ impl MessagesApi {
         trace!("Sending request to Anthropic API: {request:?}");
 
         let response: Response = self
-            .client
-            .request(Method::POST, format!("{BASE_URL}/v1/messages"))
-            .header("anthropic-version", "2023-06-01")
-            .header("x-api-key", &self.api_key)
+            .messages_request("/v1/messages", &request)
             .header(
                 reqwest::header::ACCEPT,
                 HeaderValue::from_static("text/event-stream"),
             )
             .json(&request)
-            .send()
+            .send_with_retry()
             .map_err(|err| from_reqwest_error("Request failed", err))?;
 
         trace!("Initializing SSE stream");
@@ -61,6 +59,157 @@ impl MessagesApi {
         EventSource::new(response)
             .map_err(|err| from_event_source_error("Failed to create SSE stream", err))
     }
+
+    /// Lists all the available models, following the pagination of the models endpoint
+    pub fn list_models(&self) -> Result<Vec<ModelResponse>, Error> {
+        trace!("Listing models from Anthropic API");
+
+        let mut models = Vec::new();
+        let mut after_id: Option<String> = None;
+        loop {
+            let mut query = vec![("limit", "1000".to_string())];
+            if let Some(after_id) = after_id.take() {
+                query.push(("after_id", after_id));
+            }
+
+            let response: Response = self
+                .client
+                .request(Method::GET, format!("{BASE_URL}/v1/models"))
+                .header("anthropic-version", "2023-06-01")
+                .header("x-api-key", &self.api_key)
+                .query(&query)
+                .send_with_retry()
+                .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+            let page: ListModelsResponse = parse_response(response)?;
+            models.extend(page.data);
+
+            match page.last_id {
+                Some(last_id) if page.has_more => after_id = Some(last_id),
+                _ => break Ok(models),
+            }
+        }
+    }
+
+    pub fn count_tokens(&self, request: MessagesRequest) -> Result<CountTokensResponse, Error> {
+        trace!("Sending token counting request to Anthropic API: {request:?}");
+
+        let response: Response = self
+            .messages_request("/v1/messages/count_tokens", &request)
+            .json(&CountTokensRequest::from(request))
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    /// Creates a message batch, which is processed asynchronously by Anthropic
+    pub fn create_message_batch(
+        &self,
+        requests: Vec<MessageBatchRequest>,
+    ) -> Result<MessageBatch, Error> {
+        trace!(
+            "Creating message batch of {} requests in Anthropic API",
+            requests.len()
+        );
+
+        // The beta features used by any of the requests have to be enabled for the whole batch
+        let mut betas = Vec::new();
+        for request in &requests {
+            for beta in request.params.betas() {
+                if !betas.contains(&beta) {
+                    betas.push(beta);
+                }
+            }
+        }
+
+        let response: Response = self
+            .post_request("/v1/messages/batches", &betas)
+            .json(&CreateMessageBatchRequest { requests })
+            .send_unprocessed_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    pub fn retrieve_message_batch(&self, batch_id: &str) -> Result<MessageBatch, Error> {
+        trace!("Retrieving message batch {batch_id} from Anthropic API");
+
+        let response: Response = self
+            .client
+            .request(
+                Method::GET,
+                format!("{BASE_URL}/v1/messages/batches/{batch_id}"),
+            )
+            .header("anthropic-version", "2023-06-01")
+            .header("x-api-key", &self.api_key)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    /// Cancels a message batch, which has no effect on a batch that has ended
+    pub fn cancel_message_batch(&self, batch_id: &str) -> Result<MessageBatch, Error> {
+        trace!("Cancelling message batch {batch_id} in Anthropic API");
+
+        let response: Response = self
+            .post_request(&format!("/v1/messages/batches/{batch_id}/cancel"), &[])
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    /// Downloads the results of an ended message batch from its `results_url`
+    pub fn message_batch_results(
+        &self,
+        results_url: &str,
+    ) -> Result<Vec<MessageBatchResult>, Error> {
+        trace!("Downloading message batch results from {results_url}");
+
+        let response: Response = self
+            .client
+            .request(Method::GET, results_url)
+            .header("anthropic-version", "2023-06-01")
+            .header("x-api-key", &self.api_key)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .map_err(|err| from_reqwest_error("Failed to receive response body", err))?;
+        if status.is_success() {
+            parse_jsonl(&body)
+        } else {
+            trace!("Received {status} response from Anthropic API: {body:?}");
+
+            Err(Error {
+                code: error_code_from_status(status),
+                message: format!("Request failed with {status}"),
+                provider_error_json: Some(body),
+            })
+        }
+    }
+
+    fn messages_request(&self, path: &str, request: &MessagesRequest) -> RequestBuilder {
+        self.post_request(path, &request.betas())
+    }
+
+    fn post_request(&self, path: &str, betas: &[&str]) -> RequestBuilder {
+        let builder = self
+            .client
+            .request(Method::POST, format!("{BASE_URL}{path}"))
+            .header("anthropic-version", "2023-06-01")
+            .header("x-api-key", &self.api_key);
+
+        if betas.is_empty() {
+            builder
+        } else {
+            builder.header("anthropic-beta", betas.join(","))
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -75,7 +224,8 @@ pub struct MessagesRequest {
     pub system: Vec<Content>, // can only be Text
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
-    // thinking
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<Thinking>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<ToolChoice>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -86,6 +236,86 @@ pub struct MessagesRequest {
     pub top_p: Option<f32>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Thinking {
+    #[serde(rename = "enabled")]
+    Enabled { budget_tokens: u32 },
+}
+
+impl MessagesRequest {
+    /// The beta features the request has to be sent with
+    fn betas(&self) -> Vec<&'static str> {
+        let mut betas = Vec::new();
+        if self.uses_extended_cache_ttl() {
+            betas.push(EXTENDED_CACHE_TTL_BETA);
+        }
+        if self.uses_code_execution() {
+            betas.push(CODE_EXECUTION_BETA);
+        }
+        betas
+    }
+
+    /// Cache breakpoints with a one hour TTL are only accepted with a beta header
+    fn uses_extended_cache_ttl(&self) -> bool {
+        self.system
+            .iter()
+            .chain(
+                self.messages
+                    .iter()
+                    .flat_map(|message| message.content.iter()),
+            )
+            .any(|content| {
+                matches!(
+                    content.cache_control(),
+                    Some(CacheControl::Ephemeral {
+                        ttl: Some(CacheTtl::OneHour)
+                    })
+                )
+            })
+    }
+
+    /// The code execution tool is only accepted with a beta header
+    fn uses_code_execution(&self) -> bool {
+        self.tools
+            .iter()
+            .flatten()
+            .any(|tool| matches!(tool, Tool::CodeExecution { .. }))
+    }
+}
+
+/// The subset of `MessagesRequest` accepted by the token counting endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CountTokensRequest {
+    pub messages: Vec<Message>,
+    pub model: String,
+    pub system: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<Thinking>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+}
+
+impl From<MessagesRequest> for CountTokensRequest {
+    fn from(request: MessagesRequest) -> Self {
+        Self {
+            messages: request.messages,
+            model: request.model,
+            system: request.system,
+            thinking: request.thinking,
+            tool_choice: request.tool_choice,
+            tools: request.tools,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CountTokensResponse {
+    pub input_tokens: u32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessagesRequestMetadata {
     pub user_id: Option<String>,
@@ -104,7 +334,9 @@ pub enum Content {
     Text {
         text: String,
         #[serde(skip_serializing_if = "Option::is_none")]
-        cache_control: Option<CacheControl>, // citations
+        cache_control: Option<CacheControl>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        citations: Vec<TextCitation>,
     },
     #[serde(rename = "image")]
     Image {
@@ -128,15 +360,129 @@ pub enum Content {
         content: Vec<Content>, // can only be Text or Image
         is_error: bool,
     },
-    // Document
-    // Thinking
-    // RedactedThinking
+    #[serde(rename = "document")]
+    Document {
+        source: DocumentSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    #[serde(rename = "thinking")]
+    Thinking {
+        thinking: String,
+        #[serde(default)] // not present in content_block_start events
+        signature: String,
+    },
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
+    /// A call of a tool run by Anthropic, like the web search or code execution
+    #[serde(rename = "server_tool_use")]
+    ServerToolUse {
+        id: String,
+        name: String,
+        #[serde(default)] // not present in content_block_start events
+        input: Value,
+    },
+    /// The results of the search are returned as citations of the text blocks
+    #[serde(rename = "web_search_tool_result")]
+    WebSearchToolResult { tool_use_id: String, content: Value },
+    #[serde(rename = "code_execution_tool_result")]
+    CodeExecutionToolResult {
+        tool_use_id: String,
+        content: CodeExecutionResult,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum TextCitation {
+    #[serde(rename = "web_search_result_location")]
+    WebSearchResultLocation {
+        url: String,
+        title: Option<String>,
+        cited_text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CodeExecutionResult {
+    #[serde(rename = "code_execution_result")]
+    Result {
+        stdout: String,
+        stderr: String,
+        return_code: i32,
+    },
+    #[serde(rename = "code_execution_tool_result_error")]
+    Error { error_code: String },
+}
+
+impl Content {
+    pub fn cache_control(&self) -> Option<&CacheControl> {
+        match self {
+            Content::Text { cache_control, .. }
+            | Content::Image { cache_control, .. }
+            | Content::ToolUse { cache_control, .. }
+            | Content::ToolResult { cache_control, .. }
+            | Content::Document { cache_control, .. } => cache_control.as_ref(),
+            Content::Thinking { .. }
+            | Content::RedactedThinking { .. }
+            | Content::ServerToolUse { .. }
+            | Content::WebSearchToolResult { .. }
+            | Content::CodeExecutionToolResult { .. } => None,
+        }
+    }
+
+    /// Sets the cache breakpoint of the block, returns false for blocks that cannot have one
+    pub fn set_cache_control(&mut self, value: CacheControl) -> bool {
+        match self {
+            Content::Text { cache_control, .. }
+            | Content::Image { cache_control, .. }
+            | Content::ToolUse { cache_control, .. }
+            | Content::ToolResult { cache_control, .. }
+            | Content::Document { cache_control, .. } => {
+                *cache_control = Some(value);
+                true
+            }
+            Content::Thinking { .. }
+            | Content::RedactedThinking { .. }
+            | Content::ServerToolUse { .. }
+            | Content::WebSearchToolResult { .. }
+            | Content::CodeExecutionToolResult { .. } => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum CacheControl {
     #[serde(rename = "ephemeral")]
-    Ephemeral,
+    Ephemeral {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ttl: Option<CacheTtl>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CacheTtl {
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DocumentSource {
+    #[serde(rename = "base64")]
+    Base64 { data: String, media_type: String },
+    #[serde(rename = "text")]
+    Text { data: String, media_type: String },
+    #[serde(rename = "url")]
+    Url { url: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -195,6 +541,16 @@ pub enum Tool {
         #[serde(skip_serializing_if = "Option::is_none")]
         description: Option<String>,
     },
+    #[serde(rename = "web_search_20250305")]
+    WebSearch {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_uses: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        allowed_domains: Option<Vec<String>>,
+    },
+    #[serde(rename = "code_execution_20250522")]
+    CodeExecution { name: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -217,6 +573,10 @@ pub enum StopReason {
     StopSequence,
     #[serde(rename = "tool_use")]
     ToolUse,
+    /// A long running turn with server tools was paused, and can be continued by sending the
+    /// response back as it is
+    #[serde(rename = "pause_turn")]
+    PauseTurn,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -225,6 +585,7 @@ pub struct Usage {
     pub cache_creation_input_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_read_input_tokens: Option<u32>,
+    #[serde(default)] // message_delta events may only report the output tokens
     pub input_tokens: u32,
     pub output_tokens: u32,
 }
@@ -256,6 +617,85 @@ pub enum ContentBlockDelta {
     TextDelta { text: String },
     #[serde(rename = "input_json_delta")]
     InputJsonDelta { partial_json: String },
+    #[serde(rename = "thinking_delta")]
+    ThinkingDelta { thinking: String },
+    #[serde(rename = "signature_delta")]
+    SignatureDelta { signature: String },
+    #[serde(rename = "citations_delta")]
+    CitationsDelta { citation: TextCitation },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateMessageBatchRequest {
+    pub requests: Vec<MessageBatchRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageBatchRequest {
+    pub custom_id: String,
+    pub params: MessagesRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageBatch {
+    pub id: String,
+    pub processing_status: ProcessingStatus,
+    pub request_counts: RequestCounts,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProcessingStatus {
+    #[serde(rename = "in_progress")]
+    InProgress,
+    #[serde(rename = "canceling")]
+    Canceling,
+    #[serde(rename = "ended")]
+    Ended,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestCounts {
+    pub processing: u32,
+    pub succeeded: u32,
+    pub errored: u32,
+    pub canceled: u32,
+    pub expired: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageBatchResult {
+    pub custom_id: String,
+    pub result: MessageBatchResultType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum MessageBatchResultType {
+    #[serde(rename = "succeeded")]
+    Succeeded { message: MessagesResponse },
+    #[serde(rename = "errored")]
+    Errored { error: ErrorResponse },
+    #[serde(rename = "canceled")]
+    Canceled,
+    #[serde(rename = "expired")]
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListModelsResponse {
+    pub data: Vec<ModelResponse>,
+    pub has_more: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelResponse {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
 }
 
 fn parse_response<T: DeserializeOwned + Debug>(response: Response) -> Result<T, Error> {
@@ -1,28 +1,48 @@
 use crate::client::{
-    Content, ImageSource as ClientImageSource, MediaType, MessagesRequest, MessagesRequestMetadata,
-    MessagesResponse, StopReason, Tool, ToolChoice,
+    CacheControl as ClientCacheControl, CacheTtl as ClientCacheTtl, CodeExecutionResult, Content,
+    DocumentSource, ImageSource as ClientImageSource, MediaType, MessageBatch, MessageBatchResult,
+    MessageBatchResultType, MessagesRequest, MessagesRequestMetadata, MessagesResponse,
+    ModelResponse, RequestCounts, StopReason, TextCitation, Thinking, Tool, ToolChoice,
 };
 use base64::{engine::general_purpose, Engine as _};
+use golem_llm::builtin_tools::unsupported_builtin_tool;
+use golem_llm::error::unsupported;
 use golem_llm::golem::llm::llm::{
-    ChatEvent, CompleteResponse, Config, ContentPart, Error, ErrorCode, FinishReason,
-    ImageReference, ImageSource, ImageUrl, Message, ResponseMetadata, Role, ToolCall,
+    BatchProgress, BatchResult, BatchStatus, BuiltinTool, BuiltinToolResult, CacheControl,
+    CacheTtl, ChatEvent, Citation, CodeExecution, CompleteResponse, Config, ContentPart,
+    DocumentData, DocumentSource as LlmDocumentSource, Error, ErrorCode, FinishReason,
+    ImageReference, ImageSource, ImageUrl, Message, ModelCapabilities, ModelInfo, ReasoningConfig,
+    ReasoningContent, ReasoningEffort, ResponseFormat, ResponseMetadata, Role, ToolCall,
     ToolDefinition, ToolResult, Usage,
 };
+use golem_llm::sampling::{validate_sampling_parameters, SamplingParameter};
 use std::collections::HashMap;
 
+/// Name of the tool the model is forced to call when a structured response format is requested.
+/// Anthropic has no native JSON mode, so the tool's input becomes the response content.
+pub const RESPONSE_FORMAT_TOOL_NAME: &str = "json_response";
+
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+const SAMPLING_PARAMETERS: &[SamplingParameter] = &[
+    SamplingParameter::TopP,
+    SamplingParameter::TopK,
+    SamplingParameter::User,
+];
+
 pub fn messages_to_request(
     messages: Vec<Message>,
-    config: Config,
+    mut config: Config,
 ) -> Result<MessagesRequest, Error> {
-    let options = config
-        .provider_options
-        .into_iter()
-        .map(|kv| (kv.key, kv.value))
-        .collect::<HashMap<_, _>>();
+    validate_sampling_parameters(&mut config, SAMPLING_PARAMETERS)?;
 
     let mut anthropic_messages = Vec::new();
     for message in &messages {
         if message.role != Role::System {
+            let mut content = message_to_content(message)?;
+            if let Some(cache_control) = &message.cache_control {
+                set_cache_breakpoint(&mut content, cache_control);
+            }
             anthropic_messages.push(crate::client::Message {
                 role: match &message.role {
                     Role::User => crate::client::Role::User,
@@ -30,7 +50,7 @@ pub fn messages_to_request(
                     Role::Tool => crate::client::Role::User,
                     Role::System => unreachable!(),
                 },
-                content: message_to_content(message),
+                content,
             })
         }
     }
@@ -38,45 +58,128 @@ pub fn messages_to_request(
     let mut system_messages = Vec::new();
     for message in &messages {
         if message.role == Role::System {
-            system_messages.extend(message_to_content(message))
+            system_messages.extend(message_to_content(message)?);
+            if let Some(cache_control) = &message.cache_control {
+                set_cache_breakpoint(&mut system_messages, cache_control);
+            }
         }
     }
 
-    let tool_choice = config.tool_choice.map(convert_tool_choice);
-    let tools = if config.tools.is_empty() {
-        None
-    } else {
-        let mut tools = Vec::new();
-        for tool in &config.tools {
-            tools.push(tool_definition_to_tool(tool)?)
+    let mut tool_choice = config.tool_choice.map(convert_tool_choice);
+    let mut tools = Vec::new();
+    for tool in &config.tools {
+        tools.push(tool_definition_to_tool(tool)?)
+    }
+    for tool in &config.builtin_tools {
+        tools.push(builtin_tool_to_tool(tool)?)
+    }
+
+    if let Some(response_format_tool) = config
+        .response_format
+        .map(response_format_to_tool)
+        .transpose()?
+        .flatten()
+    {
+        if tool_choice.is_some() {
+            return Err(unsupported(
+                "tool-choice cannot be combined with a structured response-format",
+            ));
         }
-        Some(tools)
+        tools.push(response_format_tool);
+        tool_choice = Some(ToolChoice::Tool {
+            name: RESPONSE_FORMAT_TOOL_NAME.to_string(),
+            disable_parallel_tool_use: Some(true),
+        });
+    }
+    let tools = if tools.is_empty() { None } else { Some(tools) };
+
+    let thinking = config.reasoning.map(reasoning_to_thinking);
+    let max_tokens = match (&thinking, config.max_tokens) {
+        (_, Some(max_tokens)) => max_tokens,
+        // max_tokens has to be larger than the thinking budget
+        (Some(Thinking::Enabled { budget_tokens }), None) => budget_tokens + DEFAULT_MAX_TOKENS,
+        (None, None) => DEFAULT_MAX_TOKENS,
     };
 
     Ok(MessagesRequest {
-        max_tokens: config.max_tokens.unwrap_or(4096),
+        max_tokens,
         messages: anthropic_messages,
         model: config.model,
-        metadata: options
-            .get("user_id")
-            .map(|user_id| MessagesRequestMetadata {
-                user_id: Some(user_id.to_string()),
-            }),
+        metadata: config.user.map(|user_id| MessagesRequestMetadata {
+            user_id: Some(user_id),
+        }),
         stop_sequences: config.stop_sequences,
         stream: false,
         system: system_messages,
         temperature: config.temperature,
+        thinking,
         tool_choice,
         tools,
-        top_k: options
-            .get("top_k")
-            .and_then(|top_k_s| top_k_s.parse::<u32>().ok()),
-        top_p: options
-            .get("top_p")
-            .and_then(|top_p_s| top_p_s.parse::<f32>().ok()),
+        top_k: config.top_k,
+        top_p: config.top_p,
     })
 }
 
+/// Puts the cache breakpoint on the last block of the message that can hold one,
+/// so the prompt is cached up to and including this message
+fn set_cache_breakpoint(content: &mut [Content], cache_control: &CacheControl) {
+    let cache_control = ClientCacheControl::Ephemeral {
+        ttl: cache_control.ttl.map(|ttl| match ttl {
+            CacheTtl::FiveMinutes => ClientCacheTtl::FiveMinutes,
+            CacheTtl::OneHour => ClientCacheTtl::OneHour,
+        }),
+    };
+    for block in content.iter_mut().rev() {
+        if block.set_cache_control(cache_control.clone()) {
+            break;
+        }
+    }
+}
+
+fn reasoning_to_thinking(reasoning: ReasoningConfig) -> Thinking {
+    let budget_tokens = reasoning.budget_tokens.unwrap_or(match reasoning.effort {
+        Some(ReasoningEffort::Low) | None => 1024,
+        Some(ReasoningEffort::Medium) => 4096,
+        Some(ReasoningEffort::High) => 16384,
+    });
+    Thinking::Enabled { budget_tokens }
+}
+
+fn response_format_to_tool(response_format: ResponseFormat) -> Result<Option<Tool>, Error> {
+    let (input_schema, description) = match response_format {
+        ResponseFormat::Text => return Ok(None),
+        ResponseFormat::JsonObject => (
+            serde_json::json!({ "type": "object" }),
+            "Respond with a JSON object".to_string(),
+        ),
+        ResponseFormat::JsonSchema(json_schema) => {
+            let input_schema =
+                serde_json::from_str(&json_schema.schema).map_err(|error| Error {
+                    code: ErrorCode::InvalidRequest,
+                    message: format!(
+                        "Failed to parse response format schema {}: {error}",
+                        json_schema.name
+                    ),
+                    provider_error_json: None,
+                })?;
+            (
+                input_schema,
+                format!(
+                    "Respond with a JSON value matching the {} schema",
+                    json_schema.name
+                ),
+            )
+        }
+    };
+
+    Ok(Some(Tool::CustomTool {
+        input_schema,
+        name: RESPONSE_FORMAT_TOOL_NAME.to_string(),
+        cache_control: None,
+        description: Some(description),
+    }))
+}
+
 fn convert_tool_choice(tool_name: String) -> ToolChoice {
     if &tool_name == "auto" {
         ToolChoice::Auto {
@@ -96,13 +199,39 @@ fn convert_tool_choice(tool_name: String) -> ToolChoice {
     }
 }
 
+fn builtin_tool_to_tool(tool: &BuiltinTool) -> Result<Tool, Error> {
+    match tool {
+        BuiltinTool::WebSearch(options) => Ok(Tool::WebSearch {
+            name: "web_search".to_string(),
+            max_uses: options.max_uses,
+            allowed_domains: options.allowed_domains.clone(),
+        }),
+        BuiltinTool::CodeInterpreter => Ok(Tool::CodeExecution {
+            name: "code_execution".to_string(),
+        }),
+        BuiltinTool::FileSearch(_) => Err(unsupported_builtin_tool(tool)),
+    }
+}
+
 pub fn process_response(response: MessagesResponse) -> ChatEvent {
     let mut contents = Vec::new();
     let mut tool_calls = Vec::new();
+    let mut structured_response = false;
+    let mut citations = Vec::new();
+    let mut builtin_tool_results = Vec::new();
+    // The code run by the server tool calls, by the id of the call
+    let mut server_tool_code = HashMap::new();
 
     for content in response.content {
         match content {
-            Content::Text { text, .. } => contents.push(ContentPart::Text(text)),
+            Content::Text {
+                text,
+                citations: text_citations,
+                ..
+            } => {
+                citations.extend(text_citations.into_iter().filter_map(convert_citation));
+                contents.push(ContentPart::Text(text))
+            }
             Content::Image { source, .. } => match source {
                 ClientImageSource::Url { url } => {
                     contents.push(ContentPart::Image(ImageReference::Url(ImageUrl {
@@ -137,26 +266,84 @@ pub fn process_response(response: MessagesResponse) -> ChatEvent {
                     }
                 }
             },
+            Content::ToolUse { input, name, .. } if name == RESPONSE_FORMAT_TOOL_NAME => {
+                structured_response = true;
+                contents.push(ContentPart::Text(serde_json::to_string(&input).unwrap()))
+            }
             Content::ToolUse {
                 id, input, name, ..
             } => tool_calls.push(ToolCall {
                 id,
                 name,
                 arguments_json: serde_json::to_string(&input).unwrap(),
+                validation_error: None,
             }),
-            Content::ToolResult { .. } => {}
+            Content::ToolResult { .. } | Content::Document { .. } => {}
+            Content::Thinking {
+                thinking,
+                signature,
+            } => contents.push(ContentPart::Reasoning(ReasoningContent {
+                text: thinking,
+                signature: Some(signature),
+                redacted_data: None,
+            })),
+            Content::RedactedThinking { data } => {
+                contents.push(ContentPart::Reasoning(ReasoningContent {
+                    text: String::new(),
+                    signature: None,
+                    redacted_data: Some(data),
+                }))
+            }
+            Content::ServerToolUse { id, input, .. } => {
+                if let Some(code) = input.get("code").and_then(|code| code.as_str()) {
+                    server_tool_code.insert(id, code.to_string());
+                }
+            }
+            Content::WebSearchToolResult { .. } => {}
+            Content::CodeExecutionToolResult {
+                tool_use_id,
+                content,
+            } => {
+                let code = server_tool_code.remove(&tool_use_id);
+                builtin_tool_results.push(BuiltinToolResult::CodeExecution(match content {
+                    CodeExecutionResult::Result {
+                        stdout,
+                        stderr,
+                        return_code,
+                    } => CodeExecution {
+                        code,
+                        output: Some(code_execution_output(stdout, stderr)),
+                        exit_code: Some(return_code),
+                    },
+                    CodeExecutionResult::Error { error_code } => CodeExecution {
+                        code,
+                        output: Some(format!("Error: {error_code}")),
+                        exit_code: None,
+                    },
+                }));
+            }
         }
     }
+    if !citations.is_empty() {
+        builtin_tool_results.push(BuiltinToolResult::Citations(citations));
+    }
 
     if contents.is_empty() {
         ChatEvent::ToolRequest(tool_calls)
     } else {
+        let finish_reason = if structured_response {
+            Some(FinishReason::Stop)
+        } else {
+            response.stop_reason.map(stop_reason_to_finish_reason)
+        };
         let metadata = ResponseMetadata {
-            finish_reason: response.stop_reason.map(stop_reason_to_finish_reason),
+            finish_reason,
             usage: Some(convert_usage(response.usage)),
             provider_id: None,
             timestamp: None,
             provider_metadata_json: None,
+            rate_limit: None,
+            cost: None,
         };
 
         ChatEvent::Message(CompleteResponse {
@@ -164,32 +351,40 @@ pub fn process_response(response: MessagesResponse) -> ChatEvent {
             content: contents,
             tool_calls,
             metadata,
+            logprobs: None,
+            alternatives: vec![],
+            builtin_tool_results,
         })
     }
 }
 
-pub fn tool_results_to_messages(
+/// Appends the tool calls and their results to the request's messages.
+///
+/// All the tool uses are sent in a single assistant message, merged into the last message if it is
+/// also an assistant message. This way the signed thinking blocks of the response that requested
+/// the tools stay in the same turn as the tool uses, as required by extended thinking.
+pub fn append_tool_results(
+    messages: &mut Vec<crate::client::Message>,
     tool_results: Vec<(ToolCall, ToolResult)>,
-) -> Vec<crate::client::Message> {
-    let mut messages = Vec::new();
+) {
+    let mut tool_uses = Vec::new();
+    let mut results = Vec::new();
 
     for (tool_call, tool_result) in tool_results {
-        messages.push(crate::client::Message {
-            content: vec![Content::ToolUse {
-                id: tool_call.id.clone(),
-                input: serde_json::from_str(&tool_call.arguments_json).unwrap(),
-                name: tool_call.name,
-                cache_control: None,
-            }],
-            role: crate::client::Role::Assistant,
+        tool_uses.push(Content::ToolUse {
+            id: tool_call.id.clone(),
+            input: serde_json::from_str(&tool_call.arguments_json).unwrap(),
+            name: tool_call.name,
+            cache_control: None,
         });
-        let content = match tool_result {
+        results.push(match tool_result {
             ToolResult::Success(success) => Content::ToolResult {
                 tool_use_id: tool_call.id,
                 cache_control: None,
                 content: vec![Content::Text {
                     text: success.result_json,
                     cache_control: None,
+                    citations: vec![],
                 }],
                 is_error: false,
             },
@@ -199,17 +394,116 @@ pub fn tool_results_to_messages(
                 content: vec![Content::Text {
                     text: error.error_message,
                     cache_control: None,
+                    citations: vec![],
                 }],
                 is_error: true,
             },
-        };
-        messages.push(crate::client::Message {
-            content: vec![content],
-            role: crate::client::Role::User,
         });
     }
 
-    messages
+    if tool_uses.is_empty() {
+        return;
+    }
+
+    match messages.last_mut() {
+        Some(last) if matches!(last.role, crate::client::Role::Assistant) => {
+            last.content.extend(tool_uses)
+        }
+        _ => messages.push(crate::client::Message {
+            content: tool_uses,
+            role: crate::client::Role::Assistant,
+        }),
+    }
+    messages.push(crate::client::Message {
+        content: results,
+        role: crate::client::Role::User,
+    });
+}
+
+/// Sends the content of a turn paused by a long-running server tool back as the last assistant
+/// message, so the next request continues the turn. The citations of the text are not sent back,
+/// they are optional in requests and only partially kept from the response.
+pub fn append_paused_turn(messages: &mut Vec<crate::client::Message>, content: &[Content]) {
+    let content = content.iter().cloned().map(|content| match content {
+        Content::Text {
+            text,
+            cache_control,
+            ..
+        } => Content::Text {
+            text,
+            cache_control,
+            citations: vec![],
+        },
+        content => content,
+    });
+
+    match messages.last_mut() {
+        Some(last) if matches!(last.role, crate::client::Role::Assistant) => {
+            last.content.extend(content)
+        }
+        _ => messages.push(crate::client::Message {
+            content: content.collect(),
+            role: crate::client::Role::Assistant,
+        }),
+    }
+}
+
+/// Merges the response continuing a paused turn into the response of the turn so far, adding
+/// up the usage of the requests
+pub fn merge_paused_turn(
+    paused: MessagesResponse,
+    continuation: MessagesResponse,
+) -> MessagesResponse {
+    let mut content = paused.content;
+    content.extend(continuation.content);
+    let add = |a: Option<u32>, b: Option<u32>| match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+    };
+    MessagesResponse {
+        content,
+        id: continuation.id,
+        model: continuation.model,
+        role: continuation.role,
+        stop_reason: continuation.stop_reason,
+        usage: crate::client::Usage {
+            cache_creation_input_tokens: add(
+                paused.usage.cache_creation_input_tokens,
+                continuation.usage.cache_creation_input_tokens,
+            ),
+            cache_read_input_tokens: add(
+                paused.usage.cache_read_input_tokens,
+                continuation.usage.cache_read_input_tokens,
+            ),
+            input_tokens: paused.usage.input_tokens + continuation.usage.input_tokens,
+            output_tokens: paused.usage.output_tokens + continuation.usage.output_tokens,
+        },
+    }
+}
+
+/// Combines the standard output and error of the executed code, on separate lines
+fn code_execution_output(stdout: String, stderr: String) -> String {
+    if stdout.is_empty() || stderr.is_empty() || stdout.ends_with('\n') {
+        stdout + &stderr
+    } else {
+        format!("{stdout}\n{stderr}")
+    }
+}
+
+fn convert_citation(citation: TextCitation) -> Option<Citation> {
+    match citation {
+        TextCitation::WebSearchResultLocation {
+            url,
+            title,
+            cited_text,
+        } => Some(Citation {
+            url: Some(url),
+            file_id: None,
+            title,
+            cited_text: Some(cited_text),
+        }),
+        TextCitation::Other => None,
+    }
 }
 
 pub fn stop_reason_to_finish_reason(stop_reason: StopReason) -> FinishReason {
@@ -218,6 +512,7 @@ pub fn stop_reason_to_finish_reason(stop_reason: StopReason) -> FinishReason {
         StopReason::MaxTokens => FinishReason::Length,
         StopReason::StopSequence => FinishReason::Stop,
         StopReason::ToolUse => FinishReason::ToolCalls,
+        StopReason::PauseTurn => FinishReason::Other,
     }
 }
 
@@ -226,10 +521,69 @@ pub fn convert_usage(usage: crate::client::Usage) -> Usage {
         input_tokens: Some(usage.input_tokens),
         output_tokens: Some(usage.output_tokens),
         total_tokens: None,
+        cache_read_tokens: usage.cache_read_input_tokens,
+        cache_write_tokens: usage.cache_creation_input_tokens,
+        reasoning_tokens: None,
     }
 }
 
-fn message_to_content(message: &Message) -> Vec<Content> {
+/// Converts an ended message batch and its results to the batch status. The results of the
+/// requests which were cancelled or expired are not returned, and such a batch is reported as
+/// cancelled.
+pub fn message_batch_to_status(
+    batch: &MessageBatch,
+    results: Vec<MessageBatchResult>,
+) -> BatchStatus {
+    let results = results
+        .into_iter()
+        .filter_map(|result| {
+            let event = match result.result {
+                MessageBatchResultType::Succeeded { message } => process_response(message),
+                MessageBatchResultType::Errored { error } => ChatEvent::Error(Error {
+                    code: error_type_to_error_code(&error.error.typ),
+                    message: error.error.message.clone(),
+                    provider_error_json: serde_json::to_string(&error).ok(),
+                }),
+                MessageBatchResultType::Canceled | MessageBatchResultType::Expired => {
+                    return None;
+                }
+            };
+            Some(BatchResult {
+                custom_id: result.custom_id,
+                event,
+            })
+        })
+        .collect();
+
+    let counts = &batch.request_counts;
+    if counts.canceled > 0 || counts.expired > 0 {
+        BatchStatus::Cancelled(results)
+    } else {
+        BatchStatus::Completed(results)
+    }
+}
+
+pub fn request_counts_to_progress(counts: &RequestCounts) -> BatchProgress {
+    let finished = counts.succeeded + counts.errored + counts.canceled + counts.expired;
+    BatchProgress {
+        total: Some(counts.processing + finished),
+        finished: Some(finished),
+    }
+}
+
+fn error_type_to_error_code(typ: &str) -> ErrorCode {
+    match typ {
+        "invalid_request_error" | "not_found_error" | "request_too_large" => {
+            ErrorCode::InvalidRequest
+        }
+        "authentication_error" | "permission_error" => ErrorCode::AuthenticationFailed,
+        "rate_limit_error" => ErrorCode::RateLimitExceeded,
+        "api_error" | "overloaded_error" => ErrorCode::InternalError,
+        _ => ErrorCode::Unknown,
+    }
+}
+
+fn message_to_content(message: &Message) -> Result<Vec<Content>, Error> {
     let mut result = Vec::new();
 
     for content_part in &message.content {
@@ -237,6 +591,7 @@ fn message_to_content(message: &Message) -> Vec<Content> {
             ContentPart::Text(text) => result.push(Content::Text {
                 text: text.clone(),
                 cache_control: None,
+                citations: vec![],
             }),
             ContentPart::Image(image_reference) => match image_reference {
                 ImageReference::Url(image_url) => result.push(Content::Image {
@@ -264,10 +619,50 @@ fn message_to_content(message: &Message) -> Vec<Content> {
                     });
                 }
             },
+            ContentPart::Reasoning(reasoning) => {
+                // Thinking blocks can only be sent back as part of assistant turns, and only
+                // if they were produced by Anthropic (and therefore have a signature)
+                if message.role == Role::Assistant {
+                    if let Some(data) = &reasoning.redacted_data {
+                        result.push(Content::RedactedThinking { data: data.clone() });
+                    } else if let Some(signature) = &reasoning.signature {
+                        result.push(Content::Thinking {
+                            thinking: reasoning.text.clone(),
+                            signature: signature.clone(),
+                        });
+                    }
+                }
+            }
+            ContentPart::Document(document) => result.push(document_to_content(document)?),
+            ContentPart::Audio(_) => return Err(unsupported("audio input")),
         }
     }
 
-    result
+    Ok(result)
+}
+
+fn document_to_content(document: &LlmDocumentSource) -> Result<Content, Error> {
+    let source = match &document.data {
+        DocumentData::Url(url) => DocumentSource::Url { url: url.clone() },
+        // Plain text documents are sent as text, everything else (PDFs) as base64 data
+        DocumentData::Inline(data) if document.mime_type == "text/plain" => DocumentSource::Text {
+            data: String::from_utf8(data.clone()).map_err(|err| Error {
+                code: ErrorCode::InvalidRequest,
+                message: format!("Plain text document is not valid UTF-8: {err}"),
+                provider_error_json: None,
+            })?,
+            media_type: document.mime_type.clone(),
+        },
+        DocumentData::Inline(data) => DocumentSource::Base64 {
+            data: general_purpose::STANDARD.encode(data),
+            media_type: document.mime_type.clone(),
+        },
+    };
+    Ok(Content::Document {
+        source,
+        title: document.title.clone(),
+        cache_control: None,
+    })
 }
 
 fn tool_definition_to_tool(tool: &ToolDefinition) -> Result<Tool, Error> {
@@ -285,3 +680,125 @@ fn tool_definition_to_tool(tool: &ToolDefinition) -> Result<Tool, Error> {
         }),
     }
 }
+
+/// The models endpoint does not report limits or capabilities, so they are filled in based on
+/// the documented properties of the known model families. Structured output is available for
+/// every model supporting tools, as it is implemented with a forced tool call.
+pub fn model_to_model_info(model: ModelResponse) -> ModelInfo {
+    let id = model.id;
+    let (context_window, max_output_tokens, reasoning) = match id.as_str() {
+        id if id.starts_with("claude-opus-4") => (Some(200_000), Some(32_000), true),
+        id if id.starts_with("claude-sonnet-4") || id.starts_with("claude-haiku-4") => {
+            (Some(200_000), Some(64_000), true)
+        }
+        id if id.starts_with("claude-3-7-sonnet") => (Some(200_000), Some(64_000), true),
+        id if id.starts_with("claude-3-5") => (Some(200_000), Some(8_192), false),
+        id if id.starts_with("claude-3") => (Some(200_000), Some(4_096), false),
+        _ => (None, None, false),
+    };
+
+    ModelInfo {
+        id,
+        context_window,
+        max_output_tokens,
+        capabilities: ModelCapabilities {
+            tools: true,
+            vision: true,
+            reasoning,
+            json_schema: true,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::{Content, Message, MessagesResponse, Role, StopReason, Usage};
+    use crate::conversions::{append_paused_turn, code_execution_output, merge_paused_turn};
+    use serde_json::json;
+
+    fn response(content: Vec<Content>, stop_reason: StopReason) -> MessagesResponse {
+        MessagesResponse {
+            content,
+            id: "msg_1".to_string(),
+            model: "claude-sonnet-4-0".to_string(),
+            role: Role::Assistant,
+            stop_reason: Some(stop_reason),
+            usage: Usage {
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: Some(10),
+                input_tokens: 100,
+                output_tokens: 20,
+            },
+        }
+    }
+
+    fn text(text: &str) -> Content {
+        Content::Text {
+            text: text.to_string(),
+            cache_control: None,
+            citations: vec![],
+        }
+    }
+
+    fn server_tool_use() -> Content {
+        Content::ServerToolUse {
+            id: "srvtoolu_1".to_string(),
+            name: "web_search".to_string(),
+            input: json!({ "query": "weather" }),
+        }
+    }
+
+    #[test]
+    fn sends_back_the_paused_turn() {
+        let mut messages = vec![Message {
+            content: vec![text("What is the weather?")],
+            role: Role::User,
+        }];
+        let paused = response(
+            vec![text("Let me search."), server_tool_use()],
+            StopReason::PauseTurn,
+        );
+        append_paused_turn(&mut messages, &paused.content);
+
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(messages[1].role, Role::Assistant));
+        assert!(matches!(
+            messages[1].content.as_slice(),
+            [Content::Text { .. }, Content::ServerToolUse { id, .. }] if id == "srvtoolu_1"
+        ));
+    }
+
+    #[test]
+    fn merges_the_continuation_of_a_paused_turn() {
+        let paused = response(
+            vec![text("Let me search."), server_tool_use()],
+            StopReason::PauseTurn,
+        );
+        let continuation = response(vec![text("It is sunny.")], StopReason::EndTurn);
+        let merged = merge_paused_turn(paused, continuation);
+
+        assert_eq!(merged.content.len(), 3);
+        assert!(matches!(merged.stop_reason, Some(StopReason::EndTurn)));
+        assert_eq!(merged.usage.input_tokens, 200);
+        assert_eq!(merged.usage.output_tokens, 40);
+        assert_eq!(merged.usage.cache_read_input_tokens, Some(20));
+        assert_eq!(merged.usage.cache_creation_input_tokens, None);
+    }
+
+    #[test]
+    fn separates_stdout_and_stderr() {
+        assert_eq!(
+            code_execution_output("4".to_string(), "warning".to_string()),
+            "4\nwarning"
+        );
+        assert_eq!(
+            code_execution_output("4\n".to_string(), "warning".to_string()),
+            "4\nwarning"
+        );
+        assert_eq!(code_execution_output("4".to_string(), String::new()), "4");
+        assert_eq!(
+            code_execution_output(String::new(), "error".to_string()),
+            "error"
+        );
+    }
+}
@@ -2,37 +2,43 @@ mod client;
 mod conversions;
 
 use crate::client::{
-    Content, ContentBlockDelta, ErrorResponse, MessagesApi, MessagesRequest, StopReason, Usage,
+    Content, ContentBlockDelta, ErrorResponse, MessageBatchRequest, MessagesApi, MessagesRequest,
+    MessagesResponse, ProcessingStatus, StopReason, Usage,
 };
 use crate::conversions::{
-    convert_usage, messages_to_request, process_response, stop_reason_to_finish_reason,
-    tool_results_to_messages,
+    append_paused_turn, append_tool_results, convert_usage, merge_paused_turn,
+    message_batch_to_status, messages_to_request, model_to_model_info, process_response,
+    request_counts_to_progress, stop_reason_to_finish_reason, RESPONSE_FORMAT_TOOL_NAME,
 };
+use golem_llm::batch::{BatchApi, ProviderChatBatch};
+use golem_llm::chat_job::EmulatedChatJob;
 use golem_llm::chat_stream::{LlmChatStream, LlmChatStreamState};
-use golem_llm::config::with_config_key;
-use golem_llm::durability::{DurableLLM, ExtendedGuest};
+use golem_llm::config::{get_config_key, with_config_key};
+use golem_llm::conversation::LlmConversation;
+use golem_llm::durability::ExtendedGuest;
 use golem_llm::event_source::EventSource;
 use golem_llm::golem::llm::llm::{
-    ChatEvent, ChatStream, Config, ContentPart, Error, ErrorCode, Guest, Message, ResponseMetadata,
-    Role, StreamDelta, StreamEvent, ToolCall, ToolResult,
+    BatchRequest, BatchStatus, ChatBatch, ChatEvent, ChatJob, ChatStream, Config, ContentPart,
+    Error, ErrorCode, FinishReason, Guest, Message, ModelInfo, ReasoningContent, ResponseMetadata,
+    Role, StreamDelta, StreamEvent, TokenCount, ToolCall, ToolResult,
 };
 use golem_rust::wasm_rpc::Pollable;
 use log::trace;
 use std::cell::{Ref, RefCell, RefMut};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-#[derive(Default)]
 struct JsonFragment {
     id: String,
     name: String,
     json: String,
 }
 
-struct AnthropicChatStream {
+pub struct AnthropicChatStream {
     stream: RefCell<Option<EventSource>>,
     failure: Option<Error>,
     finished: RefCell<bool>,
     json_fragments: RefCell<HashMap<u64, JsonFragment>>,
+    response_format_blocks: RefCell<HashSet<u64>>,
     response_metadata: RefCell<ResponseMetadata>,
 }
 
@@ -43,12 +49,15 @@ impl AnthropicChatStream {
             failure: None,
             finished: RefCell::new(false),
             json_fragments: RefCell::new(HashMap::new()),
+            response_format_blocks: RefCell::new(HashSet::new()),
             response_metadata: RefCell::new(ResponseMetadata {
                 finish_reason: None,
                 usage: None,
                 provider_id: None,
                 timestamp: None,
                 provider_metadata_json: None,
+                rate_limit: None,
+                cost: None,
             }),
         })
     }
@@ -59,12 +68,15 @@ impl AnthropicChatStream {
             failure: Some(error),
             finished: RefCell::new(false),
             json_fragments: RefCell::new(HashMap::new()),
+            response_format_blocks: RefCell::new(HashSet::new()),
             response_metadata: RefCell::new(ResponseMetadata {
                 finish_reason: None,
                 usage: None,
                 provider_id: None,
                 timestamp: None,
                 provider_metadata_json: None,
+                rate_limit: None,
+                cost: None,
             }),
         })
     }
@@ -110,6 +122,19 @@ impl LlmChatStreamState for AnthropicChatStream {
                     provider_error_json: None,
                 })))
             }
+            Some("message_start") => {
+                // The input and cache usage is only reported at the start of the message
+                let usage = json
+                    .as_object()
+                    .and_then(|obj| obj.get("message"))
+                    .and_then(|v| v.as_object())
+                    .and_then(|obj| obj.get("usage"))
+                    .and_then(|v| serde_json::from_value::<Usage>(v.clone()).ok());
+                if let Some(usage) = usage {
+                    self.response_metadata.borrow_mut().usage = Some(convert_usage(usage));
+                }
+                Ok(None)
+            }
             Some("content_block_start") => {
                 let index = json
                     .as_object()
@@ -130,18 +155,36 @@ impl LlmChatStreamState for AnthropicChatStream {
                 let content_block = serde_json::from_value::<Content>(raw_content_block.clone())
                     .map_err(|err| format!("Failed to deserialize stream event: {err}"))?;
 
-                if let Content::ToolUse { id, name, .. } = content_block {
-                    self.json_fragments.borrow_mut().insert(
-                        index,
-                        JsonFragment {
-                            id,
-                            name,
-                            json: String::new(),
-                        },
-                    );
+                match content_block {
+                    Content::ToolUse { id, name, .. } => {
+                        if name == RESPONSE_FORMAT_TOOL_NAME {
+                            // The forced response format tool's input is streamed as text content
+                            self.response_format_blocks.borrow_mut().insert(index);
+                        } else {
+                            self.json_fragments.borrow_mut().insert(
+                                index,
+                                JsonFragment {
+                                    id,
+                                    name,
+                                    json: String::new(),
+                                },
+                            );
+                        }
+                        Ok(None)
+                    }
+                    Content::RedactedThinking { data } => {
+                        Ok(Some(StreamEvent::Delta(StreamDelta {
+                            content: Some(vec![ContentPart::Reasoning(ReasoningContent {
+                                text: String::new(),
+                                signature: None,
+                                redacted_data: Some(data),
+                            })]),
+                            tool_calls: None,
+                            logprobs: None,
+                        })))
+                    }
+                    _ => Ok(None),
                 }
-
-                Ok(None)
             }
             Some("content_block_delta") => {
                 let raw_delta = json
@@ -158,6 +201,29 @@ impl LlmChatStreamState for AnthropicChatStream {
                         Ok(Some(StreamEvent::Delta(StreamDelta {
                             content: Some(vec![ContentPart::Text(text)]),
                             tool_calls: None,
+                            logprobs: None,
+                        })))
+                    }
+                    ContentBlockDelta::ThinkingDelta { thinking } => {
+                        Ok(Some(StreamEvent::Delta(StreamDelta {
+                            content: Some(vec![ContentPart::Reasoning(ReasoningContent {
+                                text: thinking,
+                                signature: None,
+                                redacted_data: None,
+                            })]),
+                            tool_calls: None,
+                            logprobs: None,
+                        })))
+                    }
+                    ContentBlockDelta::SignatureDelta { signature } => {
+                        Ok(Some(StreamEvent::Delta(StreamDelta {
+                            content: Some(vec![ContentPart::Reasoning(ReasoningContent {
+                                text: String::new(),
+                                signature: Some(signature),
+                                redacted_data: None,
+                            })]),
+                            tool_calls: None,
+                            logprobs: None,
                         })))
                     }
                     ContentBlockDelta::InputJsonDelta { partial_json } => {
@@ -170,12 +236,22 @@ impl LlmChatStreamState for AnthropicChatStream {
                                     .to_string()
                             })?;
 
-                        let mut json_fragments = self.json_fragments.borrow_mut();
-                        let fragment = json_fragments.entry(index).or_default();
-                        fragment.json.push_str(&partial_json);
+                        if self.response_format_blocks.borrow().contains(&index) {
+                            return Ok(Some(StreamEvent::Delta(StreamDelta {
+                                content: Some(vec![ContentPart::Text(partial_json)]),
+                                tool_calls: None,
+                                logprobs: None,
+                            })));
+                        }
+
+                        // The input of the server tool calls is not tracked, as they are run by Anthropic
+                        if let Some(fragment) = self.json_fragments.borrow_mut().get_mut(&index) {
+                            fragment.json.push_str(&partial_json);
+                        }
 
                         Ok(None)
                     }
+                    ContentBlockDelta::CitationsDelta { .. } => Ok(None),
                 }
             }
             Some("content_block_stop") => {
@@ -194,7 +270,9 @@ impl LlmChatStreamState for AnthropicChatStream {
                             id: tool_use.id,
                             name: tool_use.name,
                             arguments_json: tool_use.json,
+                            validation_error: None,
                         }]),
+                        logprobs: None,
                     })))
                 } else {
                     Ok(None)
@@ -214,10 +292,26 @@ impl LlmChatStreamState for AnthropicChatStream {
 
                 if let Some(stop_reason) = stop_reason {
                     self.response_metadata.borrow_mut().finish_reason =
-                        Some(stop_reason_to_finish_reason(stop_reason));
+                        if self.response_format_blocks.borrow().is_empty() {
+                            Some(stop_reason_to_finish_reason(stop_reason))
+                        } else {
+                            Some(FinishReason::Stop)
+                        };
                 }
                 if let Some(usage) = usage {
-                    self.response_metadata.borrow_mut().usage = Some(convert_usage(usage));
+                    let mut response_metadata = self.response_metadata.borrow_mut();
+                    let mut usage = convert_usage(usage);
+                    if let Some(start_usage) = response_metadata.usage.take() {
+                        usage.input_tokens = usage
+                            .input_tokens
+                            .filter(|tokens| *tokens > 0)
+                            .or(start_usage.input_tokens);
+                        usage.cache_read_tokens =
+                            usage.cache_read_tokens.or(start_usage.cache_read_tokens);
+                        usage.cache_write_tokens =
+                            usage.cache_write_tokens.or(start_usage.cache_write_tokens);
+                    }
+                    response_metadata.usage = Some(usage);
                 }
                 Ok(None)
             }
@@ -231,16 +325,38 @@ impl LlmChatStreamState for AnthropicChatStream {
     }
 }
 
-struct AnthropicComponent;
+pub struct AnthropicComponent;
 
 impl AnthropicComponent {
     const ENV_VAR_NAME: &'static str = "ANTHROPIC_API_KEY";
 
-    fn request(client: MessagesApi, request: MessagesRequest) -> ChatEvent {
-        match client.send_messages(request) {
-            Ok(response) => process_response(response),
-            Err(err) => ChatEvent::Error(err),
+    /// The number of times a turn paused by a long-running server tool is continued before
+    /// returning it as it is
+    const MAX_PAUSED_TURN_CONTINUATIONS: usize = 10;
+
+    /// Sends the request, and continues the turn while it is paused by a long-running server
+    /// tool, returning the content of the whole turn
+    fn request(client: MessagesApi, mut request: MessagesRequest) -> ChatEvent {
+        let mut paused: Option<MessagesResponse> = None;
+        for _ in 0..=Self::MAX_PAUSED_TURN_CONTINUATIONS {
+            let response = match client.send_messages(request.clone()) {
+                Ok(response) => response,
+                Err(err) => return ChatEvent::Error(err),
+            };
+            let is_paused = matches!(response.stop_reason, Some(StopReason::PauseTurn));
+            if is_paused {
+                append_paused_turn(&mut request.messages, &response.content);
+            }
+            let response = match paused.take() {
+                Some(paused) => merge_paused_turn(paused, response),
+                None => response,
+            };
+            if !is_paused {
+                return process_response(response);
+            }
+            paused = Some(response);
         }
+        process_response(paused.expect("The loop runs at least once"))
     }
 
     fn streaming_request(
@@ -257,6 +373,9 @@ impl AnthropicComponent {
 
 impl Guest for AnthropicComponent {
     type ChatStream = LlmChatStream<AnthropicChatStream>;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = ProviderChatBatch<Self>;
 
     fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
         with_config_key(Self::ENV_VAR_NAME, ChatEvent::Error, |anthropic_api_key| {
@@ -279,9 +398,7 @@ impl Guest for AnthropicComponent {
 
             match messages_to_request(messages, config) {
                 Ok(mut request) => {
-                    request
-                        .messages
-                        .extend(tool_results_to_messages(tool_results));
+                    append_tool_results(&mut request.messages, tool_results);
                     Self::request(client, request)
                 }
                 Err(err) => ChatEvent::Error(err),
@@ -292,6 +409,78 @@ impl Guest for AnthropicComponent {
     fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
         ChatStream::new(Self::unwrapped_stream(messages, config))
     }
+
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        with_config_key(Self::ENV_VAR_NAME, Err, |anthropic_api_key| {
+            let client = MessagesApi::new(anthropic_api_key);
+
+            client
+                .list_models()
+                .map(|models| models.into_iter().map(model_to_model_info).collect())
+        })
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        with_config_key(Self::ENV_VAR_NAME, Err, |anthropic_api_key| {
+            let client = MessagesApi::new(anthropic_api_key);
+
+            let request = messages_to_request(messages, config)?;
+            client.count_tokens(request).map(|response| TokenCount {
+                input_tokens: response.input_tokens,
+                estimated: false,
+            })
+        })
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
+}
+
+/// Batches are Anthropic's message batches, whose results can be downloaded once the batch has
+/// ended
+impl BatchApi for AnthropicComponent {
+    fn submit_batch(requests: Vec<BatchRequest>) -> Result<String, Error> {
+        let client = MessagesApi::new(get_config_key(Self::ENV_VAR_NAME)?);
+        let requests = requests
+            .into_iter()
+            .map(|request| {
+                Ok(MessageBatchRequest {
+                    custom_id: request.custom_id,
+                    params: messages_to_request(request.messages, request.config)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        client.create_message_batch(requests).map(|batch| batch.id)
+    }
+
+    fn poll_batch(id: &str) -> Result<BatchStatus, Error> {
+        let client = MessagesApi::new(get_config_key(Self::ENV_VAR_NAME)?);
+        let batch = client.retrieve_message_batch(id)?;
+        match (&batch.processing_status, &batch.results_url) {
+            (ProcessingStatus::Ended, Some(results_url)) => {
+                let results = client.message_batch_results(results_url)?;
+                Ok(message_batch_to_status(&batch, results))
+            }
+            (ProcessingStatus::Ended, None) => Err(Error {
+                code: ErrorCode::InternalError,
+                message: format!("The message batch {id} has ended without results"),
+                provider_error_json: None,
+            }),
+            _ => Ok(BatchStatus::InProgress(request_counts_to_progress(
+                &batch.request_counts,
+            ))),
+        }
+    }
+
+    fn cancel_batch(id: &str) -> Result<(), Error> {
+        let client = MessagesApi::new(get_config_key(Self::ENV_VAR_NAME)?);
+        client.cancel_message_batch(id).map(|_| ())
+    }
 }
 
 impl ExtendedGuest for AnthropicComponent {
@@ -324,6 +513,7 @@ impl ExtendedGuest for AnthropicComponent {
                      Please continue your response from where you left off. \
                      Do not include the part of the response that was already seen.".to_string()),
             ],
+            cache_control: None,
         });
         extended_messages.push(Message {
             role: Role::User,
@@ -331,6 +521,7 @@ impl ExtendedGuest for AnthropicComponent {
             content: vec![ContentPart::Text(
                 "Here is the original question:".to_string(),
             )],
+            cache_control: None,
         });
         extended_messages.extend_from_slice(original_messages);
 
@@ -358,15 +549,31 @@ impl ExtendedGuest for AnthropicComponent {
             .into_iter()
             .chain(partial_result_as_content)
             .collect(),
+            cache_control: None,
         });
         extended_messages
     }
 
+    /// Prefilling the response is not supported with extended thinking
+    fn supports_prefill(config: &Config) -> bool {
+        config.reasoning.is_none()
+    }
+
+    fn unwrapped_fetch_batch(id: String) -> Result<Self::ChatBatch, Error> {
+        Ok(ProviderChatBatch::fetch(id))
+    }
+
+    fn pricing_provider() -> Option<&'static str> {
+        Some("anthropic")
+    }
+
     fn subscribe(stream: &Self::ChatStream) -> Pollable {
         stream.subscribe()
     }
 }
 
-type DurableAnthropicComponent = DurableLLM<AnthropicComponent>;
+#[cfg(not(feature = "library"))]
+type DurableAnthropicComponent = golem_llm::durability::DurableLLM<AnthropicComponent>;
 
+#[cfg(not(feature = "library"))]
 golem_llm::export_llm!(DurableAnthropicComponent with_types_in golem_llm);
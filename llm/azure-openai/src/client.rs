@@ -0,0 +1,103 @@
+use golem_llm::error::from_reqwest_error;
+use golem_llm::golem::llm::llm::{Error, ErrorCode};
+use golem_llm::retry::SendWithRetry;
+use golem_llm_openai_compatible::client::{parse_response, CompletionsApi};
+use log::trace;
+use reqwest::{Client, Method, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// The API version used for listing the deployments, which is not available in newer versions
+const DEPLOYMENTS_API_VERSION: &str = "2022-12-01";
+
+/// Authentication of the requests, either with an API key of the Azure OpenAI resource or
+/// with a Microsoft Entra ID access token
+pub enum Auth {
+    ApiKey(String),
+    BearerToken(String),
+}
+
+/// The Azure OpenAI resource at `endpoint`, e.g. `https://my-resource.openai.azure.com`, where
+/// each model is addressed by the name of its deployment.
+pub struct AzureOpenAIApi {
+    endpoint: String,
+    api_version: String,
+    auth: Auth,
+}
+
+impl AzureOpenAIApi {
+    pub fn new(endpoint: String, api_version: String, auth: Auth) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            api_version,
+            auth,
+        }
+    }
+
+    /// The Chat Completions API of a deployment, which is the OpenAI API under the path of the
+    /// deployment, with the API version in the query
+    pub fn completions_api(&self, deployment: &str) -> Result<CompletionsApi, Error> {
+        let mut url = Url::parse(&self.endpoint).map_err(|err| Error {
+            code: ErrorCode::InvalidRequest,
+            message: format!("Invalid endpoint {}: {err}", self.endpoint),
+            provider_error_json: None,
+        })?;
+        // Pushing the segments percent-encodes the name of the deployment
+        url.path_segments_mut()
+            .map_err(|_| Error {
+                code: ErrorCode::InvalidRequest,
+                message: format!("Invalid endpoint {}", self.endpoint),
+                provider_error_json: None,
+            })?
+            .pop_if_empty()
+            .extend(["openai", "deployments", deployment]);
+
+        let (api_key, auth_header) = match &self.auth {
+            Auth::ApiKey(api_key) => (api_key.clone(), Some("api-key".to_string())),
+            Auth::BearerToken(token) => (token.clone(), None),
+        };
+        Ok(
+            CompletionsApi::new(url.to_string(), Some(api_key), auth_header, None)?
+                .with_query("api-version", &self.api_version),
+        )
+    }
+
+    pub fn list_deployments(&self) -> Result<ListDeploymentsResponse, Error> {
+        trace!("Listing deployments from Azure OpenAI API");
+
+        let client = Client::builder()
+            .build()
+            .expect("Failed to initialize HTTP client");
+        let response: Response = self
+            .authenticated(client.request(
+                Method::GET,
+                format!(
+                    "{}/openai/deployments?api-version={DEPLOYMENTS_API_VERSION}",
+                    self.endpoint
+                ),
+            ))
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    fn authenticated(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.auth {
+            Auth::ApiKey(api_key) => request.header("api-key", api_key),
+            Auth::BearerToken(token) => request.bearer_auth(token),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListDeploymentsResponse {
+    pub data: Vec<Deployment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deployment {
+    pub id: String,
+    pub model: String,
+    pub status: Option<String>,
+}
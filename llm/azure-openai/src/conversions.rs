@@ -0,0 +1,121 @@
+use crate::client::Deployment;
+use golem_llm::golem::llm::llm::{Config, Error, Message, ModelCapabilities, ModelInfo};
+use golem_llm::sampling::{validate_sampling_parameters, SamplingParameter};
+use golem_llm_openai_compatible::client::CompletionsRequest;
+
+/// Unlike most OpenAI-compatible servers, Azure OpenAI rejects `top_k`
+const SAMPLING_PARAMETERS: &[SamplingParameter] = &[
+    SamplingParameter::TopP,
+    SamplingParameter::Seed,
+    SamplingParameter::FrequencyPenalty,
+    SamplingParameter::PresencePenalty,
+    SamplingParameter::User,
+];
+
+/// Converts the request like for any OpenAI-compatible server, except that the deployment is
+/// addressed by the URL instead of the `model` field, and the maximum number of tokens is sent
+/// as `max_completion_tokens`, as reasoning models reject `max_tokens`
+pub fn messages_to_request(
+    messages: Vec<Message>,
+    mut config: Config,
+) -> Result<CompletionsRequest, Error> {
+    validate_sampling_parameters(&mut config, SAMPLING_PARAMETERS)?;
+
+    let mut request =
+        golem_llm_openai_compatible::conversions::messages_to_request(messages, config)?;
+    request.model = String::new();
+    request.max_completion_tokens = request.max_tokens.take();
+    Ok(request)
+}
+
+/// Converts the deployments of the resource to `ModelInfo`, where the identifier is the name of
+/// the deployment, and the limits and capabilities are based on the deployed model's family.
+pub fn deployments_to_model_infos(deployments: Vec<Deployment>) -> Vec<ModelInfo> {
+    deployments
+        .into_iter()
+        .filter(|deployment| {
+            deployment.status.as_deref().unwrap_or("succeeded") == "succeeded"
+                && !NON_CHAT_MODEL_PREFIXES
+                    .iter()
+                    .any(|prefix| deployment.model.starts_with(prefix))
+        })
+        .map(|deployment| model_info(deployment.id, &deployment.model))
+        .collect()
+}
+
+const NON_CHAT_MODEL_PREFIXES: &[&str] = &[
+    "dall-e",
+    "gpt-image",
+    "sora",
+    "text-embedding",
+    "tts",
+    "whisper",
+];
+
+fn model_info(id: String, model: &str) -> ModelInfo {
+    let (context_window, max_output_tokens, capabilities) = match model {
+        model if model.starts_with("gpt-5") => (
+            Some(400_000),
+            Some(128_000),
+            model_capabilities(true, true, true, true),
+        ),
+        model if model.starts_with("gpt-4.1") => (
+            Some(1_047_576),
+            Some(32_768),
+            model_capabilities(true, true, false, true),
+        ),
+        model if model.starts_with("o1-mini") => (
+            Some(128_000),
+            Some(65_536),
+            model_capabilities(false, false, true, false),
+        ),
+        model if model.starts_with("o3-mini") => (
+            Some(200_000),
+            Some(100_000),
+            model_capabilities(true, false, true, true),
+        ),
+        model if model.starts_with("o1") || model.starts_with("o3") || model.starts_with("o4") => (
+            Some(200_000),
+            Some(100_000),
+            model_capabilities(true, true, true, true),
+        ),
+        model if model.starts_with("gpt-4o") => (
+            Some(128_000),
+            Some(16_384),
+            model_capabilities(true, true, false, true),
+        ),
+        model if model.starts_with("gpt-4") => (
+            Some(128_000),
+            Some(4_096),
+            model_capabilities(true, false, false, false),
+        ),
+        // Azure names the GPT-3.5 models without the dot
+        model if model.starts_with("gpt-35-turbo") => (
+            Some(16_385),
+            Some(4_096),
+            model_capabilities(true, false, false, false),
+        ),
+        _ => (None, None, model_capabilities(false, false, false, false)),
+    };
+
+    ModelInfo {
+        id,
+        context_window,
+        max_output_tokens,
+        capabilities,
+    }
+}
+
+fn model_capabilities(
+    tools: bool,
+    vision: bool,
+    reasoning: bool,
+    json_schema: bool,
+) -> ModelCapabilities {
+    ModelCapabilities {
+        tools,
+        vision,
+        reasoning,
+        json_schema,
+    }
+}
@@ -0,0 +1,159 @@
+mod client;
+mod conversions;
+
+use crate::client::{Auth, AzureOpenAIApi};
+use crate::conversions::{deployments_to_model_infos, messages_to_request};
+use golem_llm::batch::UnsupportedChatBatch;
+use golem_llm::chat_job::EmulatedChatJob;
+use golem_llm::chat_stream::LlmChatStream;
+use golem_llm::config::{get_config_key, get_config_key_or_none};
+use golem_llm::conversation::LlmConversation;
+use golem_llm::durability::ExtendedGuest;
+use golem_llm::golem::llm::llm::{
+    ChatBatch, ChatEvent, ChatJob, ChatStream, Config, Error, ErrorCode, Guest, Message, ModelInfo,
+    TokenCount, ToolCall, ToolResult,
+};
+use golem_llm::tokens::estimate_token_count;
+use golem_llm_openai_compatible::client::CompletionsApi;
+use golem_llm_openai_compatible::conversions::tool_results_to_messages;
+use golem_llm_openai_compatible::{OpenAICompatibleChatStream, OpenAICompatibleComponent};
+use golem_rust::wasm_rpc::Pollable;
+
+/// Azure OpenAI serves the OpenAI Chat Completions API, so the requests and responses are
+/// handled by the OpenAI-compatible provider, with the deployment, the authentication and the
+/// API version of the Azure OpenAI resource.
+pub struct AzureOpenAIComponent;
+
+impl AzureOpenAIComponent {
+    const ENDPOINT_ENV_VAR_NAME: &'static str = "AZURE_OPENAI_ENDPOINT";
+    const API_KEY_ENV_VAR_NAME: &'static str = "AZURE_OPENAI_API_KEY";
+    const AD_TOKEN_ENV_VAR_NAME: &'static str = "AZURE_OPENAI_AD_TOKEN";
+    const API_VERSION_ENV_VAR_NAME: &'static str = "AZURE_OPENAI_API_VERSION";
+    const DEFAULT_API_VERSION: &'static str = "2024-10-21";
+
+    /// Creates the client, authenticating with the API key of the resource if it is set, and
+    /// with a Microsoft Entra ID bearer token otherwise
+    fn client() -> Result<AzureOpenAIApi, Error> {
+        let endpoint = get_config_key(Self::ENDPOINT_ENV_VAR_NAME)?;
+        let auth = match (
+            get_config_key_or_none(Self::API_KEY_ENV_VAR_NAME),
+            get_config_key_or_none(Self::AD_TOKEN_ENV_VAR_NAME),
+        ) {
+            (Some(api_key), _) => Auth::ApiKey(api_key),
+            (None, Some(token)) => Auth::BearerToken(token),
+            (None, None) => {
+                return Err(Error {
+                    code: ErrorCode::AuthenticationFailed,
+                    message: format!(
+                        "Either {} or {} must be set",
+                        Self::API_KEY_ENV_VAR_NAME,
+                        Self::AD_TOKEN_ENV_VAR_NAME
+                    ),
+                    provider_error_json: None,
+                })
+            }
+        };
+        let api_version = get_config_key_or_none(Self::API_VERSION_ENV_VAR_NAME)
+            .unwrap_or_else(|| Self::DEFAULT_API_VERSION.to_string());
+
+        Ok(AzureOpenAIApi::new(endpoint, api_version, auth))
+    }
+
+    /// The Chat Completions API of the deployment named by the model of the config
+    fn completions_api(config: &Config) -> Result<CompletionsApi, Error> {
+        Self::client()?.completions_api(&config.model)
+    }
+}
+
+impl Guest for AzureOpenAIComponent {
+    type ChatStream = LlmChatStream<OpenAICompatibleChatStream>;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = UnsupportedChatBatch;
+
+    fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
+        let client = match Self::completions_api(&config) {
+            Ok(client) => client,
+            Err(err) => return ChatEvent::Error(err),
+        };
+
+        match messages_to_request(messages, config) {
+            Ok(request) => OpenAICompatibleComponent::request(client, request),
+            Err(err) => ChatEvent::Error(err),
+        }
+    }
+
+    fn continue_(
+        messages: Vec<Message>,
+        tool_results: Vec<(ToolCall, ToolResult)>,
+        config: Config,
+    ) -> ChatEvent {
+        let client = match Self::completions_api(&config) {
+            Ok(client) => client,
+            Err(err) => return ChatEvent::Error(err),
+        };
+
+        match messages_to_request(messages, config) {
+            Ok(mut request) => {
+                request
+                    .messages
+                    .extend(tool_results_to_messages(tool_results));
+                OpenAICompatibleComponent::request(client, request)
+            }
+            Err(err) => ChatEvent::Error(err),
+        }
+    }
+
+    fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
+        ChatStream::new(Self::unwrapped_stream(messages, config))
+    }
+
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        Self::client()?
+            .list_deployments()
+            .map(|response| deployments_to_model_infos(response.data))
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        Ok(estimate_token_count(&messages, &config))
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
+}
+
+impl ExtendedGuest for AzureOpenAIComponent {
+    fn unwrapped_stream(
+        messages: Vec<Message>,
+        config: Config,
+    ) -> LlmChatStream<OpenAICompatibleChatStream> {
+        let client = match Self::completions_api(&config) {
+            Ok(client) => client,
+            Err(err) => return OpenAICompatibleChatStream::failed(err),
+        };
+
+        match messages_to_request(messages, config) {
+            Ok(request) => OpenAICompatibleComponent::streaming_request(client, request),
+            Err(err) => OpenAICompatibleChatStream::failed(err),
+        }
+    }
+
+    fn pricing_provider() -> Option<&'static str> {
+        Some("azure-openai")
+    }
+
+    fn subscribe(stream: &Self::ChatStream) -> Pollable {
+        stream.subscribe()
+    }
+}
+
+#[cfg(not(feature = "library"))]
+type DurableAzureOpenAIComponent = golem_llm::durability::DurableLLM<AzureOpenAIComponent>;
+
+#[cfg(not(feature = "library"))]
+golem_llm::export_llm!(DurableAzureOpenAIComponent with_types_in golem_llm);
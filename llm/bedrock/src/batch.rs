@@ -0,0 +1,621 @@
+//! Batch inference jobs, which read the records of the batch from S3 and write their results
+//! next to them.
+//!
+//! Batch inference does not support the Converse API, the records have to be in the native
+//! request format of the model. Only the Anthropic Claude models are supported, the Converse
+//! input is converted to their Messages API format.
+
+use crate::conversions::{
+    custom_error, smithy_document_to_json_value, BedrockInput, DEFAULT_MAX_TOKENS,
+    RESPONSE_FORMAT_TOOL_NAME,
+};
+use aws_sdk_bedrock::types::ModelInvocationJobStatus;
+use aws_sdk_bedrockruntime::{
+    self as bedrock,
+    types::{
+        ImageSource, InferenceConfiguration, ReasoningContentBlock, SystemContentBlock, Tool,
+        ToolChoice, ToolConfiguration, ToolInputSchema, ToolResultContentBlock,
+    },
+};
+use base64::{engine::general_purpose, Engine};
+use golem_llm::error::unsupported;
+use golem_llm::golem::llm::llm;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+const ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+
+/// The name of the records file of a job, its results are written to `<name>.out`
+const INPUT_FILE_NAME: &str = "records.jsonl";
+
+/// A record of the input file of a batch inference job
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputRecord {
+    pub record_id: String,
+    pub model_input: Value,
+}
+
+/// A record of the output file of a batch inference job
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputRecord {
+    pub record_id: String,
+    #[serde(default)]
+    pub model_output: Option<Value>,
+    #[serde(default)]
+    pub error: Option<Value>,
+}
+
+/// The locations of the input and output files of a job, under the configured S3 prefix
+pub struct JobLocation {
+    pub bucket: String,
+    /// The key prefix of the job
+    pub prefix: String,
+}
+
+impl JobLocation {
+    pub fn new(s3_uri: &str, job_name: &str) -> Result<Self, llm::Error> {
+        let (bucket, prefix) = parse_s3_uri(s3_uri)?;
+        let prefix = if prefix.is_empty() {
+            job_name.to_string()
+        } else {
+            format!("{prefix}/{job_name}")
+        };
+        Ok(Self { bucket, prefix })
+    }
+
+    /// The location of a job from the output location it was created with
+    pub fn from_output_uri(output_uri: &str) -> Result<Self, llm::Error> {
+        let (bucket, prefix) = parse_s3_uri(output_uri)?;
+        let prefix = prefix
+            .strip_suffix("/output")
+            .unwrap_or(&prefix)
+            .to_string();
+        Ok(Self { bucket, prefix })
+    }
+
+    pub fn input_key(&self) -> String {
+        format!("{}/{INPUT_FILE_NAME}", self.prefix)
+    }
+
+    pub fn input_uri(&self) -> String {
+        format!("s3://{}/{}", self.bucket, self.input_key())
+    }
+
+    pub fn output_uri(&self) -> String {
+        format!("s3://{}/{}/output/", self.bucket, self.prefix)
+    }
+
+    /// The key of the results file, which is written to a folder named after the id of the
+    /// job, the last segment of its ARN
+    pub fn output_key(&self, job_arn: &str) -> String {
+        let job_id = job_arn.rsplit('/').next().unwrap_or(job_arn);
+        format!("{}/output/{job_id}/{INPUT_FILE_NAME}.out", self.prefix)
+    }
+}
+
+fn parse_s3_uri(uri: &str) -> Result<(String, String), llm::Error> {
+    let path = uri.strip_prefix("s3://").ok_or_else(|| {
+        custom_error(
+            llm::ErrorCode::InvalidRequest,
+            format!("Invalid S3 URI {uri}, it has to start with s3://"),
+        )
+    })?;
+    let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+    Ok((bucket.to_string(), prefix.trim_matches('/').to_string()))
+}
+
+pub fn is_anthropic_model(model_id: &str) -> bool {
+    model_id.starts_with("anthropic.") || model_id.contains(".anthropic.")
+}
+
+/// Converts the Converse input of a request to the Anthropic Messages API format
+pub fn bedrock_input_to_anthropic_request(input: BedrockInput) -> Result<Value, llm::Error> {
+    let mut request = match smithy_document_to_json_value(input.additional_fields) {
+        Value::Object(fields) => fields,
+        _ => Map::new(),
+    };
+    request.insert("anthropic_version".to_string(), json!(ANTHROPIC_VERSION));
+
+    let InferenceConfiguration {
+        max_tokens,
+        temperature,
+        top_p,
+        stop_sequences,
+        ..
+    } = input.inference_configuration;
+    request.insert(
+        "max_tokens".to_string(),
+        json!(max_tokens.unwrap_or(DEFAULT_MAX_TOKENS as i32)),
+    );
+    if let Some(temperature) = temperature {
+        request.insert("temperature".to_string(), json!(temperature));
+    }
+    if let Some(top_p) = top_p {
+        request.insert("top_p".to_string(), json!(top_p));
+    }
+    if let Some(stop_sequences) = stop_sequences {
+        request.insert("stop_sequences".to_string(), json!(stop_sequences));
+    }
+
+    let mut system = Vec::new();
+    for block in input.system_instructions {
+        match block {
+            SystemContentBlock::Text(text) => system.push(json!({"type": "text", "text": text})),
+            SystemContentBlock::CachePoint(_) => set_cache_control(&mut system),
+            _ => return Err(unsupported("system content in Bedrock batches")),
+        }
+    }
+    if !system.is_empty() {
+        request.insert("system".to_string(), Value::Array(system));
+    }
+
+    let messages = input
+        .messages
+        .into_iter()
+        .map(message_to_anthropic_message)
+        .collect::<Result<Vec<_>, _>>()?;
+    request.insert("messages".to_string(), Value::Array(messages));
+
+    if let Some(tools) = input.tools {
+        tool_config_to_anthropic_tools(tools, &mut request)?;
+    }
+
+    Ok(Value::Object(request))
+}
+
+fn message_to_anthropic_message(message: bedrock::types::Message) -> Result<Value, llm::Error> {
+    let role = match message.role {
+        bedrock::types::ConversationRole::Assistant => "assistant",
+        _ => "user",
+    };
+    let mut content = Vec::new();
+    for block in message.content {
+        match block {
+            bedrock::types::ContentBlock::Text(text) => {
+                content.push(json!({"type": "text", "text": text}))
+            }
+            bedrock::types::ContentBlock::Image(image) => {
+                let data = match image.source {
+                    Some(ImageSource::Bytes(bytes)) => {
+                        general_purpose::STANDARD.encode(bytes.into_inner())
+                    }
+                    _ => return Err(unsupported("image sources other than bytes in batches")),
+                };
+                content.push(json!({
+                    "type": "image",
+                    "source": {
+                        "type": "base64",
+                        "media_type": format!("image/{}", image.format.as_str()),
+                        "data": data,
+                    },
+                }))
+            }
+            bedrock::types::ContentBlock::ToolUse(tool_use) => content.push(json!({
+                "type": "tool_use",
+                "id": tool_use.tool_use_id,
+                "name": tool_use.name,
+                "input": smithy_document_to_json_value(tool_use.input),
+            })),
+            bedrock::types::ContentBlock::ToolResult(tool_result) => {
+                let results = tool_result
+                    .content
+                    .into_iter()
+                    .map(|result| match result {
+                        ToolResultContentBlock::Text(text) => {
+                            Ok(json!({"type": "text", "text": text}))
+                        }
+                        _ => Err(unsupported("non-text tool results in Bedrock batches")),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                content.push(json!({
+                    "type": "tool_result",
+                    "tool_use_id": tool_result.tool_use_id,
+                    "content": results,
+                }))
+            }
+            bedrock::types::ContentBlock::ReasoningContent(
+                ReasoningContentBlock::ReasoningText(reasoning),
+            ) => content.push(json!({
+                "type": "thinking",
+                "thinking": reasoning.text,
+                "signature": reasoning.signature,
+            })),
+            bedrock::types::ContentBlock::ReasoningContent(
+                ReasoningContentBlock::RedactedContent(data),
+            ) => content.push(json!({
+                "type": "redacted_thinking",
+                "data": general_purpose::STANDARD.encode(data.into_inner()),
+            })),
+            bedrock::types::ContentBlock::CachePoint(_) => set_cache_control(&mut content),
+            bedrock::types::ContentBlock::Document(_) => {
+                return Err(unsupported("documents in Bedrock batches"))
+            }
+            _ => {
+                return Err(unsupported(
+                    "content blocks other than text and images in batches",
+                ))
+            }
+        }
+    }
+    Ok(json!({"role": role, "content": content}))
+}
+
+/// Marks the content block before a cache point as the end of the cached prefix
+fn set_cache_control(blocks: &mut [Value]) {
+    if let Some(Value::Object(block)) = blocks.last_mut() {
+        block.insert("cache_control".to_string(), json!({"type": "ephemeral"}));
+    }
+}
+
+fn tool_config_to_anthropic_tools(
+    config: ToolConfiguration,
+    request: &mut Map<String, Value>,
+) -> Result<(), llm::Error> {
+    let mut tools = Vec::new();
+    for tool in config.tools {
+        match tool {
+            Tool::ToolSpec(spec) => {
+                let input_schema = match spec.input_schema {
+                    Some(ToolInputSchema::Json(schema)) => smithy_document_to_json_value(schema),
+                    _ => json!({"type": "object"}),
+                };
+                let mut tool = json!({"name": spec.name, "input_schema": input_schema});
+                if let Some(description) = spec.description {
+                    tool["description"] = json!(description);
+                }
+                tools.push(tool);
+            }
+            _ => {
+                return Err(unsupported(
+                    "tools other than tool specifications in batches",
+                ))
+            }
+        }
+    }
+    request.insert("tools".to_string(), Value::Array(tools));
+
+    match config.tool_choice {
+        Some(ToolChoice::Tool(tool)) => {
+            request.insert(
+                "tool_choice".to_string(),
+                json!({"type": "tool", "name": tool.name}),
+            );
+        }
+        Some(ToolChoice::Any(_)) => {
+            request.insert("tool_choice".to_string(), json!({"type": "any"}));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// A response of the Anthropic Messages API
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    #[serde(default)]
+    id: String,
+    content: Vec<ResponseContent>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: Option<ResponseUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ResponseContent {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    Thinking {
+        thinking: String,
+        #[serde(default)]
+        signature: Option<String>,
+    },
+    RedactedThinking {
+        data: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseUsage {
+    input_tokens: u32,
+    output_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u32>,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u32>,
+}
+
+/// Converts a record of the output file to the result of its request
+pub fn output_record_to_batch_result(record: OutputRecord) -> llm::BatchResult {
+    let event = match (record.model_output, record.error) {
+        (Some(output), None) => match serde_json::from_value::<MessagesResponse>(output) {
+            Ok(response) => messages_response_to_chat_event(response),
+            Err(err) => llm::ChatEvent::Error(custom_error(
+                llm::ErrorCode::InternalError,
+                format!("Failed to parse batch result: {err}"),
+            )),
+        },
+        (_, Some(error)) => llm::ChatEvent::Error(llm::Error {
+            code: match error.get("errorCode").and_then(Value::as_u64) {
+                Some(400) => llm::ErrorCode::InvalidRequest,
+                Some(401 | 403) => llm::ErrorCode::AuthenticationFailed,
+                Some(429) => llm::ErrorCode::RateLimitExceeded,
+                _ => llm::ErrorCode::InternalError,
+            },
+            message: error
+                .get("errorMessage")
+                .and_then(Value::as_str)
+                .unwrap_or("The request of the batch failed")
+                .to_string(),
+            provider_error_json: Some(error.to_string()),
+        }),
+        (None, None) => llm::ChatEvent::Error(custom_error(
+            llm::ErrorCode::InternalError,
+            "The batch result has neither an output nor an error".to_string(),
+        )),
+    };
+    llm::BatchResult {
+        custom_id: record.record_id,
+        event,
+    }
+}
+
+fn messages_response_to_chat_event(response: MessagesResponse) -> llm::ChatEvent {
+    let mut content = Vec::new();
+    let mut tool_calls = Vec::new();
+    let mut structured_response = false;
+    let mut has_reasoning = false;
+    for block in response.content {
+        match block {
+            ResponseContent::Text { text } => content.push(llm::ContentPart::Text(text)),
+            ResponseContent::ToolUse { name, input, .. } if name == RESPONSE_FORMAT_TOOL_NAME => {
+                structured_response = true;
+                content.push(llm::ContentPart::Text(input.to_string()));
+            }
+            ResponseContent::ToolUse { id, name, input } => tool_calls.push(llm::ToolCall {
+                id,
+                name,
+                arguments_json: input.to_string(),
+                validation_error: None,
+            }),
+            ResponseContent::Thinking {
+                thinking,
+                signature,
+            } => {
+                has_reasoning = true;
+                content.push(llm::ContentPart::Reasoning(llm::ReasoningContent {
+                    text: thinking,
+                    signature,
+                    redacted_data: None,
+                }))
+            }
+            ResponseContent::RedactedThinking { data } => {
+                has_reasoning = true;
+                content.push(llm::ContentPart::Reasoning(llm::ReasoningContent {
+                    text: "".to_owned(),
+                    signature: None,
+                    redacted_data: Some(data),
+                }))
+            }
+            ResponseContent::Other => {}
+        }
+    }
+
+    let finish_reason = if structured_response {
+        llm::FinishReason::Stop
+    } else {
+        stop_reason_to_finish_reason(response.stop_reason.as_deref())
+    };
+    if finish_reason == llm::FinishReason::ToolCalls && !has_reasoning {
+        return llm::ChatEvent::ToolRequest(tool_calls);
+    }
+
+    llm::ChatEvent::Message(llm::CompleteResponse {
+        id: response.id,
+        content,
+        tool_calls,
+        metadata: llm::ResponseMetadata {
+            finish_reason: Some(finish_reason),
+            usage: response.usage.map(|usage| llm::Usage {
+                input_tokens: Some(usage.input_tokens),
+                output_tokens: Some(usage.output_tokens),
+                total_tokens: Some(usage.input_tokens + usage.output_tokens),
+                cache_read_tokens: usage.cache_read_input_tokens,
+                cache_write_tokens: usage.cache_creation_input_tokens,
+                reasoning_tokens: None,
+            }),
+            provider_id: Some("bedrock".to_owned()),
+            timestamp: None,
+            provider_metadata_json: None,
+            rate_limit: None,
+            cost: None,
+        },
+        logprobs: None,
+        alternatives: vec![],
+        builtin_tool_results: vec![],
+    })
+}
+
+fn stop_reason_to_finish_reason(stop_reason: Option<&str>) -> llm::FinishReason {
+    match stop_reason {
+        Some("end_turn" | "stop_sequence") => llm::FinishReason::Stop,
+        Some("tool_use") => llm::FinishReason::ToolCalls,
+        Some("max_tokens") => llm::FinishReason::Length,
+        Some("refusal") => llm::FinishReason::ContentFilter,
+        _ => llm::FinishReason::Other,
+    }
+}
+
+/// Converts the status of a job to the status of the batch. Jobs which were stopped or expired
+/// are reported as cancelled, with the results of the records processed before
+pub fn job_status_to_batch_status(
+    status: Option<&ModelInvocationJobStatus>,
+    message: Option<&str>,
+    results: Vec<llm::BatchResult>,
+) -> llm::BatchStatus {
+    match status {
+        Some(
+            ModelInvocationJobStatus::Submitted
+            | ModelInvocationJobStatus::Validating
+            | ModelInvocationJobStatus::Scheduled,
+        ) => llm::BatchStatus::Queued,
+        Some(
+            ModelInvocationJobStatus::Completed | ModelInvocationJobStatus::PartiallyCompleted,
+        ) => llm::BatchStatus::Completed(results),
+        Some(ModelInvocationJobStatus::Stopped | ModelInvocationJobStatus::Expired) => {
+            llm::BatchStatus::Cancelled(results)
+        }
+        Some(ModelInvocationJobStatus::Failed) => llm::BatchStatus::Failed(custom_error(
+            llm::ErrorCode::InternalError,
+            format!(
+                "The Bedrock batch job failed: {}",
+                message.unwrap_or("unknown error")
+            ),
+        )),
+        _ => llm::BatchStatus::InProgress(llm::BatchProgress {
+            total: None,
+            finished: None,
+        }),
+    }
+}
+
+/// Returns true if the results of the job can be read
+pub fn has_results(status: Option<&ModelInvocationJobStatus>) -> bool {
+    matches!(
+        status,
+        Some(
+            ModelInvocationJobStatus::Completed
+                | ModelInvocationJobStatus::PartiallyCompleted
+                | ModelInvocationJobStatus::Stopped
+                | ModelInvocationJobStatus::Expired
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::{output_record_to_batch_result, JobLocation, OutputRecord};
+    use golem_llm::golem::llm::llm;
+    use serde_json::json;
+
+    #[test]
+    fn output_key_is_under_the_id_of_the_job() {
+        let location = JobLocation::new("s3://my-bucket/batches/", "batch-1").unwrap();
+        assert_eq!(
+            location.input_uri(),
+            "s3://my-bucket/batches/batch-1/records.jsonl"
+        );
+        assert_eq!(
+            location
+                .output_key("arn:aws:bedrock:us-east-1:123456789012:model-invocation-job/abc123"),
+            "batches/batch-1/output/abc123/records.jsonl.out"
+        );
+
+        let location = JobLocation::from_output_uri(&location.output_uri()).unwrap();
+        assert_eq!(location.bucket, "my-bucket");
+        assert_eq!(location.prefix, "batches/batch-1");
+        assert_eq!(
+            location.output_key("abc123"),
+            "batches/batch-1/output/abc123/records.jsonl.out"
+        );
+    }
+
+    #[test]
+    fn converts_successful_records() {
+        let result = output_record_to_batch_result(OutputRecord {
+            record_id: "request-1".to_string(),
+            model_output: Some(json!({
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "content": [{ "type": "text", "text": "Hello!" }],
+                "stop_reason": "end_turn",
+                "usage": { "input_tokens": 10, "output_tokens": 3 }
+            })),
+            error: None,
+        });
+
+        assert_eq!(result.custom_id, "request-1");
+        let llm::ChatEvent::Message(response) = result.event else {
+            panic!("Unexpected event {:?}", result.event);
+        };
+        assert_eq!(response.id, "msg_1");
+        assert_eq!(
+            response.content,
+            vec![llm::ContentPart::Text("Hello!".to_string())]
+        );
+        assert_eq!(
+            response.metadata.finish_reason,
+            Some(llm::FinishReason::Stop)
+        );
+        assert_eq!(
+            response.metadata.usage.and_then(|usage| usage.total_tokens),
+            Some(13)
+        );
+    }
+
+    #[test]
+    fn converts_tool_calls() {
+        let result = output_record_to_batch_result(OutputRecord {
+            record_id: "request-1".to_string(),
+            model_output: Some(json!({
+                "id": "msg_1",
+                "content": [{
+                    "type": "tool_use",
+                    "id": "toolu_1",
+                    "name": "weather",
+                    "input": { "city": "Paris" }
+                }],
+                "stop_reason": "tool_use"
+            })),
+            error: None,
+        });
+
+        let llm::ChatEvent::ToolRequest(tool_calls) = result.event else {
+            panic!("Unexpected event {:?}", result.event);
+        };
+        assert_eq!(tool_calls.len(), 1);
+        assert_eq!(tool_calls[0].id, "toolu_1");
+        assert_eq!(tool_calls[0].arguments_json, r#"{"city":"Paris"}"#);
+    }
+
+    #[test]
+    fn converts_failed_records() {
+        let result = output_record_to_batch_result(OutputRecord {
+            record_id: "request-2".to_string(),
+            model_output: None,
+            error: Some(json!({ "errorCode": 400, "errorMessage": "Invalid request" })),
+        });
+
+        assert_eq!(result.custom_id, "request-2");
+        let llm::ChatEvent::Error(error) = result.event else {
+            panic!("Unexpected event {:?}", result.event);
+        };
+        assert_eq!(error.code, llm::ErrorCode::InvalidRequest);
+        assert_eq!(error.message, "Invalid request");
+
+        let result = output_record_to_batch_result(OutputRecord {
+            record_id: "request-3".to_string(),
+            model_output: None,
+            error: None,
+        });
+        assert!(matches!(
+            result.event,
+            llm::ChatEvent::Error(llm::Error {
+                code: llm::ErrorCode::InternalError,
+                ..
+            })
+        ));
+    }
+}
@@ -1,10 +1,25 @@
 use crate::{
     async_utils::UnsafeFuture,
-    conversions::{self, from_converse_sdk_error, from_converse_stream_sdk_error, BedrockInput},
+    batch::{
+        self, bedrock_input_to_anthropic_request, is_anthropic_model, job_status_to_batch_status,
+        output_record_to_batch_result, InputRecord, JobLocation, OutputRecord,
+    },
+    conversions::{
+        self, from_batch_sdk_error, from_converse_sdk_error, from_converse_stream_sdk_error,
+        from_count_tokens_sdk_error, from_list_foundation_models_sdk_error, BedrockInput,
+    },
     stream::BedrockChatStream,
     wasi_client::WasiClient,
 };
-use aws_config::BehaviorVersion;
+use aws_config::{retry::RetryConfig as SdkRetryConfig, BehaviorVersion};
+use aws_sdk_bedrock::{
+    operation::get_model_invocation_job::GetModelInvocationJobOutput,
+    types::{
+        ModelInvocationJobInputDataConfig, ModelInvocationJobOutputDataConfig,
+        ModelInvocationJobS3InputDataConfig, ModelInvocationJobS3OutputDataConfig, ModelModality,
+        S3InputFormat,
+    },
+};
 use aws_sdk_bedrockruntime::{
     self as bedrock,
     config::{AsyncSleep, Sleep},
@@ -12,34 +27,55 @@ use aws_sdk_bedrockruntime::{
         converse::builders::ConverseFluentBuilder,
         converse_stream::builders::ConverseStreamFluentBuilder,
     },
+    types::{ConverseTokensRequest, CountTokensInput},
 };
+use aws_sdk_s3::primitives::ByteStream;
 use aws_types::region;
 use golem_llm::{
+    batch::{parse_jsonl, to_jsonl},
     config::{get_config_key, get_config_key_or_none},
+    error::unsupported,
     golem::llm::llm,
+    retry::RetryConfig,
 };
 use log::trace;
-use wasi::clocks::monotonic_clock;
+use wasi::clocks::{monotonic_clock, wall_clock};
 use wstd::runtime::Reactor;
 
 #[derive(Debug)]
 pub struct Bedrock {
     client: bedrock::Client,
+    control_client: aws_sdk_bedrock::Client,
+    s3_client: aws_sdk_s3::Client,
 }
 
 impl Bedrock {
     pub async fn new() -> Result<Self, llm::Error> {
         let environment = BedrockEnvironment::load_from_env()?;
+        // The SDK retries the requests itself, configured like the other providers' retries
+        let retry_config = RetryConfig::from_env();
 
         let sdk_config = aws_config::defaults(BehaviorVersion::latest())
             .region(environment.aws_region())
             .http_client(WasiClient::new())
             .credentials_provider(environment.aws_credentials())
             .sleep_impl(WasiSleep::new())
+            .retry_config(
+                SdkRetryConfig::standard()
+                    .with_max_attempts(retry_config.max_attempts.max(1))
+                    .with_initial_backoff(retry_config.min_delay)
+                    .with_max_backoff(retry_config.max_delay),
+            )
             .load()
             .await;
         let client = bedrock::Client::new(&sdk_config);
-        Ok(Self { client })
+        let control_client = aws_sdk_bedrock::Client::new(&sdk_config);
+        let s3_client = aws_sdk_s3::Client::new(&sdk_config);
+        Ok(Self {
+            client,
+            control_client,
+            s3_client,
+        })
     }
 
     pub async fn converse(
@@ -65,7 +101,10 @@ impl Bedrock {
                     Err(err) => llm::ChatEvent::Error(err),
                     Ok(response) => {
                         let event = match response.stop_reason() {
-                            bedrock::types::StopReason::ToolUse => {
+                            bedrock::types::StopReason::ToolUse
+                                if !conversions::is_response_format_output(&response)
+                                    && !conversions::has_reasoning_content(&response) =>
+                            {
                                 conversions::converse_output_to_tool_calls(response)
                                     .map(llm::ChatEvent::ToolRequest)
                             }
@@ -107,6 +146,264 @@ impl Bedrock {
         }
     }
 
+    pub async fn count_tokens(
+        &self,
+        messages: Vec<llm::Message>,
+        config: llm::Config,
+    ) -> Result<llm::TokenCount, llm::Error> {
+        let input = BedrockInput::from(messages, config, None).await?;
+
+        trace!("Sending token counting request to AWS Bedrock: {input:?}");
+        let model_id = input.model_id.clone();
+        let request = ConverseTokensRequest::builder()
+            .set_messages(Some(input.messages))
+            .set_system(Some(input.system_instructions))
+            .build();
+        let response = self
+            .client
+            .count_tokens()
+            .model_id(input.model_id)
+            .input(CountTokensInput::Converse(request))
+            .send()
+            .await
+            .map_err(|e| from_count_tokens_sdk_error(model_id, e))?;
+
+        Ok(llm::TokenCount {
+            input_tokens: response.input_tokens() as u32,
+            estimated: false,
+        })
+    }
+
+    pub async fn list_models(&self) -> Result<Vec<llm::ModelInfo>, llm::Error> {
+        trace!("Listing foundation models from AWS Bedrock");
+
+        let response = self
+            .control_client
+            .list_foundation_models()
+            .by_output_modality(ModelModality::Text)
+            .send()
+            .await
+            .map_err(from_list_foundation_models_sdk_error)?;
+
+        Ok(response
+            .model_summaries()
+            .iter()
+            .map(conversions::foundation_model_to_model_info)
+            .collect())
+    }
+
+    /// Uploads the requests as the records of a new batch inference job and creates the job,
+    /// returning its ARN. All the requests have to use the same Anthropic Claude model.
+    pub async fn submit_batch(
+        &self,
+        requests: Vec<llm::BatchRequest>,
+        environment: BatchEnvironment,
+    ) -> Result<String, llm::Error> {
+        let model_id = requests
+            .first()
+            .map(|request| request.config.model.clone())
+            .unwrap_or_default();
+        if !is_anthropic_model(&model_id) {
+            return Err(unsupported(format!(
+                "batches of model {model_id}, only Anthropic Claude models are supported"
+            )));
+        }
+        if requests
+            .iter()
+            .any(|request| request.config.model != model_id)
+        {
+            return Err(conversions::custom_error(
+                llm::ErrorCode::InvalidRequest,
+                "All the requests of a Bedrock batch have to use the same model".to_string(),
+            ));
+        }
+
+        let mut records = Vec::with_capacity(requests.len());
+        for request in requests {
+            let input = BedrockInput::from(request.messages, request.config, None).await?;
+            records.push(InputRecord {
+                record_id: request.custom_id,
+                model_input: bedrock_input_to_anthropic_request(input)?,
+            });
+        }
+
+        let now = wall_clock::now();
+        let job_name = format!("golem-llm-batch-{}-{}", now.seconds, now.nanoseconds);
+        let location = JobLocation::new(&environment.s3_uri, &job_name)?;
+        self.put_object(&location.bucket, &location.input_key(), to_jsonl(&records)?)
+            .await?;
+        self.create_model_invocation_job(
+            job_name,
+            model_id,
+            environment.role_arn,
+            location.input_uri(),
+            location.output_uri(),
+        )
+        .await
+    }
+
+    /// Checks the status of a batch inference job, reading its results from S3 when it has
+    /// finished
+    pub async fn poll_batch(&self, job_arn: &str) -> Result<llm::BatchStatus, llm::Error> {
+        let job = self.get_model_invocation_job(job_arn).await?;
+
+        let mut results = vec![];
+        if batch::has_results(job.status()) {
+            let output_uri = match job.output_data_config() {
+                Some(ModelInvocationJobOutputDataConfig::S3OutputDataConfig(config)) => {
+                    config.s3_uri()
+                }
+                _ => {
+                    return Err(conversions::custom_error(
+                        llm::ErrorCode::InternalError,
+                        format!("The Bedrock batch job {job_arn} has no S3 output location"),
+                    ))
+                }
+            };
+            let location = JobLocation::from_output_uri(output_uri)?;
+            // Jobs stopped before processing any of the records have no results file
+            if let Some(jsonl) = self
+                .get_object(&location.bucket, &location.output_key(job_arn))
+                .await?
+            {
+                results = parse_jsonl::<OutputRecord>(&jsonl)?
+                    .into_iter()
+                    .map(output_record_to_batch_result)
+                    .collect();
+            }
+        }
+
+        Ok(job_status_to_batch_status(
+            job.status(),
+            job.message(),
+            results,
+        ))
+    }
+
+    /// Creates a batch inference job reading its records from `input_uri`, returning the ARN
+    /// of the job
+    pub async fn create_model_invocation_job(
+        &self,
+        job_name: String,
+        model_id: String,
+        role_arn: String,
+        input_uri: String,
+        output_uri: String,
+    ) -> Result<String, llm::Error> {
+        trace!("Creating AWS Bedrock batch inference job {job_name} for model {model_id}");
+
+        let input_config = ModelInvocationJobS3InputDataConfig::builder()
+            .s3_uri(input_uri)
+            .s3_input_format(S3InputFormat::Jsonl)
+            .build()
+            .map_err(|err| from_batch_sdk_error("Invalid batch input location", err))?;
+        let output_config = ModelInvocationJobS3OutputDataConfig::builder()
+            .s3_uri(output_uri)
+            .build()
+            .map_err(|err| from_batch_sdk_error("Invalid batch output location", err))?;
+
+        let response = self
+            .control_client
+            .create_model_invocation_job()
+            .job_name(job_name)
+            .model_id(model_id)
+            .role_arn(role_arn)
+            .input_data_config(ModelInvocationJobInputDataConfig::S3InputDataConfig(
+                input_config,
+            ))
+            .output_data_config(ModelInvocationJobOutputDataConfig::S3OutputDataConfig(
+                output_config,
+            ))
+            .send()
+            .await
+            .map_err(|err| from_batch_sdk_error("Error creating Bedrock batch job", err))?;
+
+        Ok(response.job_arn().to_string())
+    }
+
+    pub async fn get_model_invocation_job(
+        &self,
+        job_arn: &str,
+    ) -> Result<GetModelInvocationJobOutput, llm::Error> {
+        trace!("Retrieving AWS Bedrock batch inference job {job_arn}");
+
+        self.control_client
+            .get_model_invocation_job()
+            .job_identifier(job_arn)
+            .send()
+            .await
+            .map_err(|err| from_batch_sdk_error("Error retrieving Bedrock batch job", err))
+    }
+
+    pub async fn stop_model_invocation_job(&self, job_arn: &str) -> Result<(), llm::Error> {
+        trace!("Stopping AWS Bedrock batch inference job {job_arn}");
+
+        self.control_client
+            .stop_model_invocation_job()
+            .job_identifier(job_arn)
+            .send()
+            .await
+            .map_err(|err| from_batch_sdk_error("Error stopping Bedrock batch job", err))?;
+        Ok(())
+    }
+
+    pub async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: String,
+    ) -> Result<(), llm::Error> {
+        trace!("Uploading s3://{bucket}/{key}");
+
+        self.s3_client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(ByteStream::from(body.into_bytes()))
+            .send()
+            .await
+            .map_err(|err| from_batch_sdk_error("Error uploading the batch records to S3", err))?;
+        Ok(())
+    }
+
+    /// Downloads an object from S3, returning `None` if it does not exist
+    pub async fn get_object(&self, bucket: &str, key: &str) -> Result<Option<String>, llm::Error> {
+        trace!("Downloading s3://{bucket}/{key}");
+
+        let response = match self
+            .s3_client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_no_such_key()) =>
+            {
+                return Ok(None)
+            }
+            Err(err) => {
+                return Err(from_batch_sdk_error(
+                    "Error downloading the batch results from S3",
+                    err,
+                ))
+            }
+        };
+        let bytes = response
+            .body
+            .collect()
+            .await
+            .map_err(|err| from_batch_sdk_error("Error reading the batch results from S3", err))?
+            .into_bytes();
+        String::from_utf8(bytes.to_vec())
+            .map(Some)
+            .map_err(|err| from_batch_sdk_error("The batch results are not valid UTF-8", err))
+    }
+
     fn init_converse(&self, input: conversions::BedrockInput) -> ConverseFluentBuilder {
         self.client
             .converse()
@@ -166,6 +463,25 @@ impl BedrockEnvironment {
     }
 }
 
+/// The configuration of batch inference jobs, which read their records from and write their
+/// results to S3
+#[derive(Debug)]
+pub struct BatchEnvironment {
+    /// The IAM role Bedrock assumes to access the S3 bucket
+    pub role_arn: String,
+    /// The S3 location under which the records and results of the jobs are stored
+    pub s3_uri: String,
+}
+
+impl BatchEnvironment {
+    pub fn load_from_env() -> Result<Self, llm::Error> {
+        Ok(Self {
+            role_arn: get_config_key("BEDROCK_BATCH_ROLE_ARN")?,
+            s3_uri: get_config_key("BEDROCK_BATCH_S3_URI")?,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 struct WasiSleep;
 
@@ -1,20 +1,40 @@
-use aws_smithy_types::{Document, Number};
-use std::collections::HashMap;
+use aws_smithy_types::{Blob, Document, Number};
+use base64::{engine::general_purpose, Engine};
+use std::collections::{HashMap, HashSet};
 
+use aws_sdk_bedrock::{
+    operation::list_foundation_models::ListFoundationModelsError,
+    types::{FoundationModelSummary, ModelModality},
+};
 use aws_sdk_bedrockruntime::{
     self as bedrock,
     error::SdkError,
-    operation::{converse, converse_stream},
+    operation::{converse, converse_stream, count_tokens},
     types::{
-        ContentBlockDeltaEvent, ContentBlockStartEvent, ConversationRole,
-        ConverseStreamMetadataEvent, ConverseStreamOutput, ImageBlock, ImageFormat,
-        InferenceConfiguration, MessageStopEvent, SystemContentBlock, Tool, ToolConfiguration,
-        ToolInputSchema, ToolSpecification, ToolUseBlock,
+        CachePointBlock, CachePointType, ContentBlockDeltaEvent, ContentBlockStartEvent,
+        ConversationRole, ConverseStreamMetadataEvent, ConverseStreamOutput, DocumentBlock,
+        DocumentFormat, ImageBlock, ImageFormat, InferenceConfiguration, MessageStopEvent,
+        ReasoningContentBlock, ReasoningContentBlockDelta, ReasoningTextBlock, SpecificToolChoice,
+        SystemContentBlock, Tool, ToolChoice, ToolConfiguration, ToolInputSchema,
+        ToolSpecification, ToolUseBlock,
     },
 };
-use golem_llm::golem::llm::llm;
+use golem_llm::builtin_tools::reject_builtin_tools;
+use golem_llm::sampling::{validate_sampling_parameters, SamplingParameter};
+use golem_llm::{error::unsupported, golem::llm::llm};
 use wstd::http;
 
+/// Name of the tool the model is forced to call when a structured response format is requested.
+/// Converse has no native JSON mode, so the tool's input becomes the response content.
+pub const RESPONSE_FORMAT_TOOL_NAME: &str = "json_response";
+
+/// Output tokens reserved for the answer on top of the thinking budget when max-tokens is not set
+pub const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// The inference configuration of Converse is shared by all the models, other sampling
+/// parameters can only be passed to specific models through the provider options
+const SAMPLING_PARAMETERS: &[SamplingParameter] = &[SamplingParameter::TopP];
+
 #[derive(Debug)]
 pub struct BedrockInput {
     pub model_id: String,
@@ -28,44 +48,86 @@ pub struct BedrockInput {
 impl BedrockInput {
     pub async fn from(
         messages: Vec<llm::Message>,
-        config: llm::Config,
+        mut config: llm::Config,
         tool_results: Option<Vec<(llm::ToolCall, llm::ToolResult)>>,
     ) -> Result<Self, llm::Error> {
+        validate_sampling_parameters(&mut config, SAMPLING_PARAMETERS)?;
+        reject_builtin_tools(&config)?;
+
         let (mut user_messages, system_instructions) =
             messages_to_bedrock_message_groups(messages).await?;
 
         if let Some(tool_results) = tool_results {
-            user_messages.extend(tool_call_results_to_bedrock_tools(tool_results)?);
+            append_tool_call_results(&mut user_messages, tool_results)?;
         }
 
-        let options = config
+        let mut options = config
             .provider_options
             .into_iter()
             .map(|kv| (kv.key, Document::String(kv.value)))
             .collect::<HashMap<_, _>>();
 
+        let response_format_tool = config
+            .response_format
+            .map(response_format_to_tool_spec)
+            .transpose()?
+            .flatten();
+        if response_format_tool.is_some() && config.tool_choice.is_some() {
+            return Err(unsupported(
+                "tool-choice cannot be combined with a structured response-format",
+            ));
+        }
+
+        let thinking_budget = config.reasoning.map(reasoning_to_budget_tokens);
+        if let Some(budget_tokens) = thinking_budget {
+            options.insert(
+                "thinking".to_string(),
+                Document::Object(HashMap::from([
+                    ("type".to_string(), Document::String("enabled".to_string())),
+                    (
+                        "budget_tokens".to_string(),
+                        Document::Number(Number::PosInt(budget_tokens as u64)),
+                    ),
+                ])),
+            );
+        }
+        // max_tokens has to be larger than the thinking budget
+        let max_tokens = config
+            .max_tokens
+            .or(thinking_budget.map(|budget_tokens| budget_tokens + DEFAULT_MAX_TOKENS));
+
         Ok(BedrockInput {
             model_id: config.model,
             inference_configuration: InferenceConfiguration::builder()
-                .set_max_tokens(config.max_tokens.map(|x| x as i32))
+                .set_max_tokens(max_tokens.map(|x| x as i32))
                 .set_temperature(config.temperature)
                 .set_stop_sequences(config.stop_sequences)
-                .set_top_p(options.get("top_p").and_then(|v| match v {
-                    Document::String(v) => v.parse::<f32>().ok(),
-                    _ => None,
-                }))
+                .set_top_p(config.top_p)
                 .build(),
             messages: user_messages,
             system_instructions,
-            tools: tool_defs_to_bedrock_tool_config(config.tools)?,
+            tools: tool_defs_to_bedrock_tool_config(config.tools, response_format_tool)?,
             additional_fields: Document::Object(options),
         })
     }
 }
 
-fn tool_call_results_to_bedrock_tools(
+fn reasoning_to_budget_tokens(reasoning: llm::ReasoningConfig) -> u32 {
+    reasoning.budget_tokens.unwrap_or(match reasoning.effort {
+        Some(llm::ReasoningEffort::Low) | None => 1024,
+        Some(llm::ReasoningEffort::Medium) => 4096,
+        Some(llm::ReasoningEffort::High) => 16384,
+    })
+}
+
+/// Appends the tool uses and their results to the conversation.
+///
+/// The tool uses are merged into a preceding assistant message, so reasoning returned
+/// together with the tool request stays in the same assistant turn.
+fn append_tool_call_results(
+    messages: &mut Vec<bedrock::types::Message>,
     results: Vec<(llm::ToolCall, llm::ToolResult)>,
-) -> Result<Vec<bedrock::types::Message>, llm::Error> {
+) -> Result<(), llm::Error> {
     let mut tool_calls: Vec<bedrock::types::ContentBlock> = vec![];
     let mut tool_results: Vec<bedrock::types::ContentBlock> = vec![];
 
@@ -93,24 +155,63 @@ fn tool_call_results_to_bedrock_tools(
         ));
     }
 
-    Ok(vec![
-        bedrock::types::Message::builder()
-            .role(ConversationRole::Assistant)
-            .set_content(Some(tool_calls))
-            .build()
-            .unwrap(),
+    match messages.last_mut() {
+        Some(last) if last.role == ConversationRole::Assistant => last.content.extend(tool_calls),
+        _ => messages.push(
+            bedrock::types::Message::builder()
+                .role(ConversationRole::Assistant)
+                .set_content(Some(tool_calls))
+                .build()
+                .unwrap(),
+        ),
+    }
+    messages.push(
         bedrock::types::Message::builder()
             .role(ConversationRole::User)
             .set_content(Some(tool_results))
             .build()
             .unwrap(),
-    ])
+    );
+
+    Ok(())
+}
+
+fn response_format_to_tool_spec(
+    response_format: llm::ResponseFormat,
+) -> Result<Option<ToolSpecification>, llm::Error> {
+    let (schema, description) = match response_format {
+        llm::ResponseFormat::Text => return Ok(None),
+        llm::ResponseFormat::JsonObject => (
+            Document::Object(HashMap::from([(
+                "type".to_string(),
+                Document::String("object".to_string()),
+            )])),
+            "Respond with a JSON object".to_string(),
+        ),
+        llm::ResponseFormat::JsonSchema(json_schema) => (
+            json_str_to_smithy_document(&json_schema.schema)?,
+            format!(
+                "Respond with a JSON value matching the {} schema",
+                json_schema.name
+            ),
+        ),
+    };
+
+    Ok(Some(
+        ToolSpecification::builder()
+            .name(RESPONSE_FORMAT_TOOL_NAME)
+            .description(description)
+            .input_schema(ToolInputSchema::Json(schema))
+            .build()
+            .unwrap(),
+    ))
 }
 
 fn tool_defs_to_bedrock_tool_config(
     tools: Vec<llm::ToolDefinition>,
+    response_format_tool: Option<ToolSpecification>,
 ) -> Result<Option<ToolConfiguration>, llm::Error> {
-    if tools.is_empty() {
+    if tools.is_empty() && response_format_tool.is_none() {
         return Ok(None);
     }
 
@@ -129,9 +230,21 @@ fn tool_defs_to_bedrock_tool_config(
         ));
     }
 
+    let tool_choice = response_format_tool.map(|spec| {
+        let choice = ToolChoice::Tool(
+            SpecificToolChoice::builder()
+                .name(spec.name.clone())
+                .build()
+                .unwrap(),
+        );
+        specs.push(Tool::ToolSpec(spec));
+        choice
+    });
+
     Ok(Some(
         ToolConfiguration::builder()
             .set_tools(Some(specs))
+            .set_tool_choice(tool_choice)
             .build()
             .unwrap(),
     ))
@@ -144,21 +257,33 @@ async fn messages_to_bedrock_message_groups(
     let mut system_instructions: Vec<SystemContentBlock> = vec![];
 
     for message in messages {
+        let cache_point = message
+            .cache_control
+            .map(cache_control_to_cache_point)
+            .transpose()?;
         if message.role == llm::Role::System {
             for content in message.content {
                 if let llm::ContentPart::Text(text) = content {
                     system_instructions.push(SystemContentBlock::Text(text));
                 }
             }
+            if let Some(cache_point) = cache_point {
+                system_instructions.push(SystemContentBlock::CachePoint(cache_point));
+            }
         } else {
-            let bedrock_content = content_part_to_bedrock_content_blocks(message.content).await?;
+            let role = if message.role == llm::Role::User {
+                ConversationRole::User
+            } else {
+                ConversationRole::Assistant
+            };
+            let mut bedrock_content =
+                content_part_to_bedrock_content_blocks(message.content, &role).await?;
+            if let Some(cache_point) = cache_point {
+                bedrock_content.push(bedrock::types::ContentBlock::CachePoint(cache_point));
+            }
             user_messages.push(
                 bedrock::types::Message::builder()
-                    .role(if message.role == llm::Role::User {
-                        ConversationRole::User
-                    } else {
-                        ConversationRole::Assistant
-                    })
+                    .role(role)
                     .set_content(Some(bedrock_content))
                     .build()
                     .unwrap(),
@@ -168,10 +293,24 @@ async fn messages_to_bedrock_message_groups(
     Ok((user_messages, system_instructions))
 }
 
+fn cache_control_to_cache_point(
+    cache_control: llm::CacheControl,
+) -> Result<CachePointBlock, llm::Error> {
+    match cache_control.ttl {
+        None | Some(llm::CacheTtl::FiveMinutes) => Ok(CachePointBlock::builder()
+            .r#type(CachePointType::Default)
+            .build()
+            .unwrap()),
+        Some(llm::CacheTtl::OneHour) => Err(unsupported("one hour cache TTL")),
+    }
+}
+
 async fn content_part_to_bedrock_content_blocks(
     content_parts: Vec<llm::ContentPart>,
+    role: &ConversationRole,
 ) -> Result<Vec<bedrock::types::ContentBlock>, llm::Error> {
     let mut bedrock_content_blocks: Vec<bedrock::types::ContentBlock> = vec![];
+    let mut document_count = 0;
     for part in content_parts {
         match part {
             llm::ContentPart::Text(text) => {
@@ -180,12 +319,113 @@ async fn content_part_to_bedrock_content_blocks(
             llm::ContentPart::Image(image) => {
                 bedrock_content_blocks.push(image_ref_to_bedrock_image_content_block(image).await?);
             }
+            llm::ContentPart::Reasoning(reasoning) => {
+                // Reasoning can only be passed back as part of the assistant's own turns
+                if *role == ConversationRole::Assistant {
+                    if let Some(block) = reasoning_to_bedrock_content_block(reasoning)? {
+                        bedrock_content_blocks.push(block);
+                    }
+                }
+            }
+            llm::ContentPart::Document(document) => {
+                document_count += 1;
+                bedrock_content_blocks
+                    .push(document_to_bedrock_content_block(document, document_count).await?);
+            }
+            llm::ContentPart::Audio(_) => return Err(unsupported("audio input")),
         }
     }
 
     Ok(bedrock_content_blocks)
 }
 
+async fn document_to_bedrock_content_block(
+    document: llm::DocumentSource,
+    index: usize,
+) -> Result<bedrock::types::ContentBlock, llm::Error> {
+    let format = str_to_bedrock_document_format(&document.mime_type)?;
+    let bytes = match document.data {
+        llm::DocumentData::Inline(data) => data,
+        llm::DocumentData::Url(url) => get_bytes_from_url(&url).await?,
+    };
+    // Converse requires a name for each document, and only accepts a limited set of characters in it
+    let name = match document.title {
+        Some(title) => title
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c.is_whitespace() || "-()[]".contains(c) {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => format!("document-{index}"),
+    };
+
+    Ok(bedrock::types::ContentBlock::Document(
+        DocumentBlock::builder()
+            .format(format)
+            .name(name)
+            .source(bedrock::types::DocumentSource::Bytes(bytes.into()))
+            .build()
+            .unwrap(),
+    ))
+}
+
+fn str_to_bedrock_document_format(mime_type: &str) -> Result<DocumentFormat, llm::Error> {
+    match mime_type {
+        "application/pdf" => Ok(DocumentFormat::Pdf),
+        "text/csv" => Ok(DocumentFormat::Csv),
+        "application/msword" => Ok(DocumentFormat::Doc),
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+            Ok(DocumentFormat::Docx)
+        }
+        "application/vnd.ms-excel" => Ok(DocumentFormat::Xls),
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => {
+            Ok(DocumentFormat::Xlsx)
+        }
+        "text/html" => Ok(DocumentFormat::Html),
+        "text/plain" => Ok(DocumentFormat::Txt),
+        "text/markdown" => Ok(DocumentFormat::Md),
+        other => Err(unsupported(format!("document type {other}"))),
+    }
+}
+
+fn reasoning_to_bedrock_content_block(
+    reasoning: llm::ReasoningContent,
+) -> Result<Option<bedrock::types::ContentBlock>, llm::Error> {
+    if let Some(redacted_data) = reasoning.redacted_data {
+        let data = general_purpose::STANDARD
+            .decode(redacted_data)
+            .map_err(|err| {
+                custom_error(
+                    llm::ErrorCode::InvalidRequest,
+                    format!("Invalid redacted reasoning data: {err}"),
+                )
+            })?;
+        Ok(Some(bedrock::types::ContentBlock::ReasoningContent(
+            ReasoningContentBlock::RedactedContent(Blob::new(data)),
+        )))
+    } else if let Some(signature) = reasoning.signature {
+        Ok(Some(bedrock::types::ContentBlock::ReasoningContent(
+            ReasoningContentBlock::ReasoningText(
+                ReasoningTextBlock::builder()
+                    .text(reasoning.text)
+                    .signature(signature)
+                    .build()
+                    .unwrap(),
+            ),
+        )))
+    } else {
+        // Unsigned reasoning is not accepted by the model
+        Ok(None)
+    }
+}
+
 async fn image_ref_to_bedrock_image_content_block(
     image_reference: llm::ImageReference,
 ) -> Result<bedrock::types::ContentBlock, llm::Error> {
@@ -237,14 +477,14 @@ async fn get_bytes_from_url(url: &str) -> Result<Vec<u8>, llm::Error> {
     let response = client.send(request).await.map_err(|err| {
         custom_error(
             llm::ErrorCode::InvalidRequest,
-            format!("Could not read image bytes from url: {url}, cause: {err}"),
+            format!("Could not read bytes from url: {url}, cause: {err}"),
         )
     })?;
     if !response.status().is_success() {
         return Err(custom_error(
             llm::ErrorCode::InvalidRequest,
             format!(
-                "Could not read image bytes from url: {url}, cause: request failed with status: {}",
+                "Could not read bytes from url: {url}, cause: request failed with status: {}",
                 response.status()
             ),
         ));
@@ -253,7 +493,7 @@ async fn get_bytes_from_url(url: &str) -> Result<Vec<u8>, llm::Error> {
     let bytes = response.into_body().bytes().await.map_err(|err| {
         custom_error(
             llm::ErrorCode::InvalidRequest,
-            format!("Could not read image bytes from url: {url}, cause: {err}"),
+            format!("Could not read bytes from url: {url}, cause: {err}"),
         )
     })?;
 
@@ -319,6 +559,7 @@ pub fn converse_output_to_complete_response(
         Ok(message) => {
             let mut content_parts: Vec<llm::ContentPart> = vec![];
             let mut tool_calls: Vec<llm::ToolCall> = vec![];
+            let mut structured_response = false;
             for block in message.content.clone() {
                 match block {
                     bedrock::types::ContentBlock::Text(text) => {
@@ -327,13 +568,29 @@ pub fn converse_output_to_complete_response(
                     bedrock::types::ContentBlock::Image(image) => {
                         content_parts.push(bedrock_image_to_llm_content_part(image));
                     }
+                    bedrock::types::ContentBlock::ReasoningContent(reasoning) => {
+                        if let Some(part) = bedrock_reasoning_to_llm_content_part(reasoning) {
+                            content_parts.push(part);
+                        }
+                    }
+                    bedrock::types::ContentBlock::ToolUse(tool)
+                        if tool.name == RESPONSE_FORMAT_TOOL_NAME =>
+                    {
+                        structured_response = true;
+                        content_parts.push(llm::ContentPart::Text(
+                            bedrock_tool_use_to_llm_tool_call(tool)?.arguments_json,
+                        ));
+                    }
                     bedrock::types::ContentBlock::ToolUse(tool) => {
                         tool_calls.push(bedrock_tool_use_to_llm_tool_call(tool)?);
                     }
                     _ => {}
                 }
             }
-            let metadata = converse_output_to_response_metadata(&response);
+            let mut metadata = converse_output_to_response_metadata(&response);
+            if structured_response {
+                metadata.finish_reason = Some(llm::FinishReason::Stop);
+            }
             Ok(llm::CompleteResponse {
                 // bedrock does not return an id as part of the response struct.
                 // there may be one present in `additional_model_response_fields`
@@ -343,11 +600,43 @@ pub fn converse_output_to_complete_response(
                 content: content_parts,
                 tool_calls,
                 metadata,
+                logprobs: None,
+                alternatives: vec![],
+                builtin_tool_results: vec![],
             })
         }
     }
 }
 
+/// Returns true if the response is the forced call of the response format tool
+/// instead of a tool request made by the model
+pub fn is_response_format_output(response: &converse::ConverseOutput) -> bool {
+    response
+        .output()
+        .and_then(|output| output.as_message().ok())
+        .is_some_and(|message| {
+            message.content.iter().any(|block| {
+                block
+                    .as_tool_use()
+                    .is_ok_and(|tool| tool.name == RESPONSE_FORMAT_TOOL_NAME)
+            })
+        })
+}
+
+/// Returns true if the response contains reasoning, which has to be kept together with
+/// the requested tool calls when continuing the conversation
+pub fn has_reasoning_content(response: &converse::ConverseOutput) -> bool {
+    response
+        .output()
+        .and_then(|output| output.as_message().ok())
+        .is_some_and(|message| {
+            message
+                .content
+                .iter()
+                .any(|block| block.is_reasoning_content())
+        })
+}
+
 fn bedrock_tool_use_to_llm_tool_call(tool: ToolUseBlock) -> Result<llm::ToolCall, llm::Error> {
     Ok(llm::ToolCall {
         id: tool.tool_use_id,
@@ -360,6 +649,7 @@ fn bedrock_tool_use_to_llm_tool_call(tool: ToolUseBlock) -> Result<llm::ToolCall
                 )
             },
         )?,
+        validation_error: None,
     })
 }
 
@@ -375,6 +665,8 @@ fn converse_output_to_response_metadata(
             .clone()
             .and_then(smithy_document_to_metadata_json),
         timestamp: None,
+        rate_limit: None,
+        cost: None,
     }
 }
 
@@ -387,6 +679,9 @@ fn bedrock_usage_to_llm_usage(usage: &bedrock::types::TokenUsage) -> llm::Usage
         input_tokens: Some(usage.input_tokens() as u32),
         output_tokens: Some(usage.output_tokens() as u32),
         total_tokens: Some(usage.total_tokens() as u32),
+        cache_read_tokens: usage.cache_read_input_tokens().map(|tokens| tokens as u32),
+        cache_write_tokens: usage.cache_write_input_tokens().map(|tokens| tokens as u32),
+        reasoning_tokens: None,
     }
 }
 
@@ -430,49 +725,135 @@ fn bedrock_image_to_llm_content_part(block: bedrock::types::ImageBlock) -> llm::
     llm::ContentPart::Image(reference)
 }
 
+fn bedrock_reasoning_to_llm_content_part(block: ReasoningContentBlock) -> Option<llm::ContentPart> {
+    match block {
+        ReasoningContentBlock::ReasoningText(reasoning) => {
+            Some(llm::ContentPart::Reasoning(llm::ReasoningContent {
+                text: reasoning.text,
+                signature: reasoning.signature,
+                redacted_data: None,
+            }))
+        }
+        ReasoningContentBlock::RedactedContent(data) => {
+            Some(llm::ContentPart::Reasoning(llm::ReasoningContent {
+                text: "".to_owned(),
+                signature: None,
+                redacted_data: Some(general_purpose::STANDARD.encode(data.into_inner())),
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Converts a single event of the Converse stream.
+///
+/// `response_format_blocks` holds the indices of content blocks that belong to the forced
+/// response format tool, their input deltas are emitted as text content.
+///
+/// `tool_calls` holds the tool calls being received by the index of their content block. The
+/// input of a tool call is streamed in fragments, and the call is only emitted once its content
+/// block stops.
 pub fn converse_stream_output_to_stream_event(
     event: ConverseStreamOutput,
+    response_format_blocks: &mut HashSet<i32>,
+    tool_calls: &mut HashMap<i32, llm::ToolCall>,
 ) -> Option<llm::StreamEvent> {
     match event {
-        ConverseStreamOutput::ContentBlockStart(block) => process_content_block_start_event(block),
-        ConverseStreamOutput::ContentBlockDelta(block) => process_content_block_delta_event(block),
+        ConverseStreamOutput::ContentBlockStart(block) => {
+            process_content_block_start_event(block, response_format_blocks, tool_calls)
+        }
+        ConverseStreamOutput::ContentBlockDelta(block) => {
+            process_content_block_delta_event(block, response_format_blocks, tool_calls)
+        }
+        ConverseStreamOutput::ContentBlockStop(block) => tool_calls
+            .remove(&block.content_block_index)
+            .map(|tool_call| {
+                llm::StreamEvent::Delta(llm::StreamDelta {
+                    content: None,
+                    tool_calls: Some(vec![tool_call]),
+                    logprobs: None,
+                })
+            }),
         ConverseStreamOutput::Metadata(metadata) => process_metadata_event(metadata),
-        ConverseStreamOutput::MessageStop(event) => process_message_stop_event(event),
+        ConverseStreamOutput::MessageStop(event) => {
+            process_message_stop_event(event, response_format_blocks)
+        }
         _ => None,
     }
 }
 
-fn process_content_block_start_event(block: ContentBlockStartEvent) -> Option<llm::StreamEvent> {
+fn process_content_block_start_event(
+    block: ContentBlockStartEvent,
+    response_format_blocks: &mut HashSet<i32>,
+    tool_calls: &mut HashMap<i32, llm::ToolCall>,
+) -> Option<llm::StreamEvent> {
     if let Some(start_info) = block.start {
         if let Ok(tool_use) = start_info.as_tool_use() {
-            return Some(llm::StreamEvent::Delta(llm::StreamDelta {
-                content: None,
-                tool_calls: Some(vec![llm::ToolCall {
-                    id: tool_use.tool_use_id.clone(),
-                    name: tool_use.name.clone(),
-                    arguments_json: "".to_owned(),
-                }]),
-            }));
+            if tool_use.name == RESPONSE_FORMAT_TOOL_NAME {
+                response_format_blocks.insert(block.content_block_index);
+            } else {
+                tool_calls.insert(
+                    block.content_block_index,
+                    llm::ToolCall {
+                        id: tool_use.tool_use_id.clone(),
+                        name: tool_use.name.clone(),
+                        arguments_json: "".to_owned(),
+                        validation_error: None,
+                    },
+                );
+            }
         }
     }
     None
 }
 
-fn process_content_block_delta_event(block: ContentBlockDeltaEvent) -> Option<llm::StreamEvent> {
+fn process_content_block_delta_event(
+    block: ContentBlockDeltaEvent,
+    response_format_blocks: &HashSet<i32>,
+    tool_calls: &mut HashMap<i32, llm::ToolCall>,
+) -> Option<llm::StreamEvent> {
     if let Some(block_info) = block.delta {
         if let Ok(tool_use) = block_info.as_tool_use() {
-            return Some(llm::StreamEvent::Delta(llm::StreamDelta {
-                content: None,
-                tool_calls: Some(vec![llm::ToolCall {
-                    id: "".to_owned(),
-                    name: "".to_owned(),
-                    arguments_json: tool_use.input.clone(),
-                }]),
-            }));
+            if response_format_blocks.contains(&block.content_block_index) {
+                return Some(llm::StreamEvent::Delta(llm::StreamDelta {
+                    content: Some(vec![llm::ContentPart::Text(tool_use.input.clone())]),
+                    tool_calls: None,
+                    logprobs: None,
+                }));
+            }
+            if let Some(tool_call) = tool_calls.get_mut(&block.content_block_index) {
+                tool_call.arguments_json.push_str(&tool_use.input);
+            }
+            return None;
         } else if let Ok(text) = block_info.as_text() {
             return Some(llm::StreamEvent::Delta(llm::StreamDelta {
                 content: Some(vec![llm::ContentPart::Text(text.clone())]),
                 tool_calls: None,
+                logprobs: None,
+            }));
+        } else if let Ok(reasoning) = block_info.as_reasoning_content() {
+            let reasoning = match reasoning {
+                ReasoningContentBlockDelta::Text(text) => llm::ReasoningContent {
+                    text: text.clone(),
+                    signature: None,
+                    redacted_data: None,
+                },
+                ReasoningContentBlockDelta::Signature(signature) => llm::ReasoningContent {
+                    text: "".to_owned(),
+                    signature: Some(signature.clone()),
+                    redacted_data: None,
+                },
+                ReasoningContentBlockDelta::RedactedContent(data) => llm::ReasoningContent {
+                    text: "".to_owned(),
+                    signature: None,
+                    redacted_data: Some(general_purpose::STANDARD.encode(data.as_ref())),
+                },
+                _ => return None,
+            };
+            return Some(llm::StreamEvent::Delta(llm::StreamDelta {
+                content: Some(vec![llm::ContentPart::Reasoning(reasoning)]),
+                tool_calls: None,
+                logprobs: None,
             }));
         }
     }
@@ -486,12 +867,22 @@ fn process_metadata_event(metadata: ConverseStreamMetadataEvent) -> Option<llm::
         usage: metadata.usage().map(bedrock_usage_to_llm_usage),
         provider_id: Some("bedrock".to_owned()),
         provider_metadata_json: None,
+        rate_limit: None,
+        cost: None,
     }))
 }
 
-fn process_message_stop_event(event: MessageStopEvent) -> Option<llm::StreamEvent> {
+fn process_message_stop_event(
+    event: MessageStopEvent,
+    response_format_blocks: &HashSet<i32>,
+) -> Option<llm::StreamEvent> {
+    let finish_reason = if response_format_blocks.is_empty() {
+        bedrock_stop_reason_to_finish_reason(event.stop_reason())
+    } else {
+        llm::FinishReason::Stop
+    };
     Some(llm::StreamEvent::Finish(llm::ResponseMetadata {
-        finish_reason: Some(bedrock_stop_reason_to_finish_reason(event.stop_reason())),
+        finish_reason: Some(finish_reason),
         timestamp: None,
         usage: None,
         provider_id: None,
@@ -499,6 +890,8 @@ fn process_message_stop_event(event: MessageStopEvent) -> Option<llm::StreamEven
             .additional_model_response_fields
             .clone()
             .and_then(smithy_document_to_metadata_json),
+        rate_limit: None,
+        cost: None,
     }))
 }
 
@@ -511,7 +904,7 @@ fn json_str_to_smithy_document(value: &str) -> Result<Document, llm::Error> {
     Ok(serde_json_to_smithy_document(json_value))
 }
 
-fn smithy_document_to_json_value(document: Document) -> serde_json::Value {
+pub fn smithy_document_to_json_value(document: Document) -> serde_json::Value {
     match document {
         Document::Null => serde_json::Value::Null,
         Document::Bool(b) => serde_json::Value::Bool(b),
@@ -594,6 +987,35 @@ pub fn from_converse_stream_sdk_error(
     }
 }
 
+pub fn from_count_tokens_sdk_error(
+    model_id: String,
+    sdk_error: SdkError<count_tokens::CountTokensError>,
+) -> llm::Error {
+    llm::Error {
+        code: llm::ErrorCode::InternalError,
+        message: format!("Error counting tokens for Bedrock model {model_id}: {sdk_error:?}"),
+        provider_error_json: None,
+    }
+}
+
+pub fn from_list_foundation_models_sdk_error(
+    sdk_error: SdkError<ListFoundationModelsError>,
+) -> llm::Error {
+    llm::Error {
+        code: llm::ErrorCode::InternalError,
+        message: format!("Error listing Bedrock foundation models: {sdk_error:?}"),
+        provider_error_json: None,
+    }
+}
+
+pub fn from_batch_sdk_error(message: &str, error: impl std::fmt::Debug) -> llm::Error {
+    llm::Error {
+        code: llm::ErrorCode::InternalError,
+        message: format!("{message}: {error:?}"),
+        provider_error_json: None,
+    }
+}
+
 pub fn custom_error(code: llm::ErrorCode, message: String) -> llm::Error {
     llm::Error {
         code,
@@ -607,6 +1029,7 @@ pub fn merge_metadata(
     metadata2: llm::ResponseMetadata,
 ) -> llm::ResponseMetadata {
     metadata1.usage = metadata1.usage.or(metadata2.usage);
+    metadata1.cost = metadata1.cost.or(metadata2.cost);
     metadata1.timestamp = metadata1.timestamp.or(metadata2.timestamp);
     metadata1.provider_id = metadata1.provider_id.or(metadata2.provider_id);
     metadata1.finish_reason = metadata1.finish_reason.or(metadata2.finish_reason);
@@ -616,3 +1039,52 @@ pub fn merge_metadata(
 
     metadata1
 }
+
+/// Bedrock reports the input modalities of the foundation models, but not their limits or whether
+/// they support tools and reasoning through the Converse API. These are derived from the model
+/// families documented to support them. Structured output is implemented with a forced tool call,
+/// so it is available wherever tools are.
+pub fn foundation_model_to_model_info(model: &FoundationModelSummary) -> llm::ModelInfo {
+    let id = model.model_id();
+    let vision = model
+        .input_modalities()
+        .iter()
+        .any(|modality| *modality == ModelModality::Image);
+    let tools = [
+        "anthropic.claude-3",
+        "anthropic.claude-opus-4",
+        "anthropic.claude-sonnet-4",
+        "anthropic.claude-haiku-4",
+        "amazon.nova",
+        "cohere.command-r",
+        "meta.llama3-1",
+        "meta.llama3-2",
+        "meta.llama3-3",
+        "meta.llama4",
+        "mistral.mistral-large",
+        "mistral.pixtral",
+    ]
+    .iter()
+    .any(|prefix| id.starts_with(prefix));
+    let reasoning = [
+        "anthropic.claude-3-7",
+        "anthropic.claude-opus-4",
+        "anthropic.claude-sonnet-4",
+        "anthropic.claude-haiku-4",
+        "deepseek.r1",
+    ]
+    .iter()
+    .any(|prefix| id.starts_with(prefix));
+
+    llm::ModelInfo {
+        id: id.to_string(),
+        context_window: None,
+        max_output_tokens: None,
+        capabilities: llm::ModelCapabilities {
+            tools,
+            vision,
+            reasoning,
+            json_schema: tools,
+        },
+    }
+}
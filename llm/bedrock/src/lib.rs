@@ -1,22 +1,32 @@
 use async_utils::get_async_runtime;
-use client::Bedrock;
+use client::{BatchEnvironment, Bedrock};
 use golem_llm::{
-    durability::{DurableLLM, ExtendedGuest},
-    golem::llm::llm::{self, ChatEvent, ChatStream, Config, Guest, Message, ToolCall, ToolResult},
+    batch::{BatchApi, ProviderChatBatch},
+    chat_job::EmulatedChatJob,
+    conversation::LlmConversation,
+    durability::ExtendedGuest,
+    golem::llm::llm::{
+        self, BatchRequest, BatchStatus, ChatBatch, ChatEvent, ChatJob, ChatStream, Config, Guest,
+        Message, ModelInfo, TokenCount, ToolCall, ToolResult,
+    },
 };
 use golem_rust::bindings::wasi::clocks::monotonic_clock;
 use stream::BedrockChatStream;
 
 mod async_utils;
+mod batch;
 mod client;
 mod conversions;
 mod stream;
 mod wasi_client;
 
-struct BedrockComponent;
+pub struct BedrockComponent;
 
 impl Guest for BedrockComponent {
     type ChatStream = BedrockChatStream;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = ProviderChatBatch<Self>;
 
     fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
         let runtime = get_async_runtime();
@@ -51,6 +61,63 @@ impl Guest for BedrockComponent {
     fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
         ChatStream::new(Self::unwrapped_stream(messages, config))
     }
+
+    fn list_models() -> Result<Vec<ModelInfo>, llm::Error> {
+        let runtime = get_async_runtime();
+
+        runtime.block_on(async {
+            let bedrock = get_bedrock_client().await?;
+            bedrock.list_models().await
+        })
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, llm::Error> {
+        let runtime = get_async_runtime();
+
+        runtime.block_on(async {
+            let bedrock = get_bedrock_client().await?;
+            bedrock.count_tokens(messages, config).await
+        })
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, llm::Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, llm::Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
+}
+
+/// Batches are run as batch inference jobs, identified by the ARN of the job
+impl BatchApi for BedrockComponent {
+    fn submit_batch(requests: Vec<BatchRequest>) -> Result<String, llm::Error> {
+        let environment = BatchEnvironment::load_from_env()?;
+        let runtime = get_async_runtime();
+
+        runtime.block_on(async {
+            let bedrock = get_bedrock_client().await?;
+            bedrock.submit_batch(requests, environment).await
+        })
+    }
+
+    fn poll_batch(id: &str) -> Result<BatchStatus, llm::Error> {
+        let runtime = get_async_runtime();
+
+        runtime.block_on(async {
+            let bedrock = get_bedrock_client().await?;
+            bedrock.poll_batch(id).await
+        })
+    }
+
+    fn cancel_batch(id: &str) -> Result<(), llm::Error> {
+        let runtime = get_async_runtime();
+
+        runtime.block_on(async {
+            let bedrock = get_bedrock_client().await?;
+            bedrock.stop_model_invocation_job(id).await
+        })
+    }
 }
 
 impl ExtendedGuest for BedrockComponent {
@@ -84,6 +151,7 @@ impl ExtendedGuest for BedrockComponent {
                      Please continue your response from where you left off. \
                      Do not include the part of the response that was already seen. If the response starts with a new word and no punctuation then add a space to the beginning".to_string()),
             ],
+            cache_control: None,
         });
         extended_messages.push(Message {
             role: llm::Role::User,
@@ -91,6 +159,7 @@ impl ExtendedGuest for BedrockComponent {
             content: vec![llm::ContentPart::Text(
                 "Here is the original question:".to_string(),
             )],
+            cache_control: None,
         });
         extended_messages.extend_from_slice(original_messages);
 
@@ -118,12 +187,28 @@ impl ExtendedGuest for BedrockComponent {
             .into_iter()
             .chain(partial_result_as_content)
             .collect(),
+            cache_control: None,
         });
         extended_messages
     }
 
+    /// Only the Claude models continue a prefilled response, and not with extended thinking
+    fn supports_prefill(config: &Config) -> bool {
+        batch::is_anthropic_model(&config.model) && config.reasoning.is_none()
+    }
+
+    fn unwrapped_fetch_batch(id: String) -> Result<Self::ChatBatch, llm::Error> {
+        Ok(ProviderChatBatch::fetch(id))
+    }
+
+    fn pricing_provider() -> Option<&'static str> {
+        Some("bedrock")
+    }
+
     fn subscribe(_stream: &Self::ChatStream) -> golem_rust::wasm_rpc::Pollable {
-        // this function will never get called in bedrock implementation because of `golem-llm/nopoll` feature flag
+        // The stream has no pollable of its own, its `get_next` waits for the next event in the
+        // async runtime instead. It is only called when the stream is used by the router, the
+        // component itself is built with the `golem-llm/nopoll` feature flag.
         monotonic_clock::subscribe_duration(0)
     }
 }
@@ -132,6 +217,8 @@ async fn get_bedrock_client() -> Result<Bedrock, llm::Error> {
     Bedrock::new().await
 }
 
-type DurableBedrockComponent = DurableLLM<BedrockComponent>;
+#[cfg(not(feature = "library"))]
+type DurableBedrockComponent = golem_llm::durability::DurableLLM<BedrockComponent>;
 
+#[cfg(not(feature = "library"))]
 golem_llm::export_llm!(DurableBedrockComponent with_types_in golem_llm);
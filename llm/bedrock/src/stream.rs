@@ -2,8 +2,11 @@ use aws_sdk_bedrockruntime::{
     self as bedrock, primitives::event_stream::EventReceiver,
     types::error::ConverseStreamOutputError,
 };
-use golem_llm::golem::llm::llm;
-use std::cell::{RefCell, RefMut};
+use golem_llm::{chat_stream::cancelled_event, golem::llm::llm};
+use std::{
+    cell::{RefCell, RefMut},
+    collections::{HashMap, HashSet},
+};
 
 use crate::{
     async_utils,
@@ -17,6 +20,9 @@ pub struct BedrockChatStream {
     stream: RefCell<Option<BedrockEventSource>>,
     failure: Option<llm::Error>,
     finished: RefCell<bool>,
+    cancelled: RefCell<bool>,
+    response_format_blocks: RefCell<HashSet<i32>>,
+    tool_calls: RefCell<HashMap<i32, llm::ToolCall>>,
 }
 
 impl BedrockChatStream {
@@ -25,6 +31,9 @@ impl BedrockChatStream {
             stream: RefCell::new(Some(stream)),
             failure: None,
             finished: RefCell::new(false),
+            cancelled: RefCell::new(false),
+            response_format_blocks: RefCell::new(HashSet::new()),
+            tool_calls: RefCell::new(HashMap::new()),
         }
     }
 
@@ -33,6 +42,9 @@ impl BedrockChatStream {
             stream: RefCell::new(None),
             failure: Some(error),
             finished: RefCell::new(true),
+            cancelled: RefCell::new(false),
+            response_format_blocks: RefCell::new(HashSet::new()),
+            tool_calls: RefCell::new(HashMap::new()),
         }
     }
 
@@ -62,7 +74,11 @@ impl BedrockChatStream {
                 match token {
                     Ok(Some(output)) => {
                         log::trace!("Processing bedrock stream event: {output:?}");
-                        converse_stream_output_to_stream_event(output)
+                        converse_stream_output_to_stream_event(
+                            output,
+                            &mut self.response_format_blocks.borrow_mut(),
+                            &mut self.tool_calls.borrow_mut(),
+                        )
                     }
                     Ok(None) => {
                         log::trace!("running set_finished on stream due to None event received");
@@ -83,6 +99,7 @@ impl BedrockChatStream {
             self.set_finished();
             Some(llm::StreamEvent::Error(error.clone()))
         } else {
+            self.set_finished();
             None
         }
     }
@@ -90,20 +107,31 @@ impl BedrockChatStream {
 
 impl llm::GuestChatStream for BedrockChatStream {
     fn get_next(&self) -> Option<Vec<llm::StreamEvent>> {
-        if self.is_finished() {
-            return Some(vec![]);
+        if self.cancelled.replace(false) {
+            self.set_finished();
+            return Some(vec![cancelled_event()]);
         }
-        self.get_single_event().map(|event| {
-            if let llm::StreamEvent::Finish(metadata) = event.clone() {
-                if let Some(llm::StreamEvent::Finish(final_metadata)) = self.get_single_event() {
-                    return vec![llm::StreamEvent::Finish(merge_metadata(
-                        metadata,
-                        final_metadata,
-                    ))];
+
+        // The events are read in the async runtime, which waits for them, so instead of
+        // returning `None` for the events not emitted, like the tool call fragments, the
+        // stream reads on until it has something to emit
+        loop {
+            if self.is_finished() {
+                return Some(vec![]);
+            }
+            if let Some(event) = self.get_single_event() {
+                if let llm::StreamEvent::Finish(metadata) = event.clone() {
+                    if let Some(llm::StreamEvent::Finish(final_metadata)) = self.get_single_event()
+                    {
+                        return Some(vec![llm::StreamEvent::Finish(merge_metadata(
+                            metadata,
+                            final_metadata,
+                        ))]);
+                    }
                 }
+                return Some(vec![event]);
             }
-            vec![event]
-        })
+        }
     }
 
     fn blocking_get_next(&self) -> Vec<llm::StreamEvent> {
@@ -118,4 +146,14 @@ impl llm::GuestChatStream for BedrockChatStream {
             }
         }
     }
+
+    fn cancel(&self) {
+        if self.is_finished() {
+            return;
+        }
+
+        // Dropping the event receiver closes the underlying response body
+        drop(self.stream_mut().take());
+        *self.cancelled.borrow_mut() = true;
+    }
 }
@@ -0,0 +1,206 @@
+use data_encoding::BASE64URL_NOPAD;
+use golem_llm::error::{error_code_from_status, from_reqwest_error};
+use golem_llm::golem::llm::llm::{Error, ErrorCode};
+use golem_llm::retry::SendWithRetry;
+use log::{debug, trace};
+use rsa::pkcs1v15::Pkcs1v15Sign;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::RsaPrivateKey;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+/// SHA-256 DigestInfo prefix for PKCS#1 v1.5 signatures (RFC 8017)
+const SHA256_PREFIX: &[u8] = &[
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+/// Access tokens are refreshed this many seconds before they expire, so that they do not expire
+/// while a request is in flight
+const EXPIRY_MARGIN_SECS: u64 = 60;
+
+/// The lifetime of the access tokens when the token response does not include it
+const DEFAULT_EXPIRES_IN_SECS: u64 = 3600;
+
+/// An access token of a service account, valid until `expires_at` (seconds since the Unix epoch)
+struct AccessToken {
+    client_email: String,
+    scope: String,
+    token: String,
+    expires_at: u64,
+}
+
+thread_local! {
+    /// The last generated access token, reused by the requests until shortly before it expires
+    static ACCESS_TOKEN: RefCell<Option<AccessToken>> = const { RefCell::new(None) };
+}
+
+/// Returns a GCP access token for the service account, generating a new one when there is no
+/// cached token for it or the cached one is about to expire
+pub fn access_token(
+    client_email: &str,
+    private_key_pem: &str,
+    scope: &str,
+) -> Result<String, Error> {
+    let now = now()?;
+    let cached = ACCESS_TOKEN.with_borrow(|cached| {
+        cached
+            .as_ref()
+            .filter(|cached| {
+                cached.client_email == client_email
+                    && cached.scope == scope
+                    && now + EXPIRY_MARGIN_SECS < cached.expires_at
+            })
+            .map(|cached| cached.token.clone())
+    });
+    if let Some(token) = cached {
+        return Ok(token);
+    }
+
+    let (token, expires_in) = generate_access_token(client_email, private_key_pem, scope)?;
+    ACCESS_TOKEN.set(Some(AccessToken {
+        client_email: client_email.to_string(),
+        scope: scope.to_string(),
+        token: token.clone(),
+        expires_at: now + expires_in,
+    }));
+    Ok(token)
+}
+
+/// Generates a GCP access token using service account credentials, returning it with its
+/// lifetime in seconds
+fn generate_access_token(
+    client_email: &str,
+    private_key_pem: &str,
+    scope: &str,
+) -> Result<(String, u64), Error> {
+    trace!("Generating GCP access token for client: {client_email}");
+
+    let jwt = generate_jwt(client_email, private_key_pem, scope)?;
+    exchange_jwt_for_token(&jwt)
+}
+
+/// The current time in seconds since the Unix epoch
+fn now() -> Result<u64, Error> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| internal_error(format!("Failed to get current time: {e}")))?
+        .as_secs())
+}
+
+/// Generates a signed JWT for GCP authentication
+fn generate_jwt(client_email: &str, private_key_pem: &str, scope: &str) -> Result<String, Error> {
+    // Private keys copied from the service account's JSON key file contain literal \n sequences
+    let processed_key = private_key_pem.replace("\\n", "\n");
+
+    let private_key = RsaPrivateKey::from_pkcs8_pem(&processed_key)
+        .map_err(|e| authentication_error(format!("Failed to parse private key: {e}")))?;
+
+    let now = now()?;
+
+    let header = json!({
+        "alg": "RS256",
+        "typ": "JWT"
+    });
+
+    let payload = json!({
+        "iss": client_email,
+        "scope": scope,
+        "aud": TOKEN_URL,
+        "iat": now,
+        "exp": now + 120
+    });
+
+    let encoded_header = BASE64URL_NOPAD.encode(
+        &serde_json::to_vec(&header)
+            .map_err(|e| internal_error(format!("Failed to serialize header: {e}")))?,
+    );
+    let encoded_payload = BASE64URL_NOPAD.encode(
+        &serde_json::to_vec(&payload)
+            .map_err(|e| internal_error(format!("Failed to serialize payload: {e}")))?,
+    );
+
+    let signing_input = format!("{encoded_header}.{encoded_payload}");
+
+    let mut hasher = Sha256::new();
+    hasher.update(signing_input.as_bytes());
+    let hash = hasher.finalize();
+
+    // DigestInfo structure for PKCS#1 v1.5 (ASN.1 DER encoded)
+    let mut digest_info = Vec::new();
+    digest_info.extend_from_slice(SHA256_PREFIX);
+    digest_info.extend_from_slice(&hash);
+
+    let signature = private_key
+        .sign(Pkcs1v15Sign::new_unprefixed(), &digest_info)
+        .map_err(|e| internal_error(format!("Failed to sign JWT: {e}")))?;
+
+    let encoded_signature = BASE64URL_NOPAD.encode(&signature);
+
+    debug!("Generated JWT token for GCP authentication");
+    Ok(format!("{signing_input}.{encoded_signature}"))
+}
+
+/// Exchanges the signed JWT for a GCP access token and its lifetime in seconds
+fn exchange_jwt_for_token(jwt: &str) -> Result<(String, u64), Error> {
+    let client = reqwest::Client::builder()
+        .build()
+        .map_err(|e| from_reqwest_error("Failed to create HTTP client", e))?;
+
+    let body = format!("grant_type=urn:ietf:params:oauth:grant-type:jwt-bearer&assertion={jwt}");
+
+    let response = client
+        .post(TOKEN_URL)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(body)
+        .send_with_retry()
+        .map_err(|e| from_reqwest_error("Failed to request access token", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_body = response
+            .text()
+            .map_err(|e| from_reqwest_error("Failed to read error response", e))?;
+        return Err(Error {
+            code: error_code_from_status(status),
+            message: format!("Token exchange failed with status {status}"),
+            provider_error_json: Some(error_body),
+        });
+    }
+
+    let response_body: serde_json::Value = response
+        .json()
+        .map_err(|e| from_reqwest_error("Failed to parse token response", e))?;
+
+    let access_token = response_body
+        .get("access_token")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| internal_error("No access_token in token response"))?;
+    let expires_in = response_body
+        .get("expires_in")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_EXPIRES_IN_SECS);
+
+    debug!("Successfully obtained GCP access token, expiring in {expires_in} seconds");
+    Ok((access_token.to_string(), expires_in))
+}
+
+fn authentication_error(message: String) -> Error {
+    Error {
+        code: ErrorCode::AuthenticationFailed,
+        message,
+        provider_error_json: None,
+    }
+}
+
+fn internal_error(message: impl Into<String>) -> Error {
+    Error {
+        code: ErrorCode::InternalError,
+        message: message.into(),
+        provider_error_json: None,
+    }
+}
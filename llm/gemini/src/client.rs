@@ -0,0 +1,482 @@
+use crate::authentication::access_token;
+use golem_llm::error::{error_code_from_status, from_event_source_error, from_reqwest_error};
+use golem_llm::event_source::EventSource;
+use golem_llm::golem::llm::llm::Error;
+use golem_llm::retry::SendWithRetry;
+use log::trace;
+use reqwest::header::HeaderValue;
+use reqwest::{Client, Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+const AI_STUDIO_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const VERTEX_AI_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// The API serving the Gemini models
+pub enum Backend {
+    /// The Gemini Developer API of Google AI Studio, authenticated with an API key
+    AiStudio { api_key: String },
+    /// Vertex AI, authenticated with the credentials of a service account
+    VertexAi {
+        project_id: String,
+        location: String,
+        client_email: String,
+        private_key: String,
+    },
+}
+
+/// The Gemini API client for generating content, working with both Google AI Studio
+/// and Vertex AI.
+pub struct GeminiApi {
+    backend: Backend,
+    client: Client,
+}
+
+impl GeminiApi {
+    pub fn new(backend: Backend) -> Self {
+        let client = Client::builder()
+            .build()
+            .expect("Failed to initialize HTTP client");
+        Self { backend, client }
+    }
+
+    pub fn generate_content(
+        &self,
+        model: &str,
+        request: GenerateContentRequest,
+    ) -> Result<GenerateContentResponse, Error> {
+        trace!("Sending request to Gemini API: {request:?}");
+
+        let response: Response = self
+            .authenticated(
+                self.client
+                    .request(Method::POST, self.model_url(model, "generateContent")),
+            )?
+            .json(&request)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    pub fn stream_generate_content(
+        &self,
+        model: &str,
+        request: GenerateContentRequest,
+    ) -> Result<EventSource, Error> {
+        trace!("Sending streaming request to Gemini API: {request:?}");
+
+        let response: Response = self
+            .authenticated(self.client.request(
+                Method::POST,
+                format!("{}?alt=sse", self.model_url(model, "streamGenerateContent")),
+            ))?
+            .header(
+                reqwest::header::ACCEPT,
+                HeaderValue::from_static("text/event-stream"),
+            )
+            .json(&request)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        trace!("Initializing SSE stream");
+
+        EventSource::new(response)
+            .map_err(|err| from_event_source_error("Failed to create SSE stream", err))
+    }
+
+    pub fn count_tokens(
+        &self,
+        model: &str,
+        request: GenerateContentRequest,
+    ) -> Result<CountTokensResponse, Error> {
+        trace!("Sending token counting request to Gemini API: {request:?}");
+
+        // AI Studio only counts the system instruction and the tools when they are wrapped
+        // in a full generation request, while Vertex AI takes them directly
+        let body = match &self.backend {
+            Backend::AiStudio { .. } => serde_json::json!({
+                "generateContentRequest": {
+                    "model": format!("models/{}", model_id(model)),
+                    "contents": request.contents,
+                    "systemInstruction": request.system_instruction,
+                    "tools": request.tools,
+                }
+            }),
+            Backend::VertexAi { .. } => serde_json::json!({
+                "contents": request.contents,
+                "systemInstruction": request.system_instruction,
+                "tools": request.tools,
+            }),
+        };
+
+        let response: Response = self
+            .authenticated(
+                self.client
+                    .request(Method::POST, self.model_url(model, "countTokens")),
+            )?
+            .json(&body)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    /// Lists all the models available through Google AI Studio, following the pagination of the
+    /// models endpoint. Vertex AI has no API listing the Gemini models, so it returns `None`.
+    pub fn list_models(&self) -> Result<Option<Vec<Model>>, Error> {
+        if let Backend::VertexAi { .. } = self.backend {
+            return Ok(None);
+        }
+
+        trace!("Listing models from Gemini API");
+
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut query = vec![("pageSize", "1000".to_string())];
+            if let Some(page_token) = page_token.take() {
+                query.push(("pageToken", page_token));
+            }
+
+            let response: Response = self
+                .authenticated(
+                    self.client
+                        .request(Method::GET, format!("{AI_STUDIO_BASE_URL}/models")),
+                )?
+                .query(&query)
+                .send_with_retry()
+                .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+            let page: ListModelsResponse = parse_response(response)?;
+            models.extend(page.models);
+
+            match page.next_page_token {
+                Some(next_page_token) if !next_page_token.is_empty() => {
+                    page_token = Some(next_page_token)
+                }
+                _ => break Ok(Some(models)),
+            }
+        }
+    }
+
+    fn model_url(&self, model: &str, method: &str) -> String {
+        let model = model_id(model);
+        match &self.backend {
+            Backend::AiStudio { .. } => format!("{AI_STUDIO_BASE_URL}/models/{model}:{method}"),
+            Backend::VertexAi {
+                project_id,
+                location,
+                ..
+            } => {
+                let host = if location == "global" {
+                    "aiplatform.googleapis.com".to_string()
+                } else {
+                    format!("{location}-aiplatform.googleapis.com")
+                };
+                format!(
+                    "https://{host}/v1/projects/{project_id}/locations/{location}/publishers/google/models/{model}:{method}"
+                )
+            }
+        }
+    }
+
+    fn authenticated(&self, request: RequestBuilder) -> Result<RequestBuilder, Error> {
+        match &self.backend {
+            Backend::AiStudio { api_key } => Ok(request.header("x-goog-api-key", api_key)),
+            Backend::VertexAi {
+                client_email,
+                private_key,
+                ..
+            } => {
+                let token = access_token(client_email, private_key, VERTEX_AI_SCOPE)?;
+                Ok(request.bearer_auth(token))
+            }
+        }
+    }
+}
+
+/// The model identifier without the `models/` prefix used by the model listing of AI Studio
+fn model_id(model: &str) -> &str {
+    model.strip_prefix("models/").unwrap_or(model)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateContentRequest {
+    pub contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_instruction: Option<Content>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Tool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_config: Option<ToolConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub safety_settings: Vec<SafetySetting>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation_config: Option<GenerationConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Content {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub parts: Vec<Part>,
+}
+
+/// A part of the content, where exactly one of the data fields is set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Part {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inline_data: Option<Blob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_data: Option<FileData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<FunctionCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function_response: Option<FunctionResponse>,
+    /// Marks the text as a summary of the model's thoughts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thought: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thought_signature: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Blob {
+    pub mime_type: String,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileData {
+    pub mime_type: String,
+    pub file_uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    pub response: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tool {
+    pub function_declarations: Vec<FunctionDeclaration>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolConfig {
+    pub function_calling_config: FunctionCallingConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionCallingConfig {
+    pub mode: FunctionCallingMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_function_names: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FunctionCallingMode {
+    Auto,
+    Any,
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_config: Option<ThinkingConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThinkingConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_thoughts: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateContentResponse {
+    #[serde(default)]
+    pub candidates: Vec<Candidate>,
+    #[serde(default)]
+    pub prompt_feedback: Option<PromptFeedback>,
+    #[serde(default)]
+    pub usage_metadata: Option<UsageMetadata>,
+    #[serde(default)]
+    pub model_version: Option<String>,
+    #[serde(default)]
+    pub response_id: Option<String>,
+    #[serde(default)]
+    pub create_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    #[serde(default)]
+    pub content: Option<Content>,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+    #[serde(default)]
+    pub safety_ratings: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptFeedback {
+    #[serde(default)]
+    pub block_reason: Option<String>,
+    #[serde(default)]
+    pub safety_ratings: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageMetadata {
+    #[serde(default)]
+    pub prompt_token_count: Option<u32>,
+    #[serde(default)]
+    pub candidates_token_count: Option<u32>,
+    #[serde(default)]
+    pub total_token_count: Option<u32>,
+    #[serde(default)]
+    pub cached_content_token_count: Option<u32>,
+    #[serde(default)]
+    pub thoughts_token_count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountTokensResponse {
+    pub total_tokens: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListModelsResponse {
+    #[serde(default)]
+    pub models: Vec<Model>,
+    #[serde(default)]
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub name: String,
+    #[serde(default)]
+    pub input_token_limit: Option<u32>,
+    #[serde(default)]
+    pub output_token_limit: Option<u32>,
+    #[serde(default)]
+    pub supported_generation_methods: Vec<String>,
+    #[serde(default)]
+    pub thinking: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorBody {
+    #[serde(default)]
+    pub code: Option<u16>,
+    pub message: String,
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+fn parse_response<T: DeserializeOwned + Debug>(response: Response) -> Result<T, Error> {
+    let status = response.status();
+    if status.is_success() {
+        let body = response
+            .json::<T>()
+            .map_err(|err| from_reqwest_error("Failed to decode response body", err))?;
+
+        trace!("Received response from Gemini API: {body:?}");
+
+        Ok(body)
+    } else {
+        let error_body = response
+            .text()
+            .map_err(|err| from_reqwest_error("Failed to receive error response body", err))?;
+
+        trace!("Received {status} response from Gemini API: {error_body:?}");
+
+        let message = serde_json::from_str::<ErrorResponse>(&error_body)
+            .map(|error| format!("Request failed with {status}: {}", error.error.message))
+            .unwrap_or_else(|_| format!("Request failed with {status}"));
+
+        Err(Error {
+            code: error_code_from_status(status),
+            message,
+            provider_error_json: Some(error_body),
+        })
+    }
+}
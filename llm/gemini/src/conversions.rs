@@ -0,0 +1,603 @@
+use crate::client::{
+    Blob, Content, FileData, FunctionCall, FunctionCallingConfig, FunctionCallingMode,
+    FunctionDeclaration, FunctionResponse, GenerateContentRequest, GenerateContentResponse,
+    GenerationConfig, Model, Part, SafetySetting, ThinkingConfig, Tool, ToolConfig, UsageMetadata,
+};
+use base64::{engine::general_purpose, Engine as _};
+use golem_llm::builtin_tools::reject_builtin_tools;
+use golem_llm::golem::llm::llm::{
+    ChatEvent, CompleteResponse, Config, ContentPart, DocumentData, Error, ErrorCode, FinishReason,
+    ImageReference, ImageSource, Message, ModelCapabilities, ModelInfo, ReasoningConfig,
+    ReasoningContent, ReasoningEffort, ResponseFormat, ResponseMetadata, Role, ToolCall,
+    ToolDefinition, ToolResult, Usage,
+};
+use golem_llm::retry::SendWithRetry;
+use golem_llm::sampling::{signed_seed, validate_sampling_parameters, SamplingParameter};
+use reqwest::header::CONTENT_TYPE;
+use std::collections::HashMap;
+
+const SAMPLING_PARAMETERS: &[SamplingParameter] = &[
+    SamplingParameter::TopP,
+    SamplingParameter::TopK,
+    SamplingParameter::Seed,
+    SamplingParameter::FrequencyPenalty,
+    SamplingParameter::PresencePenalty,
+];
+
+/// The prefix of the urls of the files uploaded to the Files API
+const FILES_API_URL: &str = "https://generativelanguage.googleapis.com/";
+
+/// The harm categories the `safety_threshold` provider option applies to
+const HARM_CATEGORIES: &[&str] = &[
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+
+pub fn messages_to_request(
+    messages: Vec<Message>,
+    mut config: Config,
+) -> Result<GenerateContentRequest, Error> {
+    validate_sampling_parameters(&mut config, SAMPLING_PARAMETERS)?;
+    reject_builtin_tools(&config)?;
+
+    let options = config
+        .provider_options
+        .into_iter()
+        .map(|kv| (kv.key, kv.value))
+        .collect::<HashMap<_, _>>();
+
+    let mut contents = Vec::new();
+    let mut system_parts = Vec::new();
+    for message in messages {
+        match message.role {
+            Role::System => system_parts.extend(convert_content_parts(message.content)?),
+            Role::Assistant => contents.push(Content {
+                role: Some("model".to_string()),
+                parts: convert_content_parts(message.content)?,
+            }),
+            Role::User | Role::Tool => contents.push(Content {
+                role: Some("user".to_string()),
+                parts: convert_content_parts(message.content)?,
+            }),
+        }
+    }
+
+    let mut function_declarations = Vec::new();
+    for tool in config.tools {
+        function_declarations.push(tool_definition_to_function_declaration(tool)?);
+    }
+    let tools = if function_declarations.is_empty() {
+        Vec::new()
+    } else {
+        vec![Tool {
+            function_declarations,
+        }]
+    };
+
+    let (response_mime_type, response_schema) = match config.response_format {
+        Some(response_format) => convert_response_format(response_format)?,
+        None => (None, None),
+    };
+
+    Ok(GenerateContentRequest {
+        contents,
+        system_instruction: if system_parts.is_empty() {
+            None
+        } else {
+            Some(Content {
+                role: None,
+                parts: system_parts,
+            })
+        },
+        tools,
+        tool_config: config.tool_choice.map(convert_tool_choice),
+        safety_settings: safety_settings(&options)?,
+        generation_config: Some(GenerationConfig {
+            stop_sequences: config.stop_sequences,
+            response_mime_type,
+            response_schema,
+            max_output_tokens: config.max_tokens,
+            temperature: config.temperature,
+            top_p: config.top_p,
+            top_k: config.top_k,
+            seed: config.seed.map(signed_seed),
+            presence_penalty: config.presence_penalty,
+            frequency_penalty: config.frequency_penalty,
+            thinking_config: config.reasoning.map(reasoning_to_thinking_config),
+        }),
+    })
+}
+
+/// Appends the tool calls of the model and their results to the request's contents
+pub fn append_tool_results(contents: &mut Vec<Content>, tool_results: Vec<(ToolCall, ToolResult)>) {
+    if tool_results.is_empty() {
+        return;
+    }
+
+    let mut function_calls = Vec::new();
+    let mut function_responses = Vec::new();
+    for (tool_call, tool_result) in tool_results {
+        function_calls.push(Part {
+            function_call: Some(FunctionCall {
+                id: None,
+                name: tool_call.name.clone(),
+                args: serde_json::from_str(&tool_call.arguments_json)
+                    .unwrap_or_else(|_| serde_json::json!({})),
+            }),
+            ..Default::default()
+        });
+
+        // The response has to be a JSON object
+        let response = match tool_result {
+            ToolResult::Success(success) => {
+                match serde_json::from_str::<serde_json::Value>(&success.result_json) {
+                    Ok(value @ serde_json::Value::Object(_)) => value,
+                    Ok(value) => serde_json::json!({ "result": value }),
+                    Err(_) => serde_json::json!({ "result": success.result_json }),
+                }
+            }
+            ToolResult::Error(failure) => serde_json::json!({ "error": failure.error_message }),
+        };
+        function_responses.push(Part {
+            function_response: Some(FunctionResponse {
+                id: None,
+                name: tool_call.name,
+                response,
+            }),
+            ..Default::default()
+        });
+    }
+
+    contents.push(Content {
+        role: Some("model".to_string()),
+        parts: function_calls,
+    });
+    contents.push(Content {
+        role: Some("user".to_string()),
+        parts: function_responses,
+    });
+}
+
+pub fn process_response(response: GenerateContentResponse) -> ChatEvent {
+    let id = response.response_id.clone().unwrap_or_default();
+
+    let Some(candidate) = response.candidates.first() else {
+        return if response
+            .prompt_feedback
+            .as_ref()
+            .is_some_and(|feedback| feedback.block_reason.is_some())
+        {
+            // The prompt itself was blocked by the safety filters
+            ChatEvent::Message(CompleteResponse {
+                id,
+                content: vec![],
+                tool_calls: vec![],
+                metadata: response_metadata(&response, Some(FinishReason::ContentFilter)),
+                logprobs: None,
+                alternatives: vec![],
+                builtin_tool_results: vec![],
+            })
+        } else {
+            ChatEvent::Error(Error {
+                code: ErrorCode::InternalError,
+                message: "No candidates in response".to_string(),
+                provider_error_json: None,
+            })
+        };
+    };
+
+    let parts = candidate
+        .content
+        .as_ref()
+        .map(|content| content.parts.clone())
+        .unwrap_or_default();
+    let (contents, tool_calls) = convert_parts(parts, &mut 0);
+
+    let finish_reason = if tool_calls.is_empty() {
+        candidate
+            .finish_reason
+            .as_deref()
+            .map(convert_finish_reason)
+    } else {
+        Some(FinishReason::ToolCalls)
+    };
+
+    if contents.is_empty() && !tool_calls.is_empty() {
+        ChatEvent::ToolRequest(tool_calls)
+    } else {
+        ChatEvent::Message(CompleteResponse {
+            id,
+            content: contents,
+            tool_calls,
+            metadata: response_metadata(&response, finish_reason),
+            logprobs: None,
+            alternatives: vec![],
+            builtin_tool_results: vec![],
+        })
+    }
+}
+
+/// Converts the parts of a candidate to content parts and tool calls. Gemini does not always
+/// identify the function calls, so they get an identifier based on `tool_call_count`, the number
+/// of tool calls already seen in the response.
+pub fn convert_parts(
+    parts: Vec<Part>,
+    tool_call_count: &mut usize,
+) -> (Vec<ContentPart>, Vec<ToolCall>) {
+    let mut contents = Vec::new();
+    let mut tool_calls = Vec::new();
+
+    for part in parts {
+        if let Some(function_call) = part.function_call {
+            *tool_call_count += 1;
+            tool_calls.push(ToolCall {
+                id: function_call
+                    .id
+                    .unwrap_or_else(|| format!("call-{tool_call_count}-{}", function_call.name)),
+                name: function_call.name,
+                arguments_json: function_call.args.to_string(),
+                validation_error: None,
+            });
+        } else if let Some(text) = part.text {
+            if part.thought == Some(true) {
+                contents.push(ContentPart::Reasoning(ReasoningContent {
+                    text,
+                    signature: part.thought_signature,
+                    redacted_data: None,
+                }));
+            } else if !text.is_empty() {
+                contents.push(ContentPart::Text(text));
+            }
+        } else if let Some(blob) = part.inline_data {
+            if let Ok(data) = general_purpose::STANDARD.decode(blob.data) {
+                if blob.mime_type.starts_with("image/") {
+                    contents.push(ContentPart::Image(ImageReference::Inline(ImageSource {
+                        data,
+                        mime_type: blob.mime_type,
+                        detail: None,
+                    })));
+                }
+            }
+        }
+    }
+
+    (contents, tool_calls)
+}
+
+pub fn response_metadata(
+    response: &GenerateContentResponse,
+    finish_reason: Option<FinishReason>,
+) -> ResponseMetadata {
+    ResponseMetadata {
+        finish_reason,
+        usage: response.usage_metadata.as_ref().map(convert_usage),
+        provider_id: response.response_id.clone(),
+        timestamp: response.create_time.clone(),
+        provider_metadata_json: Some(provider_metadata_json(response)),
+        rate_limit: None,
+        cost: None,
+    }
+}
+
+/// The model version and the safety feedback of the response
+pub fn provider_metadata_json(response: &GenerateContentResponse) -> String {
+    let candidate = response.candidates.first();
+    serde_json::json!({
+        "model_version": response.model_version,
+        "safety_ratings": candidate.and_then(|candidate| candidate.safety_ratings.clone()),
+        "block_reason": response
+            .prompt_feedback
+            .as_ref()
+            .and_then(|feedback| feedback.block_reason.clone()),
+    })
+    .to_string()
+}
+
+/// Converts Gemini's finish reason, where all the reasons for blocking the response map to
+/// `content-filter`
+pub fn convert_finish_reason(value: &str) -> FinishReason {
+    match value {
+        "STOP" => FinishReason::Stop,
+        "MAX_TOKENS" => FinishReason::Length,
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" | "IMAGE_SAFETY" => {
+            FinishReason::ContentFilter
+        }
+        "MALFORMED_FUNCTION_CALL" | "UNEXPECTED_TOOL_CALL" => FinishReason::Error,
+        _ => FinishReason::Other,
+    }
+}
+
+pub fn convert_usage(value: &UsageMetadata) -> Usage {
+    Usage {
+        input_tokens: value.prompt_token_count,
+        // The thinking tokens are billed as output tokens
+        output_tokens: match (value.candidates_token_count, value.thoughts_token_count) {
+            (None, None) => None,
+            (candidates, thoughts) => Some(candidates.unwrap_or(0) + thoughts.unwrap_or(0)),
+        },
+        total_tokens: value.total_token_count,
+        cache_read_tokens: value.cached_content_token_count,
+        cache_write_tokens: None,
+        reasoning_tokens: value.thoughts_token_count,
+    }
+}
+
+fn convert_content_parts(contents: Vec<ContentPart>) -> Result<Vec<Part>, Error> {
+    let mut result = Vec::new();
+    for content in contents {
+        match content {
+            ContentPart::Text(text) => result.push(Part {
+                text: Some(text),
+                ..Default::default()
+            }),
+            ContentPart::Image(image_reference) => match image_reference {
+                ImageReference::Url(image_url) if is_file_uri(&image_url.url) => {
+                    result.push(Part {
+                        file_data: Some(FileData {
+                            mime_type: mime_guess::from_path(&image_url.url)
+                                .first_raw()
+                                .unwrap_or("image/jpeg")
+                                .to_string(),
+                            file_uri: image_url.url,
+                        }),
+                        ..Default::default()
+                    })
+                }
+                ImageReference::Url(image_url) => result.push(Part {
+                    inline_data: Some(download_image(&image_url.url)?),
+                    ..Default::default()
+                }),
+                ImageReference::Inline(image_source) => result.push(Part {
+                    inline_data: Some(Blob {
+                        mime_type: image_source.mime_type,
+                        data: general_purpose::STANDARD.encode(&image_source.data),
+                    }),
+                    ..Default::default()
+                }),
+            },
+            ContentPart::Document(document) => match document.data {
+                DocumentData::Url(url) => result.push(Part {
+                    file_data: Some(FileData {
+                        mime_type: document.mime_type,
+                        file_uri: url,
+                    }),
+                    ..Default::default()
+                }),
+                DocumentData::Inline(data) => result.push(Part {
+                    inline_data: Some(Blob {
+                        mime_type: document.mime_type,
+                        data: general_purpose::STANDARD.encode(&data),
+                    }),
+                    ..Default::default()
+                }),
+            },
+            ContentPart::Audio(audio) => result.push(Part {
+                inline_data: Some(Blob {
+                    mime_type: audio.mime_type,
+                    data: general_purpose::STANDARD.encode(&audio.data),
+                }),
+                ..Default::default()
+            }),
+            // Thought summaries are not used as input
+            ContentPart::Reasoning(_) => {}
+        }
+    }
+    Ok(result)
+}
+
+/// Whether the url can be referenced as file data: Cloud Storage objects and files uploaded to
+/// the Files API. The other urls are not fetched by Gemini, so their images are sent inline.
+fn is_file_uri(url: &str) -> bool {
+    url.starts_with("gs://") || url.starts_with(FILES_API_URL)
+}
+
+/// Downloads the image at the url, taking its mime type from the response or the url's extension
+fn download_image(url: &str) -> Result<Blob, Error> {
+    let invalid_url = |cause: String| Error {
+        code: ErrorCode::InvalidRequest,
+        message: format!("Could not download the image from url: {url}, cause: {cause}"),
+        provider_error_json: None,
+    };
+
+    let response = reqwest::Client::new()
+        .get(url)
+        .send_with_retry()
+        .map_err(|e| invalid_url(e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(invalid_url(format!("request failed with status: {status}")));
+    }
+
+    let mime_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or(value).trim().to_string())
+        .filter(|mime_type| mime_type.starts_with("image/"))
+        .or_else(|| mime_guess::from_path(url).first_raw().map(str::to_string))
+        .ok_or_else(|| invalid_url("the mime type of the image is unknown".to_string()))?;
+    let bytes = response.bytes().map_err(|e| invalid_url(e.to_string()))?;
+
+    Ok(Blob {
+        mime_type,
+        data: general_purpose::STANDARD.encode(&bytes),
+    })
+}
+
+fn tool_definition_to_function_declaration(
+    tool: ToolDefinition,
+) -> Result<FunctionDeclaration, Error> {
+    match serde_json::from_str(&tool.parameters_schema) {
+        Ok(value) => Ok(FunctionDeclaration {
+            name: tool.name,
+            description: tool.description,
+            parameters: Some(to_gemini_schema(value)),
+        }),
+        Err(error) => Err(Error {
+            code: ErrorCode::InternalError,
+            message: format!("Failed to parse tool parameters for {}: {error}", tool.name),
+            provider_error_json: None,
+        }),
+    }
+}
+
+/// Removes the JSON Schema keywords which are not part of the OpenAPI schema subset accepted by
+/// Gemini for function parameters and response schemas
+fn to_gemini_schema(schema: serde_json::Value) -> serde_json::Value {
+    match schema {
+        serde_json::Value::Object(object) => serde_json::Value::Object(
+            object
+                .into_iter()
+                .filter(|(key, _)| {
+                    !matches!(key.as_str(), "$schema" | "$id" | "additionalProperties")
+                })
+                .map(|(key, value)| (key, to_gemini_schema(value)))
+                .collect(),
+        ),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(to_gemini_schema).collect())
+        }
+        value => value,
+    }
+}
+
+fn convert_tool_choice(tool_choice: String) -> ToolConfig {
+    let (mode, allowed_function_names) = match tool_choice.as_str() {
+        "auto" => (FunctionCallingMode::Auto, None),
+        "none" => (FunctionCallingMode::None, None),
+        "any" | "required" => (FunctionCallingMode::Any, None),
+        _ => (FunctionCallingMode::Any, Some(vec![tool_choice])),
+    };
+    ToolConfig {
+        function_calling_config: FunctionCallingConfig {
+            mode,
+            allowed_function_names,
+        },
+    }
+}
+
+fn convert_response_format(
+    response_format: ResponseFormat,
+) -> Result<(Option<String>, Option<serde_json::Value>), Error> {
+    match response_format {
+        ResponseFormat::Text => Ok((None, None)),
+        ResponseFormat::JsonObject => Ok((Some("application/json".to_string()), None)),
+        ResponseFormat::JsonSchema(json_schema) => {
+            match serde_json::from_str(&json_schema.schema) {
+                Ok(schema) => Ok((
+                    Some("application/json".to_string()),
+                    Some(to_gemini_schema(schema)),
+                )),
+                Err(error) => Err(Error {
+                    code: ErrorCode::InvalidRequest,
+                    message: format!(
+                        "Failed to parse response format schema {}: {error}",
+                        json_schema.name
+                    ),
+                    provider_error_json: None,
+                }),
+            }
+        }
+    }
+}
+
+fn reasoning_to_thinking_config(reasoning: ReasoningConfig) -> ThinkingConfig {
+    let thinking_budget = reasoning.budget_tokens.or(match reasoning.effort {
+        Some(ReasoningEffort::Low) => Some(1024),
+        Some(ReasoningEffort::Medium) => Some(8192),
+        Some(ReasoningEffort::High) => Some(24576),
+        None => None,
+    });
+    ThinkingConfig {
+        include_thoughts: Some(true),
+        thinking_budget,
+    }
+}
+
+/// Builds the safety settings from the `safety_threshold` provider option, applying a threshold
+/// such as `BLOCK_ONLY_HIGH` to all the harm categories, and the `safety_settings` provider option,
+/// a JSON object of thresholds by harm category overriding it
+fn safety_settings(options: &HashMap<String, String>) -> Result<Vec<SafetySetting>, Error> {
+    let mut thresholds = HashMap::new();
+    if let Some(threshold) = options.get("safety_threshold") {
+        for category in HARM_CATEGORIES {
+            thresholds.insert(category.to_string(), threshold.clone());
+        }
+    }
+    if let Some(settings) = options.get("safety_settings") {
+        let settings: HashMap<String, String> =
+            serde_json::from_str(settings).map_err(|error| Error {
+                code: ErrorCode::InvalidRequest,
+                message: format!("Failed to parse the safety_settings provider option: {error}"),
+                provider_error_json: None,
+            })?;
+        thresholds.extend(settings);
+    }
+
+    let mut safety_settings = thresholds
+        .into_iter()
+        .map(|(category, threshold)| SafetySetting {
+            category,
+            threshold,
+        })
+        .collect::<Vec<_>>();
+    safety_settings.sort_by(|a, b| a.category.cmp(&b.category));
+    Ok(safety_settings)
+}
+
+pub fn models_to_model_infos(models: Vec<Model>) -> Vec<ModelInfo> {
+    models
+        .into_iter()
+        .filter(|model| {
+            model
+                .supported_generation_methods
+                .iter()
+                .any(|method| method == "generateContent")
+        })
+        .map(|model| {
+            let id = model
+                .name
+                .strip_prefix("models/")
+                .unwrap_or(&model.name)
+                .to_string();
+            let capabilities = model_capabilities(&id, model.thinking);
+            ModelInfo {
+                id,
+                context_window: model.input_token_limit,
+                max_output_tokens: model.output_token_limit,
+                capabilities,
+            }
+        })
+        .collect()
+}
+
+/// The Gemini models available on Vertex AI, which has no API for listing them
+pub fn known_model_infos() -> Vec<ModelInfo> {
+    [
+        ("gemini-2.5-pro", 65_536),
+        ("gemini-2.5-flash", 65_536),
+        ("gemini-2.5-flash-lite", 65_536),
+        ("gemini-2.0-flash", 8_192),
+        ("gemini-2.0-flash-lite", 8_192),
+    ]
+    .into_iter()
+    .map(|(id, max_output_tokens)| ModelInfo {
+        id: id.to_string(),
+        context_window: Some(1_048_576),
+        max_output_tokens: Some(max_output_tokens),
+        capabilities: model_capabilities(id, None),
+    })
+    .collect()
+}
+
+fn model_capabilities(id: &str, thinking: Option<bool>) -> ModelCapabilities {
+    let gemini = id.starts_with("gemini-");
+    ModelCapabilities {
+        tools: gemini,
+        vision: gemini,
+        reasoning: thinking.unwrap_or(id.starts_with("gemini-2.5")),
+        json_schema: gemini,
+    }
+}
@@ -0,0 +1,325 @@
+mod authentication;
+mod client;
+mod conversions;
+
+use crate::client::{
+    Backend, ErrorResponse, GeminiApi, GenerateContentRequest, GenerateContentResponse,
+};
+use crate::conversions::{
+    append_tool_results, convert_finish_reason, convert_parts, convert_usage, known_model_infos,
+    messages_to_request, models_to_model_infos, process_response, provider_metadata_json,
+};
+use golem_llm::batch::UnsupportedChatBatch;
+use golem_llm::chat_job::EmulatedChatJob;
+use golem_llm::chat_stream::{LlmChatStream, LlmChatStreamState};
+use golem_llm::config::{get_config_key, get_config_key_or_none};
+use golem_llm::conversation::LlmConversation;
+use golem_llm::durability::ExtendedGuest;
+use golem_llm::event_source::EventSource;
+use golem_llm::golem::llm::llm::{
+    ChatBatch, ChatEvent, ChatJob, ChatStream, Config, Error, ErrorCode, FinishReason, Guest,
+    Message, ModelInfo, ResponseMetadata, StreamDelta, StreamEvent, TokenCount, ToolCall,
+    ToolResult,
+};
+use golem_rust::wasm_rpc::Pollable;
+use log::trace;
+use std::cell::{Cell, Ref, RefCell, RefMut};
+
+pub struct GeminiChatStream {
+    stream: RefCell<Option<EventSource>>,
+    failure: Option<Error>,
+    finished: RefCell<bool>,
+    tool_call_count: Cell<usize>,
+    response_metadata: RefCell<ResponseMetadata>,
+}
+
+impl GeminiChatStream {
+    pub fn new(stream: EventSource) -> LlmChatStream<Self> {
+        LlmChatStream::new(GeminiChatStream {
+            stream: RefCell::new(Some(stream)),
+            failure: None,
+            finished: RefCell::new(false),
+            tool_call_count: Cell::new(0),
+            response_metadata: RefCell::new(ResponseMetadata {
+                finish_reason: None,
+                usage: None,
+                provider_id: None,
+                timestamp: None,
+                provider_metadata_json: None,
+                rate_limit: None,
+                cost: None,
+            }),
+        })
+    }
+
+    pub fn failed(error: Error) -> LlmChatStream<Self> {
+        LlmChatStream::new(GeminiChatStream {
+            stream: RefCell::new(None),
+            failure: Some(error),
+            finished: RefCell::new(false),
+            tool_call_count: Cell::new(0),
+            response_metadata: RefCell::new(ResponseMetadata {
+                finish_reason: None,
+                usage: None,
+                provider_id: None,
+                timestamp: None,
+                provider_metadata_json: None,
+                rate_limit: None,
+                cost: None,
+            }),
+        })
+    }
+}
+
+impl LlmChatStreamState for GeminiChatStream {
+    fn failure(&self) -> &Option<Error> {
+        &self.failure
+    }
+
+    fn is_finished(&self) -> bool {
+        *self.finished.borrow()
+    }
+
+    fn set_finished(&self) {
+        *self.finished.borrow_mut() = true;
+    }
+
+    fn stream(&self) -> Ref<'_, Option<EventSource>> {
+        self.stream.borrow()
+    }
+
+    fn stream_mut(&self) -> RefMut<'_, Option<EventSource>> {
+        self.stream.borrow_mut()
+    }
+
+    fn decode_message(&self, raw: &str) -> Result<Option<StreamEvent>, String> {
+        trace!("Received raw stream event: {raw}");
+        let json: serde_json::Value = serde_json::from_str(raw)
+            .map_err(|err| format!("Failed to deserialize stream event: {err}"))?;
+
+        if json.get("error").is_some() {
+            let error: ErrorResponse = serde_json::from_value(json)
+                .map_err(|err| format!("Failed to parse stream error: {err}"))?;
+            return Ok(Some(StreamEvent::Error(Error {
+                code: ErrorCode::InternalError,
+                message: error.error.message,
+                provider_error_json: Some(raw.to_string()),
+            })));
+        }
+
+        let chunk: GenerateContentResponse = serde_json::from_value(json)
+            .map_err(|err| format!("Failed to parse stream event: {err}"))?;
+
+        // Every chunk is a partial response, and the metadata of the last one is reported
+        // when the stream ends
+        let mut response_metadata = self.response_metadata.borrow_mut();
+        if let Some(usage) = &chunk.usage_metadata {
+            response_metadata.usage = Some(convert_usage(usage));
+        }
+        if chunk.response_id.is_some() {
+            response_metadata.provider_id = chunk.response_id.clone();
+        }
+        if chunk.create_time.is_some() {
+            response_metadata.timestamp = chunk.create_time.clone();
+        }
+        response_metadata.provider_metadata_json = Some(provider_metadata_json(&chunk));
+        if chunk
+            .prompt_feedback
+            .as_ref()
+            .is_some_and(|feedback| feedback.block_reason.is_some())
+        {
+            response_metadata.finish_reason = Some(FinishReason::ContentFilter);
+        }
+
+        let Some(candidate) = chunk.candidates.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let mut tool_call_count = self.tool_call_count.get();
+        let (content, tool_calls) = convert_parts(
+            candidate
+                .content
+                .map(|content| content.parts)
+                .unwrap_or_default(),
+            &mut tool_call_count,
+        );
+        self.tool_call_count.set(tool_call_count);
+
+        if let Some(finish_reason) = candidate.finish_reason {
+            response_metadata.finish_reason = if tool_call_count > 0 {
+                Some(FinishReason::ToolCalls)
+            } else {
+                Some(convert_finish_reason(&finish_reason))
+            };
+        }
+
+        if content.is_empty() && tool_calls.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(StreamEvent::Delta(StreamDelta {
+                content: if content.is_empty() {
+                    None
+                } else {
+                    Some(content)
+                },
+                tool_calls: if tool_calls.is_empty() {
+                    None
+                } else {
+                    Some(tool_calls)
+                },
+                logprobs: None,
+            })))
+        }
+    }
+
+    /// Gemini sends the finish reason together with the last part of the content, and
+    /// ends the stream without a separate finishing event
+    fn end_of_stream(&self) -> Option<StreamEvent> {
+        Some(StreamEvent::Finish(self.response_metadata.borrow().clone()))
+    }
+}
+
+pub struct GeminiComponent;
+
+impl GeminiComponent {
+    const API_KEY_ENV_VAR_NAME: &'static str = "GEMINI_API_KEY";
+    const PROJECT_ID_ENV_VAR_NAME: &'static str = "VERTEX_AI_PROJECT_ID";
+    const LOCATION_ENV_VAR_NAME: &'static str = "VERTEX_AI_LOCATION";
+    const CLIENT_EMAIL_ENV_VAR_NAME: &'static str = "VERTEX_AI_CLIENT_EMAIL";
+    const PRIVATE_KEY_ENV_VAR_NAME: &'static str = "VERTEX_AI_PRIVATE_KEY";
+    const DEFAULT_LOCATION: &'static str = "us-central1";
+
+    /// Creates the client for Google AI Studio if the Gemini API key is set, and for
+    /// Vertex AI with service account credentials otherwise
+    fn client() -> Result<GeminiApi, Error> {
+        let backend = match get_config_key_or_none(Self::API_KEY_ENV_VAR_NAME) {
+            Some(api_key) => Backend::AiStudio { api_key },
+            None => Backend::VertexAi {
+                project_id: get_config_key(Self::PROJECT_ID_ENV_VAR_NAME)?,
+                location: get_config_key_or_none(Self::LOCATION_ENV_VAR_NAME)
+                    .unwrap_or_else(|| Self::DEFAULT_LOCATION.to_string()),
+                client_email: get_config_key(Self::CLIENT_EMAIL_ENV_VAR_NAME)?,
+                private_key: get_config_key(Self::PRIVATE_KEY_ENV_VAR_NAME)?,
+            },
+        };
+        Ok(GeminiApi::new(backend))
+    }
+
+    fn request(client: GeminiApi, model: &str, request: GenerateContentRequest) -> ChatEvent {
+        match client.generate_content(model, request) {
+            Ok(response) => process_response(response),
+            Err(err) => ChatEvent::Error(err),
+        }
+    }
+
+    fn streaming_request(
+        client: GeminiApi,
+        model: &str,
+        request: GenerateContentRequest,
+    ) -> LlmChatStream<GeminiChatStream> {
+        match client.stream_generate_content(model, request) {
+            Ok(stream) => GeminiChatStream::new(stream),
+            Err(err) => GeminiChatStream::failed(err),
+        }
+    }
+}
+
+impl Guest for GeminiComponent {
+    type ChatStream = LlmChatStream<GeminiChatStream>;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = UnsupportedChatBatch;
+
+    fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
+        let client = match Self::client() {
+            Ok(client) => client,
+            Err(err) => return ChatEvent::Error(err),
+        };
+
+        let model = config.model.clone();
+        match messages_to_request(messages, config) {
+            Ok(request) => Self::request(client, &model, request),
+            Err(err) => ChatEvent::Error(err),
+        }
+    }
+
+    fn continue_(
+        messages: Vec<Message>,
+        tool_results: Vec<(ToolCall, ToolResult)>,
+        config: Config,
+    ) -> ChatEvent {
+        let client = match Self::client() {
+            Ok(client) => client,
+            Err(err) => return ChatEvent::Error(err),
+        };
+
+        let model = config.model.clone();
+        match messages_to_request(messages, config) {
+            Ok(mut request) => {
+                append_tool_results(&mut request.contents, tool_results);
+                Self::request(client, &model, request)
+            }
+            Err(err) => ChatEvent::Error(err),
+        }
+    }
+
+    fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
+        ChatStream::new(Self::unwrapped_stream(messages, config))
+    }
+
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        Self::client()?.list_models().map(|models| match models {
+            Some(models) => models_to_model_infos(models),
+            None => known_model_infos(),
+        })
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        let client = Self::client()?;
+        let model = config.model.clone();
+        let request = messages_to_request(messages, config)?;
+        client
+            .count_tokens(&model, request)
+            .map(|response| TokenCount {
+                input_tokens: response.total_tokens,
+                estimated: false,
+            })
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
+}
+
+impl ExtendedGuest for GeminiComponent {
+    fn unwrapped_stream(messages: Vec<Message>, config: Config) -> LlmChatStream<GeminiChatStream> {
+        let client = match Self::client() {
+            Ok(client) => client,
+            Err(err) => return GeminiChatStream::failed(err),
+        };
+
+        let model = config.model.clone();
+        match messages_to_request(messages, config) {
+            Ok(request) => Self::streaming_request(client, &model, request),
+            Err(err) => GeminiChatStream::failed(err),
+        }
+    }
+
+    fn pricing_provider() -> Option<&'static str> {
+        Some("gemini")
+    }
+
+    fn subscribe(stream: &Self::ChatStream) -> Pollable {
+        stream.subscribe()
+    }
+}
+
+#[cfg(not(feature = "library"))]
+type DurableGeminiComponent = golem_llm::durability::DurableLLM<GeminiComponent>;
+
+#[cfg(not(feature = "library"))]
+golem_llm::export_llm!(DurableGeminiComponent with_types_in golem_llm);
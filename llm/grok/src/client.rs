@@ -1,6 +1,8 @@
 use golem_llm::error::{error_code_from_status, from_event_source_error, from_reqwest_error};
 use golem_llm::event_source::EventSource;
 use golem_llm::golem::llm::llm::Error;
+use golem_llm::logprobs::Logprob;
+use golem_llm::retry::SendWithRetry;
 use log::trace;
 use reqwest::header::HeaderValue;
 use reqwest::{Client, Method, Response};
@@ -33,7 +35,7 @@ impl CompletionsApi {
             .request(Method::POST, format!("{BASE_URL}/v1/chat/completions"))
             .bearer_auth(self.api_key.clone())
             .json(&request)
-            .send()
+            .send_with_retry()
             .map_err(|err| from_reqwest_error("Request failed", err))?;
 
         parse_response(response)
@@ -51,7 +53,7 @@ impl CompletionsApi {
                 HeaderValue::from_static("text/event-stream"),
             )
             .json(&request)
-            .send()
+            .send_with_retry()
             .map_err(|err| from_reqwest_error("Request failed", err))?;
 
         trace!("Initializing SSE stream");
@@ -59,6 +61,19 @@ impl CompletionsApi {
         EventSource::new(response)
             .map_err(|err| from_event_source_error("Failed to create SSE stream", err))
     }
+
+    pub fn list_models(&self) -> Result<ListModelsResponse, Error> {
+        trace!("Listing models from xAI API");
+
+        let response: Response = self
+            .client
+            .request(Method::GET, format!("{BASE_URL}/v1/models"))
+            .bearer_auth(self.api_key.clone())
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,6 +83,8 @@ pub struct CompletionsRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub frequency_penalty: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_completion_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub n: Option<u32>,
@@ -86,6 +103,8 @@ pub struct CompletionsRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub tools: Vec<Tool>,
@@ -95,6 +114,35 @@ pub struct CompletionsRequest {
     pub top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_parameters: Option<SearchParameters>,
+}
+
+/// Enables Live Search, see https://docs.x.ai/docs/guides/live-search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchParameters {
+    pub mode: SearchMode,
+    pub sources: Vec<SearchSource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_search_results: Option<u32>,
+    pub return_citations: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SearchMode {
+    /// The model decides whether to search
+    #[serde(rename = "auto")]
+    Auto,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SearchSource {
+    #[serde(rename = "web")]
+    Web {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        allowed_websites: Option<Vec<String>>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,6 +150,25 @@ pub struct StreamOptions {
     pub include_usage: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseFormat {
+    #[serde(rename = "text")]
+    Text,
+    #[serde(rename = "json_object")]
+    JsonObject,
+    #[serde(rename = "json_schema")]
+    JsonSchema { json_schema: JsonSchema },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchema {
+    pub name: String,
+    pub schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Tool {
@@ -232,6 +299,9 @@ pub struct CompletionsResponse {
     pub model: String,
     pub system_fingerprint: Option<String>,
     pub usage: Option<Usage>,
+    /// The URLs of the sources found by Live Search
+    #[serde(default)]
+    pub citations: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -239,6 +309,12 @@ pub struct Choice {
     pub finish_reason: Option<FinishReason>,
     pub index: u32,
     pub message: ResponseMessage,
+    pub logprobs: Option<Logprobs>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Logprobs {
+    pub content: Option<Vec<Logprob>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -301,15 +377,31 @@ pub struct ChoiceChunk {
     pub index: u32,
     pub delta: ChoiceDelta,
     pub finish_reason: Option<FinishReason>,
+    pub logprobs: Option<Logprobs>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChoiceDelta {
     pub content: Option<String>,
+    pub reasoning_content: Option<String>,
     pub tool_calls: Option<Vec<ToolCall>>,
     pub role: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListModelsResponse {
+    pub data: Vec<Model>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Model {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owned_by: Option<String>,
+}
+
 fn parse_response<T: DeserializeOwned + Debug>(response: Response) -> Result<T, Error> {
     let status = response.status();
     if status.is_success() {
@@ -1,15 +1,34 @@
-use crate::client::{CompletionsRequest, CompletionsResponse, Detail, Effort};
+use crate::client::{
+    CompletionsRequest, CompletionsResponse, Detail, Effort, ListModelsResponse, SearchMode,
+    SearchParameters, SearchSource,
+};
 use base64::{engine::general_purpose, Engine as _};
+use golem_llm::builtin_tools::unsupported_builtin_tool;
+use golem_llm::error::unsupported;
 use golem_llm::golem::llm::llm::{
-    ChatEvent, CompleteResponse, Config, ContentPart, Error, ErrorCode, FinishReason, ImageDetail,
-    ImageReference, Message, ResponseMetadata, Role, ToolCall, ToolDefinition, ToolResult, Usage,
+    Alternative, BuiltinTool, BuiltinToolResult, ChatEvent, Citation, CompleteResponse, Config,
+    ContentPart, Error, ErrorCode, FinishReason, ImageDetail, ImageReference, Message,
+    ModelCapabilities, ModelInfo, ReasoningConfig, ReasoningContent, ReasoningEffort,
+    ResponseFormat, ResponseMetadata, Role, ToolCall, ToolDefinition, ToolResult, Usage,
 };
+use golem_llm::logprobs::convert_logprobs;
+use golem_llm::sampling::{validate_sampling_parameters, SamplingParameter};
 use std::collections::HashMap;
 
+const SAMPLING_PARAMETERS: &[SamplingParameter] = &[
+    SamplingParameter::TopP,
+    SamplingParameter::Seed,
+    SamplingParameter::FrequencyPenalty,
+    SamplingParameter::PresencePenalty,
+    SamplingParameter::User,
+];
+
 pub fn messages_to_request(
     messages: Vec<Message>,
-    config: Config,
+    mut config: Config,
 ) -> Result<CompletionsRequest, Error> {
+    validate_sampling_parameters(&mut config, SAMPLING_PARAMETERS)?;
+
     let options = config
         .provider_options
         .into_iter()
@@ -21,20 +40,20 @@ pub fn messages_to_request(
         match message.role {
             Role::User => completion_messages.push(crate::client::Message::User {
                 name: message.name,
-                content: convert_content_parts(message.content),
+                content: convert_content_parts(message.content)?,
             }),
             Role::Assistant => completion_messages.push(crate::client::Message::Assistant {
                 name: message.name,
-                content: Some(convert_content_parts(message.content)),
+                content: Some(convert_content_parts(message.content)?),
                 tool_calls: None,
             }),
             Role::System => completion_messages.push(crate::client::Message::System {
                 name: message.name,
-                content: convert_content_parts(message.content),
+                content: convert_content_parts(message.content)?,
             }),
             Role::Tool => completion_messages.push(crate::client::Message::Tool {
                 name: message.name,
-                content: convert_content_parts(message.content),
+                content: convert_content_parts(message.content)?,
                 tool_call_id: None,
             }),
         }
@@ -45,53 +64,77 @@ pub fn messages_to_request(
         tools.push(tool_definition_to_tool(tool)?)
     }
 
+    let mut search_parameters = None;
+    for tool in config.builtin_tools {
+        match tool {
+            BuiltinTool::WebSearch(options) => {
+                search_parameters = Some(SearchParameters {
+                    mode: SearchMode::Auto,
+                    sources: vec![SearchSource::Web {
+                        allowed_websites: options.allowed_domains,
+                    }],
+                    max_search_results: options.max_uses,
+                    return_citations: true,
+                })
+            }
+            _ => return Err(unsupported_builtin_tool(&tool)),
+        }
+    }
+
+    let top_logprobs = options
+        .get("top_logprobs")
+        .and_then(|top_logprobs_s| top_logprobs_s.parse::<u8>().ok());
+
     Ok(CompletionsRequest {
         messages: completion_messages,
         model: config.model,
-        frequency_penalty: options
-            .get("frequency_penalty")
-            .and_then(|fp_s| fp_s.parse::<f32>().ok()),
+        frequency_penalty: config.frequency_penalty,
+        // The top log-probabilities are only returned together with the sampled tokens' ones
+        logprobs: options
+            .get("logprobs")
+            .and_then(|logprobs_s| logprobs_s.parse::<bool>().ok())
+            .or(top_logprobs.map(|_| true)),
         max_completion_tokens: config.max_tokens,
         n: options.get("n").and_then(|n_s| n_s.parse::<u32>().ok()),
-        presence_penalty: options
-            .get("presence_penalty")
-            .and_then(|pp_s| pp_s.parse::<f32>().ok()),
-        reasoning_effort: options
-            .get("reasoning_effort")
-            .and_then(|effort_s| effort_s.parse::<Effort>().ok()),
-        seed: options
-            .get("seed")
-            .and_then(|seed_s| seed_s.parse::<u32>().ok()),
+        presence_penalty: config.presence_penalty,
+        reasoning_effort: match config.reasoning {
+            Some(reasoning) => reasoning_config_to_effort(reasoning)?,
+            None => options
+                .get("reasoning_effort")
+                .and_then(|effort_s| effort_s.parse::<Effort>().ok()),
+        },
+        seed: config.seed,
         stop: config.stop_sequences,
         stream: Some(false),
         stream_options: None,
         temperature: config.temperature,
+        response_format: config
+            .response_format
+            .map(convert_response_format)
+            .transpose()?,
         tool_choice: config.tool_choice,
         tools,
-        top_logprobs: options
-            .get("top_logprobs")
-            .and_then(|top_logprobs_s| top_logprobs_s.parse::<u8>().ok()),
-        top_p: options
-            .get("top_p")
-            .and_then(|top_p_s| top_p_s.parse::<f32>().ok()),
-        user: options.get("user_id").cloned(),
+        top_logprobs,
+        top_p: config.top_p,
+        user: config.user,
+        search_parameters,
     })
 }
 
-pub fn process_response(response: CompletionsResponse) -> ChatEvent {
-    let choice = response.choices.first();
-    if let Some(choice) = choice {
-        let mut contents = Vec::new();
-        let mut tool_calls = Vec::new();
-
-        if let Some(content) = &choice.message.content {
-            contents.push(ContentPart::Text(content.clone()));
-        }
+fn url_citation(url: String) -> Citation {
+    Citation {
+        url: Some(url),
+        file_id: None,
+        title: None,
+        cited_text: None,
+    }
+}
 
-        let empty = Vec::new();
-        for tool_call in choice.message.tool_calls.as_ref().unwrap_or(&empty) {
-            tool_calls.push(convert_tool_call(tool_call));
-        }
+pub fn process_response(mut response: CompletionsResponse) -> ChatEvent {
+    response.choices.sort_by_key(|choice| choice.index);
+    let mut choices = response.choices.into_iter();
+    if let Some(choice) = choices.next() {
+        let (contents, tool_calls) = convert_response_message(&choice.message);
 
         if contents.is_empty() {
             ChatEvent::ToolRequest(tool_calls)
@@ -102,6 +145,8 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
                 provider_id: None,
                 timestamp: Some(response.created.to_string()),
                 provider_metadata_json: None,
+                rate_limit: None,
+                cost: None,
             };
 
             ChatEvent::Message(CompleteResponse {
@@ -109,6 +154,18 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
                 content: contents,
                 tool_calls,
                 metadata,
+                logprobs: choice
+                    .logprobs
+                    .and_then(|logprobs| logprobs.content)
+                    .map(convert_logprobs),
+                alternatives: choices.map(convert_alternative).collect(),
+                builtin_tool_results: if response.citations.is_empty() {
+                    vec![]
+                } else {
+                    vec![BuiltinToolResult::Citations(
+                        response.citations.into_iter().map(url_citation).collect(),
+                    )]
+                },
             })
         }
     } else {
@@ -120,6 +177,48 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
     }
 }
 
+fn convert_response_message(
+    message: &crate::client::ResponseMessage,
+) -> (Vec<ContentPart>, Vec<ToolCall>) {
+    let mut contents = Vec::new();
+
+    if let Some(reasoning) = &message.reasoning_content {
+        contents.push(ContentPart::Reasoning(ReasoningContent {
+            text: reasoning.clone(),
+            signature: None,
+            redacted_data: None,
+        }));
+    }
+
+    if let Some(content) = &message.content {
+        contents.push(ContentPart::Text(content.clone()));
+    }
+
+    let tool_calls = message
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(convert_tool_call)
+        .collect();
+
+    (contents, tool_calls)
+}
+
+/// Further choices are returned when more than one was requested with the `n` option
+fn convert_alternative(choice: crate::client::Choice) -> Alternative {
+    let (content, tool_calls) = convert_response_message(&choice.message);
+    Alternative {
+        index: choice.index,
+        content,
+        tool_calls,
+        finish_reason: choice.finish_reason.as_ref().map(convert_finish_reason),
+        logprobs: choice
+            .logprobs
+            .and_then(|logprobs| logprobs.content)
+            .map(convert_logprobs),
+    }
+}
+
 pub fn tool_results_to_messages(
     tool_results: Vec<(ToolCall, ToolResult)>,
 ) -> Vec<crate::client::Message> {
@@ -160,11 +259,12 @@ pub fn convert_tool_call(tool_call: &crate::client::ToolCall) -> ToolCall {
             id: id.clone(),
             name: function.name.clone(),
             arguments_json: function.arguments.clone(),
+            validation_error: None,
         },
     }
 }
 
-fn convert_content_parts(contents: Vec<ContentPart>) -> crate::client::Content {
+fn convert_content_parts(contents: Vec<ContentPart>) -> Result<crate::client::Content, Error> {
     let mut result = Vec::new();
     for content in contents {
         match content {
@@ -189,9 +289,12 @@ fn convert_content_parts(contents: Vec<ContentPart>) -> crate::client::Content {
                     });
                 }
             },
+            ContentPart::Reasoning(_) => {}
+            ContentPart::Document(_) => return Err(unsupported("document input")),
+            ContentPart::Audio(_) => return Err(unsupported("audio input")),
         }
     }
-    crate::client::Content::List(result)
+    Ok(crate::client::Content::List(result))
 }
 
 impl From<ImageDetail> for Detail {
@@ -218,6 +321,9 @@ pub fn convert_usage(value: &crate::client::Usage) -> Usage {
         input_tokens: Some(value.prompt_tokens),
         output_tokens: Some(value.completion_tokens),
         total_tokens: Some(value.total_tokens),
+        cache_read_tokens: Some(value.prompt_tokens_details.cached_tokens),
+        cache_write_tokens: None,
+        reasoning_tokens: Some(value.completion_tokens_details.reasoning_tokens),
     }
 }
 
@@ -237,3 +343,101 @@ fn tool_definition_to_tool(tool: ToolDefinition) -> Result<crate::client::Tool,
         }),
     }
 }
+
+fn convert_response_format(
+    response_format: ResponseFormat,
+) -> Result<crate::client::ResponseFormat, Error> {
+    match response_format {
+        ResponseFormat::Text => Ok(crate::client::ResponseFormat::Text),
+        ResponseFormat::JsonObject => Ok(crate::client::ResponseFormat::JsonObject),
+        ResponseFormat::JsonSchema(json_schema) => {
+            match serde_json::from_str(&json_schema.schema) {
+                Ok(schema) => Ok(crate::client::ResponseFormat::JsonSchema {
+                    json_schema: crate::client::JsonSchema {
+                        name: json_schema.name,
+                        schema,
+                        strict: json_schema.strict,
+                    },
+                }),
+                Err(error) => Err(Error {
+                    code: ErrorCode::InvalidRequest,
+                    message: format!(
+                        "Failed to parse response format schema {}: {error}",
+                        json_schema.name
+                    ),
+                    provider_error_json: None,
+                }),
+            }
+        }
+    }
+}
+
+fn reasoning_config_to_effort(reasoning: ReasoningConfig) -> Result<Option<Effort>, Error> {
+    match (reasoning.effort, reasoning.budget_tokens) {
+        (Some(ReasoningEffort::Low), _) => Ok(Some(Effort::Low)),
+        (Some(ReasoningEffort::High), _) => Ok(Some(Effort::High)),
+        (Some(ReasoningEffort::Medium), _) => Err(unsupported("medium reasoning effort")),
+        (None, Some(_)) => Err(unsupported("reasoning budget tokens")),
+        (None, None) => Ok(None),
+    }
+}
+
+pub fn models_to_model_infos(response: ListModelsResponse) -> Vec<ModelInfo> {
+    response
+        .data
+        .into_iter()
+        // Image generation models cannot be used with the chat completions API
+        .filter(|model| !model.id.contains("image"))
+        .map(|model| model_info(model.id))
+        .collect()
+}
+
+/// The models endpoint only returns model identifiers, so the limits and capabilities
+/// are filled in based on the documented properties of the known model families.
+fn model_info(id: String) -> ModelInfo {
+    let (context_window, capabilities) = match id.as_str() {
+        id if id.starts_with("grok-4-fast") => {
+            (Some(2_000_000), model_capabilities(true, true, true, true))
+        }
+        id if id.starts_with("grok-4") => {
+            (Some(256_000), model_capabilities(true, true, true, true))
+        }
+        id if id.starts_with("grok-code-fast") => {
+            (Some(256_000), model_capabilities(true, false, true, true))
+        }
+        id if id.starts_with("grok-3-mini") => {
+            (Some(131_072), model_capabilities(true, false, true, true))
+        }
+        id if id.starts_with("grok-3") => {
+            (Some(131_072), model_capabilities(true, false, false, true))
+        }
+        id if id.starts_with("grok-2-vision") => {
+            (Some(32_768), model_capabilities(true, true, false, true))
+        }
+        id if id.starts_with("grok-2") => {
+            (Some(131_072), model_capabilities(true, false, false, true))
+        }
+        _ => (None, model_capabilities(false, false, false, false)),
+    };
+
+    ModelInfo {
+        id,
+        context_window,
+        max_output_tokens: None,
+        capabilities,
+    }
+}
+
+fn model_capabilities(
+    tools: bool,
+    vision: bool,
+    reasoning: bool,
+    json_schema: bool,
+) -> ModelCapabilities {
+    ModelCapabilities {
+        tools,
+        vision,
+        reasoning,
+        json_schema,
+    }
+}
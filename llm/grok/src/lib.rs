@@ -3,22 +3,28 @@ mod conversions;
 
 use crate::client::{ChatCompletionChunk, CompletionsApi, CompletionsRequest, StreamOptions};
 use crate::conversions::{
-    convert_finish_reason, convert_tool_call, convert_usage, messages_to_request, process_response,
-    tool_results_to_messages,
+    convert_finish_reason, convert_tool_call, convert_usage, messages_to_request,
+    models_to_model_infos, process_response, tool_results_to_messages,
 };
+use golem_llm::batch::UnsupportedChatBatch;
+use golem_llm::chat_job::EmulatedChatJob;
 use golem_llm::chat_stream::{LlmChatStream, LlmChatStreamState};
 use golem_llm::config::with_config_key;
-use golem_llm::durability::{DurableLLM, ExtendedGuest};
+use golem_llm::conversation::LlmConversation;
+use golem_llm::durability::ExtendedGuest;
 use golem_llm::event_source::EventSource;
 use golem_llm::golem::llm::llm::{
-    ChatEvent, ChatStream, Config, ContentPart, Error, FinishReason, Guest, Message,
-    ResponseMetadata, StreamDelta, StreamEvent, ToolCall, ToolResult,
+    ChatBatch, ChatEvent, ChatJob, ChatStream, Config, ContentPart, Error, FinishReason, Guest,
+    Message, ModelInfo, ReasoningContent, ResponseMetadata, StreamDelta, StreamEvent, TokenCount,
+    ToolCall, ToolResult,
 };
+use golem_llm::logprobs::convert_logprobs;
+use golem_llm::tokens::estimate_token_count;
 use golem_rust::wasm_rpc::Pollable;
 use log::trace;
 use std::cell::{Ref, RefCell, RefMut};
 
-struct GrokChatStream {
+pub struct GrokChatStream {
     stream: RefCell<Option<EventSource>>,
     failure: Option<Error>,
     finished: RefCell<bool>,
@@ -79,20 +85,37 @@ impl LlmChatStreamState for GrokChatStream {
             Some("chat.completion.chunk") => {
                 let message: ChatCompletionChunk = serde_json::from_value(json)
                     .map_err(|err| format!("Failed to parse stream event: {err}"))?;
-                if let Some(choice) = message.choices.into_iter().next() {
+                // Only the first choice is streamed when more than one was requested
+                if let Some(choice) = message.choices.into_iter().find(|choice| choice.index == 0) {
                     if let Some(finish_reason) = choice.finish_reason {
                         *self.finish_reason.borrow_mut() =
                             Some(convert_finish_reason(&finish_reason));
                     }
+                    let mut content = Vec::new();
+                    if let Some(reasoning) = choice.delta.reasoning_content {
+                        content.push(ContentPart::Reasoning(ReasoningContent {
+                            text: reasoning,
+                            signature: None,
+                            redacted_data: None,
+                        }));
+                    }
+                    if let Some(text) = choice.delta.content {
+                        content.push(ContentPart::Text(text));
+                    }
                     Ok(Some(StreamEvent::Delta(StreamDelta {
-                        content: choice
-                            .delta
-                            .content
-                            .map(|text| vec![ContentPart::Text(text)]),
+                        content: if content.is_empty() {
+                            None
+                        } else {
+                            Some(content)
+                        },
                         tool_calls: choice
                             .delta
                             .tool_calls
                             .map(|calls| calls.iter().map(convert_tool_call).collect()),
+                        logprobs: choice
+                            .logprobs
+                            .and_then(|logprobs| logprobs.content)
+                            .map(convert_logprobs),
                     })))
                 } else if let Some(usage) = message.usage {
                     let finish_reason = self.finish_reason.borrow();
@@ -102,6 +125,8 @@ impl LlmChatStreamState for GrokChatStream {
                         provider_id: None,
                         timestamp: Some(message.created.to_string()),
                         provider_metadata_json: None,
+                        rate_limit: None,
+                        cost: None,
                     })))
                 } else {
                     Ok(None)
@@ -113,7 +138,7 @@ impl LlmChatStreamState for GrokChatStream {
     }
 }
 
-struct GrokComponent;
+pub struct GrokComponent;
 
 impl GrokComponent {
     const ENV_VAR_NAME: &'static str = "XAI_API_KEY";
@@ -142,6 +167,9 @@ impl GrokComponent {
 
 impl Guest for GrokComponent {
     type ChatStream = LlmChatStream<GrokChatStream>;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = UnsupportedChatBatch;
 
     fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
         with_config_key(Self::ENV_VAR_NAME, ChatEvent::Error, |xai_api_key| {
@@ -177,6 +205,26 @@ impl Guest for GrokComponent {
     fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
         ChatStream::new(Self::unwrapped_stream(messages, config))
     }
+
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        with_config_key(Self::ENV_VAR_NAME, Err, |xai_api_key| {
+            let client = CompletionsApi::new(xai_api_key);
+
+            client.list_models().map(models_to_model_infos)
+        })
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        Ok(estimate_token_count(&messages, &config))
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
 }
 
 impl ExtendedGuest for GrokComponent {
@@ -191,11 +239,17 @@ impl ExtendedGuest for GrokComponent {
         })
     }
 
+    fn pricing_provider() -> Option<&'static str> {
+        Some("grok")
+    }
+
     fn subscribe(stream: &Self::ChatStream) -> Pollable {
         stream.subscribe()
     }
 }
 
-type DurableGrokComponent = DurableLLM<GrokComponent>;
+#[cfg(not(feature = "library"))]
+type DurableGrokComponent = golem_llm::durability::DurableLLM<GrokComponent>;
 
+#[cfg(not(feature = "library"))]
 golem_llm::export_llm!(DurableGrokComponent with_types_in golem_llm);
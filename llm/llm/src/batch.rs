@@ -0,0 +1,210 @@
+use crate::error::unsupported;
+use crate::golem::llm::llm::{BatchRequest, BatchStatus, Error, ErrorCode, GuestChatBatch};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+/// The batch API of a provider, used by [`ProviderChatBatch`]
+pub trait BatchApi: 'static {
+    /// Submits the requests as a batch, returning the provider's id of the batch
+    fn submit_batch(requests: Vec<BatchRequest>) -> Result<String, Error>;
+
+    /// Checks the status of the batch, returning the results of its requests when it has
+    /// finished
+    fn poll_batch(id: &str) -> Result<BatchStatus, Error>;
+
+    fn cancel_batch(id: &str) -> Result<(), Error>;
+}
+
+/// Implementation of the `chat-batch` resource for providers with a batch API, which can be
+/// polled and cancelled by the id of the batch
+pub struct ProviderChatBatch<Api> {
+    /// The id of the batch, or the error the batch failed to be submitted with
+    id: Result<String, Error>,
+    phantom: PhantomData<Api>,
+}
+
+impl<Api: BatchApi> ProviderChatBatch<Api> {
+    /// A batch submitted earlier with the given id
+    pub fn fetch(id: String) -> Self {
+        Self {
+            id: Ok(id),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<Api: BatchApi> GuestChatBatch for ProviderChatBatch<Api> {
+    fn new(requests: Vec<BatchRequest>) -> Self {
+        Self {
+            id: validate_batch_requests(&requests).and_then(|_| Api::submit_batch(requests)),
+            phantom: PhantomData,
+        }
+    }
+
+    fn id(&self) -> Option<String> {
+        self.id.as_ref().ok().cloned()
+    }
+
+    fn poll(&self) -> Result<BatchStatus, Error> {
+        match &self.id {
+            Ok(id) => Api::poll_batch(id),
+            Err(error) => Err(error.clone()),
+        }
+    }
+
+    fn cancel(&self) -> Result<(), Error> {
+        match &self.id {
+            Ok(id) => Api::cancel_batch(id),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// Implementation of the `chat-batch` resource for providers without a batch API, failing with
+/// an `unsupported` error when the batch is used
+pub struct UnsupportedChatBatch;
+
+impl GuestChatBatch for UnsupportedChatBatch {
+    fn new(_requests: Vec<BatchRequest>) -> Self {
+        Self
+    }
+
+    fn id(&self) -> Option<String> {
+        None
+    }
+
+    fn poll(&self) -> Result<BatchStatus, Error> {
+        Err(unsupported("batches"))
+    }
+
+    fn cancel(&self) -> Result<(), Error> {
+        Err(unsupported("batches"))
+    }
+}
+
+/// Checks that the batch is not empty, and that the custom ids of its requests are unique
+pub fn validate_batch_requests(requests: &[BatchRequest]) -> Result<(), Error> {
+    if requests.is_empty() {
+        return Err(invalid_request("The batch has no requests"));
+    }
+
+    let mut custom_ids = HashSet::new();
+    for request in requests {
+        if request.custom_id.is_empty() {
+            return Err(invalid_request("The custom id of a batch request is empty"));
+        }
+        if !custom_ids.insert(request.custom_id.as_str()) {
+            return Err(invalid_request(format!(
+                "The custom id {} is used by multiple requests of the batch",
+                request.custom_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Serializes the items as JSON Lines, the format of the batch input files
+pub fn to_jsonl<T: Serialize>(items: &[T]) -> Result<String, Error> {
+    let mut jsonl = String::new();
+    for item in items {
+        let line = serde_json::to_string(item).map_err(|err| Error {
+            code: ErrorCode::InternalError,
+            message: format!("Failed to serialize batch request: {err}"),
+            provider_error_json: None,
+        })?;
+        jsonl.push_str(&line);
+        jsonl.push('\n');
+    }
+    Ok(jsonl)
+}
+
+/// Parses the JSON Lines of a batch results file, skipping the empty lines
+pub fn parse_jsonl<T: DeserializeOwned>(jsonl: &str) -> Result<Vec<T>, Error> {
+    jsonl
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|err| Error {
+                code: ErrorCode::InternalError,
+                message: format!("Failed to parse batch result: {err}"),
+                provider_error_json: Some(line.to_string()),
+            })
+        })
+        .collect()
+}
+
+fn invalid_request(message: impl Into<String>) -> Error {
+    Error {
+        code: ErrorCode::InvalidRequest,
+        message: message.into(),
+        provider_error_json: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::{parse_jsonl, to_jsonl, validate_batch_requests};
+    use crate::golem::llm::llm::{BatchRequest, Config, ErrorCode};
+    use serde::{Deserialize, Serialize};
+
+    fn request(custom_id: &str) -> BatchRequest {
+        BatchRequest {
+            custom_id: custom_id.to_string(),
+            messages: vec![],
+            config: Config {
+                model: "test-model".to_string(),
+                temperature: None,
+                max_tokens: None,
+                stop_sequences: None,
+                tools: vec![],
+                builtin_tools: vec![],
+                tool_choice: None,
+                provider_options: vec![],
+                response_format: None,
+                reasoning: None,
+                top_p: None,
+                top_k: None,
+                seed: None,
+                frequency_penalty: None,
+                presence_penalty: None,
+                user: None,
+                validate_tool_calls: None,
+            },
+        }
+    }
+
+    #[test]
+    fn accepts_unique_custom_ids() {
+        assert!(validate_batch_requests(&[request("a"), request("b")]).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_batches() {
+        for requests in [vec![], vec![request("")], vec![request("a"), request("a")]] {
+            let error = validate_batch_requests(&requests).unwrap_err();
+            assert_eq!(error.code, ErrorCode::InvalidRequest);
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Line {
+        custom_id: String,
+    }
+
+    #[test]
+    fn jsonl_roundtrip() {
+        let lines = vec![
+            Line {
+                custom_id: "a".to_string(),
+            },
+            Line {
+                custom_id: "b".to_string(),
+            },
+        ];
+        let jsonl = to_jsonl(&lines).unwrap();
+        assert_eq!(jsonl, "{\"custom_id\":\"a\"}\n{\"custom_id\":\"b\"}\n");
+        assert_eq!(parse_jsonl::<Line>(&format!("{jsonl}\n")).unwrap(), lines);
+    }
+}
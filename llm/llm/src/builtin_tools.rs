@@ -0,0 +1,59 @@
+use crate::error::unsupported;
+use crate::golem::llm::llm::{BuiltinTool, Config, Error};
+
+pub fn builtin_tool_name(tool: &BuiltinTool) -> &'static str {
+    match tool {
+        BuiltinTool::WebSearch(_) => "web-search",
+        BuiltinTool::CodeInterpreter => "code-interpreter",
+        BuiltinTool::FileSearch(_) => "file-search",
+    }
+}
+
+/// The `unsupported` error for a built-in tool the provider does not host
+pub fn unsupported_builtin_tool(tool: &BuiltinTool) -> Error {
+    unsupported(format!("built-in tool {}", builtin_tool_name(tool)))
+}
+
+/// Fails with an `unsupported` error if `config` enables any built-in tools, used by the
+/// providers without hosted tools
+pub fn reject_builtin_tools(config: &Config) -> Result<(), Error> {
+    match config.builtin_tools.first() {
+        Some(tool) => Err(unsupported_builtin_tool(tool)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtin_tools::reject_builtin_tools;
+    use crate::golem::llm::llm::{BuiltinTool, Config, ErrorCode};
+
+    #[test]
+    fn rejects_builtin_tools() {
+        let mut config = Config {
+            model: "test-model".to_string(),
+            temperature: None,
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+        assert!(reject_builtin_tools(&config).is_ok());
+
+        config.builtin_tools = vec![BuiltinTool::CodeInterpreter];
+        let error = reject_builtin_tools(&config).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Unsupported);
+        assert!(error.message.contains("code-interpreter"));
+    }
+}
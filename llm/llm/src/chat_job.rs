@@ -0,0 +1,223 @@
+use crate::golem::llm::llm::{Config, Error, ErrorCode, Guest, GuestChatJob, JobStatus, Message};
+use crate::retry::with_rate_limit;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+/// The background job API of a provider, used by [`BackgroundChatJob`]
+pub trait BackgroundJobApi: 'static {
+    /// Starts generating the response in the background, returning the provider's id of the job
+    fn start_job(messages: Vec<Message>, config: Config) -> Result<String, Error>;
+
+    /// Checks the status of the job, returning the response when it has finished
+    fn poll_job(id: &str) -> Result<JobStatus, Error>;
+
+    fn cancel_job(id: &str) -> Result<(), Error>;
+}
+
+/// Implementation of the `chat-job` resource for providers running the requests as background
+/// jobs, which can be polled and cancelled by their id
+pub struct BackgroundChatJob<Api> {
+    /// The id of the job, or the error the job failed to start with
+    id: Result<String, Error>,
+    phantom: PhantomData<Api>,
+}
+
+impl<Api: BackgroundJobApi> BackgroundChatJob<Api> {
+    /// A job started earlier with the given id
+    pub fn fetch(id: String) -> Self {
+        Self {
+            id: Ok(id),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<Api: BackgroundJobApi> GuestChatJob for BackgroundChatJob<Api> {
+    fn new(messages: Vec<Message>, config: Config) -> Self {
+        Self {
+            id: Api::start_job(messages, config),
+            phantom: PhantomData,
+        }
+    }
+
+    fn id(&self) -> Option<String> {
+        self.id.as_ref().ok().cloned()
+    }
+
+    fn poll(&self) -> Result<JobStatus, Error> {
+        match &self.id {
+            Ok(id) => Api::poll_job(id),
+            Err(error) => Err(error.clone()),
+        }
+    }
+
+    fn cancel(&self) -> Result<(), Error> {
+        match &self.id {
+            Ok(id) => Api::cancel_job(id),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// Implementation of the `chat-job` resource for providers without background jobs, sending
+/// the request with `send` when the job is first polled.
+pub struct EmulatedChatJob<Impl> {
+    request: RefCell<Option<(Vec<Message>, Config)>>,
+    status: RefCell<Option<JobStatus>>,
+    phantom: PhantomData<Impl>,
+}
+
+impl<Impl: Guest + 'static> GuestChatJob for EmulatedChatJob<Impl> {
+    fn new(messages: Vec<Message>, config: Config) -> Self {
+        Self {
+            request: RefCell::new(Some((messages, config))),
+            status: RefCell::new(None),
+            phantom: PhantomData,
+        }
+    }
+
+    fn id(&self) -> Option<String> {
+        None
+    }
+
+    fn poll(&self) -> Result<JobStatus, Error> {
+        let mut status = self.status.borrow_mut();
+        if let Some((messages, config)) = self.request.borrow_mut().take() {
+            let event = with_rate_limit(|| Impl::send(messages, config));
+            *status = Some(JobStatus::Completed(event));
+        }
+        status.clone().ok_or_else(|| Error {
+            code: ErrorCode::InternalError,
+            message: "The job was neither sent nor cancelled".to_string(),
+            provider_error_json: None,
+        })
+    }
+
+    fn cancel(&self) -> Result<(), Error> {
+        if self.request.borrow_mut().take().is_some() {
+            *self.status.borrow_mut() = Some(JobStatus::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chat_job::{BackgroundChatJob, BackgroundJobApi, EmulatedChatJob};
+    use crate::golem::llm::llm::{
+        ChatEvent, Config, Error, ErrorCode, GuestChatJob, JobStatus, Message, Role,
+    };
+    use crate::testing::{config, message, response, TestModel};
+    use std::cell::RefCell;
+
+    thread_local! {
+        static JOBS: RefCell<Vec<(String, JobStatus)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// A background job API keeping the jobs in memory, completing them with their last message
+    struct TestJobApi;
+
+    impl BackgroundJobApi for TestJobApi {
+        fn start_job(messages: Vec<Message>, _config: Config) -> Result<String, Error> {
+            if messages.is_empty() {
+                return Err(Error {
+                    code: ErrorCode::InvalidRequest,
+                    message: "No messages".to_string(),
+                    provider_error_json: None,
+                });
+            }
+            JOBS.with_borrow_mut(|jobs| {
+                let id = format!("job-{}", jobs.len() + 1);
+                jobs.push((id.clone(), JobStatus::Queued));
+                Ok(id)
+            })
+        }
+
+        fn poll_job(id: &str) -> Result<JobStatus, Error> {
+            JOBS.with_borrow(|jobs| {
+                jobs.iter()
+                    .find(|(job_id, _)| job_id == id)
+                    .map(|(_, status)| status.clone())
+                    .ok_or_else(|| Error {
+                        code: ErrorCode::InvalidRequest,
+                        message: format!("Unknown job {id}"),
+                        provider_error_json: None,
+                    })
+            })
+        }
+
+        fn cancel_job(id: &str) -> Result<(), Error> {
+            JOBS.with_borrow_mut(|jobs| {
+                for (job_id, status) in jobs.iter_mut() {
+                    if job_id == id && !matches!(status, JobStatus::Completed(_)) {
+                        *status = JobStatus::Cancelled;
+                    }
+                }
+            });
+            Ok(())
+        }
+    }
+
+    fn complete_job(id: &str, event: ChatEvent) {
+        JOBS.with_borrow_mut(|jobs| {
+            for (job_id, status) in jobs.iter_mut() {
+                if job_id == id {
+                    *status = JobStatus::Completed(event.clone());
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn emulated_job_is_sent_when_first_polled() {
+        TestModel::respond_with(vec![response("Hello!")]);
+        let job = EmulatedChatJob::<TestModel>::new(vec![message(Role::User, "Hi")], config());
+        assert_eq!(job.id(), None);
+        assert!(TestModel::requests().is_empty());
+
+        assert_eq!(job.poll(), Ok(JobStatus::Completed(response("Hello!"))));
+        assert_eq!(job.poll(), Ok(JobStatus::Completed(response("Hello!"))));
+        assert_eq!(TestModel::requests().len(), 1);
+
+        // Cancelling a completed job does not affect it
+        assert_eq!(job.cancel(), Ok(()));
+        assert_eq!(job.poll(), Ok(JobStatus::Completed(response("Hello!"))));
+    }
+
+    #[test]
+    fn emulated_job_cancelled_before_polling_is_not_sent() {
+        let job = EmulatedChatJob::<TestModel>::new(vec![message(Role::User, "Hi")], config());
+        assert_eq!(job.cancel(), Ok(()));
+        assert_eq!(job.poll(), Ok(JobStatus::Cancelled));
+        assert!(TestModel::requests().is_empty());
+    }
+
+    #[test]
+    fn background_job_is_polled_and_cancelled_by_its_id() {
+        let job = BackgroundChatJob::<TestJobApi>::new(vec![message(Role::User, "Hi")], config());
+        let id = job.id().unwrap();
+        assert_eq!(job.poll(), Ok(JobStatus::Queued));
+
+        complete_job(&id, response("Hello!"));
+        assert_eq!(job.poll(), Ok(JobStatus::Completed(response("Hello!"))));
+        assert_eq!(
+            BackgroundChatJob::<TestJobApi>::fetch(id).poll(),
+            Ok(JobStatus::Completed(response("Hello!")))
+        );
+
+        let job = BackgroundChatJob::<TestJobApi>::new(vec![message(Role::User, "Hi")], config());
+        assert_eq!(job.cancel(), Ok(()));
+        assert_eq!(job.poll(), Ok(JobStatus::Cancelled));
+    }
+
+    #[test]
+    fn background_job_failing_to_start_fails_to_poll() {
+        let job = BackgroundChatJob::<TestJobApi>::new(vec![], config());
+        assert_eq!(job.id(), None);
+        assert_eq!(
+            job.poll().map_err(|error| error.code),
+            Err(ErrorCode::InvalidRequest)
+        );
+        assert_eq!(job.cancel(), Ok(()));
+    }
+}
@@ -1,7 +1,10 @@
 use crate::event_source::{Event, EventSource, MessageEvent};
-use crate::golem::llm::llm::{Error, ErrorCode, GuestChatStream, StreamEvent};
+use crate::golem::llm::llm::{
+    Error, ErrorCode, FinishReason, GuestChatStream, RateLimit, ResponseMetadata, StreamEvent,
+};
+use crate::retry::take_last_rate_limit;
 use golem_rust::wasm_rpc::Pollable;
-use std::cell::{Ref, RefMut};
+use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::task::Poll;
 
 pub trait LlmChatStreamState: 'static {
@@ -11,15 +14,46 @@ pub trait LlmChatStreamState: 'static {
     fn stream(&self) -> Ref<'_, Option<EventSource>>;
     fn stream_mut(&self) -> RefMut<'_, Option<EventSource>>;
     fn decode_message(&self, raw: &str) -> Result<Option<StreamEvent>, String>;
+
+    /// The event to emit when the underlying stream ends, for providers which do not send a
+    /// separate event for finishing the response
+    fn end_of_stream(&self) -> Option<StreamEvent> {
+        None
+    }
+
+    /// The id of the last decoded event, for providers identifying the events in their data
+    /// instead of the `id` field of the server-sent events. It is used for resuming the stream
+    /// after the connection was lost.
+    fn last_event_id(&self) -> Option<String> {
+        None
+    }
 }
 
 pub struct LlmChatStream<T> {
     implementation: T,
+    cancelled: Cell<bool>,
+    rate_limit: RefCell<Option<RateLimit>>,
 }
 
 impl<T: LlmChatStreamState> LlmChatStream<T> {
     pub fn new(implementation: T) -> Self {
-        Self { implementation }
+        Self {
+            implementation,
+            cancelled: Cell::new(false),
+            // The streaming request has just been sent, so the last response is the one
+            // this stream reads from
+            rate_limit: RefCell::new(take_last_rate_limit()),
+        }
+    }
+
+    /// The id of the last received event if the stream can be resumed from it, see
+    /// [`EventSource::with_reconnect`]
+    pub fn last_event_id(&self) -> Option<String> {
+        self.implementation
+            .stream()
+            .as_ref()
+            .filter(|stream| stream.is_resumable() && !stream.last_event_id().is_empty())
+            .map(|stream| stream.last_event_id().to_string())
     }
 
     pub fn subscribe(&self) -> Pollable {
@@ -29,10 +63,29 @@ impl<T: LlmChatStreamState> LlmChatStream<T> {
             golem_rust::bindings::wasi::clocks::monotonic_clock::subscribe_duration(0)
         }
     }
+
+    /// Fills the rate limit reported with the streaming response into the finishing
+    /// event's metadata, unless the provider already did so
+    fn with_rate_limit(&self, event: StreamEvent) -> StreamEvent {
+        match event {
+            StreamEvent::Finish(mut metadata) => {
+                if metadata.rate_limit.is_none() {
+                    metadata.rate_limit = self.rate_limit.borrow_mut().take();
+                }
+                StreamEvent::Finish(metadata)
+            }
+            event => event,
+        }
+    }
 }
 
 impl<T: LlmChatStreamState> GuestChatStream for LlmChatStream<T> {
     fn get_next(&self) -> Option<Vec<StreamEvent>> {
+        if self.cancelled.replace(false) {
+            self.implementation.set_finished();
+            return Some(vec![cancelled_event()]);
+        }
+
         if self.implementation.is_finished() {
             return Some(vec![]);
         }
@@ -40,13 +93,16 @@ impl<T: LlmChatStreamState> GuestChatStream for LlmChatStream<T> {
         let mut stream = self.implementation.stream_mut();
         if let Some(stream) = stream.as_mut() {
             match stream.poll_next() {
-                Poll::Ready(None) => {
-                    self.implementation.set_finished();
-                    Some(vec![])
-                }
-                Poll::Ready(Some(Err(crate::event_source::error::Error::StreamEnded))) => {
+                Poll::Ready(None)
+                | Poll::Ready(Some(Err(crate::event_source::error::Error::StreamEnded))) => {
                     self.implementation.set_finished();
-                    Some(vec![])
+                    Some(
+                        self.implementation
+                            .end_of_stream()
+                            .map(|event| self.with_rate_limit(event))
+                            .into_iter()
+                            .collect(),
+                    )
                 }
                 Poll::Ready(Some(Err(error))) => Some(vec![StreamEvent::Error(Error {
                     code: ErrorCode::InternalError,
@@ -60,12 +116,16 @@ impl<T: LlmChatStreamState> GuestChatStream for LlmChatStream<T> {
                         Event::Open => {}
                         Event::Message(MessageEvent { data, .. }) => {
                             if data != "[DONE]" {
-                                match self.implementation.decode_message(&data) {
+                                let decoded = self.implementation.decode_message(&data);
+                                if let Some(id) = self.implementation.last_event_id() {
+                                    stream.set_last_event_id(id);
+                                }
+                                match decoded {
                                     Ok(Some(stream_event)) => {
                                         if matches!(stream_event, StreamEvent::Finish(_)) {
                                             self.implementation.set_finished();
                                         }
-                                        events.push(stream_event);
+                                        events.push(self.with_rate_limit(stream_event));
                                     }
                                     Ok(None) => {
                                         // Ignored event
@@ -99,10 +159,10 @@ impl<T: LlmChatStreamState> GuestChatStream for LlmChatStream<T> {
     }
 
     fn blocking_get_next(&self) -> Vec<StreamEvent> {
-        let pollable = self.subscribe();
         let mut result = Vec::new();
         loop {
-            pollable.block();
+            // Subscribing again each time, as a reconnected stream has a new pollable
+            self.subscribe().block();
             match self.get_next() {
                 Some(events) => {
                     result.extend(events);
@@ -112,4 +172,29 @@ impl<T: LlmChatStreamState> GuestChatStream for LlmChatStream<T> {
             }
         }
     }
+
+    fn cancel(&self) {
+        if self.implementation.is_finished() {
+            return;
+        }
+
+        // Dropping the event source closes the underlying response body
+        if let Some(mut stream) = self.implementation.stream_mut().take() {
+            stream.close();
+        }
+        self.cancelled.set(true);
+    }
+}
+
+/// The final event emitted by a chat stream after it was cancelled
+pub fn cancelled_event() -> StreamEvent {
+    StreamEvent::Finish(ResponseMetadata {
+        finish_reason: Some(FinishReason::Cancelled),
+        usage: None,
+        provider_id: None,
+        timestamp: None,
+        provider_metadata_json: None,
+        rate_limit: None,
+        cost: None,
+    })
 }
@@ -0,0 +1,303 @@
+use crate::golem::llm::llm::{ContentPart, Message, Role, StreamDelta, StreamEvent};
+
+/// The shortest repeated text removed from the start of a continuation, unless it is only
+/// whitespace. Shorter overlaps are more likely to be a coincidence, like a word starting with
+/// the letter the previous one ended with.
+const MIN_OVERLAP: usize = 8;
+
+/// The length of the end of the partial response a continuation is compared with
+const MAX_OVERLAP: usize = 512;
+
+/// The text of the partially received response
+pub fn partial_text(partial_result: &[StreamDelta]) -> String {
+    partial_result
+        .iter()
+        .flat_map(|delta| delta.content.iter().flatten())
+        .filter_map(|part| match part {
+            ContentPart::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Creates the prompt continuing an interrupted response by appending the partial response as
+/// a trailing assistant message, which the model continues from where it stopped. Returns `None`
+/// if the partial response has no text, or contains tool calls or reasoning which can not be
+/// prefilled.
+///
+/// The trailing whitespace of the partial response is not sent, as it is rejected by some
+/// providers. It is removed from the continuation by the [`Seam`] instead.
+pub fn prefill_prompt(
+    original_messages: &[Message],
+    partial_result: &[StreamDelta],
+) -> Option<Vec<Message>> {
+    let only_text = partial_result.iter().all(is_text_only);
+    let text = partial_text(partial_result);
+    let text = text.trim_end();
+    if !only_text || text.is_empty() {
+        return None;
+    }
+
+    let mut messages = original_messages.to_vec();
+    match messages.last_mut() {
+        Some(last) if last.role == Role::Assistant => {
+            last.content.push(ContentPart::Text(text.to_string()));
+        }
+        _ => messages.push(Message {
+            role: Role::Assistant,
+            name: None,
+            content: vec![ContentPart::Text(text.to_string())],
+            cache_control: None,
+        }),
+    }
+    Some(messages)
+}
+
+/// Removes the text a continuation repeats from the end of the interrupted response before it
+/// is emitted. The text deltas at the start of the continuation are held back until they can
+/// no longer be a repetition, and then emitted as a single delta without the repeated part.
+pub struct Seam {
+    tail: String,
+    buffer: Option<StreamDelta>,
+    resolved: bool,
+}
+
+impl Seam {
+    /// Creates the seam following the given partial response, `None` if it has no text
+    pub fn new(partial_text: &str) -> Option<Self> {
+        if partial_text.is_empty() {
+            return None;
+        }
+        let mut start = partial_text.len().saturating_sub(MAX_OVERLAP);
+        while !partial_text.is_char_boundary(start) {
+            start += 1;
+        }
+        Some(Self {
+            tail: partial_text[start..].to_string(),
+            buffer: None,
+            resolved: false,
+        })
+    }
+
+    /// Processes the events of the continuation. The result can be empty even if `events` is
+    /// not, when the received text is held back.
+    pub fn process(&mut self, events: Vec<StreamEvent>) -> Vec<StreamEvent> {
+        let mut result = Vec::new();
+        for event in events {
+            match event {
+                StreamEvent::Delta(delta) if !self.resolved && is_text_only(&delta) => {
+                    let buffer = self.buffer.get_or_insert_with(|| StreamDelta {
+                        content: None,
+                        tool_calls: None,
+                        logprobs: None,
+                    });
+                    buffer
+                        .content
+                        .get_or_insert_with(Vec::new)
+                        .extend(delta.content.into_iter().flatten());
+                    if let Some(logprobs) = delta.logprobs {
+                        buffer
+                            .logprobs
+                            .get_or_insert_with(Vec::new)
+                            .extend(logprobs);
+                    }
+
+                    let text = self.buffered_text();
+                    if text.len() >= self.tail.len() || !self.tail.contains(&text) {
+                        result.extend(self.resolve());
+                    }
+                }
+                event => {
+                    if !self.resolved {
+                        result.extend(self.resolve());
+                    }
+                    result.push(event);
+                }
+            }
+        }
+        result
+    }
+
+    /// Emits the text still held back when the continuation ends without any other event
+    pub fn finish(&mut self) -> Vec<StreamEvent> {
+        if self.resolved {
+            vec![]
+        } else {
+            self.resolve().into_iter().collect()
+        }
+    }
+
+    fn buffered_text(&self) -> String {
+        match &self.buffer {
+            Some(buffer) => partial_text(std::slice::from_ref(buffer)),
+            None => String::new(),
+        }
+    }
+
+    /// Emits the held back text without the part repeating the end of the partial response
+    fn resolve(&mut self) -> Option<StreamEvent> {
+        self.resolved = true;
+        let text = self.buffered_text();
+        let mut buffer = self.buffer.take()?;
+        let overlap = (1..=text.len().min(self.tail.len()))
+            .rev()
+            .filter(|len| text.is_char_boundary(*len))
+            .map(|len| &text[..len])
+            .find(|repeated| {
+                (repeated.len() >= MIN_OVERLAP || repeated.trim().is_empty())
+                    && self.tail.ends_with(repeated)
+            })
+            .map_or(0, |repeated| repeated.len());
+
+        let remaining = &text[overlap..];
+        if remaining.is_empty() {
+            return None;
+        }
+        buffer.content = Some(vec![ContentPart::Text(remaining.to_string())]);
+        Some(StreamEvent::Delta(buffer))
+    }
+}
+
+fn is_text_only(delta: &StreamDelta) -> bool {
+    delta
+        .tool_calls
+        .as_ref()
+        .is_none_or(|calls| calls.is_empty())
+        && delta
+            .content
+            .iter()
+            .flatten()
+            .all(|part| matches!(part, ContentPart::Text(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::continuation::{prefill_prompt, Seam};
+    use crate::golem::llm::llm::{
+        ContentPart, Message, ResponseMetadata, Role, StreamDelta, StreamEvent, ToolCall,
+    };
+
+    fn delta(text: &str) -> StreamEvent {
+        StreamEvent::Delta(StreamDelta {
+            content: Some(vec![ContentPart::Text(text.to_string())]),
+            tool_calls: None,
+            logprobs: None,
+        })
+    }
+
+    fn finish() -> StreamEvent {
+        StreamEvent::Finish(ResponseMetadata {
+            finish_reason: None,
+            usage: None,
+            provider_id: None,
+            timestamp: None,
+            provider_metadata_json: None,
+            rate_limit: None,
+            cost: None,
+        })
+    }
+
+    fn deltas(texts: &[&str]) -> Vec<StreamDelta> {
+        texts
+            .iter()
+            .map(|text| match delta(text) {
+                StreamEvent::Delta(delta) => delta,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    fn user(text: &str) -> Message {
+        Message {
+            role: Role::User,
+            name: None,
+            content: vec![ContentPart::Text(text.to_string())],
+            cache_control: None,
+        }
+    }
+
+    fn continuation(partial: &str, chunks: &[&str]) -> Vec<StreamEvent> {
+        let mut seam = Seam::new(partial).unwrap();
+        let mut result = Vec::new();
+        for chunk in chunks {
+            result.extend(seam.process(vec![delta(chunk)]));
+        }
+        result.extend(seam.process(vec![finish()]));
+        result
+    }
+
+    #[test]
+    fn prefills_partial_text() {
+        let messages = prefill_prompt(&[user("Hi")], &deltas(&["Hello, ", "how are "])).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].role, Role::Assistant);
+        assert_eq!(
+            messages[1].content,
+            vec![ContentPart::Text("Hello, how are".to_string())]
+        );
+    }
+
+    #[test]
+    fn does_not_prefill_tool_calls() {
+        let mut partial = deltas(&["Let me check"]);
+        partial.push(StreamDelta {
+            content: None,
+            tool_calls: Some(vec![ToolCall {
+                id: "call-1".to_string(),
+                name: "weather".to_string(),
+                arguments_json: "{}".to_string(),
+                validation_error: None,
+            }]),
+            logprobs: None,
+        });
+        assert!(prefill_prompt(&[user("Hi")], &partial).is_none());
+        assert!(prefill_prompt(&[user("Hi")], &deltas(&["  "])).is_none());
+    }
+
+    #[test]
+    fn removes_repeated_text_across_chunks() {
+        let result = continuation(
+            "The quick brown fox jumps",
+            &["brown ", "fox jumps", " over the lazy dog."],
+        );
+        assert_eq!(result, vec![delta(" over the lazy dog."), finish()]);
+    }
+
+    #[test]
+    fn keeps_short_coincidental_overlaps() {
+        let result = continuation("It is a cat", &["t and a dog"]);
+        assert_eq!(result, vec![delta("t and a dog"), finish()]);
+    }
+
+    #[test]
+    fn removes_repeated_whitespace() {
+        let result = continuation("Hello ", &[" world"]);
+        assert_eq!(result, vec![delta("world"), finish()]);
+    }
+
+    #[test]
+    fn flushes_held_back_text_before_other_events() {
+        let mut seam = Seam::new("Some text").unwrap();
+        assert!(seam.process(vec![delta("text")]).is_empty());
+        assert_eq!(seam.process(vec![finish()]), vec![delta("text"), finish()]);
+
+        let mut seam = Seam::new("Some text").unwrap();
+        assert!(seam.process(vec![delta("me")]).is_empty());
+        assert_eq!(
+            seam.process(vec![delta("!"), delta(" More")]),
+            vec![delta("me!"), delta(" More")]
+        );
+    }
+
+    #[test]
+    fn flushes_held_back_text_at_the_end_of_the_stream() {
+        let mut seam = Seam::new("The quick brown fox").unwrap();
+        assert!(seam.process(vec![delta("quick")]).is_empty());
+        assert_eq!(seam.finish(), vec![delta("quick")]);
+        assert_eq!(seam.finish(), vec![]);
+
+        let mut seam = Seam::new("The quick brown fox").unwrap();
+        assert!(seam.process(vec![delta("brown fox")]).is_empty());
+        assert_eq!(seam.finish(), vec![]);
+    }
+}
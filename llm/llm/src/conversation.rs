@@ -0,0 +1,777 @@
+use crate::durability::ExtendedGuest;
+use crate::error::unsupported;
+use crate::golem::llm::llm::{
+    ChatEvent, Config, ContentPart, ConversationOptions, Error, ErrorCode, Guest,
+    GuestConversation, Kv, Message, Role, ToolCall, ToolResult, TruncationStrategy,
+};
+use crate::retry::with_rate_limit;
+use crate::tokens::{estimate_message_tokens, estimate_tool_results_tokens};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+const DEFAULT_MAX_ITERATIONS: u32 = 10;
+
+/// The start of the summary of the truncated history in the leading system message
+const SUMMARY_PREFIX: &str = "Summary of the earlier part of the conversation: ";
+
+/// An LLM implementation the `conversation` resource can be built on
+pub trait ConversationGuest: Guest + 'static {
+    /// Whether requests can be chained on a previous response stored by the provider, with the
+    /// `previous_response_id` provider option, instead of sending the whole history
+    fn chains_responses() -> bool;
+}
+
+impl<Impl: ExtendedGuest> ConversationGuest for Impl {
+    fn chains_responses() -> bool {
+        Impl::stores_responses()
+    }
+}
+
+/// Generic implementation of the `conversation` resource on top of the `send`, `continue` and
+/// `count-tokens` functions of an LLM implementation.
+///
+/// The conversation has no side effects of its own, so when `Impl` is wrapped with `DurableLLM`,
+/// replaying the persisted calls restores its history deterministically, including the
+/// truncations and summaries made along the way.
+pub struct LlmConversation<Impl> {
+    state: RefCell<ConversationState>,
+    phantom: PhantomData<Impl>,
+}
+
+struct ConversationState {
+    messages: Vec<Message>,
+    config: Config,
+    options: ConversationOptions,
+    /// The tool calls and results of the current turn, sent with `continue` after the history.
+    /// They are not kept when the turn finishes, as the providers only accept tool results
+    /// directly following the response requesting them, and the history can only hold messages.
+    tool_results: Vec<(ToolCall, ToolResult)>,
+    pending_tool_calls: Vec<ToolCall>,
+    iterations: u32,
+    finished: bool,
+    /// The id of the model's last response when the conversation is chained on the responses
+    /// stored by the provider
+    previous_response_id: Option<String>,
+    /// The number of messages in the history which are already part of the stored responses
+    stored_messages: usize,
+}
+
+impl ConversationState {
+    fn server_side_state(&self) -> bool {
+        self.options.server_side_state == Some(true)
+    }
+
+    /// Checks that the given tool results answer exactly the pending tool calls
+    fn validate_tool_results(&self, tool_results: &[(ToolCall, ToolResult)]) -> Result<(), Error> {
+        if self.pending_tool_calls.is_empty() {
+            if tool_results.is_empty() {
+                Ok(())
+            } else {
+                Err(invalid_request("There are no pending tool calls to answer"))
+            }
+        } else {
+            let pending_ids = self
+                .pending_tool_calls
+                .iter()
+                .map(|tool_call| tool_call.id.as_str())
+                .collect::<HashSet<_>>();
+            let answered_ids = tool_results
+                .iter()
+                .map(|(tool_call, _)| tool_call.id.as_str())
+                .collect::<HashSet<_>>();
+
+            if pending_ids == answered_ids {
+                Ok(())
+            } else {
+                Err(invalid_request(
+                    "The tool results must answer all the pending tool calls",
+                ))
+            }
+        }
+    }
+
+    /// Truncates the history with the configured strategy until it fits in `max-context-tokens`
+    /// together with the tool results sent after it. The messages of the current turn are never
+    /// removed, and the messages are removed in whole turns, so the history still starts with a
+    /// user message after the system messages.
+    fn truncate<Impl: Guest>(
+        &mut self,
+        tool_results: &[(ToolCall, ToolResult)],
+    ) -> Result<(), Error> {
+        let Some(max_context_tokens) = self.options.max_context_tokens else {
+            return Ok(());
+        };
+        let tool_result_tokens = estimate_tool_results_tokens(tool_results);
+
+        loop {
+            let token_count = Impl::count_tokens(self.messages.clone(), self.config.clone())?;
+            let tokens = token_count.input_tokens + tool_result_tokens;
+            if tokens <= max_context_tokens {
+                return Ok(());
+            }
+
+            let context_exceeded = || {
+                invalid_request(format!(
+                    "The conversation history ({tokens} tokens) does not fit in the maximum context size ({max_context_tokens} tokens)"
+                ))
+            };
+            let removed = match self.options.truncation.clone() {
+                None => return Err(context_exceeded()),
+                Some(TruncationStrategy::DropOldest) => {
+                    let dropped = self.oldest_turns(tokens - max_context_tokens);
+                    self.remove_messages(&dropped);
+                    dropped
+                }
+                Some(TruncationStrategy::KeepSystemAndLast(count)) => {
+                    let current_turn = self.current_turn_start();
+                    let mut kept = 0;
+                    let mut dropped = Vec::new();
+                    for (idx, message) in self.messages.iter().enumerate().rev() {
+                        if message.role != Role::System {
+                            if kept < count || idx >= current_turn {
+                                kept += 1;
+                            } else {
+                                dropped.push(idx);
+                            }
+                        }
+                    }
+                    // The partial turn before the oldest kept user message is dropped as well
+                    let first_kept_turn = (0..self.messages.len())
+                        .filter(|idx| !dropped.contains(idx))
+                        .find(|idx| self.messages[*idx].role == Role::User)
+                        .unwrap_or(current_turn);
+                    let partial_turn = (0..first_kept_turn)
+                        .filter(|idx| {
+                            self.messages[*idx].role != Role::System && !dropped.contains(idx)
+                        })
+                        .collect::<Vec<_>>();
+                    dropped.extend(partial_turn);
+                    self.remove_messages(&dropped);
+                    dropped
+                }
+                Some(TruncationStrategy::Summarize) => {
+                    let summarized = self.oldest_turns(tokens - max_context_tokens);
+                    if !summarized.is_empty() {
+                        let summary = self.summarize::<Impl>(&summarized)?;
+                        self.remove_messages(&summarized);
+                        self.set_summary(summary);
+                    }
+                    summarized
+                }
+            };
+
+            if removed.is_empty() {
+                return Err(context_exceeded());
+            }
+        }
+    }
+
+    /// The index of the first message of the current turn, which starts with the latest user
+    /// message, or is only the last message if there is no user message
+    fn current_turn_start(&self) -> usize {
+        self.messages
+            .iter()
+            .rposition(|message| message.role == Role::User)
+            .unwrap_or(self.messages.len().saturating_sub(1))
+    }
+
+    /// Selects the non-system messages of the oldest turns with an estimated size of at least
+    /// `tokens`, always keeping the messages of the current turn. A turn is a user message and
+    /// the messages following it until the next user message, the messages before the first user
+    /// message are selected with the first turn.
+    fn oldest_turns(&self, tokens: u32) -> Vec<usize> {
+        let current_turn = self.current_turn_start();
+        let mut selected = Vec::new();
+        let mut selected_tokens = 0;
+        for (idx, message) in self.messages.iter().enumerate() {
+            let turn_start = message.role == Role::User;
+            if (selected_tokens >= tokens && turn_start) || idx >= current_turn {
+                break;
+            }
+            if message.role != Role::System {
+                selected.push(idx);
+                selected_tokens += estimate_message_tokens(message);
+            }
+        }
+        selected
+    }
+
+    fn remove_messages(&mut self, indices: &[usize]) {
+        let indices = indices.iter().copied().collect::<HashSet<_>>();
+        let mut idx = 0;
+        self.messages.retain(|_| {
+            let keep = !indices.contains(&idx);
+            idx += 1;
+            keep
+        });
+    }
+
+    /// The summary of an earlier truncation in the leading system message
+    fn summary(&self) -> Option<&str> {
+        self.messages
+            .first()
+            .filter(|message| message.role == Role::System)
+            .and_then(|message| message.content.iter().find_map(summary_text))
+    }
+
+    /// Puts the summary into the leading system message, replacing the summary of an earlier
+    /// truncation. A system message is added for it if the history does not start with one.
+    fn set_summary(&mut self, summary: String) {
+        let summary = ContentPart::Text(format!("{SUMMARY_PREFIX}{summary}"));
+        match self.messages.first_mut() {
+            Some(message) if message.role == Role::System => {
+                match message
+                    .content
+                    .iter_mut()
+                    .find(|content| summary_text(content).is_some())
+                {
+                    Some(content) => *content = summary,
+                    None => message.content.push(summary),
+                }
+            }
+            _ => self.messages.insert(
+                0,
+                Message {
+                    role: Role::System,
+                    name: None,
+                    content: vec![summary],
+                    cache_control: None,
+                },
+            ),
+        }
+    }
+
+    /// Asks the model to summarize the given messages, continuing the summary of an earlier
+    /// truncation
+    fn summarize<Impl: Guest>(&self, indices: &[usize]) -> Result<String, Error> {
+        let mut messages = indices
+            .iter()
+            .map(|idx| self.messages[*idx].clone())
+            .collect::<Vec<_>>();
+        let mut instruction = "Summarize the conversation above in a few sentences, keeping all \
+                               the facts and decisions that may be needed to continue it."
+            .to_string();
+        if let Some(summary) = self.summary() {
+            instruction.push_str(&format!(
+                " It continues the conversation summarized as follows, include its facts and \
+                 decisions in the summary as well: {summary}"
+            ));
+        }
+        messages.push(Message {
+            role: Role::User,
+            name: None,
+            content: vec![ContentPart::Text(instruction)],
+            cache_control: None,
+        });
+        let config = Config {
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            response_format: None,
+            reasoning: None,
+            ..self.config.clone()
+        };
+
+        match Impl::send(messages, config) {
+            ChatEvent::Message(response) => Ok(response
+                .content
+                .into_iter()
+                .filter_map(|content| match content {
+                    ContentPart::Text(text) => Some(text),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("")),
+            ChatEvent::ToolRequest(_) => Err(Error {
+                code: ErrorCode::InternalError,
+                message: "The model requested tool calls instead of summarizing the conversation"
+                    .to_string(),
+                provider_error_json: None,
+            }),
+            ChatEvent::Error(error) => Err(error),
+        }
+    }
+}
+
+impl<Impl: ConversationGuest> GuestConversation for LlmConversation<Impl> {
+    fn new(messages: Vec<Message>, config: Config, options: ConversationOptions) -> Self {
+        Self {
+            state: RefCell::new(ConversationState {
+                messages,
+                config,
+                options,
+                tool_results: Vec::new(),
+                pending_tool_calls: Vec::new(),
+                iterations: 0,
+                finished: false,
+                previous_response_id: None,
+                stored_messages: 0,
+            }),
+            phantom: PhantomData,
+        }
+    }
+
+    fn step(&self, tool_results: Vec<(ToolCall, ToolResult)>) -> ChatEvent {
+        let mut state = self.state.borrow_mut();
+
+        if state.finished {
+            return ChatEvent::Error(invalid_request(
+                "The conversation turn has already finished, add a new message to continue",
+            ));
+        }
+        if let Err(error) = state.validate_tool_results(&tool_results) {
+            return ChatEvent::Error(error);
+        }
+        let max_iterations = state
+            .options
+            .max_iterations
+            .unwrap_or(DEFAULT_MAX_ITERATIONS);
+        if state.iterations >= max_iterations {
+            return ChatEvent::Error(invalid_request(format!(
+                "The conversation reached the maximum number of iterations ({max_iterations})"
+            )));
+        }
+        let mut all_tool_results = state.tool_results.clone();
+        all_tool_results.extend(tool_results.clone());
+
+        if state.server_side_state() {
+            if !Impl::chains_responses() {
+                return ChatEvent::Error(unsupported("server-side conversation state"));
+            }
+        } else if let Err(error) = state.truncate::<Impl>(&all_tool_results) {
+            return ChatEvent::Error(error);
+        }
+        state.iterations += 1;
+
+        // When chained on the previous response, only what the provider has not stored yet is sent
+        let mut config = state.config.clone();
+        let (messages, request_tool_results) = match &state.previous_response_id {
+            Some(previous_response_id) => {
+                config.provider_options.push(Kv {
+                    key: "previous_response_id".to_string(),
+                    value: previous_response_id.clone(),
+                });
+                (
+                    state.messages[state.stored_messages..].to_vec(),
+                    tool_results,
+                )
+            }
+            None => (state.messages.clone(), all_tool_results.clone()),
+        };
+        if state.server_side_state() {
+            config.provider_options.push(Kv {
+                key: "store".to_string(),
+                value: "true".to_string(),
+            });
+        }
+
+        let event = with_rate_limit(|| {
+            if request_tool_results.is_empty() {
+                Impl::send(messages.clone(), config.clone())
+            } else {
+                Impl::continue_(
+                    messages.clone(),
+                    request_tool_results.clone(),
+                    config.clone(),
+                )
+            }
+        });
+
+        // On failure the state is kept, so the step can be retried with the same tool results
+        match &event {
+            ChatEvent::Message(response) => {
+                state.tool_results = all_tool_results;
+                state.pending_tool_calls = response.tool_calls.clone();
+                if response.tool_calls.is_empty() {
+                    state.tool_results.clear();
+                    state.messages.push(Message {
+                        role: Role::Assistant,
+                        name: None,
+                        content: response.content.clone(),
+                        cache_control: None,
+                    });
+                    state.finished = true;
+                }
+            }
+            ChatEvent::ToolRequest(tool_calls) => {
+                state.tool_results = all_tool_results;
+                state.pending_tool_calls = tool_calls.clone();
+            }
+            ChatEvent::Error(_) => {}
+        }
+
+        if state.server_side_state() {
+            match &event {
+                ChatEvent::Message(response) => {
+                    state.previous_response_id = Some(response.id.clone());
+                    state.stored_messages = state.messages.len();
+                }
+                // Without a response id the next request falls back to sending the whole history
+                ChatEvent::ToolRequest(_) => state.previous_response_id = None,
+                ChatEvent::Error(_) => {}
+            }
+        }
+
+        event
+    }
+
+    fn add_message(&self, message: Message) {
+        let mut state = self.state.borrow_mut();
+        state.messages.push(message);
+        if state.finished {
+            state.finished = false;
+            state.iterations = 0;
+        }
+    }
+
+    fn history(&self) -> Vec<Message> {
+        self.state.borrow().messages.clone()
+    }
+}
+
+/// The summary of the truncated history, if the content part is one
+fn summary_text(content: &ContentPart) -> Option<&str> {
+    match content {
+        ContentPart::Text(text) => text.strip_prefix(SUMMARY_PREFIX),
+        _ => None,
+    }
+}
+
+fn invalid_request(message: impl Into<String>) -> Error {
+    Error {
+        code: ErrorCode::InvalidRequest,
+        message: message.into(),
+        provider_error_json: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conversation::{LlmConversation, SUMMARY_PREFIX};
+    use crate::golem::llm::llm::{
+        ChatEvent, ContentPart, ConversationOptions, ErrorCode, GuestConversation, Message, Role,
+        TruncationStrategy,
+    };
+    use crate::testing::{
+        config, message, response, tool_call, tool_success, TestModel, TestRequest,
+    };
+
+    fn options(
+        max_context_tokens: Option<u32>,
+        truncation: Option<TruncationStrategy>,
+    ) -> ConversationOptions {
+        ConversationOptions {
+            max_iterations: None,
+            max_context_tokens,
+            truncation,
+            server_side_state: None,
+        }
+    }
+
+    /// A history of 48 estimated tokens, with a system message of 6 tokens and the other
+    /// messages of 14 tokens each
+    fn history() -> Vec<Message> {
+        vec![
+            message(Role::System, "Be brief"),
+            message(Role::User, &"a".repeat(40)),
+            message(Role::Assistant, &"b".repeat(40)),
+            message(Role::User, &"c".repeat(40)),
+        ]
+    }
+
+    fn new_conversation(
+        messages: Vec<Message>,
+        options: ConversationOptions,
+    ) -> LlmConversation<TestModel> {
+        LlmConversation::new(messages, config(), options)
+    }
+
+    /// Checks that the system messages come first, followed by a user message
+    fn assert_role_order(messages: &[Message]) {
+        let first = messages
+            .iter()
+            .position(|message| message.role != Role::System)
+            .unwrap();
+        assert_eq!(messages[first].role, Role::User, "{messages:?}");
+        assert!(
+            messages[first..]
+                .iter()
+                .all(|message| message.role != Role::System),
+            "{messages:?}"
+        );
+    }
+
+    fn assert_invalid_request(event: ChatEvent) {
+        match event {
+            ChatEvent::Error(error) => assert_eq!(error.code, ErrorCode::InvalidRequest),
+            event => panic!("Expected an invalid request error, got {event:?}"),
+        }
+    }
+
+    #[test]
+    fn tool_results_are_sent_with_continue_during_the_turn() {
+        let call = tool_call("call-1");
+        let result = tool_success(&call, r#"{"forecast": "sunny"}"#);
+        TestModel::respond_with(vec![
+            ChatEvent::ToolRequest(vec![call.clone()]),
+            response("It is sunny"),
+            response("It will rain"),
+        ]);
+        let user = message(Role::User, "What is the weather?");
+        let conversation = new_conversation(vec![user.clone()], options(None, None));
+
+        assert_eq!(
+            conversation.step(vec![]),
+            ChatEvent::ToolRequest(vec![call])
+        );
+        assert_eq!(
+            conversation.step(vec![result.clone()]),
+            response("It is sunny")
+        );
+        let answer = message(Role::Assistant, "It is sunny");
+        assert_eq!(conversation.history(), vec![user.clone(), answer.clone()]);
+
+        let next = message(Role::User, "And tomorrow?");
+        conversation.add_message(next.clone());
+        assert_eq!(conversation.step(vec![]), response("It will rain"));
+
+        assert_eq!(
+            TestModel::requests(),
+            vec![
+                TestRequest {
+                    messages: vec![user.clone()],
+                    tool_results: vec![],
+                },
+                TestRequest {
+                    messages: vec![user.clone()],
+                    tool_results: vec![result],
+                },
+                TestRequest {
+                    messages: vec![user, answer, next],
+                    tool_results: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tool_results_must_answer_the_pending_calls() {
+        let call = tool_call("call-1");
+        TestModel::respond_with(vec![ChatEvent::ToolRequest(vec![call.clone()])]);
+        let conversation = new_conversation(history(), options(None, None));
+
+        assert_invalid_request(conversation.step(vec![tool_success(&call, "{}")]));
+        assert_eq!(
+            conversation.step(vec![]),
+            ChatEvent::ToolRequest(vec![call])
+        );
+        assert_invalid_request(conversation.step(vec![]));
+        assert_invalid_request(conversation.step(vec![tool_success(&tool_call("call-2"), "{}")]));
+        assert_eq!(TestModel::requests().len(), 1);
+    }
+
+    #[test]
+    fn turn_ends_with_the_final_answer() {
+        TestModel::respond_with(vec![response("Done"), response("Done again")]);
+        let conversation = new_conversation(history(), options(None, None));
+
+        assert_eq!(conversation.step(vec![]), response("Done"));
+        assert_invalid_request(conversation.step(vec![]));
+
+        conversation.add_message(message(Role::User, "Once more"));
+        assert_eq!(conversation.step(vec![]), response("Done again"));
+    }
+
+    #[test]
+    fn stops_after_max_iterations() {
+        let call = tool_call("call-1");
+        TestModel::respond_with(vec![
+            ChatEvent::ToolRequest(vec![call.clone()]),
+            ChatEvent::ToolRequest(vec![call.clone()]),
+        ]);
+        let conversation = new_conversation(
+            history(),
+            ConversationOptions {
+                max_iterations: Some(2),
+                ..options(None, None)
+            },
+        );
+
+        conversation.step(vec![]);
+        conversation.step(vec![tool_success(&call, "{}")]);
+        assert_invalid_request(conversation.step(vec![tool_success(&call, "{}")]));
+        assert_eq!(TestModel::requests().len(), 2);
+    }
+
+    #[test]
+    fn drop_oldest_keeps_the_current_turn() {
+        TestModel::respond_with(vec![response("Done")]);
+        let conversation = new_conversation(
+            history(),
+            options(Some(25), Some(TruncationStrategy::DropOldest)),
+        );
+
+        assert_eq!(conversation.step(vec![]), response("Done"));
+        let expected = vec![history()[0].clone(), history()[3].clone()];
+        assert_eq!(TestModel::requests()[0].messages, expected);
+
+        let conversation = new_conversation(
+            history(),
+            options(Some(10), Some(TruncationStrategy::DropOldest)),
+        );
+        assert_invalid_request(conversation.step(vec![]));
+        assert_eq!(TestModel::requests().len(), 1);
+    }
+
+    #[test]
+    fn keep_system_and_last_keeps_the_current_turn() {
+        TestModel::respond_with(vec![response("Done"), response("Done"), response("Done")]);
+
+        let mut messages = history();
+        messages.push(message(Role::Assistant, &"d".repeat(40)));
+        messages.push(message(Role::User, &"e".repeat(40)));
+        let conversation = new_conversation(
+            messages.clone(),
+            options(Some(60), Some(TruncationStrategy::KeepSystemAndLast(3))),
+        );
+        conversation.step(vec![]);
+        let expected = vec![
+            messages[0].clone(),
+            messages[3].clone(),
+            messages[4].clone(),
+            messages[5].clone(),
+        ];
+        assert_eq!(TestModel::requests()[0].messages, expected);
+
+        // The kept messages start with a whole turn
+        let conversation = new_conversation(
+            messages.clone(),
+            options(Some(60), Some(TruncationStrategy::KeepSystemAndLast(2))),
+        );
+        conversation.step(vec![]);
+        let expected = vec![messages[0].clone(), messages[5].clone()];
+        assert_eq!(TestModel::requests()[1].messages, expected);
+
+        let conversation = new_conversation(
+            history(),
+            options(Some(25), Some(TruncationStrategy::KeepSystemAndLast(0))),
+        );
+        conversation.step(vec![]);
+        let expected = vec![history()[0].clone(), history()[3].clone()];
+        assert_eq!(TestModel::requests()[2].messages, expected);
+    }
+
+    #[test]
+    fn truncation_drops_whole_turns() {
+        // The first turn has two assistant messages
+        let mut messages = history();
+        messages.insert(2, message(Role::Assistant, &"b".repeat(40)));
+        for truncation in [
+            TruncationStrategy::DropOldest,
+            TruncationStrategy::KeepSystemAndLast(2),
+            TruncationStrategy::Summarize,
+        ] {
+            if matches!(truncation, TruncationStrategy::Summarize) {
+                TestModel::respond_with(vec![response("They talked")]);
+            }
+            TestModel::respond_with(vec![response("Done")]);
+            let conversation =
+                new_conversation(messages.clone(), options(Some(50), Some(truncation)));
+
+            assert_eq!(conversation.step(vec![]), response("Done"));
+            let sent = TestModel::requests().last().unwrap().messages.clone();
+            assert_role_order(&sent);
+            assert_eq!(sent.len(), 2);
+            assert_eq!(sent[1], messages[4]);
+            assert_role_order(&conversation.history());
+        }
+    }
+
+    #[test]
+    fn summarize_replaces_the_oldest_messages() {
+        TestModel::respond_with(vec![response("They talked"), response("Done")]);
+        let mut messages = history();
+        messages[1] = message(Role::User, &"a".repeat(80));
+        messages[2] = message(Role::Assistant, &"b".repeat(80));
+        let conversation = new_conversation(
+            messages.clone(),
+            options(Some(40), Some(TruncationStrategy::Summarize)),
+        );
+
+        assert_eq!(conversation.step(vec![]), response("Done"));
+        let requests = TestModel::requests();
+        assert_eq!(requests[0].messages.len(), 3);
+        assert_eq!(requests[0].messages[..2], messages[1..3]);
+        let expected = vec![
+            summarized_system_message("They talked"),
+            messages[3].clone(),
+        ];
+        assert_eq!(requests[1].messages, expected);
+        assert_eq!(conversation.history()[..2], expected);
+        assert_role_order(&conversation.history());
+    }
+
+    /// The system message of `history` with the summary of the truncated messages
+    fn summarized_system_message(summary: &str) -> Message {
+        let mut system = history()[0].clone();
+        system
+            .content
+            .push(ContentPart::Text(format!("{SUMMARY_PREFIX}{summary}")));
+        system
+    }
+
+    #[test]
+    fn summarize_replaces_the_earlier_summary() {
+        TestModel::respond_with(vec![
+            response("They talked"),
+            response("Done"),
+            response("They talked more"),
+            response("Done again"),
+        ]);
+        let mut messages = history();
+        messages[1] = message(Role::User, &"a".repeat(80));
+        messages[2] = message(Role::Assistant, &"b".repeat(80));
+        let conversation = new_conversation(
+            messages.clone(),
+            options(Some(40), Some(TruncationStrategy::Summarize)),
+        );
+
+        conversation.step(vec![]);
+        let next = message(Role::User, &"d".repeat(40));
+        conversation.add_message(next.clone());
+        assert_eq!(conversation.step(vec![]), response("Done again"));
+
+        // The earlier summary is summarized together with the truncated messages
+        let requests = TestModel::requests();
+        let instruction = requests[2].messages.last().unwrap();
+        assert!(
+            matches!(&instruction.content[..], [ContentPart::Text(text)] if text.contains("They talked")),
+            "{instruction:?}"
+        );
+        let expected = vec![summarized_system_message("They talked more"), next];
+        assert_eq!(requests[3].messages, expected);
+        assert_role_order(&conversation.history());
+    }
+
+    #[test]
+    fn truncation_counts_the_pending_tool_results() {
+        let call = tool_call("call-1");
+        let result = tool_success(&call, &"r".repeat(40));
+        TestModel::respond_with(vec![
+            ChatEvent::ToolRequest(vec![call.clone()]),
+            response("Done"),
+        ]);
+        let conversation = new_conversation(
+            history(),
+            options(Some(50), Some(TruncationStrategy::DropOldest)),
+        );
+
+        conversation.step(vec![]);
+        conversation.step(vec![result]);
+        let requests = TestModel::requests();
+        assert_eq!(requests[0].messages, history());
+        let expected = vec![history()[0].clone(), history()[3].clone()];
+        assert_eq!(requests[1].messages, expected);
+    }
+}
@@ -1,5 +1,14 @@
-use crate::golem::llm::llm::{Config, ContentPart, Guest, Message, Role, StreamDelta};
+use crate::conversation::ConversationGuest;
+use crate::error::unsupported;
+use crate::golem::llm::llm::{
+    BatchRequest, BatchResult, BatchStatus, ChatEvent, Config, ContentPart, Error, Guest,
+    JobStatus, Message, Role, StreamDelta, StreamEvent, Usage,
+};
+use crate::pricing::{add_usage, estimate_cost, fill_batch_cost, fill_cost};
+use crate::tool_validation::validate_tool_calls;
 use golem_rust::wasm_rpc::Pollable;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::marker::PhantomData;
 
 /// Wraps an LLM implementation with custom durability
@@ -14,7 +23,7 @@ pub trait ExtendedGuest: Guest + 'static {
 
     /// Creates the retry prompt with a combination of the original messages, and the partially received
     /// streaming responses. There is a default implementation here, but it can be overridden with provider-specific
-    /// prompts if needed.
+    /// prompts if needed. Not used when the partial response can be prefilled, see `supports_prefill`.
     fn retry_prompt(original_messages: &[Message], partial_result: &[StreamDelta]) -> Vec<Message> {
         let mut extended_messages = Vec::new();
         extended_messages.push(Message {
@@ -27,6 +36,7 @@ pub trait ExtendedGuest: Guest + 'static {
                                         Do not include the part of the response that was already seen.".to_string()),
                 ContentPart::Text("Here is the original question:".to_string()),
             ],
+            cache_control: None,
         });
         extended_messages.extend_from_slice(original_messages);
 
@@ -54,28 +64,217 @@ pub trait ExtendedGuest: Guest + 'static {
             .into_iter()
             .chain(partial_result_as_content)
             .collect(),
+            cache_control: None,
         });
         extended_messages
     }
 
+    /// Resumes a stream interrupted by a restart after the event with the given id, instead of
+    /// re-asking the model with the retry prompt. Only supported by providers which can resume
+    /// their streams, the default implementation returns `None` to fall back to `retry_prompt`.
+    fn resume_stream(
+        _original_messages: &[Message],
+        _config: &Config,
+        _last_event_id: &str,
+    ) -> Option<Self::ChatStream> {
+        None
+    }
+
+    /// Whether the model continues a trailing assistant message, so an interrupted stream can be
+    /// continued by prefilling the partial response instead of re-asking with `retry_prompt`.
+    /// The default implementation returns `false`.
+    fn supports_prefill(_config: &Config) -> bool {
+        false
+    }
+
+    /// The id of the last event received by the stream, if it can be resumed from it with
+    /// `resume_stream`
+    fn last_event_id(_stream: &Self::ChatStream) -> Option<String> {
+        None
+    }
+
+    /// Whether the provider stores its responses, so requests can be chained on them with the
+    /// `previous_response_id` provider option
+    fn stores_responses() -> bool {
+        false
+    }
+
+    /// Creates an instance of the LLM specific `ChatJob` for a background job started earlier,
+    /// without wrapping it in a `Resource`. Only supported by providers with background jobs.
+    fn unwrapped_fetch_job(_id: String) -> Result<Self::ChatJob, Error> {
+        Err(unsupported("background jobs"))
+    }
+
+    /// The name of the provider in the pricing table, used to estimate the cost of its responses.
+    /// The default implementation returns `None`, leaving the cost of the responses unknown.
+    fn pricing_provider() -> Option<&'static str> {
+        None
+    }
+
+    /// Creates an instance of the LLM specific `ChatBatch` for a batch submitted earlier,
+    /// without wrapping it in a `Resource`. Only supported by providers with a batch API.
+    fn unwrapped_fetch_batch(_id: String) -> Result<Self::ChatBatch, Error> {
+        Err(unsupported("batches"))
+    }
+
     fn subscribe(stream: &Self::ChatStream) -> Pollable;
 }
 
-/// When the durability feature flag is off, wrapping with `DurableLLM` is just a passthrough
+/// Fills the estimated cost of a response from its usage, if the provider is in the pricing table
+fn with_cost<Impl: ExtendedGuest>(model: &str, event: ChatEvent) -> ChatEvent {
+    match (event, Impl::pricing_provider()) {
+        (ChatEvent::Message(mut response), Some(provider)) => {
+            fill_cost(provider, model, &mut response.metadata);
+            ChatEvent::Message(response)
+        }
+        (event, _) => event,
+    }
+}
+
+/// Fills the estimated cost of the response to a request of a batch from its usage, with the
+/// batch discount of the provider, if the provider is in the pricing table
+fn with_batch_cost<Impl: ExtendedGuest>(model: &str, event: ChatEvent) -> ChatEvent {
+    match (event, Impl::pricing_provider()) {
+        (ChatEvent::Message(mut response), Some(provider)) => {
+            fill_batch_cost(provider, model, &mut response.metadata);
+            ChatEvent::Message(response)
+        }
+        (event, _) => event,
+    }
+}
+
+/// Prices the finishing events of a stream by the total usage of the finishing events received
+/// so far, as the providers may report the usage in more than one of them
+struct StreamPricing<Impl> {
+    model: String,
+    usage: RefCell<Option<Usage>>,
+    phantom: PhantomData<Impl>,
+}
+
+impl<Impl: ExtendedGuest> StreamPricing<Impl> {
+    fn new(config: &Config) -> Self {
+        Self {
+            model: config.model.clone(),
+            usage: RefCell::new(None),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Adds the usage of the finishing events to the total, and fills their cost from it
+    fn price_events(&self, events: &mut [StreamEvent]) {
+        for event in events {
+            if let StreamEvent::Finish(metadata) = event {
+                if let Some(usage) = &metadata.usage {
+                    let mut total = self.usage.borrow_mut();
+                    *total = Some(match &*total {
+                        Some(total) => add_usage(total, usage),
+                        None => usage.clone(),
+                    });
+                }
+                if let (Some(provider), Some(usage), None) = (
+                    Impl::pricing_provider(),
+                    &*self.usage.borrow(),
+                    &metadata.cost,
+                ) {
+                    metadata.cost = estimate_cost(provider, &self.model, usage);
+                }
+            }
+        }
+    }
+}
+
+/// Validates the tool calls and estimates the cost of the response of a completed job. The
+/// config is not known for fetched jobs, so their responses are returned as they are.
+fn validate_job_status<Impl: ExtendedGuest>(
+    config: Option<&Config>,
+    status: JobStatus,
+) -> JobStatus {
+    match (status, config) {
+        (JobStatus::Completed(event), Some(config)) => JobStatus::Completed(validate_tool_calls(
+            config,
+            with_cost::<Impl>(&config.model, event),
+        )),
+        (status, _) => status,
+    }
+}
+
+/// The configs of the requests of a batch by their custom id
+fn request_configs(requests: &[BatchRequest]) -> HashMap<String, Config> {
+    requests
+        .iter()
+        .map(|request| (request.custom_id.clone(), request.config.clone()))
+        .collect()
+}
+
+/// Validates the tool calls and estimates the cost of the responses of a finished batch, with the
+/// configs of their requests. The configs are not known for fetched batches, so their responses
+/// are returned as they are.
+fn validate_batch_status<Impl: ExtendedGuest>(
+    configs: &HashMap<String, Config>,
+    status: BatchStatus,
+) -> BatchStatus {
+    let validate_results = |results: Vec<BatchResult>| {
+        results
+            .into_iter()
+            .map(|result| match configs.get(&result.custom_id) {
+                Some(config) => BatchResult {
+                    event: validate_tool_calls(
+                        config,
+                        with_batch_cost::<Impl>(&config.model, result.event),
+                    ),
+                    ..result
+                },
+                None => result,
+            })
+            .collect()
+    };
+    match status {
+        BatchStatus::Completed(results) => BatchStatus::Completed(validate_results(results)),
+        BatchStatus::Cancelled(results) => BatchStatus::Cancelled(validate_results(results)),
+        status => status,
+    }
+}
+
+impl<Impl: ExtendedGuest> ConversationGuest for DurableLLM<Impl> {
+    fn chains_responses() -> bool {
+        Impl::stores_responses()
+    }
+}
+
+/// When the durability feature flag is off, wrapping with `DurableLLM` is just a passthrough,
+/// apart from validating the tool calls and estimating the cost of the responses
 #[cfg(not(feature = "durability"))]
 mod passthrough_impl {
-    use crate::durability::{DurableLLM, ExtendedGuest};
+    use crate::conversation::LlmConversation;
+    use crate::durability::{
+        request_configs, validate_batch_status, validate_job_status, with_cost, DurableLLM,
+        ExtendedGuest, StreamPricing,
+    };
     use crate::golem::llm::llm::{
-        ChatEvent, ChatStream, Config, Guest, Message, ToolCall, ToolResult,
+        BatchRequest, BatchStatus, ChatBatch, ChatEvent, ChatJob, ChatStream, Config, Error, Guest,
+        GuestChatBatch, GuestChatJob, GuestChatStream, JobStatus, Message, ModelInfo, StreamEvent,
+        TokenCount, ToolCall, ToolResult,
     };
     use crate::init_logging;
+    use crate::retry::with_rate_limit;
+    use crate::tool_validation::{validate_tool_calls, ToolCallValidator};
+    use std::collections::HashMap;
 
     impl<Impl: ExtendedGuest> Guest for DurableLLM<Impl> {
-        type ChatStream = Impl::ChatStream;
+        type ChatStream = PassthroughChatStream<Impl>;
+        type Conversation = LlmConversation<Self>;
+        type ChatJob = PassthroughChatJob<Impl>;
+        type ChatBatch = PassthroughChatBatch<Impl>;
 
         fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
             init_logging();
-            Impl::send(messages, config)
+            validate_tool_calls(
+                &config,
+                with_cost::<Impl>(
+                    &config.model,
+                    with_rate_limit(|| Impl::send(messages, config.clone())),
+                ),
+            )
         }
 
         fn continue_(
@@ -84,12 +283,252 @@ mod passthrough_impl {
             config: Config,
         ) -> ChatEvent {
             init_logging();
-            Impl::continue_(messages, tool_results, config)
+            validate_tool_calls(
+                &config,
+                with_cost::<Impl>(
+                    &config.model,
+                    with_rate_limit(|| Impl::continue_(messages, tool_results, config.clone())),
+                ),
+            )
         }
 
         fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
             init_logging();
-            Impl::stream(messages, config)
+            ChatStream::new(PassthroughChatStream::<Impl>::new(messages, config))
+        }
+
+        fn list_models() -> Result<Vec<ModelInfo>, Error> {
+            init_logging();
+            Impl::list_models()
+        }
+
+        fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+            init_logging();
+            Impl::count_tokens(messages, config)
+        }
+
+        fn fetch_job(id: String) -> Result<ChatJob, Error> {
+            init_logging();
+            Impl::unwrapped_fetch_job(id)
+                .map(|job| ChatJob::new(PassthroughChatJob::<Impl> { job, config: None }))
+        }
+
+        fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+            init_logging();
+            Impl::unwrapped_fetch_batch(id).map(|batch| {
+                ChatBatch::new(PassthroughChatBatch::<Impl> {
+                    batch,
+                    configs: HashMap::new(),
+                })
+            })
+        }
+    }
+
+    /// Passes the events of the LLM specific stream through, validating their tool calls if
+    /// enabled in the config, and pricing the finishing events by the total usage
+    pub struct PassthroughChatStream<Impl: ExtendedGuest> {
+        stream: Impl::ChatStream,
+        tool_call_validator: Option<ToolCallValidator>,
+        pricing: StreamPricing<Impl>,
+    }
+
+    impl<Impl: ExtendedGuest> PassthroughChatStream<Impl> {
+        fn new(messages: Vec<Message>, config: Config) -> Self {
+            Self {
+                tool_call_validator: ToolCallValidator::new(&config),
+                pricing: StreamPricing::new(&config),
+                stream: Impl::unwrapped_stream(messages, config),
+            }
+        }
+
+        fn process_events(&self, events: &mut [StreamEvent]) {
+            if let Some(validator) = &self.tool_call_validator {
+                validator.validate_stream_events(events);
+            }
+            self.pricing.price_events(events);
+        }
+    }
+
+    impl<Impl: ExtendedGuest> GuestChatStream for PassthroughChatStream<Impl> {
+        fn get_next(&self) -> Option<Vec<StreamEvent>> {
+            let mut events = self.stream.get_next();
+            if let Some(events) = &mut events {
+                self.process_events(events);
+            }
+            events
+        }
+
+        fn blocking_get_next(&self) -> Vec<StreamEvent> {
+            let mut events = self.stream.blocking_get_next();
+            self.process_events(&mut events);
+            events
+        }
+
+        fn cancel(&self) {
+            self.stream.cancel()
+        }
+    }
+
+    /// Passes the statuses of the LLM specific job through, validating the tool calls of its
+    /// response and estimating its cost
+    pub struct PassthroughChatJob<Impl: ExtendedGuest> {
+        job: Impl::ChatJob,
+        /// The config the job was started with. It is not known for fetched jobs.
+        config: Option<Config>,
+    }
+
+    impl<Impl: ExtendedGuest> GuestChatJob for PassthroughChatJob<Impl> {
+        fn new(messages: Vec<Message>, config: Config) -> Self {
+            init_logging();
+            Self {
+                job: Impl::ChatJob::new(messages, config.clone()),
+                config: Some(config),
+            }
+        }
+
+        fn id(&self) -> Option<String> {
+            self.job.id()
+        }
+
+        fn poll(&self) -> Result<JobStatus, Error> {
+            self.job
+                .poll()
+                .map(|status| validate_job_status::<Impl>(self.config.as_ref(), status))
+        }
+
+        fn cancel(&self) -> Result<(), Error> {
+            self.job.cancel()
+        }
+    }
+
+    /// Passes the statuses of the LLM specific batch through, validating the tool calls of
+    /// its responses and estimating their cost
+    pub struct PassthroughChatBatch<Impl: ExtendedGuest> {
+        batch: Impl::ChatBatch,
+        /// The configs of the requests by their custom id. They are not known for fetched
+        /// batches.
+        configs: HashMap<String, Config>,
+    }
+
+    impl<Impl: ExtendedGuest> GuestChatBatch for PassthroughChatBatch<Impl> {
+        fn new(requests: Vec<BatchRequest>) -> Self {
+            init_logging();
+            Self {
+                configs: request_configs(&requests),
+                batch: Impl::ChatBatch::new(requests),
+            }
+        }
+
+        fn id(&self) -> Option<String> {
+            self.batch.id()
+        }
+
+        fn poll(&self) -> Result<BatchStatus, Error> {
+            self.batch
+                .poll()
+                .map(|status| validate_batch_status::<Impl>(&self.configs, status))
+        }
+
+        fn cancel(&self) -> Result<(), Error> {
+            self.batch.cancel()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::durability::passthrough_impl::{PassthroughChatJob, PassthroughChatStream};
+        use crate::durability::validate_batch_status;
+        use crate::golem::llm::llm::{
+            BatchResult, BatchStatus, ChatEvent, Config, GuestChatJob, GuestChatStream, JobStatus,
+            Role, StreamDelta, StreamEvent,
+        };
+        use crate::testing::{config, message, response, tool_call, TestModel};
+        use std::collections::HashMap;
+
+        fn validating_config() -> Config {
+            Config {
+                validate_tool_calls: Some(true),
+                ..config()
+            }
+        }
+
+        fn response_with_tool_call() -> ChatEvent {
+            match response("") {
+                ChatEvent::Message(mut response) => {
+                    response.tool_calls = vec![tool_call("call-1")];
+                    ChatEvent::Message(response)
+                }
+                event => event,
+            }
+        }
+
+        fn is_validated(event: &ChatEvent) -> bool {
+            match event {
+                ChatEvent::Message(response) => response
+                    .tool_calls
+                    .iter()
+                    .all(|tool_call| tool_call.validation_error.is_some()),
+                _ => false,
+            }
+        }
+
+        #[test]
+        fn validates_the_tool_calls_of_streams() {
+            let delta = StreamEvent::Delta(StreamDelta {
+                content: None,
+                tool_calls: Some(vec![tool_call("call-1")]),
+                logprobs: None,
+            });
+            TestModel::stream_with(vec![vec![delta.clone()], vec![delta]]);
+            let stream = PassthroughChatStream::<TestModel>::new(
+                vec![message(Role::User, "Hi")],
+                validating_config(),
+            );
+
+            for events in [stream.get_next().unwrap(), stream.blocking_get_next()] {
+                match events.as_slice() {
+                    [StreamEvent::Delta(delta)] => {
+                        let tool_calls = delta.tool_calls.as_ref().unwrap();
+                        // The test config has no tools, so the tool is unknown
+                        assert!(tool_calls[0].validation_error.is_some());
+                    }
+                    events => panic!("Unexpected events: {events:?}"),
+                }
+            }
+        }
+
+        #[test]
+        fn validates_the_tool_calls_of_jobs() {
+            TestModel::respond_with(vec![response_with_tool_call()]);
+            let job = PassthroughChatJob::<TestModel>::new(
+                vec![message(Role::User, "Hi")],
+                validating_config(),
+            );
+
+            match job.poll() {
+                Ok(JobStatus::Completed(event)) => assert!(is_validated(&event)),
+                status => panic!("Unexpected status: {status:?}"),
+            }
+        }
+
+        #[test]
+        fn validates_the_tool_calls_of_batches_by_their_requests() {
+            let result = |custom_id: &str| BatchResult {
+                custom_id: custom_id.to_string(),
+                event: response_with_tool_call(),
+            };
+            let configs = HashMap::from([("validated".to_string(), validating_config())]);
+
+            match validate_batch_status::<TestModel>(
+                &configs,
+                BatchStatus::Completed(vec![result("validated"), result("unknown")]),
+            ) {
+                BatchStatus::Completed(results) => {
+                    assert!(is_validated(&results[0].event));
+                    assert_eq!(results[1], result("unknown"));
+                }
+                status => panic!("Unexpected status: {status:?}"),
+            }
         }
     }
 }
@@ -104,12 +543,21 @@ mod passthrough_impl {
 /// which is implemented using the type classes and builder in the `golem-rust` library.
 #[cfg(feature = "durability")]
 mod durable_impl {
-    use crate::durability::{DurableLLM, ExtendedGuest};
+    use crate::chat_stream::cancelled_event;
+    use crate::continuation::{partial_text, prefill_prompt, Seam};
+    use crate::conversation::LlmConversation;
+    use crate::durability::{
+        request_configs, validate_batch_status, validate_job_status, with_cost, DurableLLM,
+        ExtendedGuest, StreamPricing,
+    };
     use crate::golem::llm::llm::{
-        ChatEvent, ChatStream, Config, Guest, GuestChatStream, Message, StreamDelta, StreamEvent,
-        ToolCall, ToolResult,
+        BatchRequest, BatchStatus, ChatBatch, ChatEvent, ChatJob, ChatStream, Config, Error, Guest,
+        GuestChatBatch, GuestChatJob, GuestChatStream, JobStatus, Message, ModelInfo, StreamDelta,
+        StreamEvent, TokenCount, ToolCall, ToolResult,
     };
     use crate::init_logging;
+    use crate::retry::with_rate_limit;
+    use crate::tool_validation::{validate_tool_calls, ToolCallValidator};
     use golem_rust::bindings::golem::durability::durability::DurableFunctionType;
     #[cfg(not(feature = "nopoll"))]
     use golem_rust::bindings::golem::durability::durability::LazyInitializedPollable;
@@ -117,10 +565,14 @@ mod durable_impl {
     use golem_rust::wasm_rpc::Pollable;
     use golem_rust::{with_persistence_level, FromValueAndType, IntoValue, PersistenceLevel};
     use std::cell::RefCell;
+    use std::collections::HashMap;
     use std::fmt::{Display, Formatter};
 
     impl<Impl: ExtendedGuest> Guest for DurableLLM<Impl> {
         type ChatStream = DurableChatStream<Impl>;
+        type Conversation = LlmConversation<Self>;
+        type ChatJob = DurableChatJob<Impl>;
+        type ChatBatch = DurableChatBatch<Impl>;
 
         fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
             init_logging();
@@ -132,7 +584,13 @@ mod durable_impl {
             );
             if durability.is_live() {
                 let result = with_persistence_level(PersistenceLevel::PersistNothing, || {
-                    Impl::send(messages.clone(), config.clone())
+                    validate_tool_calls(
+                        &config,
+                        with_cost::<Impl>(
+                            &config.model,
+                            with_rate_limit(|| Impl::send(messages.clone(), config.clone())),
+                        ),
+                    )
                 });
                 durability.persist_infallible(SendInput { messages, config }, result)
             } else {
@@ -154,7 +612,19 @@ mod durable_impl {
             );
             if durability.is_live() {
                 let result = with_persistence_level(PersistenceLevel::PersistNothing, || {
-                    Impl::continue_(messages.clone(), tool_results.clone(), config.clone())
+                    validate_tool_calls(
+                        &config,
+                        with_cost::<Impl>(
+                            &config.model,
+                            with_rate_limit(|| {
+                                Impl::continue_(
+                                    messages.clone(),
+                                    tool_results.clone(),
+                                    config.clone(),
+                                )
+                            }),
+                        ),
+                    )
                 });
                 durability.persist_infallible(
                     ContinueInput {
@@ -179,10 +649,10 @@ mod durable_impl {
             );
             if durability.is_live() {
                 let result = with_persistence_level(PersistenceLevel::PersistNothing, || {
-                    ChatStream::new(DurableChatStream::<Impl>::live(Impl::unwrapped_stream(
-                        messages.clone(),
-                        config.clone(),
-                    )))
+                    ChatStream::new(DurableChatStream::<Impl>::live(
+                        Impl::unwrapped_stream(messages.clone(), config.clone()),
+                        &config,
+                    ))
                 });
                 let _ = durability.persist_infallible(SendInput { messages, config }, NoOutput);
                 result
@@ -191,6 +661,63 @@ mod durable_impl {
                 ChatStream::new(DurableChatStream::<Impl>::replay(messages, config))
             }
         }
+
+        fn list_models() -> Result<Vec<ModelInfo>, Error> {
+            init_logging();
+
+            let durability = Durability::<ListModelsOutput, Error>::new(
+                "golem_llm",
+                "list_models",
+                DurableFunctionType::ReadRemote,
+            );
+            if durability.is_live() {
+                let result = with_persistence_level(PersistenceLevel::PersistNothing, || {
+                    Impl::list_models().map(|models| ListModelsOutput { models })
+                });
+                durability
+                    .persist(NoInput, result)
+                    .map(|output| output.models)
+            } else {
+                durability
+                    .replay()
+                    .map(|output: ListModelsOutput| output.models)
+            }
+        }
+
+        fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+            init_logging();
+
+            let durability = Durability::<TokenCount, Error>::new(
+                "golem_llm",
+                "count_tokens",
+                DurableFunctionType::ReadRemote,
+            );
+            if durability.is_live() {
+                let result = with_persistence_level(PersistenceLevel::PersistNothing, || {
+                    Impl::count_tokens(messages.clone(), config.clone())
+                });
+                durability.persist(CountTokensInput { messages, config }, result)
+            } else {
+                durability.replay()
+            }
+        }
+
+        /// Fetching a job only creates a handle for its id, the provider is contacted when the
+        /// job is polled or cancelled
+        fn fetch_job(id: String) -> Result<ChatJob, Error> {
+            init_logging();
+
+            Impl::unwrapped_fetch_job(id).map(|job| ChatJob::new(DurableChatJob::live(job, None)))
+        }
+
+        /// Fetching a batch only creates a handle for its id, the provider is contacted when
+        /// the batch is polled or cancelled
+        fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+            init_logging();
+
+            Impl::unwrapped_fetch_batch(id)
+                .map(|batch| ChatBatch::new(DurableChatBatch::live(batch, HashMap::new())))
+        }
     }
 
     /// Represents the durable chat stream's state
@@ -206,13 +733,25 @@ mod durable_impl {
     /// happens.
     ///
     /// When reaching the end of the replay mode, if the replayed stream was not finished yet,
-    /// the replay prompt implemented in `ExtendedGuest` is used to create a new LLM response
-    /// stream and continue the response seamlessly.
+    /// it is resumed from the last persisted event id if the provider supports it. Otherwise a
+    /// new LLM response stream is created to continue the response, with the partial response
+    /// prefilled as a trailing assistant message if the provider supports it, or with the retry
+    /// prompt implemented in `ExtendedGuest`. The text the new stream repeats from the end of
+    /// the partial response is removed by a `Seam` before the events are persisted.
+    ///
+    /// Cancellation is persisted as well, so a stream that was cancelled before the end of the
+    /// replay mode is never continued with a new LLM request.
+    ///
+    /// The usage of the finishing events is summed up, including the replayed ones, and the
+    /// finishing events are priced by the total before being persisted. The tool calls of the
+    /// live events are validated before being persisted as well, if enabled in the config.
     enum DurableChatStreamState<Impl: ExtendedGuest> {
         Live {
             stream: Impl::ChatStream,
             #[cfg(not(feature = "nopoll"))]
             pollables: Vec<LazyInitializedPollable>,
+            /// Set when the stream continues an interrupted response by asking the model again
+            seam: Option<Seam>,
         },
         Replay {
             original_messages: Vec<Message>,
@@ -220,40 +759,65 @@ mod durable_impl {
             #[cfg(not(feature = "nopoll"))]
             pollables: Vec<LazyInitializedPollable>,
             partial_result: Vec<StreamDelta>,
+            last_event_id: Option<String>,
             finished: bool,
+            cancelled: bool,
         },
     }
 
     pub struct DurableChatStream<Impl: ExtendedGuest> {
         state: RefCell<Option<DurableChatStreamState<Impl>>>,
         subscription: RefCell<Option<Pollable>>,
+        pricing: StreamPricing<Impl>,
+        tool_call_validator: Option<ToolCallValidator>,
     }
 
     impl<Impl: ExtendedGuest> DurableChatStream<Impl> {
-        fn live(stream: Impl::ChatStream) -> Self {
+        fn live(stream: Impl::ChatStream, config: &Config) -> Self {
             Self {
                 state: RefCell::new(Some(DurableChatStreamState::Live {
                     stream,
                     #[cfg(not(feature = "nopoll"))]
                     pollables: Vec::new(),
+                    seam: None,
                 })),
                 subscription: RefCell::new(None),
+                pricing: StreamPricing::new(config),
+                tool_call_validator: ToolCallValidator::new(config),
             }
         }
 
         fn replay(original_messages: Vec<Message>, config: Config) -> Self {
             Self {
+                pricing: StreamPricing::new(&config),
+                tool_call_validator: ToolCallValidator::new(&config),
                 state: RefCell::new(Some(DurableChatStreamState::Replay {
                     original_messages,
                     config,
                     #[cfg(not(feature = "nopoll"))]
                     pollables: Vec::new(),
                     partial_result: Vec::new(),
+                    last_event_id: None,
                     finished: false,
+                    cancelled: false,
                 })),
                 subscription: RefCell::new(None),
             }
         }
+        /// Validates the tool calls of the live events, before they are persisted
+        fn validate_events(&self, events: &mut Option<Vec<StreamEvent>>) {
+            if let (Some(validator), Some(events)) = (&self.tool_call_validator, events) {
+                validator.validate_stream_events(events);
+            }
+        }
+
+        /// Adds the usage of the finishing events to the total, and fills their cost from it
+        fn price_events(&self, events: &mut Option<Vec<StreamEvent>>) {
+            if let Some(events) = events {
+                self.pricing.price_events(events);
+            }
+        }
+
         #[cfg(not(feature = "nopoll"))]
         fn subscribe(&self) -> Pollable {
             let mut state = self.state.borrow_mut();
@@ -272,6 +836,25 @@ mod durable_impl {
         }
     }
 
+    /// Removes the repeated text from the start of a continuation. When all the received text is
+    /// held back, no events are returned, like when the stream has no new events yet. The held
+    /// back text is emitted when the stream ends without any other event.
+    fn join_seam(seam: &mut Option<Seam>, events: &mut Option<Vec<StreamEvent>>) {
+        if let Some(seam) = seam {
+            if let Some(received) = events.take() {
+                let received_any = !received.is_empty();
+                let joined = if received_any {
+                    seam.process(received)
+                } else {
+                    seam.finish()
+                };
+                if !joined.is_empty() || !received_any {
+                    *events = Some(joined);
+                }
+            }
+        }
+    }
+
     impl<Impl: ExtendedGuest> Drop for DurableChatStream<Impl> {
         fn drop(&mut self) {
             let _ = self.subscription.take();
@@ -281,6 +864,7 @@ mod durable_impl {
                     #[cfg(not(feature = "nopoll"))]
                     mut pollables,
                     stream,
+                    ..
                 }) => {
                     with_persistence_level(PersistenceLevel::PersistNothing, move || {
                         #[cfg(not(feature = "nopoll"))]
@@ -303,20 +887,26 @@ mod durable_impl {
 
     impl<Impl: ExtendedGuest> GuestChatStream for DurableChatStream<Impl> {
         fn get_next(&self) -> Option<Vec<StreamEvent>> {
-            let durability = Durability::<Option<Vec<StreamEvent>>, UnusedError>::new(
+            let durability = Durability::<GetNextOutput, UnusedError>::new(
                 "golem_llm",
                 "get_next",
                 DurableFunctionType::ReadRemote,
             );
             if durability.is_live() {
                 let mut state = self.state.borrow_mut();
-                let (result, new_live_stream) = match &*state {
-                    Some(DurableChatStreamState::Live { stream, .. }) => {
-                        let result =
+                let (result, new_live_stream) = match &mut *state {
+                    Some(DurableChatStreamState::Live { stream, seam, .. }) => {
+                        let mut output =
                             with_persistence_level(PersistenceLevel::PersistNothing, || {
-                                stream.get_next()
+                                GetNextOutput {
+                                    events: stream.get_next(),
+                                    last_event_id: Impl::last_event_id(stream),
+                                }
                             });
-                        (durability.persist_infallible(NoInput, result.clone()), None)
+                        join_seam(seam, &mut output.events);
+                        self.validate_events(&mut output.events);
+                        self.price_events(&mut output.events);
+                        (durability.persist_infallible(NoInput, output).events, None)
                     }
                     Some(DurableChatStreamState::Replay {
                         original_messages,
@@ -324,31 +914,74 @@ mod durable_impl {
                         #[cfg(not(feature = "nopoll"))]
                         pollables,
                         partial_result,
+                        last_event_id,
                         finished,
+                        cancelled,
                     }) => {
                         if *finished {
                             (None, None)
+                        } else if *cancelled {
+                            *finished = true;
+                            let output = GetNextOutput {
+                                events: Some(vec![cancelled_event()]),
+                                last_event_id: None,
+                            };
+                            (durability.persist_infallible(NoInput, output).events, None)
                         } else {
-                            let extended_messages =
-                                Impl::retry_prompt(original_messages, partial_result);
-
-                            let (stream, first_live_result) =
+                            let (stream, mut seam, mut first_live_output) =
                                 with_persistence_level(PersistenceLevel::PersistNothing, || {
-                                    let stream = <Impl as ExtendedGuest>::unwrapped_stream(
-                                        extended_messages,
-                                        config.clone(),
-                                    );
+                                    let resumed =
+                                        last_event_id.as_deref().and_then(|last_event_id| {
+                                            Impl::resume_stream(
+                                                original_messages,
+                                                config,
+                                                last_event_id,
+                                            )
+                                        });
+                                    let (stream, seam) = match resumed {
+                                        Some(stream) => (stream, None),
+                                        None => {
+                                            let messages = Impl::supports_prefill(config)
+                                                .then(|| {
+                                                    prefill_prompt(
+                                                        original_messages,
+                                                        partial_result,
+                                                    )
+                                                })
+                                                .flatten()
+                                                .unwrap_or_else(|| {
+                                                    Impl::retry_prompt(
+                                                        original_messages,
+                                                        partial_result,
+                                                    )
+                                                });
+                                            (
+                                                <Impl as ExtendedGuest>::unwrapped_stream(
+                                                    messages,
+                                                    config.clone(),
+                                                ),
+                                                Seam::new(&partial_text(partial_result)),
+                                            )
+                                        }
+                                    };
                                     #[cfg(not(feature = "nopoll"))]
                                     for lazy_initialized_pollable in pollables {
                                         lazy_initialized_pollable.set(Impl::subscribe(&stream));
                                     }
 
-                                    let next = stream.get_next();
-                                    (stream, next)
+                                    let next = GetNextOutput {
+                                        events: stream.get_next(),
+                                        last_event_id: Impl::last_event_id(&stream),
+                                    };
+                                    (stream, seam, next)
                                 });
-                            durability.persist_infallible(NoInput, first_live_result.clone());
+                            join_seam(&mut seam, &mut first_live_output.events);
+                            self.validate_events(&mut first_live_output.events);
+                            self.price_events(&mut first_live_output.events);
+                            let first_live_output =
+                                durability.persist_infallible(NoInput, first_live_output);
 
-                            (first_live_result, Some(stream))
+                            (first_live_output.events, Some((stream, seam)))
                         }
                     }
                     None => {
@@ -356,7 +989,7 @@ mod durable_impl {
                     }
                 };
 
-                if let Some(stream) = new_live_stream {
+                if let Some((stream, seam)) = new_live_stream {
                     #[cfg(not(feature = "nopoll"))]
                     let pollables = match state.take() {
                         Some(DurableChatStreamState::Live { pollables, .. }) => pollables,
@@ -369,12 +1002,15 @@ mod durable_impl {
                         stream,
                         #[cfg(not(feature = "nopoll"))]
                         pollables,
+                        seam,
                     });
                 }
 
                 result
             } else {
-                let result: Option<Vec<StreamEvent>> = durability.replay_infallible();
+                let mut output: GetNextOutput = durability.replay_infallible();
+                self.price_events(&mut output.events);
+                let result = output.events;
                 let mut state = self.state.borrow_mut();
                 match &mut *state {
                     Some(DurableChatStreamState::Live { .. }) => {
@@ -382,9 +1018,13 @@ mod durable_impl {
                     }
                     Some(DurableChatStreamState::Replay {
                         partial_result,
+                        last_event_id,
                         finished,
                         ..
                     }) => {
+                        if output.last_event_id.is_some() {
+                            *last_event_id = output.last_event_id;
+                        }
                         if let Some(result) = &result {
                             for event in result {
                                 match event {
@@ -427,10 +1067,367 @@ mod durable_impl {
                         result.extend(events);
                         break result;
                     }
-                    None => continue,
+                    None => {
+                        // A reconnected live stream has a new pollable, while the lazily
+                        // initialized pollables of the replay are kept until it gets live
+                        #[cfg(not(feature = "nopoll"))]
+                        if matches!(
+                            &*self.state.borrow(),
+                            Some(DurableChatStreamState::Live { .. })
+                        ) {
+                            *subscription = self.subscribe();
+                        }
+                        continue;
+                    }
                 }
             }
         }
+
+        fn cancel(&self) {
+            let durability = Durability::<NoOutput, UnusedError>::new(
+                "golem_llm",
+                "cancel",
+                DurableFunctionType::WriteRemote,
+            );
+            if durability.is_live() {
+                let mut state = self.state.borrow_mut();
+                match &mut *state {
+                    Some(DurableChatStreamState::Live { stream, .. }) => {
+                        with_persistence_level(PersistenceLevel::PersistNothing, || {
+                            stream.cancel()
+                        });
+                    }
+                    Some(DurableChatStreamState::Replay {
+                        finished,
+                        cancelled,
+                        ..
+                    }) => {
+                        if !*finished {
+                            *cancelled = true;
+                        }
+                    }
+                    None => {
+                        unreachable!()
+                    }
+                }
+                let _ = durability.persist_infallible(NoInput, NoOutput);
+            } else {
+                let _: NoOutput = durability.replay_infallible();
+                let mut state = self.state.borrow_mut();
+                match &mut *state {
+                    Some(DurableChatStreamState::Live { .. }) => {
+                        unreachable!("Durable chat stream cannot be in live mode during replay")
+                    }
+                    Some(DurableChatStreamState::Replay {
+                        finished,
+                        cancelled,
+                        ..
+                    }) => {
+                        if !*finished {
+                            *cancelled = true;
+                        }
+                    }
+                    None => {
+                        unreachable!()
+                    }
+                }
+            }
+        }
+    }
+
+    /// Represents the durable chat job's state
+    ///
+    /// Starting the job persists its id, so in replay mode no new job is started. The polls
+    /// and cancellations are persisted as well, and when the job is used after the end of the
+    /// replay, the live job is fetched by its id, continuing to poll the same job instead of
+    /// generating the response again. Only jobs of providers without background jobs, which
+    /// have no id, are started again with the original request.
+    enum DurableChatJobState<Impl: ExtendedGuest> {
+        Live(Impl::ChatJob),
+        Replay {
+            id: Option<String>,
+            messages: Vec<Message>,
+            config: Config,
+        },
+    }
+
+    pub struct DurableChatJob<Impl: ExtendedGuest> {
+        state: RefCell<DurableChatJobState<Impl>>,
+        /// The final status of the job once it has finished, which is returned by all further
+        /// polls without contacting the provider
+        final_status: RefCell<Option<JobStatus>>,
+        /// The config the job was started with, for validating the tool calls of its response.
+        /// It is not known for fetched jobs.
+        config: Option<Config>,
+    }
+
+    impl<Impl: ExtendedGuest> DurableChatJob<Impl> {
+        fn live(job: Impl::ChatJob, config: Option<Config>) -> Self {
+            Self {
+                state: RefCell::new(DurableChatJobState::Live(job)),
+                final_status: RefCell::new(None),
+                config,
+            }
+        }
+
+        /// Calls `f` with the live job, fetching or restarting it first after a replay
+        fn with_live_job<T>(
+            &self,
+            f: impl FnOnce(&Impl::ChatJob) -> Result<T, Error>,
+        ) -> Result<T, Error> {
+            let mut state = self.state.borrow_mut();
+            if let DurableChatJobState::Replay {
+                id,
+                messages,
+                config,
+            } = &*state
+            {
+                let job = match id {
+                    Some(id) => Impl::unwrapped_fetch_job(id.clone())?,
+                    None => Impl::ChatJob::new(messages.clone(), config.clone()),
+                };
+                *state = DurableChatJobState::Live(job);
+            }
+            match &*state {
+                DurableChatJobState::Live(job) => f(job),
+                DurableChatJobState::Replay { .. } => unreachable!(),
+            }
+        }
+    }
+
+    impl<Impl: ExtendedGuest> GuestChatJob for DurableChatJob<Impl> {
+        fn new(messages: Vec<Message>, config: Config) -> Self {
+            init_logging();
+
+            let durability = Durability::<StartJobOutput, UnusedError>::new(
+                "golem_llm",
+                "start_job",
+                DurableFunctionType::WriteRemote,
+            );
+            if durability.is_live() {
+                let job = with_persistence_level(PersistenceLevel::PersistNothing, || {
+                    Impl::ChatJob::new(messages.clone(), config.clone())
+                });
+                let _ = durability.persist_infallible(
+                    SendInput {
+                        messages,
+                        config: config.clone(),
+                    },
+                    StartJobOutput { id: job.id() },
+                );
+                Self::live(job, Some(config))
+            } else {
+                let output: StartJobOutput = durability.replay_infallible();
+                Self {
+                    state: RefCell::new(DurableChatJobState::Replay {
+                        id: output.id,
+                        messages,
+                        config: config.clone(),
+                    }),
+                    final_status: RefCell::new(None),
+                    config: Some(config),
+                }
+            }
+        }
+
+        fn id(&self) -> Option<String> {
+            match &*self.state.borrow() {
+                DurableChatJobState::Live(job) => job.id(),
+                DurableChatJobState::Replay { id, .. } => id.clone(),
+            }
+        }
+
+        fn poll(&self) -> Result<JobStatus, Error> {
+            if let Some(status) = self.final_status.borrow().clone() {
+                return Ok(status);
+            }
+
+            let durability = Durability::<JobStatus, Error>::new(
+                "golem_llm",
+                "poll_job",
+                DurableFunctionType::ReadRemote,
+            );
+            let result = if durability.is_live() {
+                let result = with_persistence_level(PersistenceLevel::PersistNothing, || {
+                    self.with_live_job(|job| job.poll())
+                })
+                .map(|status| validate_job_status::<Impl>(self.config.as_ref(), status));
+                durability.persist(NoInput, result)
+            } else {
+                durability.replay()
+            };
+
+            if let Ok(status @ (JobStatus::Completed(_) | JobStatus::Cancelled)) = &result {
+                *self.final_status.borrow_mut() = Some(status.clone());
+            }
+            result
+        }
+
+        fn cancel(&self) -> Result<(), Error> {
+            if self.final_status.borrow().is_some() {
+                return Ok(());
+            }
+
+            let durability = Durability::<NoOutput, Error>::new(
+                "golem_llm",
+                "cancel_job",
+                DurableFunctionType::WriteRemote,
+            );
+            let result = if durability.is_live() {
+                let result = with_persistence_level(PersistenceLevel::PersistNothing, || {
+                    self.with_live_job(|job| job.cancel())
+                });
+                durability.persist(NoInput, result.map(|_| NoOutput))
+            } else {
+                durability.replay()
+            };
+            result.map(|_: NoOutput| ())
+        }
+    }
+
+    /// Represents the durable chat batch's state
+    ///
+    /// Submitting the batch persists its id, so in replay mode the batch is never submitted
+    /// again. The polls and cancellations are persisted as well, and when the batch is used
+    /// after the end of the replay, the live batch is fetched by its id. Only a batch which
+    /// failed to be submitted, and so has no id, is submitted again with the original requests.
+    enum DurableChatBatchState<Impl: ExtendedGuest> {
+        Live(Impl::ChatBatch),
+        Replay {
+            id: Option<String>,
+            requests: Vec<BatchRequest>,
+        },
+    }
+
+    pub struct DurableChatBatch<Impl: ExtendedGuest> {
+        state: RefCell<DurableChatBatchState<Impl>>,
+        /// The final status of the batch once it has finished, which is returned by all further
+        /// polls without contacting the provider
+        final_status: RefCell<Option<BatchStatus>>,
+        /// The configs of the requests by their custom id, for validating the tool calls of
+        /// their responses. They are not known for fetched batches.
+        configs: HashMap<String, Config>,
+    }
+
+    impl<Impl: ExtendedGuest> DurableChatBatch<Impl> {
+        fn live(batch: Impl::ChatBatch, configs: HashMap<String, Config>) -> Self {
+            Self {
+                state: RefCell::new(DurableChatBatchState::Live(batch)),
+                final_status: RefCell::new(None),
+                configs,
+            }
+        }
+
+        /// Calls `f` with the live batch, fetching or resubmitting it first after a replay
+        fn with_live_batch<T>(
+            &self,
+            f: impl FnOnce(&Impl::ChatBatch) -> Result<T, Error>,
+        ) -> Result<T, Error> {
+            let mut state = self.state.borrow_mut();
+            if let DurableChatBatchState::Replay { id, requests } = &*state {
+                let batch = match id {
+                    Some(id) => Impl::unwrapped_fetch_batch(id.clone())?,
+                    None => Impl::ChatBatch::new(requests.clone()),
+                };
+                *state = DurableChatBatchState::Live(batch);
+            }
+            match &*state {
+                DurableChatBatchState::Live(batch) => f(batch),
+                DurableChatBatchState::Replay { .. } => unreachable!(),
+            }
+        }
+    }
+
+    impl<Impl: ExtendedGuest> GuestChatBatch for DurableChatBatch<Impl> {
+        fn new(requests: Vec<BatchRequest>) -> Self {
+            init_logging();
+
+            let durability = Durability::<SubmitBatchOutput, UnusedError>::new(
+                "golem_llm",
+                "submit_batch",
+                DurableFunctionType::WriteRemote,
+            );
+            if durability.is_live() {
+                let batch = with_persistence_level(PersistenceLevel::PersistNothing, || {
+                    Impl::ChatBatch::new(requests.clone())
+                });
+                let configs = request_configs(&requests);
+                let _ = durability.persist_infallible(
+                    SubmitBatchInput { requests },
+                    SubmitBatchOutput { id: batch.id() },
+                );
+                Self::live(batch, configs)
+            } else {
+                let output: SubmitBatchOutput = durability.replay_infallible();
+                Self {
+                    configs: request_configs(&requests),
+                    state: RefCell::new(DurableChatBatchState::Replay {
+                        id: output.id,
+                        requests,
+                    }),
+                    final_status: RefCell::new(None),
+                }
+            }
+        }
+
+        fn id(&self) -> Option<String> {
+            match &*self.state.borrow() {
+                DurableChatBatchState::Live(batch) => batch.id(),
+                DurableChatBatchState::Replay { id, .. } => id.clone(),
+            }
+        }
+
+        fn poll(&self) -> Result<BatchStatus, Error> {
+            if let Some(status) = self.final_status.borrow().clone() {
+                return Ok(status);
+            }
+
+            let durability = Durability::<BatchStatus, Error>::new(
+                "golem_llm",
+                "poll_batch",
+                DurableFunctionType::ReadRemote,
+            );
+            let result = if durability.is_live() {
+                let result = with_persistence_level(PersistenceLevel::PersistNothing, || {
+                    self.with_live_batch(|batch| batch.poll())
+                })
+                .map(|status| validate_batch_status::<Impl>(&self.configs, status));
+                durability.persist(NoInput, result)
+            } else {
+                durability.replay()
+            };
+
+            if let Ok(
+                status @ (BatchStatus::Completed(_)
+                | BatchStatus::Cancelled(_)
+                | BatchStatus::Failed(_)),
+            ) = &result
+            {
+                *self.final_status.borrow_mut() = Some(status.clone());
+            }
+            result
+        }
+
+        fn cancel(&self) -> Result<(), Error> {
+            if self.final_status.borrow().is_some() {
+                return Ok(());
+            }
+
+            let durability = Durability::<NoOutput, Error>::new(
+                "golem_llm",
+                "cancel_batch",
+                DurableFunctionType::WriteRemote,
+            );
+            let result = if durability.is_live() {
+                let result = with_persistence_level(PersistenceLevel::PersistNothing, || {
+                    self.with_live_batch(|batch| batch.cancel())
+                });
+                durability.persist(NoInput, result.map(|_| NoOutput))
+            } else {
+                durability.replay()
+            };
+            result.map(|_: NoOutput| ())
+        }
     }
 
     #[derive(Debug, Clone, PartialEq, IntoValue)]
@@ -446,12 +1443,46 @@ mod durable_impl {
         config: Config,
     }
 
+    #[derive(Debug, IntoValue)]
+    struct SubmitBatchInput {
+        requests: Vec<BatchRequest>,
+    }
+
+    #[derive(Debug, IntoValue)]
+    struct CountTokensInput {
+        messages: Vec<Message>,
+        config: Config,
+    }
+
     #[derive(Debug, IntoValue)]
     struct NoInput;
 
     #[derive(Debug, Clone, FromValueAndType, IntoValue)]
     struct NoOutput;
 
+    /// The events returned by the chat stream, and the id of its last received event for
+    /// resuming it if it gets interrupted
+    #[derive(Debug, Clone, PartialEq, FromValueAndType, IntoValue)]
+    struct GetNextOutput {
+        events: Option<Vec<StreamEvent>>,
+        last_event_id: Option<String>,
+    }
+
+    #[derive(Debug, Clone, FromValueAndType, IntoValue)]
+    struct StartJobOutput {
+        id: Option<String>,
+    }
+
+    #[derive(Debug, Clone, FromValueAndType, IntoValue)]
+    struct SubmitBatchOutput {
+        id: Option<String>,
+    }
+
+    #[derive(Debug, Clone, FromValueAndType, IntoValue)]
+    struct ListModelsOutput {
+        models: Vec<ModelInfo>,
+    }
+
     #[derive(Debug, FromValueAndType, IntoValue)]
     struct UnusedError;
 
@@ -463,11 +1494,15 @@ mod durable_impl {
 
     #[cfg(test)]
     mod tests {
-        use crate::durability::durable_impl::SendInput;
+        use crate::durability::durable_impl::{GetNextOutput, SendInput};
         use crate::golem::llm::llm::{
-            ChatEvent, CompleteResponse, Config, ContentPart, Error, ErrorCode, FinishReason,
-            ImageDetail, ImageReference, ImageSource, ImageUrl, Message, ResponseMetadata, Role,
-            ToolCall, Usage,
+            Alternative, AudioSource, BatchProgress, BatchResult, BatchStatus, BuiltinToolResult,
+            CacheControl, CacheTtl, ChatEvent, Citation, CodeExecution, CompleteResponse, Config,
+            ContentPart, Cost, DocumentData, DocumentSource, Error, ErrorCode, FinishReason,
+            ImageDetail, ImageReference, ImageSource, ImageUrl, JsonSchemaFormat, Message,
+            ModelCapabilities, ModelInfo, RateLimit, ReasoningConfig, ReasoningContent,
+            ReasoningEffort, ResponseFormat, ResponseMetadata, Role, StreamDelta, StreamEvent,
+            TokenCount, TokenLogprob, ToolCall, TopLogprob, Usage,
         };
         use golem_rust::value_and_type::{FromValueAndType, IntoValueAndType};
         use golem_rust::wasm_rpc::WitTypeNode;
@@ -502,6 +1537,44 @@ mod durable_impl {
             });
         }
 
+        #[test]
+        fn model_info_roundtrip() {
+            roundtrip_test(ModelInfo {
+                id: "claude-sonnet-4-20250514".to_string(),
+                context_window: Some(200000),
+                max_output_tokens: Some(64000),
+                capabilities: ModelCapabilities {
+                    tools: true,
+                    vision: true,
+                    reasoning: true,
+                    json_schema: true,
+                },
+            });
+            roundtrip_test(ModelInfo {
+                id: "llama3.2:latest".to_string(),
+                context_window: None,
+                max_output_tokens: None,
+                capabilities: ModelCapabilities {
+                    tools: false,
+                    vision: false,
+                    reasoning: false,
+                    json_schema: true,
+                },
+            });
+        }
+
+        #[test]
+        fn token_count_roundtrip() {
+            roundtrip_test(TokenCount {
+                input_tokens: 1234,
+                estimated: false,
+            });
+            roundtrip_test(TokenCount {
+                input_tokens: 0,
+                estimated: true,
+            });
+        }
+
         #[test]
         fn image_url_roundtrip() {
             roundtrip_test(ImageUrl {
@@ -540,6 +1613,64 @@ mod durable_impl {
                 mime_type: "image/jpeg".to_string(),
                 detail: Some(ImageDetail::Auto),
             })));
+            roundtrip_test(ContentPart::Reasoning(ReasoningContent {
+                text: "Let me think".to_string(),
+                signature: Some("signature".to_string()),
+                redacted_data: None,
+            }));
+            roundtrip_test(ContentPart::Reasoning(ReasoningContent {
+                text: "".to_string(),
+                signature: None,
+                redacted_data: Some("redacted".to_string()),
+            }));
+            roundtrip_test(ContentPart::Document(DocumentSource {
+                data: DocumentData::Url("https://example.com/document.pdf".to_string()),
+                mime_type: "application/pdf".to_string(),
+                title: Some("Document".to_string()),
+            }));
+            roundtrip_test(ContentPart::Document(DocumentSource {
+                data: DocumentData::Inline(vec![0, 1, 2, 3]),
+                mime_type: "text/plain".to_string(),
+                title: None,
+            }));
+            roundtrip_test(ContentPart::Audio(AudioSource {
+                data: vec![0, 1, 2, 3, 4, 5],
+                mime_type: "audio/wav".to_string(),
+            }));
+        }
+
+        #[test]
+        fn cache_control_roundtrip() {
+            roundtrip_test(CacheControl { ttl: None });
+            roundtrip_test(CacheControl {
+                ttl: Some(CacheTtl::FiveMinutes),
+            });
+            roundtrip_test(CacheControl {
+                ttl: Some(CacheTtl::OneHour),
+            });
+        }
+
+        #[test]
+        fn response_format_roundtrip() {
+            roundtrip_test(ResponseFormat::Text);
+            roundtrip_test(ResponseFormat::JsonObject);
+            roundtrip_test(ResponseFormat::JsonSchema(JsonSchemaFormat {
+                name: "answer".to_string(),
+                schema: "{\"type\": \"object\"}".to_string(),
+                strict: Some(true),
+            }));
+        }
+
+        #[test]
+        fn reasoning_config_roundtrip() {
+            roundtrip_test(ReasoningConfig {
+                effort: Some(ReasoningEffort::Medium),
+                budget_tokens: Some(2048),
+            });
+            roundtrip_test(ReasoningConfig {
+                effort: None,
+                budget_tokens: None,
+            });
         }
 
         #[test]
@@ -548,11 +1679,17 @@ mod durable_impl {
                 input_tokens: Some(100),
                 output_tokens: Some(200),
                 total_tokens: Some(300),
+                cache_read_tokens: Some(80),
+                cache_write_tokens: Some(20),
+                reasoning_tokens: Some(50),
             });
             roundtrip_test(Usage {
                 input_tokens: None,
                 output_tokens: None,
                 total_tokens: None,
+                cache_read_tokens: None,
+                cache_write_tokens: None,
+                reasoning_tokens: None,
             });
         }
 
@@ -564,10 +1701,28 @@ mod durable_impl {
                     input_tokens: Some(100),
                     output_tokens: None,
                     total_tokens: Some(100),
+                    cache_read_tokens: None,
+                    cache_write_tokens: None,
+                    reasoning_tokens: None,
                 }),
                 provider_id: Some("provider_id".to_string()),
                 timestamp: Some("2023-10-01T00:00:00Z".to_string()),
                 provider_metadata_json: Some("{\"key\": \"value\"}".to_string()),
+                rate_limit: Some(RateLimit {
+                    requests_limit: Some(1000),
+                    requests_remaining: Some(999),
+                    requests_reset: Some("60ms".to_string()),
+                    tokens_limit: Some(100000),
+                    tokens_remaining: None,
+                    tokens_reset: Some("2023-10-01T00:00:01Z".to_string()),
+                }),
+                cost: Some(Cost {
+                    input: 0.0003,
+                    cached: 0.0,
+                    output: 0.0,
+                    reasoning: 0.0,
+                    total: 0.0003,
+                }),
             });
             roundtrip_test(ResponseMetadata {
                 finish_reason: None,
@@ -575,6 +1730,8 @@ mod durable_impl {
                 provider_id: None,
                 timestamp: None,
                 provider_metadata_json: None,
+                rate_limit: None,
+                cost: None,
             });
         }
 
@@ -593,6 +1750,7 @@ mod durable_impl {
                     id: "x".to_string(),
                     name: "y".to_string(),
                     arguments_json: "\"z\"".to_string(),
+                    validation_error: None,
                 }],
                 metadata: ResponseMetadata {
                     finish_reason: Some(FinishReason::Stop),
@@ -600,7 +1758,37 @@ mod durable_impl {
                     provider_id: None,
                     timestamp: None,
                     provider_metadata_json: None,
+                    rate_limit: None,
+                    cost: None,
                 },
+                logprobs: Some(vec![TokenLogprob {
+                    token: "Hello".to_string(),
+                    logprob: -0.25,
+                    top_logprobs: vec![TopLogprob {
+                        token: "Hi".to_string(),
+                        logprob: -1.5,
+                    }],
+                }]),
+                alternatives: vec![Alternative {
+                    index: 1,
+                    content: vec![ContentPart::Text("Hi".to_string())],
+                    tool_calls: vec![],
+                    finish_reason: Some(FinishReason::Length),
+                    logprobs: None,
+                }],
+                builtin_tool_results: vec![
+                    BuiltinToolResult::Citations(vec![Citation {
+                        url: Some("https://example.com".to_string()),
+                        file_id: None,
+                        title: Some("Example".to_string()),
+                        cited_text: None,
+                    }]),
+                    BuiltinToolResult::CodeExecution(CodeExecution {
+                        code: Some("print(1 + 1)".to_string()),
+                        output: Some("2".to_string()),
+                        exit_code: Some(0),
+                    }),
+                ],
             });
         }
 
@@ -609,6 +1797,11 @@ mod durable_impl {
             roundtrip_test(ChatEvent::Message(CompleteResponse {
                 id: "response_id".to_string(),
                 content: vec![
+                    ContentPart::Reasoning(ReasoningContent {
+                        text: "Thinking".to_string(),
+                        signature: Some("signature".to_string()),
+                        redacted_data: None,
+                    }),
                     ContentPart::Text("Hello".to_string()),
                     ContentPart::Image(ImageReference::Url(ImageUrl {
                         url: "https://example.com/image.png".to_string(),
@@ -619,6 +1812,7 @@ mod durable_impl {
                     id: "x".to_string(),
                     name: "y".to_string(),
                     arguments_json: "\"z\"".to_string(),
+                    validation_error: None,
                 }],
                 metadata: ResponseMetadata {
                     finish_reason: Some(FinishReason::Stop),
@@ -626,12 +1820,18 @@ mod durable_impl {
                     provider_id: None,
                     timestamp: None,
                     provider_metadata_json: None,
+                    rate_limit: None,
+                    cost: None,
                 },
+                logprobs: None,
+                alternatives: vec![],
+                builtin_tool_results: vec![],
             }));
             roundtrip_test(ChatEvent::ToolRequest(vec![ToolCall {
                 id: "x".to_string(),
                 name: "y".to_string(),
                 arguments_json: "\"z\"".to_string(),
+                validation_error: None,
             }]));
             roundtrip_test(ChatEvent::Error(Error {
                 code: ErrorCode::InvalidRequest,
@@ -640,6 +1840,54 @@ mod durable_impl {
             }));
         }
 
+        #[test]
+        fn get_next_output_roundtrip() {
+            roundtrip_test(GetNextOutput {
+                events: Some(vec![StreamEvent::Delta(StreamDelta {
+                    content: Some(vec![ContentPart::Text("Hello".to_string())]),
+                    tool_calls: None,
+                    logprobs: None,
+                })]),
+                last_event_id: Some("resp_123:42".to_string()),
+            });
+            roundtrip_test(GetNextOutput {
+                events: None,
+                last_event_id: None,
+            });
+        }
+
+        #[test]
+        fn batch_status_roundtrip() {
+            roundtrip_test(BatchStatus::InProgress(BatchProgress {
+                total: Some(100),
+                finished: None,
+            }));
+            roundtrip_test(BatchStatus::Completed(vec![
+                BatchResult {
+                    custom_id: "request-1".to_string(),
+                    event: ChatEvent::ToolRequest(vec![ToolCall {
+                        id: "x".to_string(),
+                        name: "y".to_string(),
+                        arguments_json: "\"z\"".to_string(),
+                        validation_error: None,
+                    }]),
+                },
+                BatchResult {
+                    custom_id: "request-2".to_string(),
+                    event: ChatEvent::Error(Error {
+                        code: ErrorCode::InternalError,
+                        message: "Request expired".to_string(),
+                        provider_error_json: None,
+                    }),
+                },
+            ]));
+            roundtrip_test(BatchStatus::Failed(Error {
+                code: ErrorCode::InvalidRequest,
+                message: "Invalid batch".to_string(),
+                provider_error_json: None,
+            }));
+        }
+
         #[test]
         fn send_input_encoding() {
             let input = SendInput {
@@ -648,6 +1896,9 @@ mod durable_impl {
                         role: Role::User,
                         name: Some("user".to_string()),
                         content: vec![ContentPart::Text("Hello".to_string())],
+                        cache_control: Some(CacheControl {
+                            ttl: Some(CacheTtl::OneHour),
+                        }),
                     },
                     Message {
                         role: Role::Assistant,
@@ -656,6 +1907,7 @@ mod durable_impl {
                             url: "https://example.com/image.png".to_string(),
                             detail: Some(ImageDetail::High),
                         }))],
+                        cache_control: None,
                     },
                     Message {
                         role: Role::User,
@@ -668,6 +1920,7 @@ mod durable_impl {
                                 detail: None,
                             })),
                         ],
+                        cache_control: None,
                     },
                 ],
                 config: Config {
@@ -676,8 +1929,21 @@ mod durable_impl {
                     max_tokens: Some(100),
                     stop_sequences: Some(vec!["\n".to_string()]),
                     tools: vec![],
+                    builtin_tools: vec![],
                     tool_choice: None,
                     provider_options: vec![],
+                    response_format: Some(ResponseFormat::JsonObject),
+                    reasoning: Some(ReasoningConfig {
+                        effort: Some(ReasoningEffort::High),
+                        budget_tokens: None,
+                    }),
+                    top_p: Some(0.9),
+                    top_k: None,
+                    seed: Some(42),
+                    frequency_penalty: None,
+                    presence_penalty: None,
+                    user: Some("user-1".to_string()),
+                    validate_tool_calls: None,
                 },
             };
 
@@ -11,14 +11,24 @@ mod utf8_stream;
 
 use crate::event_source::error::Error;
 use crate::event_source::event_stream::EventStream;
+use crate::retry::RetryConfig;
+use golem_rust::bindings::wasi::clocks::monotonic_clock;
 use golem_rust::wasm_rpc::Pollable;
+use log::warn;
 pub use message_event::MessageEvent;
 use ndjson_stream::NdJsonStream;
 use reqwest::header::HeaderValue;
-use reqwest::{Response, StatusCode};
+use reqwest::{RequestBuilder, Response, StatusCode};
 use std::task::Poll;
+use std::time::Duration;
 use stream::{LlmStream, StreamType};
 
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+
+/// Builds the request resuming a stream after the event with the given id, or `None` if the
+/// stream cannot be resumed from it
+type ReconnectRequest = Box<dyn Fn(&str) -> Option<RequestBuilder>>;
+
 /// The ready state of an [`EventSource`]
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
 #[repr(u8)]
@@ -36,39 +46,67 @@ pub struct EventSource {
     stream: StreamType,
     response: Response,
     is_closed: bool,
+    reconnect: Option<ReconnectRequest>,
+    /// The reconnection time requested by the server with the `retry` field
+    retry: Option<Duration>,
+    reconnect_attempts: u32,
+    /// The reconnection waiting for its delay to pass after losing the connection
+    pending_reconnect: Option<PendingReconnect>,
+    /// The connections lost before reconnecting. They are kept alive until the event source is
+    /// dropped, because the pollables created for them have to be dropped first.
+    lost_connections: Vec<(Response, StreamType)>,
 }
 
 impl EventSource {
     #[allow(clippy::result_large_err)]
     pub fn new(response: Response) -> Result<Self, Error> {
-        match check_response(response) {
-            Ok(mut response) => {
-                let handle = unsafe {
-                    std::mem::transmute::<
-                        reqwest::InputStream,
-                        golem_rust::bindings::wasi::io::streams::InputStream,
-                    >(response.get_raw_input_stream())
-                };
-
-                let stream = if response
-                    .headers()
-                    .get(&reqwest::header::CONTENT_TYPE)
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .contains("ndjson")
-                {
-                    StreamType::NdJsonStream(NdJsonStream::new(handle))
-                } else {
-                    StreamType::EventStream(EventStream::new(handle))
-                };
-                Ok(Self {
-                    response,
-                    stream,
-                    is_closed: false,
-                })
-            }
-            Err(err) => Err(err),
+        let (response, stream) = open(response)?;
+        Ok(Self {
+            response,
+            stream,
+            is_closed: false,
+            reconnect: None,
+            retry: None,
+            reconnect_attempts: 0,
+            pending_reconnect: None,
+            lost_connections: Vec::new(),
+        })
+    }
+
+    /// Makes the event source reconnect when the connection is lost in the middle of the stream,
+    /// by sending the request built by `reconnect` from the id of the last received event, with
+    /// the `Last-Event-ID` header set to it.
+    ///
+    /// The stream can only be resumed after receiving an event with an id, either in the `id`
+    /// field of the server-sent event or set by [`EventSource::set_last_event_id`]. The number
+    /// of reconnection attempts is limited by [`RetryConfig::from_env`].
+    pub fn with_reconnect(
+        mut self,
+        reconnect: impl Fn(&str) -> Option<RequestBuilder> + 'static,
+    ) -> Self {
+        self.reconnect = Some(Box::new(reconnect));
+        self
+    }
+
+    /// Whether the stream can be resumed from its last event id after losing the connection
+    pub fn is_resumable(&self) -> bool {
+        self.reconnect.is_some()
+    }
+
+    /// The id of the last received event, or an empty string if there was none
+    pub fn last_event_id(&self) -> &str {
+        match &self.stream {
+            StreamType::EventStream(stream) => stream.last_event_id(),
+            StreamType::NdJsonStream(stream) => stream.last_event_id(),
+        }
+    }
+
+    /// Overrides the id of the last received event, for servers identifying the events in
+    /// their data instead of the `id` field
+    pub fn set_last_event_id(&mut self, id: impl Into<String>) {
+        match &mut self.stream {
+            StreamType::EventStream(stream) => stream.set_last_event_id(id),
+            StreamType::NdJsonStream(stream) => stream.set_last_event_id(id),
         }
     }
 
@@ -86,7 +124,14 @@ impl EventSource {
         }
     }
 
+    /// The pollable ready when the event source can make progress. While reconnecting, it is
+    /// a timer for the reconnection delay, and afterwards the pollable of the new connection,
+    /// so the pollables have to be created again after [`EventSource::poll_next`] returned
+    /// [`Poll::Pending`].
     pub fn subscribe(&self) -> Pollable {
+        if let Some(pending_reconnect) = &self.pending_reconnect {
+            return monotonic_clock::subscribe_instant(pending_reconnect.deadline);
+        }
         match &self.stream {
             StreamType::EventStream(stream) => stream.subscribe(),
             StreamType::NdJsonStream(stream) => stream.subscribe(),
@@ -98,6 +143,45 @@ impl EventSource {
             return Poll::Ready(None);
         }
 
+        if let Some(pending_reconnect) = self.pending_reconnect.take() {
+            if monotonic_clock::now() < pending_reconnect.deadline {
+                self.pending_reconnect = Some(pending_reconnect);
+                return Poll::Pending;
+            }
+            let PendingReconnect {
+                request,
+                last_event_id,
+                error,
+                ..
+            } = pending_reconnect;
+            return match self.reconnect(request, last_event_id) {
+                Ok(()) => Poll::Ready(Some(Ok(Event::Open))),
+                Err(reconnect_err) => {
+                    warn!("Failed to reconnect after {error}: {reconnect_err}");
+                    Poll::Ready(Some(Err(error)))
+                }
+            };
+        }
+
+        match self.poll_stream() {
+            Poll::Ready(Some(Ok(Event::Message(event)))) => {
+                if event.retry.is_some() {
+                    self.retry = event.retry;
+                }
+                self.reconnect_attempts = 0;
+                Poll::Ready(Some(Ok(Event::Message(event))))
+            }
+            Poll::Ready(Some(Err(err))) if self.can_reconnect(&err) => {
+                match self.schedule_reconnect(err) {
+                    Ok(()) => Poll::Pending,
+                    Err(err) => Poll::Ready(Some(Err(err))),
+                }
+            }
+            result => result,
+        }
+    }
+
+    fn poll_stream(&mut self) -> Poll<Option<Result<Event, Error>>> {
         match &mut self.stream {
             StreamType::EventStream(stream) => match stream.poll_next() {
                 Poll::Ready(Some(Ok(event))) => Poll::Ready(Some(Ok(Event::Message(event)))),
@@ -113,6 +197,118 @@ impl EventSource {
             },
         }
     }
+
+    fn can_reconnect(&self, err: &Error) -> bool {
+        self.reconnect.is_some()
+            && !self.last_event_id().is_empty()
+            && can_reconnect(
+                err,
+                self.reconnect_attempts,
+                RetryConfig::from_env().max_attempts,
+            )
+    }
+
+    /// Prepares the request resuming the stream after its last event, and schedules sending it
+    /// after the reconnection delay. Returns the error the connection was lost with if the
+    /// stream cannot be resumed.
+    #[allow(clippy::result_large_err)]
+    fn schedule_reconnect(&mut self, error: Error) -> Result<(), Error> {
+        let last_event_id = self.last_event_id().to_string();
+        let request = HeaderValue::from_str(&last_event_id)
+            .ok()
+            .and_then(|header_value| {
+                self.reconnect
+                    .as_ref()
+                    .and_then(|reconnect| reconnect(&last_event_id))
+                    .map(|request| request.header(LAST_EVENT_ID_HEADER, header_value))
+            });
+        let Some(request) = request else {
+            warn!(
+                "Failed to reconnect after {error}: {}",
+                Error::InvalidLastEventId(last_event_id)
+            );
+            return Err(error);
+        };
+
+        self.reconnect_attempts += 1;
+        let delay = self
+            .retry
+            .unwrap_or_else(|| RetryConfig::from_env().backoff(self.reconnect_attempts));
+        warn!(
+            "Connection lost, reconnecting in {delay:?} after event {last_event_id} (attempt {})",
+            self.reconnect_attempts
+        );
+        self.pending_reconnect = Some(PendingReconnect {
+            request,
+            last_event_id,
+            deadline: monotonic_clock::now()
+                .saturating_add(delay.as_nanos().min(u64::MAX as u128) as u64),
+            error,
+        });
+        Ok(())
+    }
+
+    /// Sends the request resuming the stream, and continues reading the events from the new
+    /// response
+    #[allow(clippy::result_large_err)]
+    fn reconnect(&mut self, request: RequestBuilder, last_event_id: String) -> Result<(), Error> {
+        let response = request.send().map_err(Error::Transport)?;
+        let (response, mut stream) = open(response)?;
+        match &mut stream {
+            StreamType::EventStream(stream) => stream.set_last_event_id(last_event_id),
+            StreamType::NdJsonStream(stream) => stream.set_last_event_id(last_event_id),
+        }
+
+        let lost_response = std::mem::replace(&mut self.response, response);
+        let lost_stream = std::mem::replace(&mut self.stream, stream);
+        self.lost_connections.push((lost_response, lost_stream));
+        Ok(())
+    }
+}
+
+/// A reconnection waiting for its delay to pass
+struct PendingReconnect {
+    /// The request resuming the stream, with the `Last-Event-ID` header
+    request: RequestBuilder,
+    last_event_id: String,
+    /// The instant of the monotonic clock after which the request is sent
+    deadline: u64,
+    /// The error the connection was lost with, returned if the reconnection fails
+    error: Error,
+}
+
+/// Whether a stream can be reconnected after failing with `err`, when it has already been
+/// reconnected `reconnect_attempts` times since the last received event. The first connection
+/// counts as one of the `max_attempts`.
+fn can_reconnect(err: &Error, reconnect_attempts: u32, max_attempts: u32) -> bool {
+    matches!(err, Error::Transport(_) | Error::TransportStream(_))
+        && reconnect_attempts + 1 < max_attempts
+}
+
+/// Checks the response and creates the stream reading its body
+#[allow(clippy::result_large_err)]
+fn open(response: Response) -> Result<(Response, StreamType), Error> {
+    let mut response = check_response(response)?;
+    let handle = unsafe {
+        std::mem::transmute::<
+            reqwest::InputStream,
+            golem_rust::bindings::wasi::io::streams::InputStream,
+        >(response.get_raw_input_stream())
+    };
+
+    let stream = if response
+        .headers()
+        .get(&reqwest::header::CONTENT_TYPE)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .contains("ndjson")
+    {
+        StreamType::NdJsonStream(NdJsonStream::new(handle))
+    } else {
+        StreamType::EventStream(EventStream::new(handle))
+    };
+    Ok((response, stream))
 }
 
 #[allow(clippy::result_large_err)]
@@ -153,7 +349,7 @@ fn check_response(response: Response) -> Result<Response, Error> {
 /// Events created by the [`EventSource`]
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Event {
-    /// The event fired when the connection is opened
+    /// The event fired when the connection is opened, or reopened after it was lost
     Open,
     /// The event fired when a [`MessageEvent`] is received
     Message(MessageEvent),
@@ -164,3 +360,29 @@ impl From<MessageEvent> for Event {
         Event::Message(event)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::event_source::can_reconnect;
+    use crate::event_source::error::Error;
+
+    #[test]
+    fn reconnects_after_transport_errors() {
+        let err = Error::TransportStream("connection reset".to_string());
+        assert!(can_reconnect(&err, 0, 4));
+        assert!(!can_reconnect(&Error::StreamEnded, 0, 4));
+        assert!(!can_reconnect(
+            &Error::InvalidLastEventId("id".to_string()),
+            0,
+            4
+        ));
+    }
+
+    #[test]
+    fn limits_the_reconnection_attempts() {
+        let err = Error::TransportStream("connection reset".to_string());
+        assert!(can_reconnect(&err, 2, 4));
+        assert!(!can_reconnect(&err, 3, 4));
+        assert!(!can_reconnect(&err, 0, 1));
+    }
+}
@@ -1,7 +1,21 @@
+pub mod batch;
+pub mod builtin_tools;
+pub mod chat_job;
 pub mod chat_stream;
 pub mod config;
+pub mod continuation;
+pub mod conversation;
 pub mod durability;
 pub mod error;
+pub mod logprobs;
+pub mod pricing;
+pub mod retry;
+pub mod sampling;
+pub mod tokens;
+pub mod tool_validation;
+
+#[cfg(test)]
+mod testing;
 
 #[allow(dead_code)]
 pub mod event_source;
@@ -0,0 +1,71 @@
+use crate::golem::llm::llm::{TokenLogprob, TopLogprob};
+use serde::{Deserialize, Serialize};
+
+/// The log-probability of a generated token, in the format shared by the OpenAI-like APIs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Logprob {
+    pub token: String,
+    pub logprob: f32,
+    #[serde(default)]
+    pub top_logprobs: Vec<LogprobCandidate>,
+}
+
+/// One of the most likely tokens at the position of a [`Logprob`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogprobCandidate {
+    pub token: String,
+    pub logprob: f32,
+}
+
+pub fn convert_logprobs(logprobs: Vec<Logprob>) -> Vec<TokenLogprob> {
+    logprobs
+        .into_iter()
+        .map(|logprob| TokenLogprob {
+            token: logprob.token,
+            logprob: logprob.logprob,
+            top_logprobs: logprob
+                .top_logprobs
+                .into_iter()
+                .map(|top| TopLogprob {
+                    token: top.token,
+                    logprob: top.logprob,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::golem::llm::llm::{TokenLogprob, TopLogprob};
+    use crate::logprobs::{convert_logprobs, Logprob};
+
+    #[test]
+    fn converts_logprobs_with_top_candidates() {
+        let logprobs: Vec<Logprob> = serde_json::from_str(
+            r#"[
+                { "token": "Hello", "logprob": -0.5, "top_logprobs": [{ "token": "Hi", "logprob": -1.5 }] },
+                { "token": "!", "logprob": -0.25 }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            convert_logprobs(logprobs),
+            vec![
+                TokenLogprob {
+                    token: "Hello".to_string(),
+                    logprob: -0.5,
+                    top_logprobs: vec![TopLogprob {
+                        token: "Hi".to_string(),
+                        logprob: -1.5,
+                    }],
+                },
+                TokenLogprob {
+                    token: "!".to_string(),
+                    logprob: -0.25,
+                    top_logprobs: vec![],
+                },
+            ]
+        );
+    }
+}
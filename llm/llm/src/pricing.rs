@@ -0,0 +1,538 @@
+use crate::config::get_config_key_or_none;
+use crate::golem::llm::llm::{Cost, ResponseMetadata, Usage};
+use log::warn;
+use serde::Deserialize;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+
+/// The pricing table bundled with the library, see `pricing.json`
+const BUNDLED_PRICING: &str = include_str!("../pricing.json");
+
+const PRICING_ENV_VAR_NAME: &str = "GOLEM_LLM_PRICING";
+const PRICING_PATH_ENV_VAR_NAME: &str = "GOLEM_LLM_PRICING_PATH";
+
+/// The prices of a model in US dollars per million tokens
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    /// Defaults to the input price
+    #[serde(default)]
+    pub cache_read: Option<f64>,
+    /// Defaults to the input price
+    #[serde(default)]
+    pub cache_write: Option<f64>,
+    /// Defaults to the output price
+    #[serde(default)]
+    pub reasoning: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ProviderPricing {
+    /// Whether the input tokens reported by the provider include the cached tokens, which is
+    /// the case for most providers except Anthropic's models
+    #[serde(default)]
+    input_includes_cached: Option<bool>,
+    /// The discount of the requests of batches as a fraction of the prices, like `0.5` for
+    /// half the price. Defaults to no discount.
+    #[serde(default)]
+    batch_discount: Option<f64>,
+    #[serde(default)]
+    models: HashMap<String, ModelPricing>,
+}
+
+/// The prices of the models by provider. The bundled table can be extended or overridden with
+/// a table of the same format in the `GOLEM_LLM_PRICING` environment variable, or in the file
+/// `GOLEM_LLM_PRICING_PATH` points to.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct PricingTable {
+    providers: HashMap<String, ProviderPricing>,
+}
+
+thread_local! {
+    static PRICING_TABLE: OnceCell<PricingTable> = const { OnceCell::new() };
+}
+
+impl PricingTable {
+    pub fn parse(raw: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(raw)
+    }
+
+    /// Loads the bundled table, merged with the overrides from the environment. An invalid
+    /// override is ignored with a warning, so it never fails the requests.
+    fn load() -> Self {
+        let mut table = Self::parse(BUNDLED_PRICING).expect("The bundled pricing table is invalid");
+
+        let overrides = match get_config_key_or_none(PRICING_ENV_VAR_NAME) {
+            Some(raw) => Some((PRICING_ENV_VAR_NAME.to_string(), raw)),
+            None => get_config_key_or_none(PRICING_PATH_ENV_VAR_NAME).and_then(|path| {
+                std::fs::read_to_string(&path)
+                    .inspect_err(|err| warn!("Failed to read the pricing table {path}: {err}"))
+                    .ok()
+                    .map(|raw| (path, raw))
+            }),
+        };
+        if let Some((source, raw)) = overrides {
+            match Self::parse(&raw) {
+                Ok(overrides) => table.merge(overrides),
+                Err(err) => warn!("Failed to parse the pricing table {source}: {err}"),
+            }
+        }
+        table
+    }
+
+    /// Adds the providers and models of `other` to the table, replacing the prices of the
+    /// models present in both
+    pub fn merge(&mut self, other: PricingTable) {
+        for (provider, pricing) in other.providers {
+            let entry = self.providers.entry(provider).or_default();
+            if pricing.input_includes_cached.is_some() {
+                entry.input_includes_cached = pricing.input_includes_cached;
+            }
+            if pricing.batch_discount.is_some() {
+                entry.batch_discount = pricing.batch_discount;
+            }
+            entry.models.extend(pricing.models);
+        }
+    }
+
+    /// Finds the prices of a model, by the model name of the table the model id is a version of,
+    /// so dated versions like `gpt-4o-2024-08-06` are priced as `gpt-4o`, but variants like
+    /// `o1-pro` are not priced as `o1`. The model id can also be preceded by a region prefix, like
+    /// Bedrock's `us.` inference profiles. Names ending with `*` match all the model ids starting
+    /// with the rest of the name, the longest such name is used.
+    pub fn model_pricing(&self, provider: &str, model: &str) -> Option<&ModelPricing> {
+        let unprefixed = model.split_once('.').map(|(_, unprefixed)| unprefixed);
+        self.providers.get(provider).and_then(|pricing| {
+            pricing
+                .models
+                .iter()
+                .filter(|(name, _)| {
+                    [Some(model), unprefixed]
+                        .into_iter()
+                        .flatten()
+                        .any(|model| is_version_of(model, name))
+                })
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, pricing)| pricing)
+        })
+    }
+
+    /// Estimates the cost of the usage, `None` if the model is not in the table or the usage
+    /// does not report the input and output tokens
+    pub fn estimate_cost(&self, provider: &str, model: &str, usage: &Usage) -> Option<Cost> {
+        let pricing = self.model_pricing(provider, model)?;
+        let input_includes_cached = self
+            .providers
+            .get(provider)
+            .and_then(|pricing| pricing.input_includes_cached)
+            .unwrap_or(true);
+        if usage.input_tokens.is_none() && usage.output_tokens.is_none() {
+            return None;
+        }
+
+        let cache_read_tokens = usage.cache_read_tokens.unwrap_or(0);
+        let cache_write_tokens = usage.cache_write_tokens.unwrap_or(0);
+        let mut input_tokens = usage.input_tokens.unwrap_or(0);
+        if input_includes_cached {
+            input_tokens = input_tokens.saturating_sub(cache_read_tokens + cache_write_tokens);
+        }
+        let output_tokens = usage.output_tokens.unwrap_or(0);
+        let reasoning_tokens = usage.reasoning_tokens.unwrap_or(0).min(output_tokens);
+
+        let input = price(input_tokens, pricing.input);
+        let cached = price(
+            cache_read_tokens,
+            pricing.cache_read.unwrap_or(pricing.input),
+        ) + price(
+            cache_write_tokens,
+            pricing.cache_write.unwrap_or(pricing.input),
+        );
+        let output = price(output_tokens - reasoning_tokens, pricing.output);
+        let reasoning = price(
+            reasoning_tokens,
+            pricing.reasoning.unwrap_or(pricing.output),
+        );
+        Some(Cost {
+            input,
+            cached,
+            output,
+            reasoning,
+            total: input + cached + output + reasoning,
+        })
+    }
+
+    /// Estimates the cost of the usage of a request of a batch, with the batch discount of the
+    /// provider
+    pub fn estimate_batch_cost(&self, provider: &str, model: &str, usage: &Usage) -> Option<Cost> {
+        let factor = 1.0
+            - self
+                .providers
+                .get(provider)
+                .and_then(|pricing| pricing.batch_discount)
+                .unwrap_or(0.0);
+        self.estimate_cost(provider, model, usage).map(|cost| Cost {
+            input: cost.input * factor,
+            cached: cost.cached * factor,
+            output: cost.output * factor,
+            reasoning: cost.reasoning * factor,
+            total: cost.total * factor,
+        })
+    }
+}
+
+/// Whether the model id is the model name of the pricing table, optionally followed by a date
+/// like `-2024-08-06`, `-20250514` or `-0709`, or by `-latest`, and by Bedrock's model version like
+/// `-v1:0`. Other suffixes, like the `-5` of `claude-opus-4-5`, make it a different model. Names
+/// ending with `*` match any suffix.
+fn is_version_of(model: &str, name: &str) -> bool {
+    if let Some(prefix) = name.strip_suffix('*') {
+        return model.starts_with(prefix);
+    }
+    let Some(suffix) = model.strip_prefix(name) else {
+        return false;
+    };
+    let suffix = strip_bedrock_version(suffix);
+    if suffix.is_empty() {
+        return true;
+    }
+    let Some(version) = suffix.strip_prefix(['-', ':']) else {
+        return false;
+    };
+    let is_number = |segment: &str, len: usize| {
+        segment.len() == len && segment.bytes().all(|b| b.is_ascii_digit())
+    };
+    match version.split('-').collect::<Vec<_>>().as_slice() {
+        ["latest"] => true,
+        [date] => is_number(date, 8) || is_number(date, 4),
+        [year, month, day] => is_number(year, 4) && is_number(month, 2) && is_number(day, 2),
+        _ => false,
+    }
+}
+
+/// Removes Bedrock's model version from the end of the suffix of a model id, like the `-v1:0` of
+/// `amazon.nova-pro-v1:0`
+fn strip_bedrock_version(suffix: &str) -> &str {
+    match suffix.rsplit_once("-v") {
+        Some((rest, version))
+            if version
+                .split(':')
+                .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit())) =>
+        {
+            rest
+        }
+        _ => suffix,
+    }
+}
+
+fn price(tokens: u32, price_per_million: f64) -> f64 {
+    tokens as f64 * price_per_million / 1_000_000.0
+}
+
+fn with_pricing_table<T>(f: impl FnOnce(&PricingTable) -> T) -> T {
+    PRICING_TABLE.with(|table| f(table.get_or_init(PricingTable::load)))
+}
+
+/// Estimates the cost of the usage of a model with the pricing table loaded from the
+/// environment
+pub fn estimate_cost(provider: &str, model: &str, usage: &Usage) -> Option<Cost> {
+    with_pricing_table(|table| table.estimate_cost(provider, model, usage))
+}
+
+/// Fills the estimated cost of a response from its usage, unless it is already known
+pub fn fill_cost(provider: &str, model: &str, metadata: &mut ResponseMetadata) {
+    if metadata.cost.is_none() {
+        if let Some(usage) = &metadata.usage {
+            metadata.cost = estimate_cost(provider, model, usage);
+        }
+    }
+}
+
+/// Fills the estimated cost of the response to a request of a batch from its usage, with the
+/// batch discount of the provider, unless it is already known
+pub fn fill_batch_cost(provider: &str, model: &str, metadata: &mut ResponseMetadata) {
+    if metadata.cost.is_none() {
+        if let Some(usage) = &metadata.usage {
+            metadata.cost =
+                with_pricing_table(|table| table.estimate_batch_cost(provider, model, usage));
+        }
+    }
+}
+
+/// Sums the token counts of two usages, a count is only missing from the result if it is
+/// missing from both
+pub fn add_usage(usage: &Usage, other: &Usage) -> Usage {
+    fn add(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+        match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+        }
+    }
+    Usage {
+        input_tokens: add(usage.input_tokens, other.input_tokens),
+        output_tokens: add(usage.output_tokens, other.output_tokens),
+        total_tokens: add(usage.total_tokens, other.total_tokens),
+        cache_read_tokens: add(usage.cache_read_tokens, other.cache_read_tokens),
+        cache_write_tokens: add(usage.cache_write_tokens, other.cache_write_tokens),
+        reasoning_tokens: add(usage.reasoning_tokens, other.reasoning_tokens),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::golem::llm::llm::Usage;
+    use crate::pricing::{add_usage, PricingTable, BUNDLED_PRICING};
+
+    fn usage(input: u32, output: u32, cache_read: u32, reasoning: u32) -> Usage {
+        Usage {
+            input_tokens: Some(input),
+            output_tokens: Some(output),
+            total_tokens: Some(input + output),
+            cache_read_tokens: Some(cache_read),
+            cache_write_tokens: None,
+            reasoning_tokens: Some(reasoning),
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn bundled_table_is_valid() {
+        PricingTable::parse(BUNDLED_PRICING).unwrap();
+    }
+
+    #[test]
+    fn matches_model_versions() {
+        let table = PricingTable::parse(BUNDLED_PRICING).unwrap();
+        assert_eq!(
+            table.model_pricing("openai", "gpt-4o-mini-2024-07-18"),
+            table.model_pricing("openai", "gpt-4o-mini")
+        );
+        assert_eq!(
+            table.model_pricing("anthropic", "claude-3-7-sonnet-latest"),
+            table.model_pricing("anthropic", "claude-3-7-sonnet-20250219")
+        );
+        assert_eq!(
+            table.model_pricing("grok", "grok-4-0709"),
+            table.model_pricing("grok", "grok-4")
+        );
+        assert_ne!(
+            table.model_pricing("openai", "gpt-4o-mini"),
+            table.model_pricing("openai", "gpt-4o")
+        );
+        assert!(table
+            .model_pricing("bedrock", "us.anthropic.claude-sonnet-4-20250514-v1:0")
+            .is_some());
+        assert!(table
+            .model_pricing("bedrock", "amazon.nova-pro-v1:0")
+            .is_some());
+        assert!(table.model_pricing("openai", "unknown-model").is_none());
+        assert!(table.model_pricing("unknown-provider", "gpt-4o").is_none());
+    }
+
+    #[test]
+    fn does_not_price_variants_as_the_base_model() {
+        let table = PricingTable::parse(BUNDLED_PRICING).unwrap();
+        assert!(table.model_pricing("openai", "o1-pro").is_none());
+        assert!(table.model_pricing("openai", "o3-pro-2025-06-10").is_none());
+        assert!(table.model_pricing("openai", "o3-deep-research").is_none());
+        assert!(table.model_pricing("grok", "grok-3-fast").is_none());
+        assert_ne!(
+            table.model_pricing("grok", "grok-3-mini-latest"),
+            table.model_pricing("grok", "grok-3")
+        );
+        assert_ne!(
+            table.model_pricing("anthropic", "claude-opus-4-5-20251101"),
+            table.model_pricing("anthropic", "claude-opus-4")
+        );
+        assert!(table
+            .model_pricing("bedrock", "anthropic.claude-opus-4-5-20251101-v1:0")
+            .is_none());
+        assert!(table
+            .model_pricing("gemini", "gemini-2.0-flash-001")
+            .is_none());
+    }
+
+    #[test]
+    fn all_providers_are_in_the_bundled_table() {
+        let table = PricingTable::parse(BUNDLED_PRICING).unwrap();
+        for provider in [
+            "anthropic",
+            "azure-openai",
+            "bedrock",
+            "gemini",
+            "grok",
+            "mock",
+            "ollama",
+            "openai",
+            "openai-compatible",
+            "openrouter",
+        ] {
+            assert!(
+                table.providers.contains_key(provider),
+                "{provider} is not in the table"
+            );
+        }
+
+        let cost = table
+            .estimate_cost("ollama", "llama3.2:latest", &usage(1_000, 1_000, 0, 0))
+            .unwrap();
+        assert_eq!(cost.total, 0.0);
+        assert!(table.model_pricing("mock", "mock-model").is_some());
+        assert!(table
+            .model_pricing("openrouter", "anthropic/claude-sonnet-4")
+            .is_some());
+        assert_eq!(
+            table.model_pricing("azure-openai", "gpt-4o-2024-08-06"),
+            table.model_pricing("openai", "gpt-4o")
+        );
+    }
+
+    #[test]
+    fn matches_wildcard_names() {
+        let table = PricingTable::parse(
+            r#"{
+                "openai": {
+                    "models": {
+                        "ft:*": { "input": 1.0, "output": 1.0 },
+                        "ft:gpt-4o-mini*": { "input": 0.3, "output": 1.2 }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            table
+                .model_pricing("openai", "ft:gpt-4o-mini-2024-07-18:org::abc123")
+                .unwrap()
+                .input,
+            0.3
+        );
+        assert_eq!(
+            table
+                .model_pricing("openai", "ft:gpt-4o:org::abc123")
+                .unwrap()
+                .input,
+            1.0
+        );
+    }
+
+    #[test]
+    fn cached_tokens_are_excluded_from_the_input() {
+        let table = PricingTable::parse(
+            r#"{
+                "included": {
+                    "input_includes_cached": true,
+                    "models": { "model": { "input": 2.0, "output": 8.0, "cache_read": 0.5 } }
+                },
+                "excluded": {
+                    "input_includes_cached": false,
+                    "models": { "model": { "input": 2.0, "output": 8.0, "cache_read": 0.5, "reasoning": 4.0 } }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let cost = table
+            .estimate_cost(
+                "included",
+                "model",
+                &usage(1_000_000, 500_000, 400_000, 100_000),
+            )
+            .unwrap();
+        assert_close(cost.input, 1.2);
+        assert_close(cost.cached, 0.2);
+        assert_close(cost.output, 3.2);
+        assert_close(cost.reasoning, 0.8);
+        assert_close(cost.total, 5.4);
+
+        let cost = table
+            .estimate_cost(
+                "excluded",
+                "model",
+                &usage(1_000_000, 500_000, 400_000, 100_000),
+            )
+            .unwrap();
+        assert_close(cost.input, 2.0);
+        assert_close(cost.reasoning, 0.4);
+        assert_close(cost.total, 5.8);
+    }
+
+    #[test]
+    fn batches_are_discounted() {
+        let table = PricingTable::parse(
+            r#"{
+                "discounted": {
+                    "batch_discount": 0.5,
+                    "models": { "model": { "input": 2.0, "output": 8.0 } }
+                },
+                "undiscounted": {
+                    "models": { "model": { "input": 2.0, "output": 8.0 } }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let cost = table
+            .estimate_batch_cost("discounted", "model", &usage(1_000_000, 500_000, 0, 0))
+            .unwrap();
+        assert_close(cost.input, 1.0);
+        assert_close(cost.output, 2.0);
+        assert_close(cost.total, 3.0);
+
+        let cost = table
+            .estimate_batch_cost("undiscounted", "model", &usage(1_000_000, 500_000, 0, 0))
+            .unwrap();
+        assert_close(cost.total, 6.0);
+    }
+
+    #[test]
+    fn overrides_replace_the_bundled_prices() {
+        let mut table = PricingTable::parse(BUNDLED_PRICING).unwrap();
+        table.merge(
+            PricingTable::parse(
+                r#"{
+                    "openai": { "models": { "gpt-4o": { "input": 1.0, "output": 1.0 } } },
+                    "openai-compatible": { "models": { "llama3.2": { "input": 0.1, "output": 0.1 } } }
+                }"#,
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(table.model_pricing("openai", "gpt-4o").unwrap().input, 1.0);
+        assert!(table.model_pricing("openai", "gpt-4o-mini").is_some());
+        assert!(table
+            .model_pricing("openai-compatible", "llama3.2:latest")
+            .is_some());
+        let cost = table
+            .estimate_cost("openai", "gpt-4o", &usage(1_000_000, 0, 1_000_000, 0))
+            .unwrap();
+        assert_close(cost.input, 0.0);
+        assert_close(cost.cached, 1.0);
+    }
+
+    #[test]
+    fn sums_usages() {
+        let total = add_usage(
+            &usage(10, 20, 5, 0),
+            &Usage {
+                input_tokens: Some(1),
+                output_tokens: None,
+                total_tokens: None,
+                cache_read_tokens: None,
+                cache_write_tokens: None,
+                reasoning_tokens: None,
+            },
+        );
+        assert_eq!(total.input_tokens, Some(11));
+        assert_eq!(total.output_tokens, Some(20));
+        assert_eq!(total.cache_read_tokens, Some(5));
+        assert_eq!(total.cache_write_tokens, None);
+    }
+}
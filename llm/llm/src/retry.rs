@@ -0,0 +1,647 @@
+use crate::golem::llm::llm::{ChatEvent, RateLimit};
+use log::warn;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::cell::RefCell;
+use std::time::Duration;
+
+/// Configuration of retrying the HTTP requests sent to the LLM providers
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// The maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    /// The delay before the first retry, doubled for each further retry
+    pub min_delay: Duration,
+    /// The longest delay between two attempts. If the provider asks for waiting longer than
+    /// this, the failed response is returned without retrying.
+    pub max_delay: Duration,
+}
+
+impl RetryConfig {
+    const MAX_RETRIES_ENV_VAR_NAME: &'static str = "GOLEM_LLM_MAX_RETRIES";
+    const MIN_DELAY_ENV_VAR_NAME: &'static str = "GOLEM_LLM_RETRY_MIN_DELAY_MS";
+    const MAX_DELAY_ENV_VAR_NAME: &'static str = "GOLEM_LLM_RETRY_MAX_DELAY_MS";
+
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 4,
+            min_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_attempts = max_retries.saturating_add(1);
+        self
+    }
+
+    pub fn with_min_delay(mut self, min_delay: Duration) -> Self {
+        self.min_delay = min_delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Reads the configuration from the `GOLEM_LLM_MAX_RETRIES`, `GOLEM_LLM_RETRY_MIN_DELAY_MS`
+    /// and `GOLEM_LLM_RETRY_MAX_DELAY_MS` environment variables, using the defaults for the
+    /// missing ones.
+    pub fn from_env() -> Self {
+        let mut config = Self::new();
+        if let Some(max_retries) = env_var(Self::MAX_RETRIES_ENV_VAR_NAME) {
+            config = config.with_max_retries(max_retries as u32);
+        }
+        if let Some(min_delay) = env_var(Self::MIN_DELAY_ENV_VAR_NAME) {
+            config = config.with_min_delay(Duration::from_millis(min_delay));
+        }
+        if let Some(max_delay) = env_var(Self::MAX_DELAY_ENV_VAR_NAME) {
+            config = config.with_max_delay(Duration::from_millis(max_delay));
+        }
+        config
+    }
+
+    /// The exponential backoff delay before the given retry, starting from 1
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.min_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn env_var(name: &str) -> Option<u64> {
+    let value = std::env::var(name).ok()?;
+    match value.trim().parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            warn!("Ignoring invalid value of {name}: {value}");
+            None
+        }
+    }
+}
+
+/// Sending requests with retrying rate limited, overloaded and failed requests
+pub trait SendWithRetry {
+    /// Sends the request, retrying it as configured by [`RetryConfig::from_env`] when the provider
+    /// responds with 429 or a 5xx status code, or the request could not be sent at all.
+    ///
+    /// The delay between attempts honours the `retry-after-ms` and `Retry-After` headers, and the
+    /// `x-ratelimit-reset-*` and `anthropic-ratelimit-*-reset` headers of the exhausted limits,
+    /// falling back to exponential backoff.
+    /// Requests with a streaming body cannot be cloned and are sent only once.
+    ///
+    /// The rate limit reported in the headers of the final response is made available
+    /// through [`take_last_rate_limit`].
+    fn send_with_retry(self) -> reqwest::Result<Response>;
+
+    /// Sends a request which must not be repeated once the provider processed it, like creating
+    /// a batch. It is only retried when the provider responds with 429 or could not be connected
+    /// to, as a 5xx status code or a timeout can happen after the request was processed.
+    /// Otherwise it behaves like [`SendWithRetry::send_with_retry`].
+    fn send_unprocessed_with_retry(self) -> reqwest::Result<Response>;
+}
+
+impl SendWithRetry for RequestBuilder {
+    fn send_with_retry(self) -> reqwest::Result<Response> {
+        send(self, is_retryable)
+    }
+
+    fn send_unprocessed_with_retry(self) -> reqwest::Result<Response> {
+        send(self, is_unprocessed)
+    }
+}
+
+/// Sends the request, retrying the failed attempts for which `retryable` is true
+fn send(
+    request: RequestBuilder,
+    retryable: fn(&reqwest::Result<Response>) -> bool,
+) -> reqwest::Result<Response> {
+    let config = RetryConfig::from_env();
+    let mut attempt = 1;
+    loop {
+        let Some(attempt_request) = request.try_clone() else {
+            return record_rate_limit(request.send());
+        };
+
+        let result = attempt_request.send();
+        if attempt < config.max_attempts && retryable(&result) {
+            let delay = match &result {
+                Ok(response) => retry_delay(response.headers(), attempt, &config, now()),
+                Err(_) => Some(config.backoff(attempt)),
+            };
+
+            if let Some(delay) = delay {
+                match &result {
+                    Ok(response) => warn!(
+                        "Request failed with status {}, retrying in {delay:?} (attempt {attempt} of {})",
+                        response.status(),
+                        config.max_attempts
+                    ),
+                    Err(err) => warn!(
+                        "Request failed with {err}, retrying in {delay:?} (attempt {attempt} of {})",
+                        config.max_attempts
+                    ),
+                }
+                sleep(delay);
+                attempt += 1;
+                continue;
+            }
+        }
+
+        return record_rate_limit(result);
+    }
+}
+
+/// Whether a failed attempt of a request which can be repeated is retried
+fn is_retryable(result: &reqwest::Result<Response>) -> bool {
+    match result {
+        Ok(response) => is_retryable_status(response.status()),
+        Err(err) => err.is_connect() || err.is_timeout() || err.is_request(),
+    }
+}
+
+/// Whether a failed attempt was certainly not processed by the provider
+fn is_unprocessed(result: &reqwest::Result<Response>) -> bool {
+    match result {
+        Ok(response) => response.status() == StatusCode::TOO_MANY_REQUESTS,
+        Err(err) => err.is_connect(),
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+pub(crate) fn sleep(delay: Duration) {
+    golem_rust::bindings::wasi::clocks::monotonic_clock::subscribe_duration(
+        delay.as_nanos().min(u64::MAX as u128) as u64,
+    )
+    .block();
+}
+
+/// The current time as the duration since the Unix epoch
+fn now() -> Duration {
+    let now = golem_rust::bindings::wasi::clocks::wall_clock::now();
+    Duration::new(now.seconds, now.nanoseconds)
+}
+
+/// The delay before retrying a failed response, or `None` if the provider asks for waiting
+/// longer than the configured maximum. `now` is the current time since the Unix epoch.
+fn retry_delay(
+    headers: &HeaderMap,
+    retry: u32,
+    config: &RetryConfig,
+    now: Duration,
+) -> Option<Duration> {
+    match requested_delay(headers, now) {
+        Some(delay) if delay > config.max_delay => None,
+        Some(delay) => Some(delay),
+        None => Some(config.backoff(retry)),
+    }
+}
+
+/// The delay the provider asks for in the response headers
+fn requested_delay(headers: &HeaderMap, now: Duration) -> Option<Duration> {
+    if let Some(millis) = header(headers, "retry-after-ms").and_then(parse_number) {
+        return Some(from_millis(millis));
+    }
+    // Retry-After may also be an HTTP date, which is not supported and falls back to the
+    // rate limit headers
+    if let Some(seconds) = header(headers, "retry-after").and_then(parse_number) {
+        return Some(from_millis(seconds * 1000.0));
+    }
+
+    // Waiting until every exhausted limit is reset. OpenAI's headers tell the time left until
+    // the reset, Anthropic's the time of the reset.
+    let exhausted = |(remaining, _): &(&str, &str)| header(headers, remaining) == Some("0");
+    let openai_resets = [
+        (
+            "x-ratelimit-remaining-requests",
+            "x-ratelimit-reset-requests",
+        ),
+        ("x-ratelimit-remaining-tokens", "x-ratelimit-reset-tokens"),
+    ]
+    .into_iter()
+    .filter(exhausted)
+    .filter_map(|(_, reset)| header(headers, reset).and_then(parse_reset_duration));
+    let anthropic_resets = [
+        (
+            "anthropic-ratelimit-requests-remaining",
+            "anthropic-ratelimit-requests-reset",
+        ),
+        (
+            "anthropic-ratelimit-tokens-remaining",
+            "anthropic-ratelimit-tokens-reset",
+        ),
+        (
+            "anthropic-ratelimit-input-tokens-remaining",
+            "anthropic-ratelimit-input-tokens-reset",
+        ),
+        (
+            "anthropic-ratelimit-output-tokens-remaining",
+            "anthropic-ratelimit-output-tokens-reset",
+        ),
+    ]
+    .into_iter()
+    .filter(exhausted)
+    .filter_map(|(_, reset)| header(headers, reset).and_then(parse_timestamp))
+    .map(|reset| reset.saturating_sub(now));
+    openai_resets.chain(anthropic_resets).max()
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite() && *number >= 0.0)
+}
+
+fn from_millis(millis: f64) -> Duration {
+    Duration::from_micros((millis * 1000.0).round() as u64)
+}
+
+/// Parses the reset durations of the `x-ratelimit-reset-*` headers, such as `1s`, `6m0s`,
+/// `1m30.5s` or `20ms`
+fn parse_reset_duration(value: &str) -> Option<Duration> {
+    let mut millis = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let unit_end = rest[number_end..]
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .map(|end| number_end + end)
+            .unwrap_or(rest.len());
+        let number = parse_number(&rest[..number_end])?;
+        let unit_millis = match &rest[number_end..unit_end] {
+            "h" => 3_600_000.0,
+            "m" => 60_000.0,
+            "s" => 1000.0,
+            "ms" => 1.0,
+            _ => return None,
+        };
+        millis += number * unit_millis;
+        rest = &rest[unit_end..];
+    }
+    (!value.is_empty()).then(|| from_millis(millis))
+}
+
+/// Parses the RFC 3339 timestamps of the `anthropic-ratelimit-*-reset` headers, such as
+/// `2025-06-01T12:00:01Z` or `2025-06-01T14:00:01.5+02:00`, to the time since the Unix epoch
+fn parse_timestamp(value: &str) -> Option<Duration> {
+    let (date, time) = value.split_once(['T', 't', ' '])?;
+    let (time, offset_seconds) = match time.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => {
+            let (time, offset) = time.split_at(time.rfind(['+', '-'])?);
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let offset_seconds =
+                hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            (time, sign * offset_seconds)
+        }
+    };
+
+    let numbers = |value: &str| {
+        value
+            .splitn(3, [':', '-'])
+            .map(|number| number.parse::<i64>().ok())
+            .collect::<Option<Vec<_>>>()
+    };
+    let [year, month, day] = numbers(date)?.try_into().ok()?;
+    let (hours_and_minutes, seconds) = time.rsplit_once(':')?;
+    let [hour, minute] = numbers(hours_and_minutes)?.try_into().ok()?;
+    let seconds = parse_number(seconds).filter(|seconds| *seconds < 61.0)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let whole_seconds =
+        days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 - offset_seconds;
+    u64::try_from(whole_seconds)
+        .ok()
+        .map(|whole_seconds| Duration::from_secs(whole_seconds) + from_millis(seconds * 1000.0))
+}
+
+/// The number of days since the Unix epoch of a date of the Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Extracts the provider's rate limits from the response headers. OpenAI-style
+/// `x-ratelimit-*-requests` and `x-ratelimit-*-tokens` headers, Anthropic's
+/// `anthropic-ratelimit-*` headers and OpenRouter's `X-RateLimit-*` headers are supported.
+pub fn rate_limit_from_headers(headers: &HeaderMap) -> Option<RateLimit> {
+    let first = |names: &[&str]| names.iter().find_map(|name| header(headers, name));
+    let number = |names: &[&str]| first(names).and_then(|value| value.parse::<u32>().ok());
+
+    let rate_limit = RateLimit {
+        requests_limit: number(&[
+            "x-ratelimit-limit-requests",
+            "anthropic-ratelimit-requests-limit",
+            "x-ratelimit-limit",
+        ]),
+        requests_remaining: number(&[
+            "x-ratelimit-remaining-requests",
+            "anthropic-ratelimit-requests-remaining",
+            "x-ratelimit-remaining",
+        ]),
+        requests_reset: first(&[
+            "x-ratelimit-reset-requests",
+            "anthropic-ratelimit-requests-reset",
+            "x-ratelimit-reset",
+        ])
+        .map(str::to_string),
+        tokens_limit: number(&[
+            "x-ratelimit-limit-tokens",
+            "anthropic-ratelimit-tokens-limit",
+        ]),
+        tokens_remaining: number(&[
+            "x-ratelimit-remaining-tokens",
+            "anthropic-ratelimit-tokens-remaining",
+        ]),
+        tokens_reset: first(&[
+            "x-ratelimit-reset-tokens",
+            "anthropic-ratelimit-tokens-reset",
+        ])
+        .map(str::to_string),
+    };
+
+    let reported = rate_limit.requests_limit.is_some()
+        || rate_limit.requests_remaining.is_some()
+        || rate_limit.requests_reset.is_some()
+        || rate_limit.tokens_limit.is_some()
+        || rate_limit.tokens_remaining.is_some()
+        || rate_limit.tokens_reset.is_some();
+    reported.then_some(rate_limit)
+}
+
+thread_local! {
+    static LAST_RATE_LIMIT: RefCell<Option<RateLimit>> = const { RefCell::new(None) };
+}
+
+fn record_rate_limit(result: reqwest::Result<Response>) -> reqwest::Result<Response> {
+    let rate_limit = result
+        .as_ref()
+        .ok()
+        .and_then(|response| rate_limit_from_headers(response.headers()));
+    LAST_RATE_LIMIT.with_borrow_mut(|last| *last = rate_limit);
+    result
+}
+
+/// Takes the rate limit reported with the last response received by
+/// [`SendWithRetry::send_with_retry`]
+pub fn take_last_rate_limit() -> Option<RateLimit> {
+    LAST_RATE_LIMIT.take()
+}
+
+/// Runs a provider request and fills the rate limit reported with its response into the
+/// returned message's metadata, unless the provider already did so
+pub fn with_rate_limit(f: impl FnOnce() -> ChatEvent) -> ChatEvent {
+    LAST_RATE_LIMIT.take();
+    let mut event = f();
+    let rate_limit = take_last_rate_limit();
+    if let ChatEvent::Message(response) = &mut event {
+        if response.metadata.rate_limit.is_none() {
+            response.metadata.rate_limit = rate_limit;
+        }
+    }
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::golem::llm::llm::RateLimit;
+    use crate::retry::{
+        parse_reset_duration, parse_timestamp, rate_limit_from_headers, retry_delay, RetryConfig,
+    };
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+    use std::time::Duration;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(
+                HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn reset_durations() {
+        assert_eq!(parse_reset_duration("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(parse_reset_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(
+            parse_reset_duration("1m30.5s"),
+            Some(Duration::from_millis(90500))
+        );
+        assert_eq!(
+            parse_reset_duration("20ms"),
+            Some(Duration::from_millis(20))
+        );
+        assert_eq!(parse_reset_duration(""), None);
+        assert_eq!(parse_reset_duration("2025-01-01T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(
+            parse_timestamp("1970-01-01T00:00:00Z"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse_timestamp("2025-06-01T12:00:01Z"),
+            Some(Duration::from_secs(1_748_779_201))
+        );
+        assert_eq!(
+            parse_timestamp("2025-06-01T14:00:01.5+02:00"),
+            Some(Duration::from_millis(1_748_779_201_500))
+        );
+        assert_eq!(
+            parse_timestamp("2024-02-29T23:59:59-01:00"),
+            Some(Duration::from_secs(1_709_254_799))
+        );
+        assert_eq!(parse_timestamp("1s"), None);
+        assert_eq!(parse_timestamp("2025-13-01T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn backoff_is_exponential_and_capped() {
+        let config = RetryConfig::new()
+            .with_min_delay(Duration::from_millis(500))
+            .with_max_delay(Duration::from_secs(3));
+        assert_eq!(config.backoff(1), Duration::from_millis(500));
+        assert_eq!(config.backoff(2), Duration::from_millis(1000));
+        assert_eq!(config.backoff(3), Duration::from_millis(2000));
+        assert_eq!(config.backoff(4), Duration::from_secs(3));
+        assert_eq!(config.backoff(40), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn retry_delay_honours_headers() {
+        let config = RetryConfig::new();
+        assert_eq!(
+            retry_delay(
+                &headers(&[("retry-after", "2")]),
+                1,
+                &config,
+                Duration::ZERO
+            ),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            retry_delay(
+                &headers(&[("retry-after-ms", "150"), ("retry-after", "1")]),
+                1,
+                &config,
+                Duration::ZERO
+            ),
+            Some(Duration::from_millis(150))
+        );
+        assert_eq!(
+            retry_delay(
+                &headers(&[
+                    ("x-ratelimit-remaining-requests", "10"),
+                    ("x-ratelimit-reset-requests", "1s"),
+                    ("x-ratelimit-remaining-tokens", "0"),
+                    ("x-ratelimit-reset-tokens", "6.5s"),
+                ]),
+                1,
+                &config,
+                Duration::ZERO
+            ),
+            Some(Duration::from_millis(6500))
+        );
+        assert_eq!(
+            retry_delay(&headers(&[]), 2, &config, Duration::ZERO),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn retry_delay_waits_until_the_anthropic_limits_reset() {
+        let config = RetryConfig::new();
+        let now = Duration::from_secs(1_748_779_200);
+        assert_eq!(
+            retry_delay(
+                &headers(&[
+                    ("anthropic-ratelimit-requests-remaining", "0"),
+                    ("anthropic-ratelimit-requests-reset", "2025-06-01T12:00:01Z"),
+                    ("anthropic-ratelimit-tokens-remaining", "0"),
+                    ("anthropic-ratelimit-tokens-reset", "2025-06-01T12:00:03.5Z"),
+                    ("anthropic-ratelimit-input-tokens-remaining", "100"),
+                    (
+                        "anthropic-ratelimit-input-tokens-reset",
+                        "2025-06-01T12:00:09Z"
+                    ),
+                ]),
+                1,
+                &config,
+                now
+            ),
+            Some(Duration::from_millis(3500))
+        );
+        // A reset in the past needs no waiting
+        assert_eq!(
+            retry_delay(
+                &headers(&[
+                    ("anthropic-ratelimit-requests-remaining", "0"),
+                    ("anthropic-ratelimit-requests-reset", "2025-06-01T11:59:00Z"),
+                ]),
+                1,
+                &config,
+                now
+            ),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn retry_delay_gives_up_on_long_waits() {
+        let config = RetryConfig::new().with_max_delay(Duration::from_secs(10));
+        assert_eq!(
+            retry_delay(
+                &headers(&[("retry-after", "60")]),
+                1,
+                &config,
+                Duration::ZERO
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn openai_rate_limit_headers() {
+        assert_eq!(
+            rate_limit_from_headers(&headers(&[
+                ("x-ratelimit-limit-requests", "500"),
+                ("x-ratelimit-remaining-requests", "499"),
+                ("x-ratelimit-reset-requests", "120ms"),
+                ("x-ratelimit-limit-tokens", "30000"),
+                ("x-ratelimit-remaining-tokens", "29000"),
+                ("x-ratelimit-reset-tokens", "2s"),
+            ])),
+            Some(RateLimit {
+                requests_limit: Some(500),
+                requests_remaining: Some(499),
+                requests_reset: Some("120ms".to_string()),
+                tokens_limit: Some(30000),
+                tokens_remaining: Some(29000),
+                tokens_reset: Some("2s".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn anthropic_rate_limit_headers() {
+        assert_eq!(
+            rate_limit_from_headers(&headers(&[
+                ("anthropic-ratelimit-requests-limit", "50"),
+                ("anthropic-ratelimit-requests-remaining", "49"),
+                ("anthropic-ratelimit-requests-reset", "2025-06-01T12:00:01Z"),
+                ("anthropic-ratelimit-tokens-limit", "40000"),
+                ("anthropic-ratelimit-tokens-remaining", "39000"),
+            ])),
+            Some(RateLimit {
+                requests_limit: Some(50),
+                requests_remaining: Some(49),
+                requests_reset: Some("2025-06-01T12:00:01Z".to_string()),
+                tokens_limit: Some(40000),
+                tokens_remaining: Some(39000),
+                tokens_reset: None,
+            })
+        );
+    }
+
+    #[test]
+    fn missing_rate_limit_headers() {
+        assert_eq!(
+            rate_limit_from_headers(&headers(&[("content-type", "application/json")])),
+            None
+        );
+    }
+}
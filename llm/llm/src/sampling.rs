@@ -0,0 +1,315 @@
+use crate::error::unsupported;
+use crate::golem::llm::llm::{Config, Error, ErrorCode};
+use log::warn;
+use std::str::FromStr;
+
+/// The optional sampling parameters of `config`, used by the providers to declare which ones
+/// they support
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingParameter {
+    TopP,
+    TopK,
+    Seed,
+    FrequencyPenalty,
+    PresencePenalty,
+    User,
+}
+
+impl SamplingParameter {
+    const ALL: [SamplingParameter; 6] = [
+        SamplingParameter::TopP,
+        SamplingParameter::TopK,
+        SamplingParameter::Seed,
+        SamplingParameter::FrequencyPenalty,
+        SamplingParameter::PresencePenalty,
+        SamplingParameter::User,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SamplingParameter::TopP => "top-p",
+            SamplingParameter::TopK => "top-k",
+            SamplingParameter::Seed => "seed",
+            SamplingParameter::FrequencyPenalty => "frequency-penalty",
+            SamplingParameter::PresencePenalty => "presence-penalty",
+            SamplingParameter::User => "user",
+        }
+    }
+
+    /// The provider option keys the parameter was read from before it was part of the config
+    fn deprecated_option_keys(&self) -> &'static [&'static str] {
+        match self {
+            SamplingParameter::TopP => &["top_p"],
+            SamplingParameter::TopK => &["top_k"],
+            SamplingParameter::Seed => &["seed"],
+            SamplingParameter::FrequencyPenalty => &["frequency_penalty"],
+            SamplingParameter::PresencePenalty => &["presence_penalty"],
+            SamplingParameter::User => &["user", "user_id"],
+        }
+    }
+
+    /// Sets the parameter from the value of its deprecated provider option, unless it is already
+    /// set in the config
+    fn set_from_option(&self, config: &mut Config, key: &str, value: &str) -> Result<(), Error> {
+        if self.is_set(config) {
+            return Ok(());
+        }
+        match self {
+            SamplingParameter::TopP => config.top_p = Some(parse_option(key, value)?),
+            SamplingParameter::TopK => config.top_k = Some(parse_option(key, value)?),
+            SamplingParameter::Seed => config.seed = Some(parse_option(key, value)?),
+            SamplingParameter::FrequencyPenalty => {
+                config.frequency_penalty = Some(parse_option(key, value)?)
+            }
+            SamplingParameter::PresencePenalty => {
+                config.presence_penalty = Some(parse_option(key, value)?)
+            }
+            SamplingParameter::User => config.user = Some(value.to_string()),
+        }
+        Ok(())
+    }
+
+    fn is_set(&self, config: &Config) -> bool {
+        match self {
+            SamplingParameter::TopP => config.top_p.is_some(),
+            SamplingParameter::TopK => config.top_k.is_some(),
+            SamplingParameter::Seed => config.seed.is_some(),
+            SamplingParameter::FrequencyPenalty => config.frequency_penalty.is_some(),
+            SamplingParameter::PresencePenalty => config.presence_penalty.is_some(),
+            SamplingParameter::User => config.user.is_some(),
+        }
+    }
+}
+
+/// Checks that the sampling parameters of `config` are in their valid ranges, and fails with
+/// an `unsupported` error if a parameter the provider does not support is set.
+///
+/// The supported parameters still passed in the provider options they were read from before
+/// they were part of the config are moved to the config first, with a deprecation warning.
+pub fn validate_sampling_parameters(
+    config: &mut Config,
+    supported: &[SamplingParameter],
+) -> Result<(), Error> {
+    take_deprecated_options(config, supported)?;
+
+    check_range("temperature", config.temperature, 0.0, 2.0)?;
+    check_range("top-p", config.top_p, 0.0, 1.0)?;
+    check_range("frequency-penalty", config.frequency_penalty, -2.0, 2.0)?;
+    check_range("presence-penalty", config.presence_penalty, -2.0, 2.0)?;
+    if config.top_k == Some(0) {
+        return Err(invalid_request("top-k must be at least 1"));
+    }
+
+    match SamplingParameter::ALL
+        .iter()
+        .find(|parameter| parameter.is_set(config) && !supported.contains(parameter))
+    {
+        Some(parameter) => Err(unsupported(parameter.name())),
+        None => Ok(()),
+    }
+}
+
+/// The seed for the providers taking a signed one. Wrapping the values above `i32::MAX` keeps
+/// the mapping deterministic, so the same seed always gives the same samples.
+pub fn signed_seed(seed: u32) -> i32 {
+    seed as i32
+}
+
+fn take_deprecated_options(
+    config: &mut Config,
+    supported: &[SamplingParameter],
+) -> Result<(), Error> {
+    let mut provider_options = Vec::with_capacity(config.provider_options.len());
+    for option in std::mem::take(&mut config.provider_options) {
+        let parameter = supported.iter().find(|parameter| {
+            parameter
+                .deprecated_option_keys()
+                .contains(&option.key.as_str())
+        });
+        match parameter {
+            Some(parameter) => {
+                warn!(
+                    "The {} provider option is deprecated, use the {} field of the config instead",
+                    option.key,
+                    parameter.name()
+                );
+                parameter.set_from_option(config, &option.key, &option.value)?;
+            }
+            None => provider_options.push(option),
+        }
+    }
+    config.provider_options = provider_options;
+    Ok(())
+}
+
+fn parse_option<T: FromStr>(key: &str, value: &str) -> Result<T, Error> {
+    value.parse().map_err(|_| {
+        invalid_request(format!(
+            "Invalid value of the {key} provider option: {value}"
+        ))
+    })
+}
+
+fn check_range(name: &str, value: Option<f32>, min: f32, max: f32) -> Result<(), Error> {
+    match value {
+        Some(value) if !(min..=max).contains(&value) => Err(invalid_request(format!(
+            "{name} must be between {min} and {max}, got {value}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn invalid_request(message: impl Into<String>) -> Error {
+    Error {
+        code: ErrorCode::InvalidRequest,
+        message: message.into(),
+        provider_error_json: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::golem::llm::llm::{Config, ErrorCode, Kv};
+    use crate::sampling::{validate_sampling_parameters, SamplingParameter};
+
+    fn config() -> Config {
+        Config {
+            model: "test-model".to_string(),
+            temperature: None,
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        }
+    }
+
+    #[test]
+    fn accepts_supported_parameters_in_range() {
+        let mut config = Config {
+            temperature: Some(0.0),
+            top_p: Some(1.0),
+            top_k: Some(40),
+            frequency_penalty: Some(-2.0),
+            presence_penalty: Some(2.0),
+            ..config()
+        };
+        let supported = [
+            SamplingParameter::TopP,
+            SamplingParameter::TopK,
+            SamplingParameter::FrequencyPenalty,
+            SamplingParameter::PresencePenalty,
+        ];
+        assert!(validate_sampling_parameters(&mut config, &supported).is_ok());
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let cases = [
+            Config {
+                temperature: Some(2.5),
+                ..config()
+            },
+            Config {
+                top_p: Some(1.1),
+                ..config()
+            },
+            Config {
+                top_p: Some(f32::NAN),
+                ..config()
+            },
+            Config {
+                top_k: Some(0),
+                ..config()
+            },
+            Config {
+                presence_penalty: Some(-3.0),
+                ..config()
+            },
+        ];
+        for mut config in cases {
+            let error =
+                validate_sampling_parameters(&mut config, &SamplingParameter::ALL).unwrap_err();
+            assert_eq!(error.code, ErrorCode::InvalidRequest);
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_parameters() {
+        let mut config = Config {
+            top_p: Some(0.5),
+            seed: Some(42),
+            ..config()
+        };
+        let error =
+            validate_sampling_parameters(&mut config, &[SamplingParameter::TopP]).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Unsupported);
+        assert!(error.message.contains("seed"));
+    }
+
+    fn option(key: &str, value: &str) -> Kv {
+        Kv {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn reads_deprecated_provider_options() {
+        let mut config = Config {
+            seed: Some(7),
+            provider_options: vec![
+                option("top_p", "0.5"),
+                option("seed", "42"),
+                option("user_id", "user-1"),
+                option("top_k", "40"),
+                option("num_ctx", "4096"),
+            ],
+            ..config()
+        };
+        let supported = [
+            SamplingParameter::TopP,
+            SamplingParameter::Seed,
+            SamplingParameter::User,
+        ];
+        assert!(validate_sampling_parameters(&mut config, &supported).is_ok());
+
+        assert_eq!(config.top_p, Some(0.5));
+        // The typed field takes precedence over the provider option
+        assert_eq!(config.seed, Some(7));
+        assert_eq!(config.user, Some("user-1".to_string()));
+        // The options of unsupported parameters are left to the provider
+        assert_eq!(config.top_k, None);
+        assert_eq!(
+            config.provider_options,
+            vec![option("top_k", "40"), option("num_ctx", "4096")]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_deprecated_provider_options() {
+        for options in [
+            vec![option("top_p", "high")],
+            vec![option("top_p", "1.5")],
+            vec![option("seed", "-1")],
+        ] {
+            let mut config = Config {
+                provider_options: options,
+                ..config()
+            };
+            let error =
+                validate_sampling_parameters(&mut config, &SamplingParameter::ALL).unwrap_err();
+            assert_eq!(error.code, ErrorCode::InvalidRequest);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use crate::batch::UnsupportedChatBatch;
+use crate::chat_job::EmulatedChatJob;
+use crate::conversation::LlmConversation;
+use crate::durability::ExtendedGuest;
+use crate::error::unsupported;
+use crate::golem::llm::llm::{
+    ChatBatch, ChatEvent, ChatJob, ChatStream, CompleteResponse, Config, ContentPart, Error,
+    ErrorCode, Guest, GuestChatStream, Message, ModelInfo, ResponseMetadata, Role, StreamEvent,
+    TokenCount, ToolCall, ToolResult, ToolSuccess,
+};
+use crate::tokens::estimate_token_count;
+use golem_rust::wasm_rpc::Pollable;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+thread_local! {
+    static RESPONSES: RefCell<VecDeque<ChatEvent>> = const { RefCell::new(VecDeque::new()) };
+    static REQUESTS: RefCell<Vec<TestRequest>> = const { RefCell::new(Vec::new()) };
+    static STREAM_EVENTS: RefCell<VecDeque<Vec<StreamEvent>>> =
+        const { RefCell::new(VecDeque::new()) };
+}
+
+/// A request received by [`TestModel`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestRequest {
+    pub messages: Vec<Message>,
+    pub tool_results: Vec<(ToolCall, ToolResult)>,
+}
+
+/// An LLM implementation for the unit tests of the resources built on top of `Guest`, answering
+/// the requests with the queued responses and recording them. The state is per thread, so each
+/// test has its own.
+pub struct TestModel;
+
+impl TestModel {
+    /// Queues the responses of the next requests
+    pub fn respond_with(events: Vec<ChatEvent>) {
+        RESPONSES.with_borrow_mut(|responses| responses.extend(events));
+    }
+
+    /// Queues the events returned by the next calls of `get-next` on the streams
+    pub fn stream_with(events: Vec<Vec<StreamEvent>>) {
+        STREAM_EVENTS.with_borrow_mut(|queued| queued.extend(events));
+    }
+
+    /// The requests received so far
+    pub fn requests() -> Vec<TestRequest> {
+        REQUESTS.with_borrow(|requests| requests.clone())
+    }
+
+    fn respond(messages: Vec<Message>, tool_results: Vec<(ToolCall, ToolResult)>) -> ChatEvent {
+        REQUESTS.with_borrow_mut(|requests| {
+            requests.push(TestRequest {
+                messages,
+                tool_results,
+            })
+        });
+        RESPONSES
+            .with_borrow_mut(|responses| responses.pop_front())
+            .unwrap_or_else(|| {
+                ChatEvent::Error(Error {
+                    code: ErrorCode::InternalError,
+                    message: "No more responses queued".to_string(),
+                    provider_error_json: None,
+                })
+            })
+    }
+}
+
+pub struct TestChatStream;
+
+impl GuestChatStream for TestChatStream {
+    fn get_next(&self) -> Option<Vec<StreamEvent>> {
+        Some(self.blocking_get_next())
+    }
+
+    fn blocking_get_next(&self) -> Vec<StreamEvent> {
+        STREAM_EVENTS
+            .with_borrow_mut(|queued| queued.pop_front())
+            .unwrap_or_default()
+    }
+
+    fn cancel(&self) {}
+}
+
+impl Guest for TestModel {
+    type ChatStream = TestChatStream;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = UnsupportedChatBatch;
+
+    fn send(messages: Vec<Message>, _config: Config) -> ChatEvent {
+        Self::respond(messages, vec![])
+    }
+
+    fn continue_(
+        messages: Vec<Message>,
+        tool_results: Vec<(ToolCall, ToolResult)>,
+        _config: Config,
+    ) -> ChatEvent {
+        Self::respond(messages, tool_results)
+    }
+
+    fn stream(_messages: Vec<Message>, _config: Config) -> ChatStream {
+        ChatStream::new(TestChatStream)
+    }
+
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        Ok(vec![])
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        Ok(estimate_token_count(&messages, &config))
+    }
+
+    fn fetch_job(_id: String) -> Result<ChatJob, Error> {
+        Err(unsupported("background jobs"))
+    }
+
+    fn fetch_batch(_id: String) -> Result<ChatBatch, Error> {
+        Err(unsupported("batches"))
+    }
+}
+
+impl ExtendedGuest for TestModel {
+    fn unwrapped_stream(_messages: Vec<Message>, _config: Config) -> Self::ChatStream {
+        TestChatStream
+    }
+
+    fn subscribe(_stream: &Self::ChatStream) -> Pollable {
+        unreachable!("The test streams are not polled")
+    }
+}
+
+pub fn config() -> Config {
+    Config {
+        model: "test-model".to_string(),
+        temperature: None,
+        max_tokens: None,
+        stop_sequences: None,
+        tools: vec![],
+        builtin_tools: vec![],
+        tool_choice: None,
+        provider_options: vec![],
+        response_format: None,
+        reasoning: None,
+        top_p: None,
+        top_k: None,
+        seed: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+        user: None,
+        validate_tool_calls: None,
+    }
+}
+
+pub fn message(role: Role, text: &str) -> Message {
+    Message {
+        role,
+        name: None,
+        content: vec![ContentPart::Text(text.to_string())],
+        cache_control: None,
+    }
+}
+
+pub fn response(text: &str) -> ChatEvent {
+    ChatEvent::Message(CompleteResponse {
+        id: "response-1".to_string(),
+        content: vec![ContentPart::Text(text.to_string())],
+        tool_calls: vec![],
+        metadata: ResponseMetadata {
+            finish_reason: None,
+            usage: None,
+            provider_id: None,
+            timestamp: None,
+            provider_metadata_json: None,
+            rate_limit: None,
+            cost: None,
+        },
+        logprobs: None,
+        alternatives: vec![],
+        builtin_tool_results: vec![],
+    })
+}
+
+pub fn tool_call(id: &str) -> ToolCall {
+    ToolCall {
+        id: id.to_string(),
+        name: "weather".to_string(),
+        arguments_json: "{}".to_string(),
+        validation_error: None,
+    }
+}
+
+pub fn tool_success(tool_call: &ToolCall, result_json: &str) -> (ToolCall, ToolResult) {
+    (
+        tool_call.clone(),
+        ToolResult::Success(ToolSuccess {
+            id: tool_call.id.clone(),
+            name: tool_call.name.clone(),
+            result_json: result_json.to_string(),
+            execution_time_ms: None,
+        }),
+    )
+}
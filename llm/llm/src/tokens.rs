@@ -0,0 +1,170 @@
+use crate::golem::llm::llm::{
+    Config, ContentPart, DocumentData, ImageDetail, ImageReference, Message, TokenCount, ToolCall,
+    ToolResult,
+};
+
+/// Average number of characters per token for English text with the common BPE tokenizers
+const CHARS_PER_TOKEN: usize = 4;
+/// Tokens used by the role markers and separators around each message
+const TOKENS_PER_MESSAGE: u32 = 4;
+/// Tokens of a low detail image, which is also the per-tile cost of high detail images
+const LOW_DETAIL_IMAGE_TOKENS: u32 = 85;
+/// Tokens of a high detail image assuming it is scaled to four tiles
+const HIGH_DETAIL_IMAGE_TOKENS: u32 = 765;
+/// Tokens of a binary document such as a PDF, where the number of pages cannot be known locally
+const BINARY_DOCUMENT_TOKENS: u32 = 1500;
+/// Tokens of an audio input, where the length of the recording cannot be known locally
+const AUDIO_TOKENS: u32 = 500;
+
+/// Estimates the number of input tokens of a request without a tokenizer, for providers that
+/// have no endpoint for counting tokens. The result is only an approximation and is always
+/// marked as `estimated`.
+pub fn estimate_token_count(messages: &[Message], config: &Config) -> TokenCount {
+    let mut tokens = 0;
+
+    for message in messages {
+        tokens += estimate_message_tokens(message);
+    }
+
+    for tool in &config.tools {
+        tokens += estimate_text_tokens(&tool.name);
+        tokens += tool
+            .description
+            .as_deref()
+            .map(estimate_text_tokens)
+            .unwrap_or(0);
+        tokens += estimate_text_tokens(&tool.parameters_schema);
+    }
+
+    TokenCount {
+        input_tokens: tokens,
+        estimated: true,
+    }
+}
+
+/// Estimates the number of tokens a single message adds to a request
+pub fn estimate_message_tokens(message: &Message) -> u32 {
+    let mut tokens = TOKENS_PER_MESSAGE;
+    if let Some(name) = &message.name {
+        tokens += estimate_text_tokens(name);
+    }
+    for content_part in &message.content {
+        tokens += estimate_content_part_tokens(content_part);
+    }
+    tokens
+}
+
+/// Estimates the number of tokens the tool calls and their results add to a request, as the
+/// assistant message requesting the calls and a message per result
+pub fn estimate_tool_results_tokens(tool_results: &[(ToolCall, ToolResult)]) -> u32 {
+    let mut tokens = 0;
+    if !tool_results.is_empty() {
+        tokens += TOKENS_PER_MESSAGE;
+    }
+    for (tool_call, tool_result) in tool_results {
+        tokens += TOKENS_PER_MESSAGE;
+        tokens += estimate_text_tokens(&tool_call.id);
+        tokens += estimate_text_tokens(&tool_call.name);
+        tokens += estimate_text_tokens(&tool_call.arguments_json);
+        tokens += match tool_result {
+            ToolResult::Success(success) => estimate_text_tokens(&success.result_json),
+            ToolResult::Error(failure) => estimate_text_tokens(&failure.error_message),
+        };
+    }
+    tokens
+}
+
+fn estimate_content_part_tokens(content_part: &ContentPart) -> u32 {
+    match content_part {
+        ContentPart::Text(text) => estimate_text_tokens(text),
+        ContentPart::Image(reference) => {
+            let detail = match reference {
+                ImageReference::Url(image_url) => image_url.detail,
+                ImageReference::Inline(image_source) => image_source.detail,
+            };
+            match detail {
+                Some(ImageDetail::Low) => LOW_DETAIL_IMAGE_TOKENS,
+                _ => HIGH_DETAIL_IMAGE_TOKENS,
+            }
+        }
+        ContentPart::Reasoning(reasoning) => estimate_text_tokens(&reasoning.text),
+        ContentPart::Document(document) => match &document.data {
+            DocumentData::Inline(data) if document.mime_type.starts_with("text/") => {
+                estimate_text_tokens(&String::from_utf8_lossy(data))
+            }
+            _ => BINARY_DOCUMENT_TOKENS,
+        },
+        ContentPart::Audio(_) => AUDIO_TOKENS,
+    }
+}
+
+fn estimate_text_tokens(text: &str) -> u32 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::golem::llm::llm::{
+        Config, ContentPart, ImageDetail, ImageReference, ImageUrl, Message, Role, ToolDefinition,
+    };
+    use crate::tokens::estimate_token_count;
+
+    fn config(tools: Vec<ToolDefinition>) -> Config {
+        Config {
+            model: "test-model".to_string(),
+            temperature: None,
+            max_tokens: None,
+            stop_sequences: None,
+            tools,
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        }
+    }
+
+    fn message(content: Vec<ContentPart>) -> Message {
+        Message {
+            role: Role::User,
+            name: None,
+            content,
+            cache_control: None,
+        }
+    }
+
+    #[test]
+    fn estimate_text_messages() {
+        let messages = vec![
+            message(vec![ContentPart::Text("abcdefgh".to_string())]),
+            message(vec![ContentPart::Text("abcde".to_string())]),
+        ];
+        let count = estimate_token_count(&messages, &config(vec![]));
+        assert_eq!(count.input_tokens, 4 + 2 + 4 + 2);
+        assert!(count.estimated);
+    }
+
+    #[test]
+    fn estimate_images_and_tools() {
+        let messages = vec![message(vec![ContentPart::Image(ImageReference::Url(
+            ImageUrl {
+                url: "https://example.com/image.png".to_string(),
+                detail: Some(ImageDetail::Low),
+            },
+        ))])];
+        let tools = vec![ToolDefinition {
+            name: "test".to_string(),
+            description: None,
+            parameters_schema: "{}".to_string(),
+        }];
+        let count = estimate_token_count(&messages, &config(tools));
+        assert_eq!(count.input_tokens, 4 + 85 + 1 + 1);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::golem::llm::llm::{ChatEvent, Config, StreamEvent, ToolCall, ToolFailure};
+use jsonschema::Validator;
+use log::warn;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The error code of the tool failures of calls with arguments not matching the schema
+pub const INVALID_ARGUMENTS: &str = "invalid-arguments";
+
+/// The error code of the tool failures of calls to tools not defined in the config
+pub const UNKNOWN_TOOL: &str = "unknown-tool";
+
+/// Validates the arguments of the tool calls returned by the model against the
+/// `parameters-schema` of the called tools, when enabled with `validate-tool-calls`. The invalid
+/// calls are returned with a `validation-error`, which can be sent back to the model as the
+/// result of the call.
+pub struct ToolCallValidator {
+    /// The compiled schemas by tool name, `None` for the tools with an invalid schema, which
+    /// are not validated
+    validators: HashMap<String, Option<Validator>>,
+}
+
+impl ToolCallValidator {
+    /// Compiles the schemas of the tools, `None` if the validation is not enabled
+    pub fn new(config: &Config) -> Option<Self> {
+        if config.validate_tool_calls != Some(true) {
+            return None;
+        }
+
+        let validators = config
+            .tools
+            .iter()
+            .map(|tool| {
+                let validator = serde_json::from_str::<Value>(&tool.parameters_schema)
+                    .map_err(|err| err.to_string())
+                    .and_then(|schema| {
+                        jsonschema::validator_for(&schema).map_err(|err| err.to_string())
+                    })
+                    .inspect_err(|err| {
+                        warn!(
+                            "The calls of tool {} are not validated, its parameters schema is invalid: {err}",
+                            tool.name
+                        )
+                    })
+                    .ok();
+                (tool.name.clone(), validator)
+            })
+            .collect();
+        Some(Self { validators })
+    }
+
+    /// Sets the `validation-error` of the tool call if it is invalid
+    pub fn validate(&self, tool_call: &mut ToolCall) {
+        let error_message = match self.validators.get(&tool_call.name) {
+            None => Some((
+                UNKNOWN_TOOL,
+                format!("The tool {} does not exist", tool_call.name),
+            )),
+            Some(None) => None,
+            Some(Some(validator)) => match parse_arguments(&tool_call.arguments_json) {
+                Ok(arguments) => {
+                    let errors = validator
+                        .iter_errors(&arguments)
+                        .map(|error| {
+                            let path = error.instance_path.to_string();
+                            if path.is_empty() {
+                                error.to_string()
+                            } else {
+                                format!("{path}: {error}")
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    (!errors.is_empty()).then(|| {
+                        (
+                            INVALID_ARGUMENTS,
+                            format!(
+                                "The arguments do not match the parameters schema of the tool: {}",
+                                errors.join("; ")
+                            ),
+                        )
+                    })
+                }
+                Err(err) => Some((
+                    INVALID_ARGUMENTS,
+                    format!("The arguments are not valid JSON: {err}"),
+                )),
+            },
+        };
+
+        tool_call.validation_error = error_message.map(|(code, message)| ToolFailure {
+            id: tool_call.id.clone(),
+            name: tool_call.name.clone(),
+            error_message: message,
+            error_code: Some(code.to_string()),
+        });
+    }
+
+    /// Validates the tool calls of a response, including the ones of its alternatives
+    pub fn validate_event(&self, mut event: ChatEvent) -> ChatEvent {
+        match &mut event {
+            ChatEvent::Message(response) => {
+                response
+                    .tool_calls
+                    .iter_mut()
+                    .chain(
+                        response
+                            .alternatives
+                            .iter_mut()
+                            .flat_map(|alternative| alternative.tool_calls.iter_mut()),
+                    )
+                    .for_each(|tool_call| self.validate(tool_call));
+            }
+            ChatEvent::ToolRequest(tool_calls) => {
+                tool_calls
+                    .iter_mut()
+                    .for_each(|tool_call| self.validate(tool_call));
+            }
+            ChatEvent::Error(_) => {}
+        }
+        event
+    }
+
+    /// Validates the tool calls of the stream deltas. The providers emit the tool calls of a
+    /// stream when their arguments have been fully received.
+    pub fn validate_stream_events(&self, events: &mut [StreamEvent]) {
+        for event in events {
+            if let StreamEvent::Delta(delta) = event {
+                delta
+                    .tool_calls
+                    .iter_mut()
+                    .flatten()
+                    .for_each(|tool_call| self.validate(tool_call));
+            }
+        }
+    }
+}
+
+/// Parses the arguments of a tool call, some providers send no arguments for tools without
+/// parameters
+fn parse_arguments(arguments_json: &str) -> serde_json::Result<Value> {
+    if arguments_json.trim().is_empty() {
+        Ok(Value::Object(Default::default()))
+    } else {
+        serde_json::from_str(arguments_json)
+    }
+}
+
+/// Validates the tool calls of a response if it is enabled in the config
+pub fn validate_tool_calls(config: &Config, event: ChatEvent) -> ChatEvent {
+    match ToolCallValidator::new(config) {
+        Some(validator) => validator.validate_event(event),
+        None => event,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::golem::llm::llm::{Config, ToolCall, ToolDefinition};
+    use crate::tool_validation::{ToolCallValidator, INVALID_ARGUMENTS, UNKNOWN_TOOL};
+
+    fn config(validate_tool_calls: Option<bool>) -> Config {
+        Config {
+            model: "test-model".to_string(),
+            temperature: None,
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![
+                ToolDefinition {
+                    name: "weather".to_string(),
+                    description: None,
+                    parameters_schema: r#"{
+                        "type": "object",
+                        "properties": {
+                            "city": { "type": "string" },
+                            "days": { "type": "integer", "minimum": 1 }
+                        },
+                        "required": ["city"]
+                    }"#
+                    .to_string(),
+                },
+                ToolDefinition {
+                    name: "broken".to_string(),
+                    description: None,
+                    parameters_schema: "not a schema".to_string(),
+                },
+            ],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls,
+        }
+    }
+
+    fn validate(name: &str, arguments_json: &str) -> Option<(String, String)> {
+        let validator = ToolCallValidator::new(&config(Some(true))).unwrap();
+        let mut tool_call = ToolCall {
+            id: "call-1".to_string(),
+            name: name.to_string(),
+            arguments_json: arguments_json.to_string(),
+            validation_error: None,
+        };
+        validator.validate(&mut tool_call);
+        tool_call.validation_error.map(|failure| {
+            assert_eq!(failure.id, "call-1");
+            assert_eq!(failure.name, name);
+            (failure.error_code.unwrap(), failure.error_message)
+        })
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        assert!(ToolCallValidator::new(&config(None)).is_none());
+        assert!(ToolCallValidator::new(&config(Some(false))).is_none());
+    }
+
+    #[test]
+    fn accepts_valid_arguments() {
+        assert_eq!(validate("weather", r#"{"city": "Paris", "days": 3}"#), None);
+    }
+
+    #[test]
+    fn rejects_arguments_not_matching_the_schema() {
+        let (code, message) = validate("weather", r#"{"days": 0}"#).unwrap();
+        assert_eq!(code, INVALID_ARGUMENTS);
+        assert!(message.contains("city"), "{message}");
+        assert!(message.contains("/days"), "{message}");
+    }
+
+    #[test]
+    fn rejects_malformed_arguments() {
+        let (code, _) = validate("weather", r#"{"city": "Par"#).unwrap();
+        assert_eq!(code, INVALID_ARGUMENTS);
+    }
+
+    #[test]
+    fn rejects_unknown_tools() {
+        let (code, _) = validate("stocks", "{}").unwrap();
+        assert_eq!(code, UNKNOWN_TOOL);
+    }
+
+    #[test]
+    fn skips_tools_with_invalid_schema() {
+        assert_eq!(validate("broken", "[1, 2]"), None);
+    }
+}
@@ -0,0 +1,214 @@
+mod script;
+mod stream;
+
+use crate::script::{Request, Script, ScriptedResponse};
+use crate::stream::{MockChatStream, StreamedResponse};
+use golem_llm::batch::UnsupportedChatBatch;
+use golem_llm::chat_job::EmulatedChatJob;
+use golem_llm::conversation::LlmConversation;
+use golem_llm::durability::ExtendedGuest;
+use golem_llm::golem::llm::llm::{
+    ChatBatch, ChatEvent, ChatJob, ChatStream, CompleteResponse, Config, ContentPart, Error,
+    FinishReason, Guest, Message, ModelCapabilities, ModelInfo, ReasoningContent, ResponseMetadata,
+    TokenCount, ToolCall, ToolResult,
+};
+use golem_llm::tokens::estimate_token_count;
+use golem_rust::wasm_rpc::Pollable;
+use log::trace;
+
+/// An LLM provider playing back the responses of a script instead of calling a real model,
+/// for testing components using `golem:llm` without network access. See [`Script`] for the
+/// format of the script.
+pub struct MockComponent;
+
+impl MockComponent {
+    fn select(request: &Request) -> Result<(String, ScriptedResponse), Error> {
+        let script = Script::load()?;
+        let (idx, response) = script.select(request)?;
+        trace!("Responding with rule {idx} of the mock script");
+        Ok((format!("mock-response-{idx}"), response.clone()))
+    }
+
+    fn request(messages: &[Message], tool_results: &[(ToolCall, ToolResult)]) -> ChatEvent {
+        let request = Request {
+            messages,
+            tool_results,
+            streaming: false,
+        };
+        match Self::select(&request) {
+            Ok((id, response)) => Self::chat_event(id, response),
+            Err(err) => ChatEvent::Error(err),
+        }
+    }
+
+    /// Converts a scripted response to the returned chat event. Scripted failures and crashes
+    /// only apply to streams, the complete response is returned at once.
+    fn chat_event(id: String, response: ScriptedResponse) -> ChatEvent {
+        match response {
+            ScriptedResponse::Message {
+                text,
+                reasoning,
+                tool_calls,
+                usage,
+                ..
+            } => {
+                let mut content = Vec::new();
+                if let Some(reasoning) = reasoning {
+                    content.push(ContentPart::Reasoning(ReasoningContent {
+                        text: reasoning,
+                        signature: None,
+                        redacted_data: None,
+                    }));
+                }
+                content.push(ContentPart::Text(text.whole()));
+                let finish_reason = if tool_calls.is_empty() {
+                    FinishReason::Stop
+                } else {
+                    FinishReason::ToolCalls
+                };
+                ChatEvent::Message(CompleteResponse {
+                    id: id.clone(),
+                    content,
+                    tool_calls: tool_calls.iter().map(ToolCall::from).collect(),
+                    metadata: ResponseMetadata {
+                        finish_reason: Some(finish_reason),
+                        usage: usage.as_ref().map(Into::into),
+                        provider_id: Some(id),
+                        timestamp: None,
+                        provider_metadata_json: None,
+                        rate_limit: None,
+                        cost: None,
+                    },
+                    logprobs: None,
+                    alternatives: vec![],
+                    builtin_tool_results: vec![],
+                })
+            }
+            ScriptedResponse::ToolRequest { tool_calls } => {
+                ChatEvent::ToolRequest(tool_calls.iter().map(ToolCall::from).collect())
+            }
+            ScriptedResponse::Error { code, message } => ChatEvent::Error(Error {
+                code: code.into(),
+                message,
+                provider_error_json: None,
+            }),
+        }
+    }
+
+    fn chat_stream(id: String, response: ScriptedResponse) -> MockChatStream {
+        match response {
+            ScriptedResponse::Message {
+                text,
+                reasoning,
+                tool_calls,
+                usage,
+                chunk_delay_ms,
+                fail_after,
+                crash_after,
+            } => MockChatStream::new(StreamedResponse {
+                id,
+                chunks: text.chunks(),
+                reasoning,
+                tool_calls: tool_calls.iter().map(ToolCall::from).collect(),
+                usage: usage.as_ref().map(Into::into),
+                chunk_delay_ms,
+                fail_after,
+                crash_after,
+            }),
+            ScriptedResponse::ToolRequest { tool_calls } => MockChatStream::new(StreamedResponse {
+                id,
+                chunks: vec![],
+                reasoning: None,
+                tool_calls: tool_calls.iter().map(ToolCall::from).collect(),
+                usage: None,
+                chunk_delay_ms: 0,
+                fail_after: None,
+                crash_after: None,
+            }),
+            ScriptedResponse::Error { code, message } => MockChatStream::failed(Error {
+                code: code.into(),
+                message,
+                provider_error_json: None,
+            }),
+        }
+    }
+}
+
+impl Guest for MockComponent {
+    type ChatStream = MockChatStream;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = UnsupportedChatBatch;
+
+    fn send(messages: Vec<Message>, _config: Config) -> ChatEvent {
+        Self::request(&messages, &[])
+    }
+
+    fn continue_(
+        messages: Vec<Message>,
+        tool_results: Vec<(ToolCall, ToolResult)>,
+        _config: Config,
+    ) -> ChatEvent {
+        Self::request(&messages, &tool_results)
+    }
+
+    fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
+        ChatStream::new(Self::unwrapped_stream(messages, config))
+    }
+
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        Ok(Script::load()?
+            .models
+            .into_iter()
+            .map(|id| ModelInfo {
+                id,
+                context_window: None,
+                max_output_tokens: None,
+                capabilities: ModelCapabilities {
+                    tools: true,
+                    vision: true,
+                    reasoning: true,
+                    json_schema: true,
+                },
+            })
+            .collect())
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        Ok(estimate_token_count(&messages, &config))
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
+}
+
+impl ExtendedGuest for MockComponent {
+    fn unwrapped_stream(messages: Vec<Message>, _config: Config) -> MockChatStream {
+        let request = Request {
+            messages: &messages,
+            tool_results: &[],
+            streaming: true,
+        };
+        match Self::select(&request) {
+            Ok((id, response)) => Self::chat_stream(id, response),
+            Err(err) => MockChatStream::failed(err),
+        }
+    }
+
+    fn pricing_provider() -> Option<&'static str> {
+        Some("mock")
+    }
+
+    fn subscribe(stream: &Self::ChatStream) -> Pollable {
+        stream.subscribe()
+    }
+}
+
+type DurableMockComponent = golem_llm::durability::DurableLLM<MockComponent>;
+
+golem_llm::export_llm!(DurableMockComponent with_types_in golem_llm);
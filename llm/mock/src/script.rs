@@ -0,0 +1,398 @@
+use golem_llm::config::get_config_key_or_none;
+use golem_llm::golem::llm::llm::{
+    ContentPart, Error, ErrorCode, Message, ToolCall, ToolResult, Usage,
+};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// The environment variable holding the script as JSON
+pub const SCRIPT_ENV_VAR_NAME: &str = "GOLEM_LLM_MOCK_SCRIPT";
+/// The environment variable holding the path of a JSON file with the script, used when
+/// `GOLEM_LLM_MOCK_SCRIPT` is not set
+pub const SCRIPT_PATH_ENV_VAR_NAME: &str = "GOLEM_LLM_MOCK_SCRIPT_PATH";
+
+thread_local! {
+    /// How many times each rule of the script was used, by the index of the rule
+    static RULE_USES: RefCell<HashMap<usize, u32>> = RefCell::new(HashMap::new());
+}
+
+/// The responses the mock plays back, selected by the first matching rule for each request.
+///
+/// Example:
+/// ```json
+/// {
+///   "rules": [
+///     { "when": { "tool_results": false },
+///       "response": { "type": "tool_request", "tool_calls": [{ "id": "call-1", "name": "test-tool", "arguments": { "maximum": 10 } }] } },
+///     { "response": { "type": "message", "text": ["The number is ", "six."], "chunk_delay_ms": 100 } }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Script {
+    pub rules: Vec<Rule>,
+    /// The models returned by `list-models`
+    #[serde(default = "default_models")]
+    pub models: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    #[serde(default)]
+    pub when: Condition,
+    /// How many times the rule can be used, unlimited by default. The uses are only counted in
+    /// memory, so they start again after the worker is restarted.
+    pub times: Option<u32>,
+    pub response: ScriptedResponse,
+}
+
+/// The conditions a request has to meet for a rule, all of them are optional
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    /// A part of the text of the last message, or of the tool results
+    pub contains: Option<String>,
+    /// Whether the request answers tool calls
+    pub tool_results: Option<bool>,
+    /// Whether the response is streamed
+    pub streaming: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ScriptedResponse {
+    Message {
+        /// The text of the response, optionally split into the chunks it is streamed in
+        text: Text,
+        reasoning: Option<String>,
+        #[serde(default)]
+        tool_calls: Vec<ScriptedToolCall>,
+        usage: Option<ScriptedUsage>,
+        /// The delay before streaming each chunk
+        #[serde(default)]
+        chunk_delay_ms: u64,
+        /// Number of chunks streamed before the stream fails with an error event
+        fail_after: Option<usize>,
+        /// Number of chunks streamed before the component panics, simulating a crash of the
+        /// worker in the middle of the stream
+        crash_after: Option<usize>,
+    },
+    ToolRequest {
+        tool_calls: Vec<ScriptedToolCall>,
+    },
+    Error {
+        code: ScriptedErrorCode,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Text {
+    Whole(String),
+    Chunks(Vec<String>),
+}
+
+impl Text {
+    pub fn chunks(&self) -> Vec<String> {
+        match self {
+            Text::Whole(text) => vec![text.clone()],
+            Text::Chunks(chunks) => chunks.clone(),
+        }
+    }
+
+    pub fn whole(&self) -> String {
+        self.chunks().concat()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptedToolCall {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+}
+
+impl From<&ScriptedToolCall> for ToolCall {
+    fn from(tool_call: &ScriptedToolCall) -> Self {
+        ToolCall {
+            id: tool_call.id.clone(),
+            name: tool_call.name.clone(),
+            arguments_json: tool_call.arguments.to_string(),
+            validation_error: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptedUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+impl From<&ScriptedUsage> for Usage {
+    fn from(usage: &ScriptedUsage) -> Self {
+        Usage {
+            input_tokens: Some(usage.input_tokens),
+            output_tokens: Some(usage.output_tokens),
+            total_tokens: Some(usage.input_tokens + usage.output_tokens),
+            cache_read_tokens: None,
+            cache_write_tokens: None,
+            reasoning_tokens: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptedErrorCode {
+    InvalidRequest,
+    AuthenticationFailed,
+    RateLimitExceeded,
+    InternalError,
+    Unsupported,
+    Unknown,
+}
+
+impl From<ScriptedErrorCode> for ErrorCode {
+    fn from(code: ScriptedErrorCode) -> Self {
+        match code {
+            ScriptedErrorCode::InvalidRequest => ErrorCode::InvalidRequest,
+            ScriptedErrorCode::AuthenticationFailed => ErrorCode::AuthenticationFailed,
+            ScriptedErrorCode::RateLimitExceeded => ErrorCode::RateLimitExceeded,
+            ScriptedErrorCode::InternalError => ErrorCode::InternalError,
+            ScriptedErrorCode::Unsupported => ErrorCode::Unsupported,
+            ScriptedErrorCode::Unknown => ErrorCode::Unknown,
+        }
+    }
+}
+
+/// The parts of a request the rules are matched against
+pub struct Request<'a> {
+    pub messages: &'a [Message],
+    pub tool_results: &'a [(ToolCall, ToolResult)],
+    pub streaming: bool,
+}
+
+impl Request<'_> {
+    fn last_text(&self) -> String {
+        let mut text = Vec::new();
+        if let Some(message) = self.messages.last() {
+            for content in &message.content {
+                if let ContentPart::Text(part) = content {
+                    text.push(part.as_str());
+                }
+            }
+        }
+        for (_, tool_result) in self.tool_results {
+            text.push(match tool_result {
+                ToolResult::Success(success) => &success.result_json,
+                ToolResult::Error(failure) => &failure.error_message,
+            });
+        }
+        text.join("\n")
+    }
+}
+
+impl Condition {
+    fn matches(&self, request: &Request) -> bool {
+        self.contains
+            .as_ref()
+            .is_none_or(|contains| request.last_text().contains(contains.as_str()))
+            && self
+                .tool_results
+                .is_none_or(|tool_results| tool_results == !request.tool_results.is_empty())
+            && self
+                .streaming
+                .is_none_or(|streaming| streaming == request.streaming)
+    }
+}
+
+impl Script {
+    /// Reads the script from `GOLEM_LLM_MOCK_SCRIPT`, or from the file in
+    /// `GOLEM_LLM_MOCK_SCRIPT_PATH`
+    pub fn load() -> Result<Self, Error> {
+        let (source, raw) = match get_config_key_or_none(SCRIPT_ENV_VAR_NAME) {
+            Some(raw) => (SCRIPT_ENV_VAR_NAME.to_string(), raw),
+            None => match get_config_key_or_none(SCRIPT_PATH_ENV_VAR_NAME) {
+                Some(path) => {
+                    let raw = std::fs::read_to_string(&path).map_err(|err| {
+                        internal_error(format!("Failed to read the mock script {path}: {err}"))
+                    })?;
+                    (path, raw)
+                }
+                None => {
+                    return Err(internal_error(format!(
+                        "Missing config key: {SCRIPT_ENV_VAR_NAME} or {SCRIPT_PATH_ENV_VAR_NAME}"
+                    )))
+                }
+            },
+        };
+        Self::parse(&raw).map_err(|err| {
+            internal_error(format!("Failed to parse the mock script {source}: {err}"))
+        })
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(raw)
+    }
+
+    /// Selects the response of the first rule matching the request which was not used up yet,
+    /// returning it together with the index of the rule
+    pub fn select(&self, request: &Request) -> Result<(usize, &ScriptedResponse), Error> {
+        RULE_USES.with_borrow_mut(|uses| {
+            for (idx, rule) in self.rules.iter().enumerate() {
+                let used = uses.entry(idx).or_default();
+                if rule.when.matches(request) && rule.times.is_none_or(|times| *used < times) {
+                    *used += 1;
+                    return Ok((idx, &rule.response));
+                }
+            }
+            Err(internal_error(
+                "None of the rules of the mock script matches the request",
+            ))
+        })
+    }
+}
+
+fn default_models() -> Vec<String> {
+    vec!["mock".to_string()]
+}
+
+fn internal_error(message: impl Into<String>) -> Error {
+    Error {
+        code: ErrorCode::InternalError,
+        message: message.into(),
+        provider_error_json: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::script::{Request, Script, ScriptedResponse};
+    use golem_llm::golem::llm::llm::{
+        ContentPart, Message, Role, ToolCall, ToolResult, ToolSuccess,
+    };
+
+    fn user_message(text: &str) -> Message {
+        Message {
+            role: Role::User,
+            name: None,
+            content: vec![ContentPart::Text(text.to_string())],
+            cache_control: None,
+        }
+    }
+
+    fn response_text(response: &ScriptedResponse) -> String {
+        match response {
+            ScriptedResponse::Message { text, .. } => text.whole(),
+            other => panic!("Unexpected response: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn selects_first_matching_rule() {
+        let script = Script::parse(
+            r#"{
+                "rules": [
+                    { "when": { "contains": "weather" }, "response": { "type": "message", "text": "Sunny" } },
+                    { "when": { "tool_results": true }, "response": { "type": "message", "text": ["Six", "."] } },
+                    { "response": { "type": "tool_request", "tool_calls": [{ "id": "call-1", "name": "test-tool" }] } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let messages = [user_message("What is the weather like?")];
+        let request = Request {
+            messages: &messages,
+            tool_results: &[],
+            streaming: false,
+        };
+        let (idx, response) = script.select(&request).unwrap();
+        assert_eq!(idx, 0);
+        assert_eq!(response_text(response), "Sunny");
+
+        let messages = [user_message("Generate a random number")];
+        let request = Request {
+            messages: &messages,
+            tool_results: &[],
+            streaming: true,
+        };
+        assert_eq!(script.select(&request).unwrap().0, 2);
+
+        let tool_results = [(
+            ToolCall {
+                id: "call-1".to_string(),
+                name: "test-tool".to_string(),
+                arguments_json: "{}".to_string(),
+                validation_error: None,
+            },
+            ToolResult::Success(ToolSuccess {
+                id: "call-1".to_string(),
+                name: "test-tool".to_string(),
+                result_json: r#"{ "value": 6 }"#.to_string(),
+                execution_time_ms: None,
+            }),
+        )];
+        let request = Request {
+            messages: &messages,
+            tool_results: &tool_results,
+            streaming: false,
+        };
+        let (idx, response) = script.select(&request).unwrap();
+        assert_eq!(idx, 1);
+        assert_eq!(response_text(response), "Six.");
+    }
+
+    #[test]
+    fn limits_rule_uses() {
+        let script = Script::parse(
+            r#"{
+                "rules": [
+                    { "times": 1, "response": { "type": "error", "code": "rate-limit-exceeded", "message": "Slow down" } },
+                    { "response": { "type": "message", "text": "Hello" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let messages = [user_message("Hi")];
+        let request = Request {
+            messages: &messages,
+            tool_results: &[],
+            streaming: false,
+        };
+        assert_eq!(script.select(&request).unwrap().0, 0);
+        assert_eq!(script.select(&request).unwrap().0, 1);
+        assert_eq!(script.select(&request).unwrap().0, 1);
+    }
+
+    #[test]
+    fn fails_without_matching_rule() {
+        let script = Script::parse(
+            r#"{ "rules": [{ "when": { "streaming": true }, "response": { "type": "message", "text": "Hello" } }] }"#,
+        )
+        .unwrap();
+        assert_eq!(script.models, vec!["mock".to_string()]);
+        let messages = [user_message("Hi")];
+        let request = Request {
+            messages: &messages,
+            tool_results: &[],
+            streaming: false,
+        };
+        assert!(script.select(&request).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(Script::parse(
+            r#"{ "rules": [{ "response": { "type": "message", "txt": "Hello" } }] }"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use golem_llm::chat_stream::cancelled_event;
+use golem_llm::golem::llm::llm::{
+    ContentPart, Error, ErrorCode, FinishReason, GuestChatStream, ReasoningContent,
+    ResponseMetadata, StreamDelta, StreamEvent, ToolCall, Usage,
+};
+use golem_rust::bindings::wasi::clocks::monotonic_clock;
+use golem_rust::wasm_rpc::Pollable;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+/// The next step of a scripted stream
+enum Step {
+    Delta(StreamDelta),
+    Fail(Error),
+    /// Panics, simulating the worker crashing in the middle of the stream
+    Crash,
+    Finish(ResponseMetadata),
+}
+
+/// A chat stream emitting the chunks of a scripted response, waiting `chunk_delay_ms` before
+/// each of them
+pub struct MockChatStream {
+    steps: RefCell<VecDeque<Step>>,
+    chunk_delay_ns: u64,
+    /// The time the next step is emitted at, as returned by `monotonic_clock::now`
+    next_at: Cell<u64>,
+    cancelled: Cell<bool>,
+}
+
+/// The parts of a scripted response emitted by [`MockChatStream`]
+pub struct StreamedResponse {
+    pub id: String,
+    pub chunks: Vec<String>,
+    pub reasoning: Option<String>,
+    pub tool_calls: Vec<ToolCall>,
+    pub usage: Option<Usage>,
+    pub chunk_delay_ms: u64,
+    pub fail_after: Option<usize>,
+    pub crash_after: Option<usize>,
+}
+
+impl MockChatStream {
+    pub fn new(response: StreamedResponse) -> Self {
+        let mut deltas = Vec::new();
+        if let Some(reasoning) = response.reasoning {
+            deltas.push(content_delta(ContentPart::Reasoning(ReasoningContent {
+                text: reasoning,
+                signature: None,
+                redacted_data: None,
+            })));
+        }
+        deltas.extend(
+            response
+                .chunks
+                .into_iter()
+                .map(|chunk| content_delta(ContentPart::Text(chunk))),
+        );
+        let finish_reason = if response.tool_calls.is_empty() {
+            FinishReason::Stop
+        } else {
+            deltas.push(StreamDelta {
+                content: None,
+                tool_calls: Some(response.tool_calls),
+                logprobs: None,
+            });
+            FinishReason::ToolCalls
+        };
+
+        let mut steps = VecDeque::new();
+        for (idx, delta) in deltas.into_iter().enumerate() {
+            if response.fail_after == Some(idx) {
+                steps.push_back(Step::Fail(Error {
+                    code: ErrorCode::InternalError,
+                    message: format!("Scripted failure after {idx} chunks"),
+                    provider_error_json: None,
+                }));
+                return Self::with_steps(steps, response.chunk_delay_ms);
+            }
+            if response.crash_after == Some(idx) {
+                steps.push_back(Step::Crash);
+            }
+            steps.push_back(Step::Delta(delta));
+        }
+        steps.push_back(Step::Finish(ResponseMetadata {
+            finish_reason: Some(finish_reason),
+            usage: response.usage,
+            provider_id: Some(response.id),
+            timestamp: None,
+            provider_metadata_json: None,
+            rate_limit: None,
+            cost: None,
+        }));
+        Self::with_steps(steps, response.chunk_delay_ms)
+    }
+
+    /// A stream failing with `error` without emitting anything
+    pub fn failed(error: Error) -> Self {
+        Self::with_steps(VecDeque::from([Step::Fail(error)]), 0)
+    }
+
+    fn with_steps(steps: VecDeque<Step>, chunk_delay_ms: u64) -> Self {
+        let chunk_delay_ns = chunk_delay_ms * 1_000_000;
+        Self {
+            steps: RefCell::new(steps),
+            chunk_delay_ns,
+            next_at: Cell::new(monotonic_clock::now() + chunk_delay_ns),
+            cancelled: Cell::new(false),
+        }
+    }
+
+    pub fn subscribe(&self) -> Pollable {
+        if self.cancelled.get() || self.steps.borrow().is_empty() {
+            monotonic_clock::subscribe_duration(0)
+        } else {
+            monotonic_clock::subscribe_instant(self.next_at.get())
+        }
+    }
+}
+
+impl GuestChatStream for MockChatStream {
+    fn get_next(&self) -> Option<Vec<StreamEvent>> {
+        if self.cancelled.replace(false) {
+            self.steps.borrow_mut().clear();
+            return Some(vec![cancelled_event()]);
+        }
+
+        let mut steps = self.steps.borrow_mut();
+        if steps.is_empty() {
+            return Some(vec![]);
+        }
+
+        let now = monotonic_clock::now();
+        if now < self.next_at.get() {
+            return None;
+        }
+        self.next_at.set(now + self.chunk_delay_ns);
+
+        match steps.pop_front() {
+            Some(Step::Delta(delta)) => Some(vec![StreamEvent::Delta(delta)]),
+            Some(Step::Fail(error)) => {
+                steps.clear();
+                Some(vec![StreamEvent::Error(error)])
+            }
+            Some(Step::Crash) => panic!("Scripted crash of the mock LLM stream"),
+            Some(Step::Finish(metadata)) => Some(vec![StreamEvent::Finish(metadata)]),
+            None => Some(vec![]),
+        }
+    }
+
+    fn blocking_get_next(&self) -> Vec<StreamEvent> {
+        let pollable = self.subscribe();
+        loop {
+            pollable.block();
+            if let Some(events) = self.get_next() {
+                break events;
+            }
+        }
+    }
+
+    fn cancel(&self) {
+        if !self.steps.borrow().is_empty() {
+            self.cancelled.set(true);
+        }
+    }
+}
+
+fn content_delta(content: ContentPart) -> StreamDelta {
+    StreamDelta {
+        content: Some(vec![content]),
+        tool_calls: None,
+        logprobs: None,
+    }
+}
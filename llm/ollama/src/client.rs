@@ -5,6 +5,8 @@ use golem_llm::{
     error::{error_code_from_status, from_event_source_error},
     event_source::EventSource,
     golem::llm::llm::{Error, ErrorCode},
+    logprobs::Logprob,
+    retry::SendWithRetry,
 };
 use log::trace;
 use reqwest::{
@@ -53,7 +55,7 @@ impl OllamaApi {
             .request(Method::POST, url)
             .headers(headers)
             .json(&modified_params)
-            .send()
+            .send_with_retry()
             .map_err(|err| from_reqwest_error("Request failed", err))?;
 
         handle_response::<CompletionsResponse>(response)
@@ -84,11 +86,40 @@ impl OllamaApi {
             .request(Method::POST, url)
             .headers(headers)
             .body(json_body)
-            .send()
+            .send_with_retry()
             .map_err(|err| from_reqwest_error("Request failed", err))?;
         EventSource::new(response)
             .map_err(|err| from_event_source_error("Failed to create EventSource stream", err))
     }
+
+    pub fn list_models(&self) -> Result<ListModelsResponse, Error> {
+        trace!("Listing models from Ollama API");
+
+        let url = format!("{}/api/tags", self.base_url);
+        let response: Response = self
+            .client
+            .request(Method::GET, url)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        handle_response::<ListModelsResponse>(response)
+    }
+
+    pub fn show_model(&self, model: &str) -> Result<ShowModelResponse, Error> {
+        trace!("Requesting model details from Ollama API: {model}");
+
+        let url = format!("{}/api/show", self.base_url);
+        let response: Response = self
+            .client
+            .request(Method::POST, url)
+            .json(&ShowModelRequest {
+                model: model.to_string(),
+            })
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        handle_response::<ShowModelResponse>(response)
+    }
 }
 
 /// GenerateOptions is Options for generating completions
@@ -161,12 +192,25 @@ pub struct CompletionsRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
 
+    /// Either `"json"` or a JSON schema the response has to conform to
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub format: Option<String>,
+    pub format: Option<serde_json::Value>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<OllamaModelOptions>,
 
+    /// Enables the separate thinking output of reasoning models
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub think: Option<bool>,
+
+    /// Returns the log-probabilities of the generated tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+
+    /// The number of most likely tokens to return at each position, requires `logprobs`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u8>,
+
     /// If false the response will be returned as a single response object, rather than a stream of objects.
     /// For `chat_completion` this will be set to false.
     /// For `chat_completion_stream` this will be set to true.
@@ -222,6 +266,8 @@ pub struct CompletionsResponse {
     pub eval_count: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub eval_duration: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<Vec<Logprob>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -242,6 +288,8 @@ pub struct MessageResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub images: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
@@ -260,6 +308,32 @@ pub struct Function {
     pub arguments: serde_json::Value,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ListModelsResponse {
+    pub models: Vec<LocalModel>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocalModel {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShowModelRequest {
+    pub model: String,
+}
+
+/// Refer to https://github.com/ollama/ollama/blob/main/docs/api.md#show-model-information
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShowModelResponse {
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_info: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OllamaRequestError {
     status_code: i32,
@@ -312,7 +386,7 @@ pub fn handle_response<T: DeserializeOwned + Debug>(response: Response) -> Resul
 pub fn image_to_base64(source: &str) -> Result<String, Box<dyn std::error::Error>> {
     let bytes = if Url::parse(source).is_ok() {
         let client = Client::new();
-        let response = client.get(source).send()?;
+        let response = client.get(source).send_with_retry()?;
 
         response.bytes()?.to_vec()
     } else {
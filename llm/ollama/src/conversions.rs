@@ -2,21 +2,36 @@ use std::collections::HashMap;
 
 use crate::client::{
     image_to_base64, CompletionsRequest, CompletionsResponse, FunctionTool, MessageRequest,
-    MessageRole, OllamaModelOptions, Tool,
+    MessageRole, OllamaModelOptions, ShowModelResponse, Tool,
 };
 use base64::{engine::general_purpose, Engine};
+use golem_llm::builtin_tools::reject_builtin_tools;
+use golem_llm::error::unsupported;
 use golem_llm::golem::llm::llm::{
     ChatEvent, CompleteResponse, Config, ContentPart, Error, ErrorCode, FinishReason,
-    ImageReference, Message, ResponseMetadata, Role, ToolCall as golem_llm_ToolCall, ToolResult,
-    Usage,
+    ImageReference, Message, ModelCapabilities, ModelInfo, ReasoningContent, ResponseFormat,
+    ResponseMetadata, Role, ToolCall as golem_llm_ToolCall, ToolResult, Usage,
 };
+use golem_llm::logprobs::convert_logprobs;
+use golem_llm::sampling::{signed_seed, validate_sampling_parameters, SamplingParameter};
 use log::trace;
 
+const SAMPLING_PARAMETERS: &[SamplingParameter] = &[
+    SamplingParameter::TopP,
+    SamplingParameter::TopK,
+    SamplingParameter::Seed,
+    SamplingParameter::FrequencyPenalty,
+    SamplingParameter::PresencePenalty,
+];
+
 pub fn messages_to_request(
     messages: Vec<Message>,
-    config: Config,
+    mut config: Config,
     tool_results: Option<Vec<(golem_llm_ToolCall, ToolResult)>>,
 ) -> Result<CompletionsRequest, Error> {
+    validate_sampling_parameters(&mut config, SAMPLING_PARAMETERS)?;
+    reject_builtin_tools(&config)?;
+
     let options = config
         .provider_options
         .into_iter()
@@ -59,6 +74,9 @@ pub fn messages_to_request(
                         attached_image.push(base64_data);
                     }
                 },
+                ContentPart::Reasoning(_) => {}
+                ContentPart::Document(_) => return Err(unsupported("document input")),
+                ContentPart::Audio(_) => return Err(unsupported("audio input")),
             }
         }
 
@@ -95,16 +113,31 @@ pub fn messages_to_request(
         });
     }
 
+    if parse_option::<u32>(&options, "n").is_some_and(|n| n > 1) {
+        return Err(unsupported("multiple choices (n)"));
+    }
+
+    let top_logprobs = parse_option::<u8>(&options, "top_logprobs");
+
+    let format = match config.response_format {
+        Some(response_format) => response_format_to_format(response_format)?,
+        None => options
+            .get("format")
+            .map(|format| serde_json::Value::String(format.clone())),
+    };
+
     let ollama_options = OllamaModelOptions {
         min_p: parse_option(&options, "min_p"),
         temperature: config.temperature,
-        top_p: parse_option(&options, "top_p"),
-        top_k: parse_option(&options, "top_k"),
+        top_p: config.top_p,
+        top_k: config
+            .top_k
+            .map(|top_k| i32::try_from(top_k).unwrap_or(i32::MAX)),
         num_predict: parse_option(&options, "num_predict"),
         stop: config.stop_sequences.clone(),
         repeat_penalty: parse_option(&options, "repeat_penalty"),
         num_ctx: parse_option(&options, "num_ctx"),
-        seed: parse_option(&options, "seed"),
+        seed: config.seed.map(signed_seed),
         mirostat: parse_option(&options, "mirostat"),
         mirostat_eta: parse_option(&options, "mirostat_eta"),
         mirostat_tau: parse_option(&options, "mirostat_tau"),
@@ -114,8 +147,8 @@ pub fn messages_to_request(
         num_keep: parse_option(&options, "num_keep"),
         typical_p: parse_option(&options, "typical_p"),
         repeat_last_n: parse_option(&options, "repeat_last_n"),
-        presence_penalty: parse_option(&options, "presence_penalty"),
-        frequency_penalty: parse_option(&options, "frequency_penalty"),
+        presence_penalty: config.presence_penalty,
+        frequency_penalty: config.frequency_penalty,
         numa: parse_option(&options, "numa"),
         num_batch: parse_option(&options, "num_batch"),
         main_gpu: parse_option(&options, "main_gpu"),
@@ -126,8 +159,11 @@ pub fn messages_to_request(
         model: Some(config.model),
         messages: Some(request_message),
         tools: Some(tools),
-        format: options.get("format").cloned(),
+        format,
         options: Some(ollama_options),
+        think: config.reasoning.map(|_| true),
+        logprobs: parse_option(&options, "logprobs").or(top_logprobs.map(|_| true)),
+        top_logprobs,
         keep_alive: options.get("keep_alive").cloned(),
         stream: Some(false),
     })
@@ -166,6 +202,25 @@ fn tool_results_to_messages(
     messages
 }
 
+fn response_format_to_format(
+    response_format: ResponseFormat,
+) -> Result<Option<serde_json::Value>, Error> {
+    match response_format {
+        ResponseFormat::Text => Ok(None),
+        ResponseFormat::JsonObject => Ok(Some(serde_json::Value::String("json".to_string()))),
+        ResponseFormat::JsonSchema(json_schema) => serde_json::from_str(&json_schema.schema)
+            .map(Some)
+            .map_err(|err| Error {
+                code: ErrorCode::InvalidRequest,
+                message: format!(
+                    "Failed to parse response format schema {}: {err}",
+                    json_schema.name
+                ),
+                provider_error_json: None,
+            }),
+    }
+}
+
 fn parse_option<T: std::str::FromStr>(options: &HashMap<String, String>, key: &str) -> Option<T> {
     options.get(key).and_then(|v| v.parse::<T>().ok())
 }
@@ -175,6 +230,14 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
         let mut content = Vec::<ContentPart>::new();
         let mut tool_calls = Vec::<golem_llm_ToolCall>::new();
 
+        if let Some(ref thinking) = message.thinking {
+            content.push(ContentPart::Reasoning(ReasoningContent {
+                text: thinking.clone(),
+                signature: None,
+                redacted_data: None,
+            }));
+        }
+
         if let Some(ref message_content) = message.content {
             content.push(ContentPart::Text(message_content.clone()));
         }
@@ -185,6 +248,7 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
                     id: format!("ollama-{}", response.created_at.clone()),
                     name: tool_call.name.clone().unwrap_or_default(),
                     arguments_json: tool_call.function.as_ref().unwrap().arguments.to_string(),
+                    validation_error: None,
                 });
             }
         }
@@ -201,6 +265,9 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
             input_tokens,
             output_tokens,
             total_tokens: Some(input_tokens.unwrap_or(0) + output_tokens.unwrap_or(0)),
+            cache_read_tokens: None,
+            cache_write_tokens: None,
+            reasoning_tokens: None,
         };
 
         let timestamp = response.created_at.clone();
@@ -211,6 +278,8 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
             provider_id: Some("ollama".to_string()),
             timestamp: Some(timestamp.clone()),
             provider_metadata_json: Some(get_provider_metadata(&response)),
+            rate_limit: None,
+            cost: None,
         };
 
         ChatEvent::Message(CompleteResponse {
@@ -218,6 +287,9 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
             content,
             tool_calls,
             metadata,
+            logprobs: response.logprobs.clone().map(convert_logprobs),
+            alternatives: vec![],
+            builtin_tool_results: vec![],
         })
     } else {
         ChatEvent::Error(Error {
@@ -244,3 +316,35 @@ pub fn get_provider_metadata(response: &CompletionsResponse) -> String {
         response.eval_count.unwrap_or(0)
     )
 }
+
+/// Converts the details of a locally available model to `ModelInfo`, returning `None`
+/// for models which cannot be used for chat completions, such as embedding models.
+pub fn show_model_to_model_info(name: String, details: ShowModelResponse) -> Option<ModelInfo> {
+    let has_capability = |capability: &str| details.capabilities.iter().any(|c| c == capability);
+
+    if !has_capability("completion") {
+        return None;
+    }
+
+    // The context length is reported under an architecture specific key, e.g. `llama.context_length`
+    let context_window = details.model_info.as_ref().and_then(|model_info| {
+        model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|value| value as u32)
+    });
+
+    Some(ModelInfo {
+        context_window,
+        max_output_tokens: None,
+        capabilities: ModelCapabilities {
+            tools: has_capability("tools"),
+            vision: has_capability("vision"),
+            reasoning: has_capability("thinking"),
+            // Structured outputs are enforced by Ollama itself regardless of the model
+            json_schema: true,
+        },
+        id: name,
+    })
+}
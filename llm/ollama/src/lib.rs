@@ -1,15 +1,21 @@
 use std::cell::{Ref, RefCell, RefMut};
 
 use client::{CompletionsRequest, OllamaApi};
-use conversions::{messages_to_request, process_response};
+use conversions::{messages_to_request, process_response, show_model_to_model_info};
 use golem_llm::{
+    batch::UnsupportedChatBatch,
+    chat_job::EmulatedChatJob,
     chat_stream::{LlmChatStream, LlmChatStreamState},
-    durability::{DurableLLM, ExtendedGuest},
+    conversation::LlmConversation,
+    durability::ExtendedGuest,
     event_source::EventSource,
     golem::llm::llm::{
-        ChatEvent, ChatStream, Config, ContentPart, Error, FinishReason, Guest, Message,
-        ResponseMetadata, Role, StreamDelta, StreamEvent, ToolCall, ToolResult, Usage,
+        ChatBatch, ChatEvent, ChatJob, ChatStream, Config, ContentPart, Error, FinishReason, Guest,
+        Message, ModelInfo, ReasoningContent, ResponseMetadata, Role, StreamDelta, StreamEvent,
+        TokenCount, ToolCall, ToolResult, Usage,
     },
+    logprobs::{convert_logprobs, Logprob},
+    tokens::estimate_token_count,
 };
 use golem_rust::wasm_rpc::Pollable;
 use log::trace;
@@ -17,7 +23,7 @@ use log::trace;
 mod client;
 mod conversions;
 
-struct OllamaChatStream {
+pub struct OllamaChatStream {
     stream: RefCell<Option<EventSource>>,
     failure: Option<Error>,
     finished: RefCell<bool>,
@@ -80,6 +86,9 @@ impl LlmChatStreamState for OllamaChatStream {
                 input_tokens: Some(input_tokens),
                 output_tokens: Some(input_tokens),
                 total_tokens: Some(input_tokens + output_tokens),
+                cache_read_tokens: None,
+                cache_write_tokens: None,
+                reasoning_tokens: None,
             };
 
             let total_duration = json
@@ -118,6 +127,8 @@ impl LlmChatStreamState for OllamaChatStream {
                 provider_id: Some("ollama".to_string()),
                 timestamp,
                 provider_metadata_json: Some(provider_metadata),
+                rate_limit: None,
+                cost: None,
             })));
         }
 
@@ -125,6 +136,16 @@ impl LlmChatStreamState for OllamaChatStream {
             let mut content = Vec::new();
             let mut tool_calls = Vec::new();
 
+            if let Some(thinking) = message.get("thinking").and_then(|t| t.as_str()) {
+                if !thinking.is_empty() {
+                    content.push(ContentPart::Reasoning(ReasoningContent {
+                        text: thinking.to_string(),
+                        signature: None,
+                        redacted_data: None,
+                    }));
+                }
+            }
+
             if let Some(text) = message.get("content").and_then(|c| c.as_str()) {
                 if !text.is_empty() {
                     content.push(ContentPart::Text(text.to_string()));
@@ -154,6 +175,7 @@ impl LlmChatStreamState for OllamaChatStream {
                             id,
                             name,
                             arguments_json: args_json.to_string(),
+                            validation_error: None,
                         });
                     }
                 }
@@ -170,13 +192,18 @@ impl LlmChatStreamState for OllamaChatStream {
                 } else {
                     Some(tool_calls)
                 },
+                logprobs: json
+                    .get("logprobs")
+                    .cloned()
+                    .and_then(|logprobs| serde_json::from_value::<Vec<Logprob>>(logprobs).ok())
+                    .map(convert_logprobs),
             })));
         }
         Ok(None)
     }
 }
 
-struct OllamaComponent;
+pub struct OllamaComponent;
 
 impl OllamaComponent {
     fn request(client: &OllamaApi, request: CompletionsRequest) -> ChatEvent {
@@ -200,6 +227,9 @@ impl OllamaComponent {
 
 impl Guest for OllamaComponent {
     type ChatStream = LlmChatStream<OllamaChatStream>;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = UnsupportedChatBatch;
 
     fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
         let client = OllamaApi::new(config.model.clone());
@@ -225,6 +255,30 @@ impl Guest for OllamaComponent {
     fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
         ChatStream::new(Self::unwrapped_stream(messages, config.clone()))
     }
+
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        let client = OllamaApi::new(String::new());
+
+        // The tags endpoint only lists the local models, their capabilities are fetched one by one
+        let mut models = Vec::new();
+        for model in client.list_models()?.models {
+            let details = client.show_model(&model.name)?;
+            models.extend(show_model_to_model_info(model.name, details));
+        }
+        Ok(models)
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        Ok(estimate_token_count(&messages, &config))
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
 }
 
 impl ExtendedGuest for OllamaComponent {
@@ -248,6 +302,7 @@ impl ExtendedGuest for OllamaComponent {
                  Do not include the part of the response that was already seen."
                     .to_string(),
             )],
+            cache_control: None,
         });
 
         extended_messages.push(Message {
@@ -256,6 +311,7 @@ impl ExtendedGuest for OllamaComponent {
             content: vec![ContentPart::Text(
                 "Here is the original question:".to_string(),
             )],
+            cache_control: None,
         });
 
         extended_messages.extend_from_slice(original_messages);
@@ -284,16 +340,29 @@ impl ExtendedGuest for OllamaComponent {
             .into_iter()
             .chain(partial_result_as_content)
             .collect(),
+            cache_control: None,
         });
 
         extended_messages
     }
 
+    /// Ollama continues a trailing assistant message instead of starting a new one, but not
+    /// with thinking enabled, as the thinking would be generated again before the continuation
+    fn supports_prefill(config: &Config) -> bool {
+        config.reasoning.is_none()
+    }
+
+    fn pricing_provider() -> Option<&'static str> {
+        Some("ollama")
+    }
+
     fn subscribe(stream: &Self::ChatStream) -> Pollable {
         stream.subscribe()
     }
 }
 
-type DurableOllamaComponent = DurableLLM<OllamaComponent>;
+#[cfg(not(feature = "library"))]
+type DurableOllamaComponent = golem_llm::durability::DurableLLM<OllamaComponent>;
 
+#[cfg(not(feature = "library"))]
 golem_llm::export_llm!(DurableOllamaComponent with_types_in golem_llm);
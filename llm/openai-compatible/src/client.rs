@@ -0,0 +1,456 @@
+use golem_llm::error::{error_code_from_status, from_event_source_error, from_reqwest_error};
+use golem_llm::event_source::EventSource;
+use golem_llm::golem::llm::llm::{Error, ErrorCode};
+use golem_llm::retry::SendWithRetry;
+use log::trace;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Client, Method, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+/// The Chat Completions API client of an OpenAI-compatible server, such as vLLM,
+/// LM Studio, llama.cpp server, Together or an in-house gateway.
+pub struct CompletionsApi {
+    base_url: String,
+    headers: HeaderMap,
+    query: Vec<(String, String)>,
+    client: Client,
+}
+
+impl CompletionsApi {
+    /// Creates a client for the API at `base_url`, which includes the version prefix,
+    /// e.g. `http://localhost:8000/v1`.
+    ///
+    /// The API key is sent as a bearer token in the `Authorization` header, unless a different
+    /// `auth_header` is given, in which case the key is sent in that header as it is.
+    /// `extra_headers` must be a JSON object of header names and values sent with every request.
+    pub fn new(
+        base_url: String,
+        api_key: Option<String>,
+        auth_header: Option<String>,
+        extra_headers: Option<String>,
+    ) -> Result<Self, Error> {
+        let mut headers = HeaderMap::new();
+
+        if let Some(extra_headers) = extra_headers {
+            let extra_headers: HashMap<String, String> = serde_json::from_str(&extra_headers)
+                .map_err(|err| config_error(format!("Failed to parse extra headers: {err}")))?;
+            for (name, value) in extra_headers {
+                headers.insert(header_name(&name)?, header_value(&value)?);
+            }
+        }
+
+        if let Some(api_key) = api_key {
+            match auth_header {
+                Some(auth_header) => {
+                    headers.insert(header_name(&auth_header)?, header_value(&api_key)?);
+                }
+                None => {
+                    headers.insert(AUTHORIZATION, header_value(&format!("Bearer {api_key}"))?);
+                }
+            }
+        }
+
+        let client = Client::builder()
+            .build()
+            .expect("Failed to initialize HTTP client");
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            headers,
+            query: Vec::new(),
+            client,
+        })
+    }
+
+    /// Adds a query parameter sent with every request, such as the `api-version` of Azure OpenAI
+    pub fn with_query(mut self, name: &str, value: &str) -> Self {
+        self.query.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn send_messages(&self, request: CompletionsRequest) -> Result<CompletionsResponse, Error> {
+        trace!("Sending request to OpenAI-compatible API: {request:?}");
+
+        let response: Response = self
+            .client
+            .request(Method::POST, format!("{}/chat/completions", self.base_url))
+            .headers(self.headers.clone())
+            .query(&self.query)
+            .json(&request)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    pub fn stream_send_messages(&self, request: CompletionsRequest) -> Result<EventSource, Error> {
+        trace!("Sending request to OpenAI-compatible API: {request:?}");
+
+        let response: Response = self
+            .client
+            .request(Method::POST, format!("{}/chat/completions", self.base_url))
+            .headers(self.headers.clone())
+            .query(&self.query)
+            .header(
+                reqwest::header::ACCEPT,
+                HeaderValue::from_static("text/event-stream"),
+            )
+            .json(&request)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        trace!("Initializing SSE stream");
+
+        EventSource::new(response)
+            .map_err(|err| from_event_source_error("Failed to create SSE stream", err))
+    }
+
+    pub fn list_models(&self) -> Result<ListModelsResponse, Error> {
+        trace!("Listing models from OpenAI-compatible API");
+
+        let response: Response = self
+            .client
+            .request(Method::GET, format!("{}/models", self.base_url))
+            .headers(self.headers.clone())
+            .query(&self.query)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+}
+
+fn header_name(name: &str) -> Result<HeaderName, Error> {
+    HeaderName::from_bytes(name.as_bytes())
+        .map_err(|err| config_error(format!("Invalid header name {name}: {err}")))
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, Error> {
+    HeaderValue::from_str(value).map_err(|err| config_error(format!("Invalid header value: {err}")))
+}
+
+fn config_error(message: String) -> Error {
+    Error {
+        code: ErrorCode::InternalError,
+        message,
+        provider_error_json: None,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionsRequest {
+    pub messages: Vec<Message>,
+    /// Not sent when empty, for servers addressing the model in the URL instead
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Replaces `max_tokens` in the newer versions of the OpenAI API, and is the only one
+    /// accepted by reasoning models there
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Tool>,
+    /// Not part of the OpenAI API, but accepted by most of the compatible servers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamOptions {
+    pub include_usage: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseFormat {
+    #[serde(rename = "text")]
+    Text,
+    #[serde(rename = "json_object")]
+    JsonObject,
+    #[serde(rename = "json_schema")]
+    JsonSchema { json_schema: JsonSchema },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchema {
+    pub name: String,
+    pub schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Tool {
+    #[serde(rename = "function")]
+    Function { function: Function },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Function {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "role")]
+pub enum Message {
+    #[serde(rename = "system")]
+    System {
+        content: Content,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    #[serde(rename = "user")]
+    User {
+        content: Content,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    #[serde(rename = "assistant")]
+    Assistant {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content: Option<Content>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tool_calls: Option<Vec<ToolCall>>,
+    },
+    #[serde(rename = "tool")]
+    Tool {
+        content: Content,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tool_call_id: Option<String>,
+    },
+}
+
+/// Message content, sent as a plain string whenever possible as not all servers
+/// accept a list of content parts for text-only messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Content {
+    TextInput(String),
+    List(Vec<ContentPart>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ContentPart {
+    #[serde(rename = "text")]
+    TextInput { text: String },
+    #[serde(rename = "image_url")]
+    ImageInput { image_url: ImageUrl },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub enum Detail {
+    #[serde(rename = "auto")]
+    #[default]
+    Auto,
+    #[serde(rename = "low")]
+    Low,
+    #[serde(rename = "high")]
+    High,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageUrl {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<Detail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    #[serde(rename = "type", default = "function_type")]
+    pub typ: String,
+    pub id: String,
+    pub function: FunctionCall,
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub arguments: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionsResponse {
+    pub choices: Vec<Choice>,
+    #[serde(default)]
+    pub created: u64,
+    pub id: String,
+    pub model: String,
+    pub system_fingerprint: Option<String>,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Choice {
+    pub finish_reason: Option<String>,
+    pub index: u32,
+    pub message: ResponseMessage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseMessage {
+    pub content: Option<String>,
+    /// Reasoning models served by vLLM and similar servers return their thinking separately,
+    /// using either of the two common field names
+    #[serde(alias = "reasoning")]
+    pub reasoning_content: Option<String>,
+    pub refusal: Option<String>,
+    pub tool_calls: Option<Vec<ToolCall>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Usage {
+    pub completion_tokens: u32,
+    pub prompt_tokens: u32,
+    pub total_tokens: u32,
+    pub prompt_tokens_details: Option<PromptTokenDetails>,
+    pub completion_tokens_details: Option<CompletionTokenDetails>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTokenDetails {
+    pub cached_tokens: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionTokenDetails {
+    pub reasoning_tokens: Option<u32>,
+}
+
+/// Azure OpenAI sends the content filter results of the prompt in a first chunk without an id,
+/// a model or a delta
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionChunk {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub created: u64,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub choices: Vec<ChoiceChunk>,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChoiceChunk {
+    #[serde(default)]
+    pub index: u32,
+    #[serde(default)]
+    pub delta: ChoiceDelta,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChoiceDelta {
+    pub content: Option<String>,
+    #[serde(alias = "reasoning")]
+    pub reasoning_content: Option<String>,
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+}
+
+/// A fragment of a streamed tool call. The first fragment of each call carries its
+/// identifier and name, and the arguments are split across the following ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallDelta {
+    #[serde(default)]
+    pub index: u32,
+    pub id: Option<String>,
+    pub function: Option<FunctionCallDelta>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCallDelta {
+    pub name: Option<String>,
+    pub arguments: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamError {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub message: String,
+    pub code: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListModelsResponse {
+    pub data: Vec<Model>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Model {
+    pub id: String,
+    /// The context size, reported by vLLM as `max_model_len` and by other servers as `context_length`
+    #[serde(alias = "context_length")]
+    pub max_model_len: Option<u32>,
+}
+
+pub fn parse_response<T: DeserializeOwned + Debug>(response: Response) -> Result<T, Error> {
+    let status = response.status();
+    if status.is_success() {
+        let body = response
+            .json::<T>()
+            .map_err(|err| from_reqwest_error("Failed to decode response body", err))?;
+
+        trace!("Received response from OpenAI-compatible API: {body:?}");
+
+        Ok(body)
+    } else {
+        let error_body = response
+            .text()
+            .map_err(|err| from_reqwest_error("Failed to receive error response body", err))?;
+
+        trace!("Received {status} response from OpenAI-compatible API: {error_body:?}");
+
+        Err(Error {
+            code: error_code_from_status(status),
+            message: format!("Request failed with {status}"),
+            provider_error_json: Some(error_body),
+        })
+    }
+}
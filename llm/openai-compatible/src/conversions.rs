@@ -0,0 +1,351 @@
+use crate::client::{CompletionsRequest, CompletionsResponse, Detail, ListModelsResponse};
+use base64::{engine::general_purpose, Engine as _};
+use golem_llm::builtin_tools::reject_builtin_tools;
+use golem_llm::error::unsupported;
+use golem_llm::golem::llm::llm::{
+    ChatEvent, CompleteResponse, Config, ContentPart, Error, ErrorCode, FinishReason, ImageDetail,
+    ImageReference, Message, ModelCapabilities, ModelInfo, ReasoningConfig, ReasoningContent,
+    ReasoningEffort, ResponseFormat, ResponseMetadata, Role, ToolCall, ToolDefinition, ToolResult,
+    Usage,
+};
+use golem_llm::sampling::{validate_sampling_parameters, SamplingParameter};
+use std::collections::HashMap;
+
+const SAMPLING_PARAMETERS: &[SamplingParameter] = &[
+    SamplingParameter::TopP,
+    SamplingParameter::TopK,
+    SamplingParameter::Seed,
+    SamplingParameter::FrequencyPenalty,
+    SamplingParameter::PresencePenalty,
+    SamplingParameter::User,
+];
+
+pub fn messages_to_request(
+    messages: Vec<Message>,
+    mut config: Config,
+) -> Result<CompletionsRequest, Error> {
+    validate_sampling_parameters(&mut config, SAMPLING_PARAMETERS)?;
+    reject_builtin_tools(&config)?;
+
+    let options = config
+        .provider_options
+        .into_iter()
+        .map(|kv| (kv.key, kv.value))
+        .collect::<HashMap<_, _>>();
+
+    let mut completion_messages = Vec::new();
+    for message in messages {
+        match message.role {
+            Role::User => completion_messages.push(crate::client::Message::User {
+                name: message.name,
+                content: convert_content_parts(message.content)?,
+            }),
+            Role::Assistant => completion_messages.push(crate::client::Message::Assistant {
+                name: message.name,
+                content: Some(convert_content_parts(message.content)?),
+                tool_calls: None,
+            }),
+            Role::System => completion_messages.push(crate::client::Message::System {
+                name: message.name,
+                content: convert_content_parts(message.content)?,
+            }),
+            Role::Tool => completion_messages.push(crate::client::Message::Tool {
+                content: convert_content_parts(message.content)?,
+                tool_call_id: None,
+            }),
+        }
+    }
+
+    let mut tools = Vec::new();
+    for tool in config.tools {
+        tools.push(tool_definition_to_tool(tool)?)
+    }
+
+    Ok(CompletionsRequest {
+        messages: completion_messages,
+        model: config.model,
+        frequency_penalty: config.frequency_penalty,
+        max_tokens: config.max_tokens,
+        max_completion_tokens: None,
+        n: options.get("n").and_then(|n_s| n_s.parse::<u32>().ok()),
+        presence_penalty: config.presence_penalty,
+        reasoning_effort: match config.reasoning {
+            Some(reasoning) => reasoning_config_to_effort(reasoning)?,
+            None => options.get("reasoning_effort").cloned(),
+        },
+        seed: config.seed,
+        stop: config.stop_sequences,
+        stream: Some(false),
+        stream_options: None,
+        temperature: config.temperature,
+        response_format: config
+            .response_format
+            .map(convert_response_format)
+            .transpose()?,
+        tool_choice: config.tool_choice,
+        tools,
+        top_k: config.top_k,
+        top_p: config.top_p,
+        user: config.user,
+    })
+}
+
+pub fn process_response(response: CompletionsResponse) -> ChatEvent {
+    let choice = response.choices.first();
+    if let Some(choice) = choice {
+        let mut contents = Vec::new();
+        let mut tool_calls = Vec::new();
+
+        if let Some(reasoning) = &choice.message.reasoning_content {
+            contents.push(ContentPart::Reasoning(ReasoningContent {
+                text: reasoning.clone(),
+                signature: None,
+                redacted_data: None,
+            }));
+        }
+
+        if let Some(content) = &choice.message.content {
+            contents.push(ContentPart::Text(content.clone()));
+        }
+
+        let empty = Vec::new();
+        for tool_call in choice.message.tool_calls.as_ref().unwrap_or(&empty) {
+            tool_calls.push(convert_tool_call(tool_call));
+        }
+
+        if contents.is_empty() {
+            ChatEvent::ToolRequest(tool_calls)
+        } else {
+            let metadata = ResponseMetadata {
+                finish_reason: choice.finish_reason.as_deref().map(convert_finish_reason),
+                usage: response.usage.as_ref().map(convert_usage),
+                provider_id: None,
+                timestamp: Some(response.created.to_string()),
+                provider_metadata_json: Some(
+                    serde_json::json!({
+                        "model": response.model,
+                        "system_fingerprint": response.system_fingerprint,
+                    })
+                    .to_string(),
+                ),
+                rate_limit: None,
+                cost: None,
+            };
+
+            ChatEvent::Message(CompleteResponse {
+                id: response.id,
+                content: contents,
+                tool_calls,
+                metadata,
+                logprobs: None,
+                alternatives: vec![],
+                builtin_tool_results: vec![],
+            })
+        }
+    } else {
+        ChatEvent::Error(Error {
+            code: ErrorCode::InternalError,
+            message: "No choices in response".to_string(),
+            provider_error_json: None,
+        })
+    }
+}
+
+pub fn tool_results_to_messages(
+    tool_results: Vec<(ToolCall, ToolResult)>,
+) -> Vec<crate::client::Message> {
+    let mut messages = Vec::new();
+    for (tool_call, tool_result) in tool_results {
+        messages.push(crate::client::Message::Assistant {
+            content: None,
+            name: None,
+            tool_calls: Some(vec![crate::client::ToolCall {
+                typ: "function".to_string(),
+                id: tool_call.id.clone(),
+                function: crate::client::FunctionCall {
+                    arguments: tool_call.arguments_json,
+                    name: tool_call.name,
+                },
+            }]),
+        });
+        let content = match tool_result {
+            ToolResult::Success(success) => success.result_json,
+            ToolResult::Error(failure) => failure.error_message,
+        };
+        messages.push(crate::client::Message::Tool {
+            content: crate::client::Content::TextInput(content),
+            tool_call_id: Some(tool_call.id),
+        });
+    }
+    messages
+}
+
+pub fn convert_tool_call(tool_call: &crate::client::ToolCall) -> ToolCall {
+    ToolCall {
+        id: tool_call.id.clone(),
+        name: tool_call.function.name.clone(),
+        arguments_json: tool_call.function.arguments.clone(),
+        validation_error: None,
+    }
+}
+
+fn convert_content_parts(contents: Vec<ContentPart>) -> Result<crate::client::Content, Error> {
+    let mut result = Vec::new();
+    for content in contents {
+        match content {
+            ContentPart::Text(text) => result.push(crate::client::ContentPart::TextInput { text }),
+            ContentPart::Image(image_reference) => match image_reference {
+                ImageReference::Url(image_url) => {
+                    result.push(crate::client::ContentPart::ImageInput {
+                        image_url: crate::client::ImageUrl {
+                            url: image_url.url,
+                            detail: image_url.detail.map(|d| d.into()),
+                        },
+                    })
+                }
+                ImageReference::Inline(image_source) => {
+                    let base64_data = general_purpose::STANDARD.encode(&image_source.data);
+                    let media_type = &image_source.mime_type;
+                    result.push(crate::client::ContentPart::ImageInput {
+                        image_url: crate::client::ImageUrl {
+                            url: format!("data:{media_type};base64,{base64_data}"),
+                            detail: image_source.detail.map(|d| d.into()),
+                        },
+                    });
+                }
+            },
+            ContentPart::Reasoning(_) => {}
+            ContentPart::Document(_) => return Err(unsupported("document input")),
+            ContentPart::Audio(_) => return Err(unsupported("audio input")),
+        }
+    }
+
+    let text_only = result
+        .iter()
+        .all(|part| matches!(part, crate::client::ContentPart::TextInput { .. }));
+    if text_only {
+        let text = result
+            .into_iter()
+            .filter_map(|part| match part {
+                crate::client::ContentPart::TextInput { text } => Some(text),
+                crate::client::ContentPart::ImageInput { .. } => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(crate::client::Content::TextInput(text))
+    } else {
+        Ok(crate::client::Content::List(result))
+    }
+}
+
+impl From<ImageDetail> for Detail {
+    fn from(value: ImageDetail) -> Self {
+        match value {
+            ImageDetail::Auto => Self::Auto,
+            ImageDetail::Low => Self::Low,
+            ImageDetail::High => Self::High,
+        }
+    }
+}
+
+pub fn convert_finish_reason(value: &str) -> FinishReason {
+    match value {
+        "stop" => FinishReason::Stop,
+        "length" => FinishReason::Length,
+        "tool_calls" | "function_call" => FinishReason::ToolCalls,
+        "content_filter" => FinishReason::ContentFilter,
+        _ => FinishReason::Other,
+    }
+}
+
+pub fn convert_usage(value: &crate::client::Usage) -> Usage {
+    Usage {
+        input_tokens: Some(value.prompt_tokens),
+        output_tokens: Some(value.completion_tokens),
+        total_tokens: Some(value.total_tokens),
+        cache_read_tokens: value
+            .prompt_tokens_details
+            .as_ref()
+            .and_then(|details| details.cached_tokens),
+        cache_write_tokens: None,
+        reasoning_tokens: value
+            .completion_tokens_details
+            .as_ref()
+            .and_then(|details| details.reasoning_tokens),
+    }
+}
+
+fn tool_definition_to_tool(tool: ToolDefinition) -> Result<crate::client::Tool, Error> {
+    match serde_json::from_str(&tool.parameters_schema) {
+        Ok(value) => Ok(crate::client::Tool::Function {
+            function: crate::client::Function {
+                name: tool.name,
+                description: tool.description,
+                parameters: Some(value),
+            },
+        }),
+        Err(error) => Err(Error {
+            code: ErrorCode::InternalError,
+            message: format!("Failed to parse tool parameters for {}: {error}", tool.name),
+            provider_error_json: None,
+        }),
+    }
+}
+
+fn convert_response_format(
+    response_format: ResponseFormat,
+) -> Result<crate::client::ResponseFormat, Error> {
+    match response_format {
+        ResponseFormat::Text => Ok(crate::client::ResponseFormat::Text),
+        ResponseFormat::JsonObject => Ok(crate::client::ResponseFormat::JsonObject),
+        ResponseFormat::JsonSchema(json_schema) => {
+            match serde_json::from_str(&json_schema.schema) {
+                Ok(schema) => Ok(crate::client::ResponseFormat::JsonSchema {
+                    json_schema: crate::client::JsonSchema {
+                        name: json_schema.name,
+                        schema,
+                        strict: json_schema.strict,
+                    },
+                }),
+                Err(error) => Err(Error {
+                    code: ErrorCode::InvalidRequest,
+                    message: format!(
+                        "Failed to parse response format schema {}: {error}",
+                        json_schema.name
+                    ),
+                    provider_error_json: None,
+                }),
+            }
+        }
+    }
+}
+
+fn reasoning_config_to_effort(reasoning: ReasoningConfig) -> Result<Option<String>, Error> {
+    match (reasoning.effort, reasoning.budget_tokens) {
+        (Some(ReasoningEffort::Low), _) => Ok(Some("low".to_string())),
+        (Some(ReasoningEffort::Medium), _) => Ok(Some("medium".to_string())),
+        (Some(ReasoningEffort::High), _) => Ok(Some("high".to_string())),
+        (None, Some(_)) => Err(unsupported("reasoning budget tokens")),
+        (None, None) => Ok(None),
+    }
+}
+
+/// The capabilities of the models served by an arbitrary server cannot be known, so they are
+/// all reported as unsupported, and only the context size is filled in when the server lists it.
+pub fn models_to_model_infos(response: ListModelsResponse) -> Vec<ModelInfo> {
+    response
+        .data
+        .into_iter()
+        .map(|model| ModelInfo {
+            id: model.id,
+            context_window: model.max_model_len,
+            max_output_tokens: None,
+            capabilities: ModelCapabilities {
+                tools: false,
+                vision: false,
+                reasoning: false,
+                json_schema: false,
+            },
+        })
+        .collect()
+}
@@ -0,0 +1,474 @@
+pub mod client;
+pub mod conversions;
+
+use crate::client::{
+    ChatCompletionChunk, CompletionsApi, CompletionsRequest, StreamError, StreamOptions,
+};
+use crate::conversions::{
+    convert_finish_reason, convert_usage, messages_to_request, models_to_model_infos,
+    process_response, tool_results_to_messages,
+};
+use golem_llm::batch::UnsupportedChatBatch;
+use golem_llm::chat_job::EmulatedChatJob;
+use golem_llm::chat_stream::{LlmChatStream, LlmChatStreamState};
+use golem_llm::config::{get_config_key, get_config_key_or_none};
+use golem_llm::conversation::LlmConversation;
+use golem_llm::durability::ExtendedGuest;
+use golem_llm::event_source::EventSource;
+use golem_llm::golem::llm::llm::{
+    ChatBatch, ChatEvent, ChatJob, ChatStream, Config, ContentPart, Error, ErrorCode, FinishReason,
+    Guest, Message, ModelInfo, ReasoningContent, ResponseMetadata, StreamDelta, StreamEvent,
+    TokenCount, ToolCall, ToolResult, Usage,
+};
+use golem_llm::tokens::estimate_token_count;
+use golem_rust::wasm_rpc::Pollable;
+use log::trace;
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+struct JsonFragment {
+    id: String,
+    name: String,
+    json: String,
+}
+
+pub struct OpenAICompatibleChatStream {
+    stream: RefCell<Option<EventSource>>,
+    failure: Option<Error>,
+    finished: RefCell<bool>,
+    finish_reason: RefCell<Option<FinishReason>>,
+    /// The usage received in a chunk with content or tool calls, reported by the finishing event
+    usage: RefCell<Option<Usage>>,
+    json_fragments: RefCell<BTreeMap<u32, JsonFragment>>,
+}
+
+impl OpenAICompatibleChatStream {
+    pub fn new(stream: EventSource) -> LlmChatStream<Self> {
+        LlmChatStream::new(OpenAICompatibleChatStream {
+            stream: RefCell::new(Some(stream)),
+            failure: None,
+            finished: RefCell::new(false),
+            finish_reason: RefCell::new(None),
+            usage: RefCell::new(None),
+            json_fragments: RefCell::new(BTreeMap::new()),
+        })
+    }
+
+    pub fn failed(error: Error) -> LlmChatStream<Self> {
+        LlmChatStream::new(OpenAICompatibleChatStream {
+            stream: RefCell::new(None),
+            failure: Some(error),
+            finished: RefCell::new(false),
+            finish_reason: RefCell::new(None),
+            usage: RefCell::new(None),
+            json_fragments: RefCell::new(BTreeMap::new()),
+        })
+    }
+
+    /// The finishing event, with the given usage or the one received earlier
+    fn finish(&self, usage: Option<Usage>, timestamp: Option<String>) -> StreamEvent {
+        let stored_usage = self.usage.take();
+        StreamEvent::Finish(ResponseMetadata {
+            finish_reason: *self.finish_reason.borrow(),
+            usage: usage.or(stored_usage),
+            provider_id: None,
+            timestamp,
+            provider_metadata_json: None,
+            rate_limit: None,
+            cost: None,
+        })
+    }
+}
+
+impl LlmChatStreamState for OpenAICompatibleChatStream {
+    fn failure(&self) -> &Option<Error> {
+        &self.failure
+    }
+
+    fn is_finished(&self) -> bool {
+        *self.finished.borrow()
+    }
+
+    fn set_finished(&self) {
+        *self.finished.borrow_mut() = true;
+    }
+
+    fn stream(&self) -> Ref<'_, Option<EventSource>> {
+        self.stream.borrow()
+    }
+
+    fn stream_mut(&self) -> RefMut<'_, Option<EventSource>> {
+        self.stream.borrow_mut()
+    }
+
+    fn decode_message(&self, raw: &str) -> Result<Option<StreamEvent>, String> {
+        trace!("Received raw stream event: {raw}");
+        let json: serde_json::Value = serde_json::from_str(raw)
+            .map_err(|err| format!("Failed to deserialize stream event: {err}"))?;
+
+        if json.get("error").is_some() {
+            let error: StreamError = serde_json::from_value(json)
+                .map_err(|err| format!("Failed to parse stream error: {err}"))?;
+            return Ok(Some(StreamEvent::Error(Error {
+                code: ErrorCode::InternalError,
+                message: error.error.message,
+                provider_error_json: Some(raw.to_string()),
+            })));
+        }
+
+        let message: ChatCompletionChunk = serde_json::from_value(json)
+            .map_err(|err| format!("Failed to parse stream event: {err}"))?;
+        let usage = message.usage.as_ref().map(convert_usage);
+
+        if let Some(choice) = message.choices.into_iter().next() {
+            let mut content = Vec::new();
+            if let Some(reasoning) = choice.delta.reasoning_content {
+                content.push(ContentPart::Reasoning(ReasoningContent {
+                    text: reasoning,
+                    signature: None,
+                    redacted_data: None,
+                }));
+            }
+            if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+                content.push(ContentPart::Text(text));
+            }
+
+            // Tool calls are streamed in fragments identified by their index, and are only
+            // emitted once complete, when the choice finishes
+            let mut json_fragments = self.json_fragments.borrow_mut();
+            for tool_call in choice.delta.tool_calls.unwrap_or_default() {
+                let fragment = json_fragments.entry(tool_call.index).or_default();
+                if let Some(id) = tool_call.id {
+                    fragment.id = id;
+                }
+                if let Some(function) = tool_call.function {
+                    if let Some(name) = function.name {
+                        fragment.name = name;
+                    }
+                    if let Some(arguments) = function.arguments {
+                        fragment.json.push_str(&arguments);
+                    }
+                }
+            }
+
+            let mut tool_calls = Vec::new();
+            if let Some(finish_reason) = choice.finish_reason {
+                *self.finish_reason.borrow_mut() = Some(convert_finish_reason(&finish_reason));
+                tool_calls.extend(std::mem::take(&mut *json_fragments).into_values().map(
+                    |fragment| ToolCall {
+                        id: fragment.id,
+                        name: fragment.name,
+                        arguments_json: fragment.json,
+                        validation_error: None,
+                    },
+                ));
+            }
+
+            if content.is_empty() && tool_calls.is_empty() {
+                // Some servers report the usage in the same chunk as the finish reason
+                Ok(usage.map(|usage| self.finish(Some(usage), Some(message.created.to_string()))))
+            } else {
+                // Or even in the same chunk as the last content or tool calls, in which case the
+                // usage is reported by the next finishing event
+                if usage.is_some() {
+                    *self.usage.borrow_mut() = usage;
+                }
+                Ok(Some(StreamEvent::Delta(StreamDelta {
+                    content: if content.is_empty() {
+                        None
+                    } else {
+                        Some(content)
+                    },
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                    logprobs: None,
+                })))
+            }
+        } else {
+            Ok(usage.map(|usage| self.finish(Some(usage), Some(message.created.to_string()))))
+        }
+    }
+
+    /// Servers ignoring `stream_options.include_usage` never send the chunk with the usage, and
+    /// servers sending it with the last content have no separate chunk for it, the response
+    /// finishes with the end of the stream instead
+    fn end_of_stream(&self) -> Option<StreamEvent> {
+        Some(self.finish(None, None))
+    }
+}
+
+pub struct OpenAICompatibleComponent;
+
+impl OpenAICompatibleComponent {
+    const BASE_URL_ENV_VAR_NAME: &'static str = "OPENAI_COMPATIBLE_BASE_URL";
+    const API_KEY_ENV_VAR_NAME: &'static str = "OPENAI_COMPATIBLE_API_KEY";
+    const AUTH_HEADER_ENV_VAR_NAME: &'static str = "OPENAI_COMPATIBLE_AUTH_HEADER";
+    const EXTRA_HEADERS_ENV_VAR_NAME: &'static str = "OPENAI_COMPATIBLE_EXTRA_HEADERS";
+
+    fn client() -> Result<CompletionsApi, Error> {
+        CompletionsApi::new(
+            get_config_key(Self::BASE_URL_ENV_VAR_NAME)?,
+            get_config_key_or_none(Self::API_KEY_ENV_VAR_NAME),
+            get_config_key_or_none(Self::AUTH_HEADER_ENV_VAR_NAME),
+            get_config_key_or_none(Self::EXTRA_HEADERS_ENV_VAR_NAME),
+        )
+    }
+
+    /// Sends a request to the Chat Completions API, also used by the providers built on it
+    pub fn request(client: CompletionsApi, request: CompletionsRequest) -> ChatEvent {
+        match client.send_messages(request) {
+            Ok(response) => process_response(response),
+            Err(err) => ChatEvent::Error(err),
+        }
+    }
+
+    /// Starts streaming the response of a request, also used by the providers built on it
+    pub fn streaming_request(
+        client: CompletionsApi,
+        mut request: CompletionsRequest,
+    ) -> LlmChatStream<OpenAICompatibleChatStream> {
+        request.stream = Some(true);
+        request.stream_options = Some(StreamOptions {
+            include_usage: true,
+        });
+        match client.stream_send_messages(request) {
+            Ok(stream) => OpenAICompatibleChatStream::new(stream),
+            Err(err) => OpenAICompatibleChatStream::failed(err),
+        }
+    }
+}
+
+impl Guest for OpenAICompatibleComponent {
+    type ChatStream = LlmChatStream<OpenAICompatibleChatStream>;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = UnsupportedChatBatch;
+
+    fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
+        let client = match Self::client() {
+            Ok(client) => client,
+            Err(err) => return ChatEvent::Error(err),
+        };
+
+        match messages_to_request(messages, config) {
+            Ok(request) => Self::request(client, request),
+            Err(err) => ChatEvent::Error(err),
+        }
+    }
+
+    fn continue_(
+        messages: Vec<Message>,
+        tool_results: Vec<(ToolCall, ToolResult)>,
+        config: Config,
+    ) -> ChatEvent {
+        let client = match Self::client() {
+            Ok(client) => client,
+            Err(err) => return ChatEvent::Error(err),
+        };
+
+        match messages_to_request(messages, config) {
+            Ok(mut request) => {
+                request
+                    .messages
+                    .extend(tool_results_to_messages(tool_results));
+                Self::request(client, request)
+            }
+            Err(err) => ChatEvent::Error(err),
+        }
+    }
+
+    fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
+        ChatStream::new(Self::unwrapped_stream(messages, config))
+    }
+
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        Self::client()?.list_models().map(models_to_model_infos)
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        Ok(estimate_token_count(&messages, &config))
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
+}
+
+impl ExtendedGuest for OpenAICompatibleComponent {
+    fn unwrapped_stream(
+        messages: Vec<Message>,
+        config: Config,
+    ) -> LlmChatStream<OpenAICompatibleChatStream> {
+        let client = match Self::client() {
+            Ok(client) => client,
+            Err(err) => return OpenAICompatibleChatStream::failed(err),
+        };
+
+        match messages_to_request(messages, config) {
+            Ok(request) => Self::streaming_request(client, request),
+            Err(err) => OpenAICompatibleChatStream::failed(err),
+        }
+    }
+
+    fn pricing_provider() -> Option<&'static str> {
+        Some("openai-compatible")
+    }
+
+    fn subscribe(stream: &Self::ChatStream) -> Pollable {
+        stream.subscribe()
+    }
+}
+
+#[cfg(not(feature = "library"))]
+type DurableOpenAICompatibleComponent =
+    golem_llm::durability::DurableLLM<OpenAICompatibleComponent>;
+
+#[cfg(not(feature = "library"))]
+golem_llm::export_llm!(DurableOpenAICompatibleComponent with_types_in golem_llm);
+
+#[cfg(test)]
+mod tests {
+    use crate::OpenAICompatibleChatStream;
+    use golem_llm::chat_stream::LlmChatStreamState;
+    use golem_llm::golem::llm::llm::{
+        ContentPart, FinishReason, ResponseMetadata, StreamDelta, StreamEvent, ToolCall, Usage,
+    };
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    fn chat_stream() -> OpenAICompatibleChatStream {
+        OpenAICompatibleChatStream {
+            stream: RefCell::new(None),
+            failure: None,
+            finished: RefCell::new(false),
+            finish_reason: RefCell::new(None),
+            usage: RefCell::new(None),
+            json_fragments: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    fn decode(stream: &OpenAICompatibleChatStream, choice: &str) -> Option<StreamEvent> {
+        let chunk = format!(
+            r#"{{"id": "chunk", "created": 1, "model": "test-model", "choices": [{choice}]}}"#
+        );
+        stream.decode_message(&chunk).unwrap()
+    }
+
+    fn decode_with_usage(stream: &OpenAICompatibleChatStream, choice: &str) -> Option<StreamEvent> {
+        let chunk = format!(
+            r#"{{"id": "chunk", "created": 1, "model": "test-model", "choices": [{choice}],
+                "usage": {{"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}}}}"#
+        );
+        stream.decode_message(&chunk).unwrap()
+    }
+
+    fn finish_usage(event: Option<StreamEvent>) -> Option<Usage> {
+        match event {
+            Some(StreamEvent::Finish(metadata)) => metadata.usage,
+            event => panic!("Unexpected event: {event:?}"),
+        }
+    }
+
+    #[test]
+    fn assembles_tool_call_fragments() {
+        let stream = chat_stream();
+        let fragments = [
+            r#"{"delta": {"tool_calls": [{"index": 0, "id": "call-1", "function": {"name": "weather", "arguments": ""}}]}}"#,
+            r#"{"delta": {"tool_calls": [{"index": 1, "id": "call-2", "function": {"name": "time", "arguments": "{}"}}]}}"#,
+            r#"{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "{\"city\": "}}]}}"#,
+            r#"{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "\"Paris\"}"}}]}}"#,
+        ];
+        for fragment in fragments {
+            assert_eq!(decode(&stream, fragment), None);
+        }
+
+        let event = decode(&stream, r#"{"delta": {}, "finish_reason": "tool_calls"}"#);
+        assert_eq!(
+            event,
+            Some(StreamEvent::Delta(StreamDelta {
+                content: None,
+                tool_calls: Some(vec![
+                    ToolCall {
+                        id: "call-1".to_string(),
+                        name: "weather".to_string(),
+                        arguments_json: r#"{"city": "Paris"}"#.to_string(),
+                        validation_error: None,
+                    },
+                    ToolCall {
+                        id: "call-2".to_string(),
+                        name: "time".to_string(),
+                        arguments_json: "{}".to_string(),
+                        validation_error: None,
+                    },
+                ]),
+                logprobs: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn finishes_at_the_end_of_the_stream_without_usage() {
+        let stream = chat_stream();
+        decode(&stream, r#"{"delta": {"content": "Hi"}}"#);
+        decode(&stream, r#"{"delta": {}, "finish_reason": "stop"}"#);
+        assert_eq!(
+            stream.end_of_stream(),
+            Some(StreamEvent::Finish(ResponseMetadata {
+                finish_reason: Some(FinishReason::Stop),
+                usage: None,
+                provider_id: None,
+                timestamp: None,
+                provider_metadata_json: None,
+                rate_limit: None,
+                cost: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_the_usage_received_with_content_at_the_end_of_the_stream() {
+        let stream = chat_stream();
+        assert_eq!(
+            decode_with_usage(
+                &stream,
+                r#"{"delta": {"content": "Hi"}, "finish_reason": "stop"}"#
+            ),
+            Some(StreamEvent::Delta(StreamDelta {
+                content: Some(vec![ContentPart::Text("Hi".to_string())]),
+                tool_calls: None,
+                logprobs: None,
+            }))
+        );
+
+        let usage = finish_usage(stream.end_of_stream()).unwrap();
+        assert_eq!(usage.input_tokens, Some(10));
+        assert_eq!(usage.output_tokens, Some(5));
+    }
+
+    #[test]
+    fn reports_the_usage_received_with_tool_calls_by_the_next_finish() {
+        let stream = chat_stream();
+        decode(
+            &stream,
+            r#"{"delta": {"tool_calls": [{"index": 0, "id": "call-1", "function": {"name": "weather", "arguments": "{}"}}]}}"#,
+        );
+        let event = decode_with_usage(&stream, r#"{"delta": {}, "finish_reason": "tool_calls"}"#);
+        assert!(
+            matches!(&event, Some(StreamEvent::Delta(delta)) if delta.tool_calls.is_some()),
+            "{event:?}"
+        );
+
+        let chunk = r#"{"id": "chunk", "created": 1, "model": "test-model", "choices": []}"#;
+        assert_eq!(stream.decode_message(chunk).unwrap(), None);
+        let usage = finish_usage(stream.end_of_stream()).unwrap();
+        assert_eq!(usage.total_tokens, Some(15));
+    }
+}
@@ -1,14 +1,19 @@
 use golem_llm::error::{error_code_from_status, from_event_source_error, from_reqwest_error};
 use golem_llm::event_source::EventSource;
-use golem_llm::golem::llm::llm::Error;
+use golem_llm::golem::llm::llm::{Error, ErrorCode};
+use golem_llm::logprobs::Logprob;
+use golem_llm::retry::SendWithRetry;
 use log::trace;
 use reqwest::header::HeaderValue;
-use reqwest::{Client, Method, Response};
+use reqwest::{Client, Method, RequestBuilder, Response};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
 const BASE_URL: &str = "https://api.openai.com";
+/// The endpoint the requests of the batches are sent to
+pub const RESPONSES_ENDPOINT: &str = "/v1/responses";
+const MULTIPART_BOUNDARY: &str = "golem-llm-batch-7d3f0b1c5e9a";
 
 /// The OpenAI API client for creating model responses.
 ///
@@ -40,7 +45,7 @@ impl ResponsesApi {
             .request(Method::POST, format!("{BASE_URL}/v1/responses"))
             .bearer_auth(&self.openai_api_key)
             .json(&request)
-            .send()
+            .send_with_retry()
             .map_err(|err| from_reqwest_error("Request failed", err))?;
 
         parse_response(response)
@@ -61,14 +66,235 @@ impl ResponsesApi {
                 HeaderValue::from_static("text/event-stream"),
             )
             .json(&request)
-            .send()
+            .send_with_retry()
             .map_err(|err| from_reqwest_error("Request failed", err))?;
 
         trace!("Initializing SSE stream");
 
+        let stream = EventSource::new(response)
+            .map_err(|err| from_event_source_error("Failed to create SSE stream", err))?;
+        if request.background == Some(true) {
+            Ok(stream.with_reconnect(self.resume_request()))
+        } else {
+            Ok(stream)
+        }
+    }
+
+    /// Streams the events of a background response after the given position, see
+    /// [`stream_position`]
+    pub fn resume_model_response(&self, position: &str) -> Result<EventSource, Error> {
+        trace!("Resuming OpenAI response stream after {position}");
+
+        let request = self.resume_request()(position).ok_or_else(|| Error {
+            code: ErrorCode::InvalidRequest,
+            message: format!("Invalid stream position: {position}"),
+            provider_error_json: None,
+        })?;
+        let response: Response = request
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
         EventSource::new(response)
+            .map(|stream| stream.with_reconnect(self.resume_request()))
             .map_err(|err| from_event_source_error("Failed to create SSE stream", err))
     }
+
+    /// Builds the request streaming a background response after a position of its stream
+    ///
+    /// Based on https://platform.openai.com/docs/guides/background#resuming-streams
+    fn resume_request(&self) -> impl Fn(&str) -> Option<RequestBuilder> + 'static {
+        let client = self.client.clone();
+        let openai_api_key = self.openai_api_key.clone();
+        move |position| {
+            let (response_id, sequence_number) = parse_stream_position(position)?;
+            Some(
+                client
+                    .request(
+                        Method::GET,
+                        format!(
+                            "{BASE_URL}/v1/responses/{response_id}?stream=true&starting_after={sequence_number}"
+                        ),
+                    )
+                    .bearer_auth(&openai_api_key)
+                    .header(
+                        reqwest::header::ACCEPT,
+                        HeaderValue::from_static("text/event-stream"),
+                    ),
+            )
+        }
+    }
+
+    pub fn retrieve_model_response(
+        &self,
+        response_id: &str,
+    ) -> Result<CreateModelResponseResponse, Error> {
+        trace!("Retrieving response {response_id} from OpenAI API");
+
+        let response: Response = self
+            .client
+            .request(
+                Method::GET,
+                format!("{BASE_URL}/v1/responses/{response_id}"),
+            )
+            .bearer_auth(&self.openai_api_key)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    /// Cancels a background response, which has no effect on a response that has finished
+    pub fn cancel_model_response(
+        &self,
+        response_id: &str,
+    ) -> Result<CreateModelResponseResponse, Error> {
+        trace!("Cancelling response {response_id} in OpenAI API");
+
+        let response: Response = self
+            .client
+            .request(
+                Method::POST,
+                format!("{BASE_URL}/v1/responses/{response_id}/cancel"),
+            )
+            .bearer_auth(&self.openai_api_key)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    /// Uploads the JSON Lines input file of a batch
+    pub fn upload_batch_file(&self, jsonl: String) -> Result<FileObject, Error> {
+        trace!("Uploading batch input file to OpenAI API");
+
+        let mut body = String::new();
+        body.push_str(&format!(
+            "--{MULTIPART_BOUNDARY}\r\nContent-Disposition: form-data; name=\"purpose\"\r\n\r\nbatch\r\n"
+        ));
+        body.push_str(&format!(
+            "--{MULTIPART_BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"batch.jsonl\"\r\nContent-Type: application/jsonl\r\n\r\n"
+        ));
+        body.push_str(&jsonl);
+        body.push_str(&format!("\r\n--{MULTIPART_BOUNDARY}--\r\n"));
+
+        let response: Response = self
+            .client
+            .request(Method::POST, format!("{BASE_URL}/v1/files"))
+            .bearer_auth(&self.openai_api_key)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={MULTIPART_BOUNDARY}"),
+            )
+            .body(body)
+            .send_unprocessed_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    /// Downloads the content of a file, such as the output file of a batch
+    pub fn file_content(&self, file_id: &str) -> Result<String, Error> {
+        trace!("Downloading file {file_id} from OpenAI API");
+
+        let response: Response = self
+            .client
+            .request(
+                Method::GET,
+                format!("{BASE_URL}/v1/files/{file_id}/content"),
+            )
+            .bearer_auth(&self.openai_api_key)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .map_err(|err| from_reqwest_error("Failed to receive response body", err))?;
+        if status.is_success() {
+            Ok(body)
+        } else {
+            trace!("Received {status} response from OpenAI API: {body:?}");
+
+            Err(Error {
+                code: error_code_from_status(status),
+                message: format!("Request failed with {status}"),
+                provider_error_json: Some(body),
+            })
+        }
+    }
+
+    /// Creates a batch of model responses from an uploaded input file
+    pub fn create_batch(&self, input_file_id: String) -> Result<BatchObject, Error> {
+        trace!("Creating batch from file {input_file_id} in OpenAI API");
+
+        let response: Response = self
+            .client
+            .request(Method::POST, format!("{BASE_URL}/v1/batches"))
+            .bearer_auth(&self.openai_api_key)
+            .json(&CreateBatchRequest {
+                input_file_id,
+                endpoint: RESPONSES_ENDPOINT.to_string(),
+                completion_window: "24h".to_string(),
+            })
+            .send_unprocessed_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    pub fn retrieve_batch(&self, batch_id: &str) -> Result<BatchObject, Error> {
+        trace!("Retrieving batch {batch_id} from OpenAI API");
+
+        let response: Response = self
+            .client
+            .request(Method::GET, format!("{BASE_URL}/v1/batches/{batch_id}"))
+            .bearer_auth(&self.openai_api_key)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    /// Cancels a batch, which has no effect on a batch that has finished
+    pub fn cancel_batch(&self, batch_id: &str) -> Result<BatchObject, Error> {
+        trace!("Cancelling batch {batch_id} in OpenAI API");
+
+        let response: Response = self
+            .client
+            .request(
+                Method::POST,
+                format!("{BASE_URL}/v1/batches/{batch_id}/cancel"),
+            )
+            .bearer_auth(&self.openai_api_key)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+
+    pub fn list_models(&self) -> Result<ListModelsResponse, Error> {
+        trace!("Listing models from OpenAI API");
+
+        let response: Response = self
+            .client
+            .request(Method::GET, format!("{BASE_URL}/v1/models"))
+            .bearer_auth(&self.openai_api_key)
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
+}
+
+/// Identifies the position of an event in the stream of a background response, used as the
+/// id of the last event for resuming the stream
+pub fn stream_position(response_id: &str, sequence_number: u64) -> String {
+    format!("{response_id}:{sequence_number}")
+}
+
+pub fn parse_stream_position(position: &str) -> Option<(&str, u64)> {
+    let (response_id, sequence_number) = position.rsplit_once(':')?;
+    Some((response_id, sequence_number.parse().ok()?))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,11 +309,66 @@ pub struct CreateModelResponseRequest {
     pub tools: Vec<Tool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tool_calls: Option<u32>,
     pub stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_response_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<TextConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<Reasoning>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reasoning {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effort: Option<Effort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Effort {
+    #[serde(rename = "low")]
+    Low,
+    #[serde(rename = "medium")]
+    Medium,
+    #[serde(rename = "high")]
+    High,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextConfig {
+    pub format: TextFormat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum TextFormat {
+    #[serde(rename = "text")]
+    Text,
+    #[serde(rename = "json_object")]
+    JsonObject,
+    #[serde(rename = "json_schema")]
+    JsonSchema {
+        name: String,
+        schema: serde_json::Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -120,17 +401,71 @@ pub enum OutputItem {
         id: String,
         status: Status,
     },
+    #[serde(rename = "reasoning")]
+    Reasoning {
+        id: String,
+        summary: Vec<ReasoningSummary>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        encrypted_content: Option<String>,
+    },
+    #[serde(rename = "web_search_call")]
+    WebSearchCall { id: String },
+    #[serde(rename = "file_search_call")]
+    FileSearchCall { id: String },
+    #[serde(rename = "code_interpreter_call")]
+    CodeInterpreterCall {
+        id: String,
+        code: Option<String>,
+        #[serde(default)]
+        outputs: Option<Vec<CodeInterpreterOutput>>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CodeInterpreterOutput {
+    #[serde(rename = "logs")]
+    Logs { logs: String },
+    #[serde(rename = "image")]
+    Image { url: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ReasoningSummary {
+    #[serde(rename = "summary_text")]
+    Text { text: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum OutputMessageContent {
     #[serde(rename = "output_text")]
-    Text { text: String },
+    Text {
+        text: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        logprobs: Vec<Logprob>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        annotations: Vec<Annotation>,
+    },
     #[serde(rename = "refusal")]
     Refusal { refusal: String },
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Annotation {
+    #[serde(rename = "url_citation")]
+    UrlCitation { url: String, title: Option<String> },
+    #[serde(rename = "file_citation")]
+    FileCitation {
+        file_id: String,
+        filename: Option<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorObject {
     pub code: String,
@@ -147,6 +482,10 @@ pub enum Status {
     InProgress,
     #[serde(rename = "incomplete")]
     Incomplete,
+    #[serde(rename = "queued")]
+    Queued,
+    #[serde(rename = "cancelled")]
+    Cancelled,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -188,6 +527,16 @@ pub enum InputItem {
         /// A JSON string of the output of the function tool call.
         output: String,
     },
+    #[serde(rename = "reasoning")]
+    Reasoning {
+        /// The unique identifier of the reasoning content.
+        id: String,
+        /// Reasoning text contents.
+        summary: Vec<ReasoningSummary>,
+        /// The encrypted content of the reasoning item, if it was requested.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        encrypted_content: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -210,6 +559,16 @@ pub enum InnerInputItem {
         #[serde(default)]
         detail: Detail,
     },
+    #[serde(rename = "input_file")]
+    FileInput {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+        /// The content of the file as a base64 data URL
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file_data: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file_url: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -235,6 +594,28 @@ pub enum Tool {
         parameters: Option<serde_json::Value>,
         strict: bool,
     },
+    #[serde(rename = "web_search")]
+    WebSearch {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filters: Option<WebSearchFilters>,
+    },
+    #[serde(rename = "code_interpreter")]
+    CodeInterpreter { container: Container },
+    #[serde(rename = "file_search")]
+    FileSearch { vector_store_ids: Vec<String> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSearchFilters {
+    pub allowed_domains: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Container {
+    /// A container created automatically for the response
+    #[serde(rename = "auto")]
+    Auto,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -259,9 +640,19 @@ pub struct OutputTokensDetails {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseOutputTextDelta {
     pub content_index: u32,
+    pub delta: String,
+    #[serde(default)]
+    pub logprobs: Vec<Logprob>,
+    pub item_id: String,
+    pub output_index: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseReasoningSummaryTextDelta {
     pub delta: String,
     pub item_id: String,
     pub output_index: u32,
+    pub summary_index: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -270,6 +661,113 @@ pub struct ResponseOutputItemDone {
     pub output_index: u32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileObject {
+    pub id: String,
+}
+
+/// A line of the input file of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequestLine {
+    pub custom_id: String,
+    pub method: String,
+    pub url: String,
+    pub body: CreateModelResponseRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateBatchRequest {
+    pub input_file_id: String,
+    pub endpoint: String,
+    pub completion_window: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchObject {
+    pub id: String,
+    pub status: BatchObjectStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_counts: Option<BatchRequestCounts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<BatchErrors>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchObjectStatus {
+    #[serde(rename = "validating")]
+    Validating,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "in_progress")]
+    InProgress,
+    #[serde(rename = "finalizing")]
+    Finalizing,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "expired")]
+    Expired,
+    #[serde(rename = "cancelling")]
+    Cancelling,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequestCounts {
+    pub total: u32,
+    pub completed: u32,
+    pub failed: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchErrors {
+    pub data: Vec<BatchError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+}
+
+/// A line of the output or error file of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResultLine {
+    pub custom_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<BatchResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<BatchError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResponse {
+    pub status_code: u16,
+    pub body: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListModelsResponse {
+    pub data: Vec<Model>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Model {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owned_by: Option<String>,
+}
+
 fn parse_response<T: DeserializeOwned + Debug>(response: Response) -> Result<T, Error> {
     let status = response.status();
     if status.is_success() {
@@ -294,3 +792,28 @@ fn parse_response<T: DeserializeOwned + Debug>(response: Response) -> Result<T,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::client::{parse_stream_position, stream_position};
+
+    #[test]
+    fn stream_position_roundtrip() {
+        let position = stream_position("resp_123", 42);
+        assert_eq!(position, "resp_123:42");
+        assert_eq!(parse_stream_position(&position), Some(("resp_123", 42)));
+    }
+
+    #[test]
+    fn parses_the_sequence_number_after_the_last_colon() {
+        assert_eq!(parse_stream_position("resp:a:b:7"), Some(("resp:a:b", 7)));
+    }
+
+    #[test]
+    fn rejects_invalid_stream_positions() {
+        assert_eq!(parse_stream_position("resp_123"), None);
+        assert_eq!(parse_stream_position("resp_123:"), None);
+        assert_eq!(parse_stream_position("resp_123:-1"), None);
+        assert_eq!(parse_stream_position("resp_123:next"), None);
+    }
+}
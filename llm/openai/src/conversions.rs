@@ -1,61 +1,206 @@
 use crate::client::{
-    CreateModelResponseRequest, CreateModelResponseResponse, Detail, InnerInput, InnerInputItem,
-    Input, InputItem, OutputItem, OutputMessageContent, Tool,
+    Annotation, BatchObject, BatchObjectStatus, BatchResultLine, CodeInterpreterOutput, Container,
+    CreateModelResponseRequest, CreateModelResponseResponse, Detail, Effort, InnerInput,
+    InnerInputItem, Input, InputItem, ListModelsResponse, OutputItem, OutputMessageContent,
+    Reasoning, ReasoningSummary, Status, TextConfig, TextFormat, Tool, WebSearchFilters,
 };
 use base64::{engine::general_purpose, Engine as _};
-use golem_llm::error::error_code_from_status;
+use golem_llm::error::{error_code_from_status, unsupported};
 use golem_llm::golem::llm::llm::{
-    ChatEvent, CompleteResponse, Config, ContentPart, Error, ErrorCode, ImageDetail,
-    ImageReference, Message, ResponseMetadata, Role, ToolCall, ToolDefinition, ToolResult, Usage,
+    BatchProgress, BatchResult, BatchStatus, BuiltinTool, BuiltinToolResult, ChatEvent, Citation,
+    CodeExecution, CompleteResponse, Config, ContentPart, DocumentData, Error, ErrorCode,
+    ImageDetail, ImageReference, JobStatus, Message, ModelCapabilities, ModelInfo, ReasoningConfig,
+    ReasoningContent, ReasoningEffort, ResponseFormat, ResponseMetadata, Role, ToolCall,
+    ToolDefinition, ToolResult, Usage,
 };
+use golem_llm::logprobs::convert_logprobs;
+use golem_llm::sampling::{validate_sampling_parameters, SamplingParameter};
 use reqwest::StatusCode;
 use std::collections::HashMap;
 use std::str::FromStr;
 
+const SAMPLING_PARAMETERS: &[SamplingParameter] =
+    &[SamplingParameter::TopP, SamplingParameter::User];
+
 pub fn create_request(
     items: Vec<InputItem>,
-    config: Config,
-    tools: Vec<Tool>,
-) -> CreateModelResponseRequest {
+    mut config: Config,
+    mut tools: Vec<Tool>,
+) -> Result<CreateModelResponseRequest, Error> {
+    validate_sampling_parameters(&mut config, SAMPLING_PARAMETERS)?;
+
+    let text = config
+        .response_format
+        .map(response_format_to_text_config)
+        .transpose()?;
+    let reasoning = config
+        .reasoning
+        .map(reasoning_config_to_reasoning)
+        .transpose()?;
     let options = config
         .provider_options
         .into_iter()
         .map(|kv| (kv.key, kv.value))
         .collect::<HashMap<_, _>>();
 
-    CreateModelResponseRequest {
+    if options
+        .get("n")
+        .and_then(|n_s| n_s.parse::<u32>().ok())
+        .is_some_and(|n| n > 1)
+    {
+        return Err(unsupported("multiple choices (n)"));
+    }
+
+    let top_logprobs = options
+        .get("top_logprobs")
+        .and_then(|top_logprobs_s| top_logprobs_s.parse::<u8>().ok());
+    let logprobs = options
+        .get("logprobs")
+        .and_then(|logprobs_s| logprobs_s.parse::<bool>().ok())
+        .unwrap_or(top_logprobs.is_some());
+
+    let mut include = Vec::new();
+    if reasoning.is_some() {
+        // Needed to pass the reasoning items back in stateless multi-turn conversations
+        include.push("reasoning.encrypted_content".to_string());
+    }
+    if logprobs {
+        include.push("message.output_text.logprobs".to_string());
+    }
+    if config
+        .builtin_tools
+        .iter()
+        .any(|tool| matches!(tool, BuiltinTool::CodeInterpreter))
+    {
+        include.push("code_interpreter_call.outputs".to_string());
+    }
+
+    // OpenAI can only limit the number of calls to all the built-in tools together
+    let max_tool_calls = config.builtin_tools.iter().find_map(|tool| match tool {
+        BuiltinTool::WebSearch(options) => options.max_uses,
+        _ => None,
+    });
+    tools.extend(config.builtin_tools.into_iter().map(builtin_tool_to_tool));
+
+    Ok(CreateModelResponseRequest {
         input: Input::List(items),
         model: config.model,
         temperature: config.temperature,
         max_output_tokens: config.max_tokens,
         tools,
         tool_choice: config.tool_choice,
+        max_tool_calls,
         stream: false,
-        top_p: options
-            .get("top_p")
-            .and_then(|top_p_s| top_p_s.parse::<f32>().ok()),
-        user: options
-            .get("user")
-            .and_then(|user_s| user_s.parse::<String>().ok()),
-    }
+        background: options
+            .get("background")
+            .and_then(|background_s| background_s.parse::<bool>().ok()),
+        store: options
+            .get("store")
+            .and_then(|store_s| store_s.parse::<bool>().ok()),
+        previous_response_id: options.get("previous_response_id").cloned(),
+        top_p: config.top_p,
+        top_logprobs,
+        user: config.user,
+        text,
+        reasoning,
+        include,
+    })
+}
+
+fn reasoning_config_to_reasoning(reasoning: ReasoningConfig) -> Result<Reasoning, Error> {
+    let effort = match reasoning.effort {
+        Some(ReasoningEffort::Low) => Some(Effort::Low),
+        Some(ReasoningEffort::Medium) => Some(Effort::Medium),
+        Some(ReasoningEffort::High) => Some(Effort::High),
+        None if reasoning.budget_tokens.is_some() => {
+            return Err(unsupported(
+                "reasoning budget-tokens, use reasoning effort instead",
+            ))
+        }
+        None => None,
+    };
+    Ok(Reasoning {
+        effort,
+        summary: Some("auto".to_string()),
+    })
 }
 
-pub fn messages_to_input_items(messages: Vec<Message>) -> Vec<InputItem> {
+fn response_format_to_text_config(response_format: ResponseFormat) -> Result<TextConfig, Error> {
+    let format = match response_format {
+        ResponseFormat::Text => TextFormat::Text,
+        ResponseFormat::JsonObject => TextFormat::JsonObject,
+        ResponseFormat::JsonSchema(json_schema) => TextFormat::JsonSchema {
+            schema: serde_json::from_str(&json_schema.schema).map_err(|err| Error {
+                code: ErrorCode::InvalidRequest,
+                message: format!(
+                    "Failed to parse response format schema {}: {err}",
+                    json_schema.name
+                ),
+                provider_error_json: None,
+            })?,
+            name: json_schema.name,
+            strict: json_schema.strict,
+        },
+    };
+    Ok(TextConfig { format })
+}
+
+pub fn messages_to_input_items(messages: Vec<Message>) -> Result<Vec<InputItem>, Error> {
     let mut items = Vec::new();
     for message in messages {
-        items.push(llm_message_to_openai_message(message));
+        if message.role == Role::Assistant {
+            items.extend(reasoning_input_items(&message));
+        }
+        if message
+            .content
+            .iter()
+            .any(|content_part| !matches!(content_part, ContentPart::Reasoning(_)))
+        {
+            items.push(llm_message_to_openai_message(message)?);
+        }
+    }
+    Ok(items)
+}
+
+/// Reasoning items produced by OpenAI are passed back as separate input items, identified
+/// by the reasoning item id stored in the content part's signature
+fn reasoning_input_items(message: &Message) -> Vec<InputItem> {
+    let mut items = Vec::new();
+    for content_part in &message.content {
+        if let ContentPart::Reasoning(reasoning) = content_part {
+            if let Some(id) = &reasoning.signature {
+                items.push(InputItem::Reasoning {
+                    id: id.clone(),
+                    summary: if reasoning.text.is_empty() {
+                        vec![]
+                    } else {
+                        vec![ReasoningSummary::Text {
+                            text: reasoning.text.clone(),
+                        }]
+                    },
+                    encrypted_content: reasoning.redacted_data.clone(),
+                });
+            }
+        }
     }
     items
 }
 
-pub fn tool_results_to_input_items(tool_results: Vec<(ToolCall, ToolResult)>) -> Vec<InputItem> {
+/// Converts the tool results to input items. When the request is chained on the previous response
+/// with `previous_response_id`, the tool calls are already part of it and only the results are sent.
+pub fn tool_results_to_input_items(
+    tool_results: Vec<(ToolCall, ToolResult)>,
+    chained: bool,
+) -> Vec<InputItem> {
     let mut items = Vec::new();
     for (tool_call, tool_result) in tool_results {
-        let tool_call = InputItem::ToolCall {
-            arguments: tool_call.arguments_json,
-            call_id: tool_call.id,
-            name: tool_call.name,
-        };
+        if !chained {
+            items.push(InputItem::ToolCall {
+                arguments: tool_call.arguments_json,
+                call_id: tool_call.id,
+                name: tool_call.name,
+            });
+        }
         let tool_result = match tool_result {
             ToolResult::Success(success) => InputItem::ToolResult {
                 call_id: success.id,
@@ -70,7 +215,6 @@ pub fn tool_results_to_input_items(tool_results: Vec<(ToolCall, ToolResult)>) ->
                 ),
             },
         };
-        items.push(tool_call);
         items.push(tool_result);
     }
     items
@@ -104,6 +248,20 @@ pub fn tool_defs_to_tools(tool_definitions: &[ToolDefinition]) -> Result<Vec<Too
     Ok(tools)
 }
 
+fn builtin_tool_to_tool(tool: BuiltinTool) -> Tool {
+    match tool {
+        BuiltinTool::WebSearch(options) => Tool::WebSearch {
+            filters: options
+                .allowed_domains
+                .map(|allowed_domains| WebSearchFilters { allowed_domains }),
+        },
+        BuiltinTool::CodeInterpreter => Tool::CodeInterpreter {
+            container: Container::Auto,
+        },
+        BuiltinTool::FileSearch(vector_store_ids) => Tool::FileSearch { vector_store_ids },
+    }
+}
+
 pub fn to_openai_role_name(role: Role) -> &'static str {
     match role {
         Role::User => "user",
@@ -113,7 +271,7 @@ pub fn to_openai_role_name(role: Role) -> &'static str {
     }
 }
 
-pub fn llm_message_to_openai_message(message: Message) -> InputItem {
+pub fn llm_message_to_openai_message(message: Message) -> Result<InputItem, Error> {
     let mut items = Vec::new();
 
     for content_part in message.content {
@@ -148,14 +306,35 @@ pub fn llm_message_to_openai_message(message: Message) -> InputItem {
                     }
                 }
             },
+            ContentPart::Reasoning(_) => continue,
+            ContentPart::Document(document) => match document.data {
+                DocumentData::Url(url) => InnerInputItem::FileInput {
+                    filename: document.title,
+                    file_data: None,
+                    file_url: Some(url),
+                },
+                DocumentData::Inline(data) => {
+                    let base64_data = general_purpose::STANDARD.encode(&data);
+                    InnerInputItem::FileInput {
+                        // The filename is required for inline files
+                        filename: Some(document.title.unwrap_or_else(|| "document".to_string())),
+                        file_data: Some(format!(
+                            "data:{};base64,{base64_data}",
+                            document.mime_type
+                        )),
+                        file_url: None,
+                    }
+                }
+            },
+            ContentPart::Audio(_) => return Err(unsupported("audio input")),
         };
         items.push(item);
     }
 
-    InputItem::InputMessage {
+    Ok(InputItem::InputMessage {
         role: to_openai_role_name(message.role).to_string(),
         content: InnerInput::List(items),
-    }
+    })
 }
 
 pub fn parse_error_code(code: String) -> ErrorCode {
@@ -169,7 +348,114 @@ pub fn parse_error_code(code: String) -> ErrorCode {
     }
 }
 
-pub fn process_model_response(response: CreateModelResponseResponse) -> ChatEvent {
+/// Converts a background response to the status of its job, which contains the response
+/// itself once it has finished
+pub fn response_to_job_status(response: CreateModelResponseResponse) -> JobStatus {
+    match response.status {
+        Status::Queued => JobStatus::Queued,
+        Status::InProgress => JobStatus::InProgress,
+        Status::Cancelled => JobStatus::Cancelled,
+        Status::Completed | Status::Failed | Status::Incomplete => {
+            JobStatus::Completed(process_model_response(response, false))
+        }
+    }
+}
+
+/// Converts a batch to its status. The results of a finished batch are read from the lines of
+/// its output and error files, skipping the requests which were cancelled or expired.
+pub fn batch_to_status(batch: BatchObject, results: Vec<BatchResultLine>) -> BatchStatus {
+    match batch.status {
+        BatchObjectStatus::Validating => BatchStatus::Queued,
+        BatchObjectStatus::InProgress
+        | BatchObjectStatus::Finalizing
+        | BatchObjectStatus::Cancelling => BatchStatus::InProgress(BatchProgress {
+            total: batch.request_counts.as_ref().map(|counts| counts.total),
+            finished: batch
+                .request_counts
+                .as_ref()
+                .map(|counts| counts.completed + counts.failed),
+        }),
+        BatchObjectStatus::Failed => {
+            let message = batch
+                .errors
+                .as_ref()
+                .map(|errors| {
+                    errors
+                        .data
+                        .iter()
+                        .filter_map(|error| error.message.clone())
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                })
+                .unwrap_or_default();
+            BatchStatus::Failed(Error {
+                code: ErrorCode::InvalidRequest,
+                message: format!("The batch failed: {message}"),
+                provider_error_json: batch
+                    .errors
+                    .as_ref()
+                    .and_then(|errors| serde_json::to_string(errors).ok()),
+            })
+        }
+        BatchObjectStatus::Completed => {
+            BatchStatus::Completed(results.into_iter().filter_map(batch_result).collect())
+        }
+        BatchObjectStatus::Expired | BatchObjectStatus::Cancelled => {
+            BatchStatus::Cancelled(results.into_iter().filter_map(batch_result).collect())
+        }
+    }
+}
+
+fn batch_result(line: BatchResultLine) -> Option<BatchResult> {
+    let event = match (line.response, line.error) {
+        (_, Some(error)) => {
+            if matches!(
+                error.code.as_deref(),
+                Some("batch_expired" | "batch_cancelled")
+            ) {
+                return None;
+            }
+            ChatEvent::Error(Error {
+                code: ErrorCode::InternalError,
+                message: error.message.clone().unwrap_or_default(),
+                provider_error_json: serde_json::to_string(&error).ok(),
+            })
+        }
+        (Some(response), None) if (200..300).contains(&response.status_code) => {
+            match serde_json::from_value::<CreateModelResponseResponse>(response.body) {
+                Ok(response) => process_model_response(response, false),
+                Err(err) => ChatEvent::Error(Error {
+                    code: ErrorCode::InternalError,
+                    message: format!("Failed to parse batch response: {err}"),
+                    provider_error_json: None,
+                }),
+            }
+        }
+        (Some(response), None) => ChatEvent::Error(Error {
+            code: parse_error_code(response.status_code.to_string()),
+            message: response
+                .body
+                .pointer("/error/message")
+                .and_then(|message| message.as_str())
+                .map(|message| message.to_string())
+                .unwrap_or_else(|| format!("Request failed with {}", response.status_code)),
+            provider_error_json: Some(response.body.to_string()),
+        }),
+        (None, None) => ChatEvent::Error(Error {
+            code: ErrorCode::Unknown,
+            message: "The batch result has neither a response nor an error".to_string(),
+            provider_error_json: None,
+        }),
+    };
+    Some(BatchResult {
+        custom_id: line.custom_id,
+        event,
+    })
+}
+
+/// Converts the response to a chat event. Responses stored to chain further requests on are always
+/// returned as a complete response, even with only tool calls, to expose their id.
+pub fn process_model_response(response: CreateModelResponseResponse, stored: bool) -> ChatEvent {
     if let Some(error) = response.error {
         ChatEvent::Error(Error {
             code: parse_error_code(error.code),
@@ -179,6 +465,9 @@ pub fn process_model_response(response: CreateModelResponseResponse) -> ChatEven
     } else {
         let mut contents = Vec::new();
         let mut tool_calls = Vec::new();
+        let mut logprobs = Vec::new();
+        let mut citations = Vec::new();
+        let mut builtin_tool_results = Vec::new();
 
         let metadata = create_response_metadata(&response);
 
@@ -187,8 +476,15 @@ pub fn process_model_response(response: CreateModelResponseResponse) -> ChatEven
                 OutputItem::Message { content, .. } => {
                     for content in content {
                         match content {
-                            OutputMessageContent::Text { text, .. } => {
+                            OutputMessageContent::Text {
+                                text,
+                                logprobs: text_logprobs,
+                                annotations,
+                            } => {
+                                citations
+                                    .extend(annotations.into_iter().filter_map(convert_annotation));
                                 contents.push(ContentPart::Text(text));
+                                logprobs.extend(convert_logprobs(text_logprobs));
                             }
                             OutputMessageContent::Refusal { refusal, .. } => {
                                 contents.push(ContentPart::Text(format!("Refusal: {refusal}")));
@@ -206,13 +502,53 @@ pub fn process_model_response(response: CreateModelResponseResponse) -> ChatEven
                         id: call_id,
                         name,
                         arguments_json: arguments,
+                        validation_error: None,
                     };
                     tool_calls.push(tool_call);
                 }
+                OutputItem::Reasoning {
+                    id,
+                    summary,
+                    encrypted_content,
+                } => {
+                    contents.push(ContentPart::Reasoning(ReasoningContent {
+                        text: summary
+                            .into_iter()
+                            .map(|ReasoningSummary::Text { text }| text)
+                            .collect::<Vec<_>>()
+                            .join("\n\n"),
+                        signature: Some(id),
+                        redacted_data: encrypted_content,
+                    }));
+                }
+                OutputItem::CodeInterpreterCall { code, outputs, .. } => {
+                    let logs = outputs
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|output| match output {
+                            CodeInterpreterOutput::Logs { logs } => Some(logs),
+                            CodeInterpreterOutput::Image { .. } => None,
+                        })
+                        .collect::<Vec<_>>();
+                    builtin_tool_results.push(BuiltinToolResult::CodeExecution(CodeExecution {
+                        code,
+                        output: if logs.is_empty() {
+                            None
+                        } else {
+                            Some(logs.join("\n"))
+                        },
+                        exit_code: None,
+                    }));
+                }
+                // The results of the searches are returned as citations in the text
+                OutputItem::WebSearchCall { .. } | OutputItem::FileSearchCall { .. } => {}
             }
         }
+        if !citations.is_empty() {
+            builtin_tool_results.push(BuiltinToolResult::Citations(citations));
+        }
 
-        if contents.is_empty() {
+        if contents.is_empty() && !stored {
             ChatEvent::ToolRequest(tool_calls)
         } else {
             ChatEvent::Message(CompleteResponse {
@@ -220,11 +556,38 @@ pub fn process_model_response(response: CreateModelResponseResponse) -> ChatEven
                 content: contents,
                 tool_calls,
                 metadata,
+                logprobs: if logprobs.is_empty() {
+                    None
+                } else {
+                    Some(logprobs)
+                },
+                alternatives: vec![],
+                builtin_tool_results,
             })
         }
     }
 }
 
+/// Converts a citation of the text. The annotations only locate the part of the response based
+/// on the source, the cited part of the source itself is not returned.
+fn convert_annotation(annotation: Annotation) -> Option<Citation> {
+    match annotation {
+        Annotation::UrlCitation { url, title } => Some(Citation {
+            url: Some(url),
+            file_id: None,
+            title,
+            cited_text: None,
+        }),
+        Annotation::FileCitation { file_id, filename } => Some(Citation {
+            url: None,
+            file_id: Some(file_id),
+            title: filename,
+            cited_text: None,
+        }),
+        Annotation::Other => None,
+    }
+}
+
 pub fn create_response_metadata(response: &CreateModelResponseResponse) -> ResponseMetadata {
     ResponseMetadata {
         finish_reason: None,
@@ -232,9 +595,115 @@ pub fn create_response_metadata(response: &CreateModelResponseResponse) -> Respo
             input_tokens: Some(usage.input_tokens),
             output_tokens: Some(usage.output_tokens),
             total_tokens: Some(usage.total_tokens),
+            cache_read_tokens: Some(usage.input_tokens_details.cached_tokens),
+            cache_write_tokens: None,
+            reasoning_tokens: Some(usage.output_tokens_details.reasoning_tokens),
         }),
         provider_id: Some(response.id.clone()),
         timestamp: Some(response.created_at.to_string()),
         provider_metadata_json: response.metadata.as_ref().map(|m| m.to_string()),
+        rate_limit: None,
+        cost: None,
+    }
+}
+
+/// Model families returned by the models endpoint that cannot be used with the Responses API
+const NON_CHAT_MODEL_PREFIXES: &[&str] = &[
+    "babbage",
+    "dall-e",
+    "davinci",
+    "gpt-image",
+    "omni-moderation",
+    "sora",
+    "text-embedding",
+    "text-moderation",
+    "tts",
+    "whisper",
+];
+
+pub fn models_to_model_infos(response: ListModelsResponse) -> Vec<ModelInfo> {
+    response
+        .data
+        .into_iter()
+        .filter(|model| {
+            !NON_CHAT_MODEL_PREFIXES
+                .iter()
+                .any(|prefix| model.id.starts_with(prefix))
+        })
+        .map(|model| model_info(model.id))
+        .collect()
+}
+
+/// The models endpoint only returns model identifiers, so the limits and capabilities
+/// are filled in based on the documented properties of the known model families.
+fn model_info(id: String) -> ModelInfo {
+    let (context_window, max_output_tokens, capabilities) = match id.as_str() {
+        id if id.starts_with("gpt-5") => (
+            Some(400_000),
+            Some(128_000),
+            model_capabilities(true, true, true, true),
+        ),
+        id if id.starts_with("gpt-4.1") => (
+            Some(1_047_576),
+            Some(32_768),
+            model_capabilities(true, true, false, true),
+        ),
+        id if id.starts_with("o1-mini") => (
+            Some(128_000),
+            Some(65_536),
+            model_capabilities(false, false, true, false),
+        ),
+        id if id.starts_with("o3-mini") => (
+            Some(200_000),
+            Some(100_000),
+            model_capabilities(true, false, true, true),
+        ),
+        id if id.starts_with("o1") || id.starts_with("o3") || id.starts_with("o4") => (
+            Some(200_000),
+            Some(100_000),
+            model_capabilities(true, true, true, true),
+        ),
+        id if id.starts_with("gpt-4o") || id.starts_with("chatgpt-4o") => (
+            Some(128_000),
+            Some(16_384),
+            model_capabilities(true, true, false, true),
+        ),
+        id if id.starts_with("gpt-4-turbo") => (
+            Some(128_000),
+            Some(4_096),
+            model_capabilities(true, true, false, false),
+        ),
+        id if id.starts_with("gpt-4") => (
+            Some(8_192),
+            Some(8_192),
+            model_capabilities(true, false, false, false),
+        ),
+        id if id.starts_with("gpt-3.5-turbo") => (
+            Some(16_385),
+            Some(4_096),
+            model_capabilities(true, false, false, false),
+        ),
+        _ => (None, None, model_capabilities(false, false, false, false)),
+    };
+
+    ModelInfo {
+        id,
+        context_window,
+        max_output_tokens,
+        capabilities,
+    }
+}
+
+fn model_capabilities(
+    tools: bool,
+    vision: bool,
+    reasoning: bool,
+    json_schema: bool,
+) -> ModelCapabilities {
+    ModelCapabilities {
+        tools,
+        vision,
+        reasoning,
+        json_schema,
     }
 }
@@ -1,30 +1,40 @@
 use crate::client::{
-    CreateModelResponseResponse, InputItem, OutputItem, ResponseOutputItemDone,
-    ResponseOutputTextDelta, ResponsesApi,
+    parse_stream_position, stream_position, BatchObject, BatchObjectStatus, BatchRequestLine,
+    BatchResultLine, CreateModelResponseResponse, InputItem, OutputItem, ResponseOutputItemDone,
+    ResponseOutputTextDelta, ResponseReasoningSummaryTextDelta, ResponsesApi, RESPONSES_ENDPOINT,
 };
 use crate::conversions::{
-    create_request, create_response_metadata, messages_to_input_items, parse_error_code,
-    process_model_response, tool_defs_to_tools, tool_results_to_input_items,
+    batch_to_status, create_request, create_response_metadata, messages_to_input_items,
+    models_to_model_infos, parse_error_code, process_model_response, response_to_job_status,
+    tool_defs_to_tools, tool_results_to_input_items,
 };
+use golem_llm::batch::{parse_jsonl, to_jsonl, BatchApi, ProviderChatBatch};
+use golem_llm::chat_job::{BackgroundChatJob, BackgroundJobApi};
 use golem_llm::chat_stream::{LlmChatStream, LlmChatStreamState};
-use golem_llm::config::with_config_key;
-use golem_llm::durability::{DurableLLM, ExtendedGuest};
+use golem_llm::config::{get_config_key, with_config_key};
+use golem_llm::conversation::LlmConversation;
+use golem_llm::durability::ExtendedGuest;
 use golem_llm::event_source::EventSource;
 use golem_llm::golem::llm::llm::{
-    ChatEvent, ChatStream, Config, ContentPart, Error, ErrorCode, Guest, Message, StreamDelta,
-    StreamEvent, ToolCall, ToolResult,
+    BatchRequest, BatchStatus, ChatBatch, ChatEvent, ChatJob, ChatStream, Config, ContentPart,
+    Error, ErrorCode, Guest, JobStatus, Message, ModelInfo, ReasoningContent, StreamDelta,
+    StreamEvent, TokenCount, ToolCall, ToolResult,
 };
+use golem_llm::logprobs::convert_logprobs;
+use golem_llm::tokens::estimate_token_count;
 use golem_rust::wasm_rpc::Pollable;
-use log::trace;
-use std::cell::{Ref, RefCell, RefMut};
+use log::{trace, warn};
+use std::cell::{Cell, Ref, RefCell, RefMut};
 
 mod client;
 mod conversions;
 
-struct OpenAIChatStream {
+pub struct OpenAIChatStream {
     stream: RefCell<Option<EventSource>>,
     failure: Option<Error>,
     finished: RefCell<bool>,
+    response_id: RefCell<Option<String>>,
+    sequence_number: Cell<Option<u64>>,
 }
 
 impl OpenAIChatStream {
@@ -33,6 +43,20 @@ impl OpenAIChatStream {
             stream: RefCell::new(Some(stream)),
             failure: None,
             finished: RefCell::new(false),
+            response_id: RefCell::new(None),
+            sequence_number: Cell::new(None),
+        })
+    }
+
+    /// Continues the stream of a background response, which does not repeat the event
+    /// identifying the response
+    pub fn resumed(stream: EventSource, response_id: String) -> LlmChatStream<Self> {
+        LlmChatStream::new(OpenAIChatStream {
+            stream: RefCell::new(Some(stream)),
+            failure: None,
+            finished: RefCell::new(false),
+            response_id: RefCell::new(Some(response_id)),
+            sequence_number: Cell::new(None),
         })
     }
 
@@ -41,6 +65,8 @@ impl OpenAIChatStream {
             stream: RefCell::new(None),
             failure: Some(error),
             finished: RefCell::new(false),
+            response_id: RefCell::new(None),
+            sequence_number: Cell::new(None),
         })
     }
 }
@@ -71,6 +97,18 @@ impl LlmChatStreamState for OpenAIChatStream {
         let json: serde_json::Value = serde_json::from_str(raw)
             .map_err(|err| format!("Failed to deserialize stream event: {err}"))?;
 
+        // Tracking the position in the stream for resuming background responses
+        if let Some(sequence_number) = json.get("sequence_number").and_then(|v| v.as_u64()) {
+            self.sequence_number.set(Some(sequence_number));
+        }
+        if let Some(response_id) = json
+            .get("response")
+            .and_then(|response| response.get("id"))
+            .and_then(|v| v.as_str())
+        {
+            *self.response_id.borrow_mut() = Some(response_id.to_string());
+        }
+
         let typ = json
             .as_object()
             .and_then(|obj| obj.get("type"))
@@ -125,50 +163,98 @@ impl LlmChatStreamState for OpenAIChatStream {
                 Ok(Some(StreamEvent::Delta(StreamDelta {
                     content: Some(vec![ContentPart::Text(decoded.delta)]),
                     tool_calls: None,
+                    logprobs: if decoded.logprobs.is_empty() {
+                        None
+                    } else {
+                        Some(convert_logprobs(decoded.logprobs))
+                    },
+                })))
+            }
+            Some("response.reasoning_summary_text.delta") => {
+                let decoded = serde_json::from_value::<ResponseReasoningSummaryTextDelta>(json)
+                    .map_err(|err| format!("Failed to deserialize stream event: {err}"))?;
+                Ok(Some(StreamEvent::Delta(StreamDelta {
+                    content: Some(vec![ContentPart::Reasoning(ReasoningContent {
+                        text: decoded.delta,
+                        signature: None,
+                        redacted_data: None,
+                    })]),
+                    tool_calls: None,
+                    logprobs: None,
                 })))
             }
             Some("response.output_item.done") => {
                 let decoded = serde_json::from_value::<ResponseOutputItemDone>(json)
                     .map_err(|err| format!("Failed to deserialize stream event: {err}"))?;
-                if let OutputItem::ToolCall {
-                    arguments,
-                    call_id,
-                    name,
-                    ..
-                } = decoded.item
-                {
-                    Ok(Some(StreamEvent::Delta(StreamDelta {
+                match decoded.item {
+                    OutputItem::ToolCall {
+                        arguments,
+                        call_id,
+                        name,
+                        ..
+                    } => Ok(Some(StreamEvent::Delta(StreamDelta {
                         content: None,
                         tool_calls: Some(vec![ToolCall {
                             id: call_id,
                             name,
                             arguments_json: arguments,
+                            validation_error: None,
                         }]),
-                    })))
-                } else {
-                    Ok(None)
+                        logprobs: None,
+                    }))),
+                    OutputItem::Reasoning {
+                        id,
+                        encrypted_content,
+                        ..
+                    } => {
+                        // The summary text was already streamed, only the identifying parts are emitted here
+                        Ok(Some(StreamEvent::Delta(StreamDelta {
+                            content: Some(vec![ContentPart::Reasoning(ReasoningContent {
+                                text: String::new(),
+                                signature: Some(id),
+                                redacted_data: encrypted_content,
+                            })]),
+                            tool_calls: None,
+                            logprobs: None,
+                        })))
+                    }
+                    OutputItem::Message { .. }
+                    | OutputItem::WebSearchCall { .. }
+                    | OutputItem::FileSearchCall { .. }
+                    | OutputItem::CodeInterpreterCall { .. } => Ok(None),
                 }
             }
             Some(_) => Ok(None),
             None => Err("Unexpected stream event format, does not have 'type' field".to_string()),
         }
     }
+
+    fn last_event_id(&self) -> Option<String> {
+        let response_id = self.response_id.borrow();
+        Some(stream_position(
+            response_id.as_ref()?,
+            self.sequence_number.get()?,
+        ))
+    }
 }
 
-struct OpenAIComponent;
+pub struct OpenAIComponent;
 
 impl OpenAIComponent {
     const ENV_VAR_NAME: &'static str = "OPENAI_API_KEY";
 
     fn request(client: ResponsesApi, items: Vec<InputItem>, config: Config) -> ChatEvent {
         match tool_defs_to_tools(&config.tools) {
-            Ok(tools) => {
-                let request = create_request(items, config, tools);
-                match client.create_model_response(request) {
-                    Ok(response) => process_model_response(response),
-                    Err(error) => ChatEvent::Error(error),
+            Ok(tools) => match create_request(items, config, tools) {
+                Ok(request) => {
+                    let stored = request.store == Some(true);
+                    match client.create_model_response(request) {
+                        Ok(response) => process_model_response(response, stored),
+                        Err(error) => ChatEvent::Error(error),
+                    }
                 }
-            }
+                Err(error) => ChatEvent::Error(error),
+            },
             Err(error) => ChatEvent::Error(error),
         }
     }
@@ -179,14 +265,16 @@ impl OpenAIComponent {
         config: Config,
     ) -> LlmChatStream<OpenAIChatStream> {
         match tool_defs_to_tools(&config.tools) {
-            Ok(tools) => {
-                let mut request = create_request(items, config, tools);
-                request.stream = true;
-                match client.stream_model_response(request) {
-                    Ok(stream) => OpenAIChatStream::new(stream),
-                    Err(error) => OpenAIChatStream::failed(error),
+            Ok(tools) => match create_request(items, config, tools) {
+                Ok(mut request) => {
+                    request.stream = true;
+                    match client.stream_model_response(request) {
+                        Ok(stream) => OpenAIChatStream::new(stream),
+                        Err(error) => OpenAIChatStream::failed(error),
+                    }
                 }
-            }
+                Err(error) => OpenAIChatStream::failed(error),
+            },
             Err(error) => OpenAIChatStream::failed(error),
         }
     }
@@ -194,13 +282,18 @@ impl OpenAIComponent {
 
 impl Guest for OpenAIComponent {
     type ChatStream = LlmChatStream<OpenAIChatStream>;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = BackgroundChatJob<Self>;
+    type ChatBatch = ProviderChatBatch<Self>;
 
     fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
         with_config_key(Self::ENV_VAR_NAME, ChatEvent::Error, |openai_api_key| {
             let client = ResponsesApi::new(openai_api_key);
 
-            let items = messages_to_input_items(messages);
-            Self::request(client, items, config)
+            match messages_to_input_items(messages) {
+                Ok(items) => Self::request(client, items, config),
+                Err(err) => ChatEvent::Error(err),
+            }
         })
     }
 
@@ -212,15 +305,127 @@ impl Guest for OpenAIComponent {
         with_config_key(Self::ENV_VAR_NAME, ChatEvent::Error, |openai_api_key| {
             let client = ResponsesApi::new(openai_api_key);
 
-            let mut items = messages_to_input_items(messages);
-            items.extend(tool_results_to_input_items(tool_results));
-            Self::request(client, items, config)
+            let chained = config
+                .provider_options
+                .iter()
+                .any(|kv| kv.key == "previous_response_id");
+            match messages_to_input_items(messages) {
+                Ok(mut items) => {
+                    items.extend(tool_results_to_input_items(tool_results, chained));
+                    Self::request(client, items, config)
+                }
+                Err(err) => ChatEvent::Error(err),
+            }
         })
     }
 
     fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
         ChatStream::new(Self::unwrapped_stream(messages, config))
     }
+
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        with_config_key(Self::ENV_VAR_NAME, Err, |openai_api_key| {
+            let client = ResponsesApi::new(openai_api_key);
+
+            client.list_models().map(models_to_model_infos)
+        })
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        Ok(estimate_token_count(&messages, &config))
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
+}
+
+/// Jobs are background responses, which are stored by OpenAI and can be retrieved by their id
+impl BackgroundJobApi for OpenAIComponent {
+    fn start_job(messages: Vec<Message>, config: Config) -> Result<String, Error> {
+        let client = ResponsesApi::new(get_config_key(Self::ENV_VAR_NAME)?);
+        let items = messages_to_input_items(messages)?;
+        let tools = tool_defs_to_tools(&config.tools)?;
+        let mut request = create_request(items, config, tools)?;
+        request.background = Some(true);
+        client
+            .create_model_response(request)
+            .map(|response| response.id)
+    }
+
+    fn poll_job(id: &str) -> Result<JobStatus, Error> {
+        let client = ResponsesApi::new(get_config_key(Self::ENV_VAR_NAME)?);
+        client
+            .retrieve_model_response(id)
+            .map(response_to_job_status)
+    }
+
+    fn cancel_job(id: &str) -> Result<(), Error> {
+        let client = ResponsesApi::new(get_config_key(Self::ENV_VAR_NAME)?);
+        client.cancel_model_response(id).map(|_| ())
+    }
+}
+
+/// Batches are sent to the Batch API as an uploaded file of requests to the Responses API, and
+/// their results are read from the output and error files once the batch has finished
+impl BatchApi for OpenAIComponent {
+    fn submit_batch(requests: Vec<BatchRequest>) -> Result<String, Error> {
+        let client = ResponsesApi::new(get_config_key(Self::ENV_VAR_NAME)?);
+        let lines = requests
+            .into_iter()
+            .map(|request| {
+                let items = messages_to_input_items(request.messages)?;
+                let tools = tool_defs_to_tools(&request.config.tools)?;
+                Ok(BatchRequestLine {
+                    custom_id: request.custom_id,
+                    method: "POST".to_string(),
+                    url: RESPONSES_ENDPOINT.to_string(),
+                    body: create_request(items, request.config, tools)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let file = client.upload_batch_file(to_jsonl(&lines)?)?;
+        client.create_batch(file.id).map(|batch| batch.id)
+    }
+
+    fn poll_batch(id: &str) -> Result<BatchStatus, Error> {
+        let client = ResponsesApi::new(get_config_key(Self::ENV_VAR_NAME)?);
+        let batch = client.retrieve_batch(id)?;
+        let results = match batch.status {
+            BatchObjectStatus::Completed
+            | BatchObjectStatus::Expired
+            | BatchObjectStatus::Cancelled => Self::batch_results(&client, &batch)?,
+            _ => vec![],
+        };
+        Ok(batch_to_status(batch, results))
+    }
+
+    fn cancel_batch(id: &str) -> Result<(), Error> {
+        let client = ResponsesApi::new(get_config_key(Self::ENV_VAR_NAME)?);
+        client.cancel_batch(id).map(|_| ())
+    }
+}
+
+impl OpenAIComponent {
+    /// Reads the results of a finished batch, the successful responses are in its output file
+    /// and the failed requests in its error file
+    fn batch_results(
+        client: &ResponsesApi,
+        batch: &BatchObject,
+    ) -> Result<Vec<BatchResultLine>, Error> {
+        let mut results = Vec::new();
+        for file_id in [&batch.output_file_id, &batch.error_file_id]
+            .into_iter()
+            .flatten()
+        {
+            results.extend(parse_jsonl(&client.file_content(file_id)?)?);
+        }
+        Ok(results)
+    }
 }
 
 impl ExtendedGuest for OpenAIComponent {
@@ -231,17 +436,59 @@ impl ExtendedGuest for OpenAIComponent {
             |openai_api_key| {
                 let client = ResponsesApi::new(openai_api_key);
 
-                let items = messages_to_input_items(messages);
-                Self::streaming_request(client, items, config)
+                match messages_to_input_items(messages) {
+                    Ok(items) => Self::streaming_request(client, items, config),
+                    Err(err) => OpenAIChatStream::failed(err),
+                }
             },
         )
     }
 
+    /// Background responses can be resumed, so the stream continues from where it was interrupted
+    fn resume_stream(
+        _original_messages: &[Message],
+        _config: &Config,
+        last_event_id: &str,
+    ) -> Option<Self::ChatStream> {
+        let (response_id, _) = parse_stream_position(last_event_id)?;
+        let client = ResponsesApi::new(get_config_key(Self::ENV_VAR_NAME).ok()?);
+        match client.resume_model_response(last_event_id) {
+            Ok(stream) => Some(OpenAIChatStream::resumed(stream, response_id.to_string())),
+            Err(err) => {
+                warn!("Failed to resume the response stream: {}", err.message);
+                None
+            }
+        }
+    }
+
+    fn last_event_id(stream: &Self::ChatStream) -> Option<String> {
+        stream.last_event_id()
+    }
+
+    /// Responses are stored by OpenAI unless the `store` provider option is false
+    fn stores_responses() -> bool {
+        true
+    }
+
+    fn unwrapped_fetch_job(id: String) -> Result<Self::ChatJob, Error> {
+        Ok(BackgroundChatJob::fetch(id))
+    }
+
+    fn unwrapped_fetch_batch(id: String) -> Result<Self::ChatBatch, Error> {
+        Ok(ProviderChatBatch::fetch(id))
+    }
+
+    fn pricing_provider() -> Option<&'static str> {
+        Some("openai")
+    }
+
     fn subscribe(stream: &Self::ChatStream) -> Pollable {
         stream.subscribe()
     }
 }
 
-type DurableOpenAIComponent = DurableLLM<OpenAIComponent>;
+#[cfg(not(feature = "library"))]
+type DurableOpenAIComponent = golem_llm::durability::DurableLLM<OpenAIComponent>;
 
+#[cfg(not(feature = "library"))]
 golem_llm::export_llm!(DurableOpenAIComponent with_types_in golem_llm);
@@ -1,6 +1,8 @@
 use golem_llm::error::{error_code_from_status, from_event_source_error, from_reqwest_error};
 use golem_llm::event_source::EventSource;
 use golem_llm::golem::llm::llm::{Error, ErrorCode};
+use golem_llm::logprobs::Logprob;
+use golem_llm::retry::SendWithRetry;
 use log::trace;
 use reqwest::header::HeaderValue;
 use reqwest::{Client, Method, Response, StatusCode};
@@ -32,7 +34,7 @@ impl CompletionsApi {
             .request(Method::POST, format!("{BASE_URL}/api/v1/chat/completions"))
             .bearer_auth(self.api_key.clone())
             .json(&request)
-            .send()
+            .send_with_retry()
             .map_err(|err| from_reqwest_error("Request failed", err))?;
 
         parse_response(response)
@@ -50,7 +52,7 @@ impl CompletionsApi {
                 HeaderValue::from_static("text/event-stream"),
             )
             .json(&request)
-            .send()
+            .send_with_retry()
             .map_err(|err| from_reqwest_error("Request failed", err))?;
 
         trace!("Initializing SSE stream");
@@ -58,6 +60,19 @@ impl CompletionsApi {
         EventSource::new(response)
             .map_err(|err| from_event_source_error("Failed to create SSE stream", err))
     }
+
+    pub fn list_models(&self) -> Result<ListModelsResponse, Error> {
+        trace!("Listing models from OpenRouter API");
+
+        let response: Response = self
+            .client
+            .request(Method::GET, format!("{BASE_URL}/api/v1/models"))
+            .bearer_auth(self.api_key.clone())
+            .send_with_retry()
+            .map_err(|err| from_reqwest_error("Request failed", err))?;
+
+        parse_response(response)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,17 +96,64 @@ pub struct CompletionsRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<Reasoning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<ToolChoice>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub tools: Vec<Tool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub top_k: Option<f32>,
+    pub top_k: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub min_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_a: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reasoning {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effort: Option<Effort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Effort {
+    #[serde(rename = "low")]
+    Low,
+    #[serde(rename = "medium")]
+    Medium,
+    #[serde(rename = "high")]
+    High,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseFormat {
+    #[serde(rename = "text")]
+    Text,
+    #[serde(rename = "json_object")]
+    JsonObject,
+    #[serde(rename = "json_schema")]
+    JsonSchema { json_schema: JsonSchema },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchema {
+    pub name: String,
+    pub schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -175,6 +237,23 @@ pub enum ContentPart {
     TextInput { text: String },
     #[serde(rename = "image_url")]
     ImageInput { image_url: ImageUrl },
+    #[serde(rename = "file")]
+    FileInput { file: File },
+    #[serde(rename = "input_audio")]
+    AudioInput { input_audio: InputAudio },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct File {
+    pub filename: String,
+    /// Either a URL or a base64 data URL
+    pub file_data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputAudio {
+    pub data: String,
+    pub format: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -230,6 +309,12 @@ pub struct Choice {
     pub native_finish_reason: Option<FinishReason>,
     pub message: ResponseMessage,
     pub error: Option<ErrorResponse>,
+    pub logprobs: Option<Logprobs>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Logprobs {
+    pub content: Option<Vec<Logprob>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -249,6 +334,7 @@ pub enum FinishReason {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseMessage {
     pub content: Option<String>,
+    pub reasoning: Option<String>,
     pub role: String,
     pub tool_calls: Option<Vec<ToolCall>>,
 }
@@ -270,6 +356,18 @@ pub struct Usage {
     pub completion_tokens: u32,
     pub prompt_tokens: u32,
     pub total_tokens: u32,
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTokensDetails {
+    pub cached_tokens: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionTokensDetails {
+    pub reasoning_tokens: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -288,15 +386,49 @@ pub struct ChoiceChunk {
     pub finish_reason: Option<FinishReason>,
     pub native_finish_reason: Option<String>,
     pub error: Option<ErrorResponse>,
+    pub logprobs: Option<Logprobs>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChoiceDelta {
     pub content: Option<String>,
+    pub reasoning: Option<String>,
     pub tool_calls: Option<Vec<ToolCall>>,
     pub role: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListModelsResponse {
+    pub data: Vec<Model>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Model {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub architecture: Option<Architecture>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_provider: Option<TopProvider>,
+    #[serde(default)]
+    pub supported_parameters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Architecture {
+    #[serde(default)]
+    pub input_modalities: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopProvider {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_completion_tokens: Option<u32>,
+}
+
 fn parse_response<T: DeserializeOwned + Debug>(response: Response) -> Result<T, Error> {
     let status = response.status();
     if status.is_success() {
@@ -1,17 +1,36 @@
 use crate::client::{
-    CompletionsRequest, CompletionsResponse, Detail, FunctionName, ToolChoiceFunction,
+    CompletionsRequest, CompletionsResponse, Detail, Effort, FunctionName, ListModelsResponse,
+    Model, Reasoning, ToolChoiceFunction,
 };
 use base64::{engine::general_purpose, Engine as _};
+use golem_llm::builtin_tools::reject_builtin_tools;
+use golem_llm::error::unsupported;
 use golem_llm::golem::llm::llm::{
-    ChatEvent, CompleteResponse, Config, ContentPart, Error, ErrorCode, FinishReason, ImageDetail,
-    ImageReference, Message, ResponseMetadata, Role, ToolCall, ToolDefinition, ToolResult, Usage,
+    ChatEvent, CompleteResponse, Config, ContentPart, DocumentData, Error, ErrorCode, FinishReason,
+    ImageDetail, ImageReference, Message, ModelCapabilities, ModelInfo, ReasoningConfig,
+    ReasoningContent, ReasoningEffort, ResponseFormat, ResponseMetadata, Role, ToolCall,
+    ToolDefinition, ToolResult, Usage,
 };
+use golem_llm::logprobs::convert_logprobs;
+use golem_llm::sampling::{validate_sampling_parameters, SamplingParameter};
 use std::collections::HashMap;
 
+const SAMPLING_PARAMETERS: &[SamplingParameter] = &[
+    SamplingParameter::TopP,
+    SamplingParameter::TopK,
+    SamplingParameter::Seed,
+    SamplingParameter::FrequencyPenalty,
+    SamplingParameter::PresencePenalty,
+    SamplingParameter::User,
+];
+
 pub fn messages_to_request(
     messages: Vec<Message>,
-    config: Config,
+    mut config: Config,
 ) -> Result<CompletionsRequest, Error> {
+    validate_sampling_parameters(&mut config, SAMPLING_PARAMETERS)?;
+    reject_builtin_tools(&config)?;
+
     let options = config
         .provider_options
         .into_iter()
@@ -23,16 +42,16 @@ pub fn messages_to_request(
         match message.role {
             Role::User => completion_messages.push(crate::client::Message::User {
                 name: message.name,
-                content: convert_content_parts(message.content),
+                content: convert_content_parts(message.content)?,
             }),
             Role::Assistant => completion_messages.push(crate::client::Message::Assistant {
                 name: message.name,
-                content: Some(convert_content_parts(message.content)),
+                content: Some(convert_content_parts(message.content)?),
                 tool_calls: None,
             }),
             Role::System => completion_messages.push(crate::client::Message::System {
                 name: message.name,
-                content: convert_content_parts(message.content),
+                content: convert_content_parts(message.content)?,
             }),
             Role::Tool => completion_messages.push(crate::client::Message::Tool {
                 name: message.name,
@@ -47,39 +66,45 @@ pub fn messages_to_request(
         tools.push(tool_definition_to_tool(tool)?)
     }
 
+    let top_logprobs = options
+        .get("top_logprobs")
+        .and_then(|top_logprobs_s| top_logprobs_s.parse::<u8>().ok());
+
     Ok(CompletionsRequest {
         messages: completion_messages,
         model: config.model,
-        frequency_penalty: options
-            .get("frequency_penalty")
-            .and_then(|fp_s| fp_s.parse::<f32>().ok()),
+        frequency_penalty: config.frequency_penalty,
         max_tokens: config.max_tokens,
-        presence_penalty: options
-            .get("presence_penalty")
-            .and_then(|pp_s| pp_s.parse::<f32>().ok()),
+        presence_penalty: config.presence_penalty,
         repetition_penalty: options
             .get("repetition_penalty")
             .and_then(|rp_s| rp_s.parse::<f32>().ok()),
-        seed: options
-            .get("seed")
-            .and_then(|seed_s| seed_s.parse::<u32>().ok()),
+        seed: config.seed,
         stop: config.stop_sequences,
         stream: Some(false),
         temperature: config.temperature,
+        reasoning: config.reasoning.map(convert_reasoning_config),
+        response_format: config
+            .response_format
+            .map(convert_response_format)
+            .transpose()?,
         tool_choice: config.tool_choice.map(convert_tool_choice),
         tools,
-        top_p: options
-            .get("top_p")
-            .and_then(|top_p_s| top_p_s.parse::<f32>().ok()),
-        top_k: options
-            .get("top_k")
-            .and_then(|top_k_s| top_k_s.parse::<f32>().ok()),
+        top_p: config.top_p,
+        top_k: config.top_k,
         min_p: options
             .get("min_p")
             .and_then(|min_p_s| min_p_s.parse::<f32>().ok()),
         top_a: options
             .get("top_a")
             .and_then(|top_a_s| top_a_s.parse::<f32>().ok()),
+        // The top log-probabilities are only returned together with the sampled tokens' ones
+        logprobs: options
+            .get("logprobs")
+            .and_then(|logprobs_s| logprobs_s.parse::<bool>().ok())
+            .or(top_logprobs.map(|_| true)),
+        top_logprobs,
+        user: config.user,
     })
 }
 
@@ -89,6 +114,14 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
         let mut contents = Vec::new();
         let mut tool_calls = Vec::new();
 
+        if let Some(reasoning) = &choice.message.reasoning {
+            contents.push(ContentPart::Reasoning(ReasoningContent {
+                text: reasoning.clone(),
+                signature: None,
+                redacted_data: None,
+            }));
+        }
+
         if let Some(content) = &choice.message.content {
             contents.push(ContentPart::Text(content.clone()));
         }
@@ -107,6 +140,8 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
                 provider_id: None,
                 timestamp: Some(response.created.to_string()),
                 provider_metadata_json: None,
+                rate_limit: None,
+                cost: None,
             };
 
             ChatEvent::Message(CompleteResponse {
@@ -114,6 +149,14 @@ pub fn process_response(response: CompletionsResponse) -> ChatEvent {
                 content: contents,
                 tool_calls,
                 metadata,
+                logprobs: choice
+                    .logprobs
+                    .clone()
+                    .and_then(|logprobs| logprobs.content)
+                    .map(convert_logprobs),
+                // OpenRouter does not support requesting multiple choices
+                alternatives: vec![],
+                builtin_tool_results: vec![],
             })
         }
     } else {
@@ -161,11 +204,12 @@ pub fn convert_tool_call(tool_call: &crate::client::ToolCall) -> ToolCall {
             id: id.clone().unwrap_or_default(),
             name: function.name.clone().unwrap_or_default(),
             arguments_json: function.arguments.clone(),
+            validation_error: None,
         },
     }
 }
 
-fn convert_content_parts(contents: Vec<ContentPart>) -> crate::client::Content {
+fn convert_content_parts(contents: Vec<ContentPart>) -> Result<crate::client::Content, Error> {
     let mut result = Vec::new();
     for content in contents {
         match content {
@@ -190,9 +234,38 @@ fn convert_content_parts(contents: Vec<ContentPart>) -> crate::client::Content {
                     });
                 }
             },
+            ContentPart::Reasoning(_) => {}
+            ContentPart::Document(document) => {
+                let file_data = match document.data {
+                    DocumentData::Url(url) => url,
+                    DocumentData::Inline(data) => {
+                        let base64_data = general_purpose::STANDARD.encode(&data);
+                        format!("data:{};base64,{base64_data}", document.mime_type)
+                    }
+                };
+                result.push(crate::client::ContentPart::FileInput {
+                    file: crate::client::File {
+                        filename: document.title.unwrap_or_else(|| "document".to_string()),
+                        file_data,
+                    },
+                });
+            }
+            ContentPart::Audio(audio) => {
+                let format = match audio.mime_type.as_str() {
+                    "audio/wav" | "audio/x-wav" | "audio/wave" => "wav",
+                    "audio/mpeg" | "audio/mp3" => "mp3",
+                    other => return Err(unsupported(format!("audio type {other}"))),
+                };
+                result.push(crate::client::ContentPart::AudioInput {
+                    input_audio: crate::client::InputAudio {
+                        data: general_purpose::STANDARD.encode(&audio.data),
+                        format: format.to_string(),
+                    },
+                });
+            }
         }
     }
-    crate::client::Content::List(result)
+    Ok(crate::client::Content::List(result))
 }
 
 fn convert_content_parts_to_string(contents: Vec<ContentPart>) -> String {
@@ -201,6 +274,9 @@ fn convert_content_parts_to_string(contents: Vec<ContentPart>) -> String {
         match content {
             ContentPart::Text(text) => result.push_str(&text),
             ContentPart::Image(_) => {} // Correctly ignores any image content
+            ContentPart::Reasoning(_) => {}
+            ContentPart::Document(_) => {}
+            ContentPart::Audio(_) => {}
         }
     }
     result
@@ -231,6 +307,15 @@ pub fn convert_usage(value: &crate::client::Usage) -> Usage {
         input_tokens: Some(value.prompt_tokens),
         output_tokens: Some(value.completion_tokens),
         total_tokens: Some(value.total_tokens),
+        cache_read_tokens: value
+            .prompt_tokens_details
+            .as_ref()
+            .and_then(|details| details.cached_tokens),
+        cache_write_tokens: None,
+        reasoning_tokens: value
+            .completion_tokens_details
+            .as_ref()
+            .and_then(|details| details.reasoning_tokens),
     }
 }
 
@@ -259,3 +344,76 @@ fn convert_tool_choice(tool_choice: String) -> crate::client::ToolChoice {
         }),
     }
 }
+
+fn convert_response_format(
+    response_format: ResponseFormat,
+) -> Result<crate::client::ResponseFormat, Error> {
+    match response_format {
+        ResponseFormat::Text => Ok(crate::client::ResponseFormat::Text),
+        ResponseFormat::JsonObject => Ok(crate::client::ResponseFormat::JsonObject),
+        ResponseFormat::JsonSchema(json_schema) => {
+            match serde_json::from_str(&json_schema.schema) {
+                Ok(schema) => Ok(crate::client::ResponseFormat::JsonSchema {
+                    json_schema: crate::client::JsonSchema {
+                        name: json_schema.name,
+                        schema,
+                        strict: json_schema.strict,
+                    },
+                }),
+                Err(error) => Err(Error {
+                    code: ErrorCode::InvalidRequest,
+                    message: format!(
+                        "Failed to parse response format schema {}: {error}",
+                        json_schema.name
+                    ),
+                    provider_error_json: None,
+                }),
+            }
+        }
+    }
+}
+
+fn convert_reasoning_config(reasoning: ReasoningConfig) -> Reasoning {
+    Reasoning {
+        effort: reasoning.effort.map(|effort| match effort {
+            ReasoningEffort::Low => Effort::Low,
+            ReasoningEffort::Medium => Effort::Medium,
+            ReasoningEffort::High => Effort::High,
+        }),
+        max_tokens: reasoning.budget_tokens,
+    }
+}
+
+pub fn models_to_model_infos(response: ListModelsResponse) -> Vec<ModelInfo> {
+    response.data.into_iter().map(convert_model).collect()
+}
+
+fn convert_model(model: Model) -> ModelInfo {
+    let supports = |parameter: &str| model.supported_parameters.iter().any(|p| p == parameter);
+    let capabilities = ModelCapabilities {
+        tools: supports("tools"),
+        vision: model.architecture.as_ref().is_some_and(|architecture| {
+            architecture
+                .input_modalities
+                .iter()
+                .any(|modality| modality == "image")
+        }),
+        reasoning: supports("reasoning"),
+        json_schema: supports("structured_outputs"),
+    };
+
+    ModelInfo {
+        context_window: model.context_length.or_else(|| {
+            model
+                .top_provider
+                .as_ref()
+                .and_then(|provider| provider.context_length)
+        }),
+        max_output_tokens: model
+            .top_provider
+            .as_ref()
+            .and_then(|provider| provider.max_completion_tokens),
+        capabilities,
+        id: model.id,
+    }
+}
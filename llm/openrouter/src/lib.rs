@@ -3,18 +3,24 @@ mod conversions;
 
 use crate::client::{ChatCompletionChunk, CompletionsApi, CompletionsRequest, FunctionCall};
 use crate::conversions::{
-    convert_finish_reason, convert_usage, messages_to_request, process_response,
-    tool_results_to_messages,
+    convert_finish_reason, convert_usage, messages_to_request, models_to_model_infos,
+    process_response, tool_results_to_messages,
 };
+use golem_llm::batch::UnsupportedChatBatch;
+use golem_llm::chat_job::EmulatedChatJob;
 use golem_llm::chat_stream::{LlmChatStream, LlmChatStreamState};
 use golem_llm::config::with_config_key;
-use golem_llm::durability::{DurableLLM, ExtendedGuest};
+use golem_llm::conversation::LlmConversation;
+use golem_llm::durability::ExtendedGuest;
 use golem_llm::error::error_code_from_status;
 use golem_llm::event_source::EventSource;
 use golem_llm::golem::llm::llm::{
-    ChatEvent, ChatStream, Config, ContentPart, Error, FinishReason, Guest, Message,
-    ResponseMetadata, Role, StreamDelta, StreamEvent, ToolCall, ToolResult,
+    ChatBatch, ChatEvent, ChatJob, ChatStream, Config, ContentPart, Error, FinishReason, Guest,
+    Message, ModelInfo, ReasoningContent, ResponseMetadata, Role, StreamDelta, StreamEvent,
+    TokenCount, ToolCall, ToolResult,
 };
+use golem_llm::logprobs::convert_logprobs;
+use golem_llm::tokens::estimate_token_count;
 use golem_rust::wasm_rpc::Pollable;
 use log::trace;
 use reqwest::StatusCode;
@@ -28,7 +34,7 @@ struct JsonFragment {
     json: String,
 }
 
-struct OpenRouterChatStream {
+pub struct OpenRouterChatStream {
     stream: RefCell<Option<EventSource>>,
     failure: Option<Error>,
     finished: RefCell<bool>,
@@ -103,6 +109,8 @@ impl LlmChatStreamState for OpenRouterChatStream {
                             provider_id: None,
                             timestamp: Some(message.created.to_string()),
                             provider_metadata_json: None,
+                            rate_limit: None,
+                            cost: None,
                         })))
                     } else if let Some(choice) = message.choices.into_iter().next() {
                         if let Some(finish_reason) = choice.finish_reason {
@@ -123,10 +131,22 @@ impl LlmChatStreamState for OpenRouterChatStream {
                                     .map(|value| serde_json::to_string(&value).unwrap()),
                             })))
                         } else {
-                            let content = choice
-                                .delta
-                                .content
-                                .map(|text| vec![ContentPart::Text(text)]);
+                            let mut content = Vec::new();
+                            if let Some(reasoning) = choice.delta.reasoning {
+                                content.push(ContentPart::Reasoning(ReasoningContent {
+                                    text: reasoning,
+                                    signature: None,
+                                    redacted_data: None,
+                                }));
+                            }
+                            if let Some(text) = choice.delta.content {
+                                content.push(ContentPart::Text(text));
+                            }
+                            let content = if content.is_empty() {
+                                None
+                            } else {
+                                Some(content)
+                            };
 
                             let mut seen_indices = HashSet::new();
                             let mut tool_calls = Vec::new();
@@ -148,6 +168,7 @@ impl LlmChatStreamState for OpenRouterChatStream {
                                             id,
                                             name,
                                             arguments_json: arguments,
+                                            validation_error: None,
                                         });
                                     }
                                     client::ToolCall::Function {
@@ -198,6 +219,7 @@ impl LlmChatStreamState for OpenRouterChatStream {
                                         id: fragment.id,
                                         name: fragment.name,
                                         arguments_json: fragment.json,
+                                        validation_error: None,
                                     });
                                 }
                             }
@@ -209,6 +231,10 @@ impl LlmChatStreamState for OpenRouterChatStream {
                                 } else {
                                     Some(tool_calls)
                                 },
+                                logprobs: choice
+                                    .logprobs
+                                    .and_then(|logprobs| logprobs.content)
+                                    .map(convert_logprobs),
                             })))
                         }
                     } else {
@@ -224,7 +250,7 @@ impl LlmChatStreamState for OpenRouterChatStream {
     }
 }
 
-struct OpenRouterComponent;
+pub struct OpenRouterComponent;
 
 impl OpenRouterComponent {
     const ENV_VAR_NAME: &'static str = "OPENROUTER_API_KEY";
@@ -250,6 +276,9 @@ impl OpenRouterComponent {
 
 impl Guest for OpenRouterComponent {
     type ChatStream = LlmChatStream<OpenRouterChatStream>;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = UnsupportedChatBatch;
 
     fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
         with_config_key(Self::ENV_VAR_NAME, ChatEvent::Error, |openrouter_api_key| {
@@ -285,6 +314,26 @@ impl Guest for OpenRouterComponent {
     fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
         ChatStream::new(Self::unwrapped_stream(messages, config))
     }
+
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        with_config_key(Self::ENV_VAR_NAME, Err, |openrouter_api_key| {
+            let client = CompletionsApi::new(openrouter_api_key);
+
+            client.list_models().map(models_to_model_infos)
+        })
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        Ok(estimate_token_count(&messages, &config))
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
 }
 
 impl ExtendedGuest for OpenRouterComponent {
@@ -317,6 +366,7 @@ impl ExtendedGuest for OpenRouterComponent {
                      Please continue your response from where you left off. \
                      Do not include the part of the response that was already seen.".to_string()),
             ],
+            cache_control: None,
         });
         extended_messages.push(Message {
             role: Role::User,
@@ -324,6 +374,7 @@ impl ExtendedGuest for OpenRouterComponent {
             content: vec![ContentPart::Text(
                 "Here is the original question:".to_string(),
             )],
+            cache_control: None,
         });
         extended_messages.extend_from_slice(original_messages);
 
@@ -351,15 +402,22 @@ impl ExtendedGuest for OpenRouterComponent {
             .into_iter()
             .chain(partial_result_as_content)
             .collect(),
+            cache_control: None,
         });
         extended_messages
     }
 
+    fn pricing_provider() -> Option<&'static str> {
+        Some("openrouter")
+    }
+
     fn subscribe(stream: &Self::ChatStream) -> Pollable {
         stream.subscribe()
     }
 }
 
-type DurableOpenRouterComponent = DurableLLM<OpenRouterComponent>;
+#[cfg(not(feature = "library"))]
+type DurableOpenRouterComponent = golem_llm::durability::DurableLLM<OpenRouterComponent>;
 
+#[cfg(not(feature = "library"))]
 golem_llm::export_llm!(DurableOpenRouterComponent with_types_in golem_llm);
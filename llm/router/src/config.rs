@@ -0,0 +1,145 @@
+use golem_llm::config::get_config_key;
+use golem_llm::golem::llm::llm::{Error, ErrorCode};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// The environment variable holding the ordered list of providers to route the requests to
+pub const ROUTER_CONFIG_ENV_VAR_NAME: &str = "GOLEM_LLM_ROUTER_CONFIG";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Anthropic,
+    #[serde(rename = "azure-openai")]
+    AzureOpenAI,
+    Bedrock,
+    Gemini,
+    Grok,
+    Ollama,
+    OpenAI,
+    #[serde(rename = "openai-compatible")]
+    OpenAICompatible,
+    OpenRouter,
+}
+
+impl Provider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Provider::Anthropic => "anthropic",
+            Provider::AzureOpenAI => "azure-openai",
+            Provider::Bedrock => "bedrock",
+            Provider::Gemini => "gemini",
+            Provider::Grok => "grok",
+            Provider::Ollama => "ollama",
+            Provider::OpenAI => "openai",
+            Provider::OpenAICompatible => "openai-compatible",
+            Provider::OpenRouter => "openrouter",
+        }
+    }
+}
+
+/// A single entry of the routing chain.
+///
+/// Example: `{"provider": "bedrock", "models": {"claude-sonnet-4-0": "anthropic.claude-sonnet-4-20250514-v1:0"}}`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderConfig {
+    pub provider: Provider,
+    /// Maps the model names used by the application to the provider's own model names.
+    /// Models without an entry are passed to the provider unchanged.
+    #[serde(default)]
+    pub models: HashMap<String, String>,
+}
+
+impl ProviderConfig {
+    pub fn model(&self, model: &str) -> String {
+        self.models
+            .get(model)
+            .cloned()
+            .unwrap_or_else(|| model.to_string())
+    }
+}
+
+/// Reads the routing chain from the `GOLEM_LLM_ROUTER_CONFIG` environment variable, which
+/// must contain a non-empty JSON array of provider configs, in the order they are tried
+pub fn get_router_config() -> Result<Vec<ProviderConfig>, Error> {
+    let raw = get_config_key(ROUTER_CONFIG_ENV_VAR_NAME)?;
+    parse_router_config(&raw)
+}
+
+fn parse_router_config(raw: &str) -> Result<Vec<ProviderConfig>, Error> {
+    let providers: Vec<ProviderConfig> = serde_json::from_str(raw).map_err(|err| Error {
+        code: ErrorCode::InternalError,
+        message: format!("Failed to parse {ROUTER_CONFIG_ENV_VAR_NAME}: {err}"),
+        provider_error_json: None,
+    })?;
+
+    if providers.is_empty() {
+        Err(Error {
+            code: ErrorCode::InternalError,
+            message: format!("{ROUTER_CONFIG_ENV_VAR_NAME} does not contain any providers"),
+            provider_error_json: None,
+        })
+    } else {
+        Ok(providers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{parse_router_config, Provider};
+
+    #[test]
+    fn parse_chain_with_model_mapping() {
+        let providers = parse_router_config(
+            r#"[
+                {"provider": "anthropic"},
+                {"provider": "bedrock", "models": {"claude-sonnet-4-0": "anthropic.claude-sonnet-4-20250514-v1:0"}},
+                {"provider": "openrouter", "models": {"claude-sonnet-4-0": "anthropic/claude-sonnet-4"}}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(providers.len(), 3);
+        assert_eq!(providers[0].provider, Provider::Anthropic);
+        assert_eq!(providers[0].model("claude-sonnet-4-0"), "claude-sonnet-4-0");
+        assert_eq!(
+            providers[1].model("claude-sonnet-4-0"),
+            "anthropic.claude-sonnet-4-20250514-v1:0"
+        );
+        assert_eq!(providers[2].provider, Provider::OpenRouter);
+        assert_eq!(providers[2].model("other-model"), "other-model");
+    }
+
+    #[test]
+    fn parse_all_providers() {
+        let providers = parse_router_config(
+            r#"[
+                {"provider": "anthropic"},
+                {"provider": "azure-openai"},
+                {"provider": "bedrock"},
+                {"provider": "gemini"},
+                {"provider": "grok"},
+                {"provider": "ollama"},
+                {"provider": "openai"},
+                {"provider": "openai-compatible"},
+                {"provider": "openrouter"}
+            ]"#,
+        )
+        .unwrap();
+
+        for provider_config in providers {
+            let name = format!("\"{}\"", provider_config.provider.name());
+            assert_eq!(
+                serde_json::from_str::<Provider>(&name).unwrap(),
+                provider_config.provider
+            );
+        }
+    }
+
+    #[test]
+    fn reject_empty_chain() {
+        assert!(parse_router_config("[]").is_err());
+        assert!(parse_router_config(r#"[{"provider": "azureopenai"}]"#).is_err());
+        assert!(parse_router_config(r#"[{"provider": "unknown"}]"#).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+mod config;
+mod provider;
+mod stream;
+
+use crate::config::{get_router_config, ProviderConfig};
+use crate::stream::RouterChatStream;
+use golem_llm::batch::UnsupportedChatBatch;
+use golem_llm::chat_job::EmulatedChatJob;
+use golem_llm::conversation::LlmConversation;
+use golem_llm::durability::{DurableLLM, ExtendedGuest};
+use golem_llm::golem::llm::llm::{
+    ChatBatch, ChatEvent, ChatJob, ChatStream, Config, Error, ErrorCode, Guest, Message, ModelInfo,
+    ResponseMetadata, TokenCount, ToolCall, ToolResult,
+};
+use golem_llm::pricing::fill_cost;
+use golem_rust::wasm_rpc::Pollable;
+use log::warn;
+
+/// Routes the requests to the providers configured in `GOLEM_LLM_ROUTER_CONFIG`, in order,
+/// falling over to the next provider when one fails with `rate-limit-exceeded` or
+/// `internal-error`. Each provider reads its own credentials from the environment.
+struct RouterComponent;
+
+impl RouterComponent {
+    /// Calls `f` with each configured provider and the model mapped to it, until one
+    /// succeeds or fails with an error that does not allow falling over to the next provider
+    fn route<T>(
+        config: &Config,
+        mut f: impl FnMut(&ProviderConfig, Config) -> Result<T, Error>,
+    ) -> Result<(ProviderConfig, T), Error> {
+        let providers = get_router_config()?;
+        let provider_count = providers.len();
+
+        let mut last_error = None;
+        for (idx, provider_config) in providers.into_iter().enumerate() {
+            let config = Config {
+                model: provider_config.model(&config.model),
+                ..config.clone()
+            };
+            match f(&provider_config, config) {
+                Ok(result) => return Ok((provider_config, result)),
+                Err(error) if is_fallback_error(&error) && idx + 1 < provider_count => {
+                    warn!(
+                        "Provider {} failed, falling over to the next provider: {}",
+                        provider_config.provider.name(),
+                        error.message
+                    );
+                }
+                Err(error) => {
+                    last_error = Some(error);
+                    break;
+                }
+            }
+        }
+
+        Err(last_error.expect("The router config has at least one provider"))
+    }
+
+    fn routed_chat_event(
+        config: &Config,
+        f: impl Fn(&ProviderConfig, Config) -> ChatEvent,
+    ) -> ChatEvent {
+        let result = Self::route(config, |provider_config, config| {
+            let model = config.model.clone();
+            match f(provider_config, config) {
+                ChatEvent::Error(error) => Err(error),
+                event => Ok((model, event)),
+            }
+        });
+
+        match result {
+            Ok((provider_config, (model, ChatEvent::Message(mut response)))) => {
+                route_metadata(
+                    provider_config.provider.name(),
+                    &model,
+                    &mut response.metadata,
+                );
+                ChatEvent::Message(response)
+            }
+            Ok((_, (_, event))) => event,
+            Err(error) => ChatEvent::Error(error),
+        }
+    }
+}
+
+impl Guest for RouterComponent {
+    type ChatStream = RouterChatStream;
+    type Conversation = LlmConversation<Self>;
+    type ChatJob = EmulatedChatJob<Self>;
+    type ChatBatch = UnsupportedChatBatch;
+
+    fn send(messages: Vec<Message>, config: Config) -> ChatEvent {
+        Self::routed_chat_event(&config, |provider_config, config| {
+            provider_config.provider.send(messages.clone(), config)
+        })
+    }
+
+    fn continue_(
+        messages: Vec<Message>,
+        tool_results: Vec<(ToolCall, ToolResult)>,
+        config: Config,
+    ) -> ChatEvent {
+        Self::routed_chat_event(&config, |provider_config, config| {
+            provider_config
+                .provider
+                .continue_(messages.clone(), tool_results.clone(), config)
+        })
+    }
+
+    fn stream(messages: Vec<Message>, config: Config) -> ChatStream {
+        ChatStream::new(Self::unwrapped_stream(messages, config))
+    }
+
+    /// Lists the models of all the configured providers, skipping the providers which are
+    /// unavailable as long as at least one of them answers
+    fn list_models() -> Result<Vec<ModelInfo>, Error> {
+        let providers = get_router_config()?;
+
+        let mut models = Vec::new();
+        let mut last_error = None;
+        for provider_config in &providers {
+            match provider_config.provider.list_models() {
+                Ok(provider_models) => models.extend(provider_models),
+                Err(error) if is_fallback_error(&error) => {
+                    warn!(
+                        "Failed to list the models of provider {}: {}",
+                        provider_config.provider.name(),
+                        error.message
+                    );
+                    last_error = Some(error);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+
+        match last_error {
+            Some(error) if models.is_empty() => Err(error),
+            _ => Ok(models),
+        }
+    }
+
+    fn count_tokens(messages: Vec<Message>, config: Config) -> Result<TokenCount, Error> {
+        Self::route(&config, |provider_config, config| {
+            provider_config
+                .provider
+                .count_tokens(messages.clone(), config)
+        })
+        .map(|(_, token_count)| token_count)
+    }
+
+    fn fetch_job(id: String) -> Result<ChatJob, Error> {
+        Self::unwrapped_fetch_job(id).map(ChatJob::new)
+    }
+
+    fn fetch_batch(id: String) -> Result<ChatBatch, Error> {
+        Self::unwrapped_fetch_batch(id).map(ChatBatch::new)
+    }
+}
+
+impl ExtendedGuest for RouterComponent {
+    /// Returns the stream of the first provider without waiting for its first events, the
+    /// stream falls over to the next providers itself
+    fn unwrapped_stream(messages: Vec<Message>, config: Config) -> RouterChatStream {
+        match get_router_config() {
+            Ok(providers) => RouterChatStream::new(messages, config, providers),
+            Err(error) => RouterChatStream::failed(messages, config, error),
+        }
+    }
+
+    fn subscribe(stream: &Self::ChatStream) -> Pollable {
+        stream.subscribe()
+    }
+}
+
+/// Whether the router should try the next provider after a provider failed with `error`
+pub(crate) fn is_fallback_error(error: &Error) -> bool {
+    matches!(
+        error.code,
+        ErrorCode::RateLimitExceeded | ErrorCode::InternalError
+    )
+}
+
+/// Records which provider and model answered in the response metadata, keeping the
+/// provider's own metadata under the `metadata` key, and estimates the cost of the response
+/// with the prices of that provider
+pub(crate) fn route_metadata(provider: &str, model: &str, metadata: &mut ResponseMetadata) {
+    fill_cost(provider, model, metadata);
+
+    let provider_metadata = metadata
+        .provider_metadata_json
+        .take()
+        .map(|json| serde_json::from_str(&json).unwrap_or(serde_json::Value::String(json)));
+
+    metadata.provider_metadata_json = Some(
+        serde_json::json!({
+            "provider": provider,
+            "model": model,
+            "metadata": provider_metadata,
+        })
+        .to_string(),
+    );
+}
+
+type DurableRouterComponent = DurableLLM<RouterComponent>;
+
+golem_llm::export_llm!(DurableRouterComponent with_types_in golem_llm);
@@ -0,0 +1,178 @@
+use crate::config::Provider;
+use golem_llm::durability::ExtendedGuest;
+use golem_llm::golem::llm::llm::{
+    ChatEvent, Config, Error, Guest, GuestChatStream, Message, ModelInfo, StreamEvent, TokenCount,
+    ToolCall, ToolResult,
+};
+use golem_llm_anthropic::AnthropicComponent;
+use golem_llm_azure_openai::AzureOpenAIComponent;
+use golem_llm_bedrock::BedrockComponent;
+use golem_llm_gemini::GeminiComponent;
+use golem_llm_grok::GrokComponent;
+use golem_llm_ollama::OllamaComponent;
+use golem_llm_openai::OpenAIComponent;
+use golem_llm_openai_compatible::OpenAICompatibleComponent;
+use golem_llm_openrouter::OpenRouterComponent;
+use golem_rust::wasm_rpc::Pollable;
+
+impl Provider {
+    pub fn send(&self, messages: Vec<Message>, config: Config) -> ChatEvent {
+        match self {
+            Provider::Anthropic => AnthropicComponent::send(messages, config),
+            Provider::AzureOpenAI => AzureOpenAIComponent::send(messages, config),
+            Provider::Bedrock => BedrockComponent::send(messages, config),
+            Provider::Gemini => GeminiComponent::send(messages, config),
+            Provider::Grok => GrokComponent::send(messages, config),
+            Provider::Ollama => OllamaComponent::send(messages, config),
+            Provider::OpenAI => OpenAIComponent::send(messages, config),
+            Provider::OpenAICompatible => OpenAICompatibleComponent::send(messages, config),
+            Provider::OpenRouter => OpenRouterComponent::send(messages, config),
+        }
+    }
+
+    pub fn continue_(
+        &self,
+        messages: Vec<Message>,
+        tool_results: Vec<(ToolCall, ToolResult)>,
+        config: Config,
+    ) -> ChatEvent {
+        match self {
+            Provider::Anthropic => AnthropicComponent::continue_(messages, tool_results, config),
+            Provider::AzureOpenAI => {
+                AzureOpenAIComponent::continue_(messages, tool_results, config)
+            }
+            Provider::Bedrock => BedrockComponent::continue_(messages, tool_results, config),
+            Provider::Gemini => GeminiComponent::continue_(messages, tool_results, config),
+            Provider::Grok => GrokComponent::continue_(messages, tool_results, config),
+            Provider::Ollama => OllamaComponent::continue_(messages, tool_results, config),
+            Provider::OpenAI => OpenAIComponent::continue_(messages, tool_results, config),
+            Provider::OpenAICompatible => {
+                OpenAICompatibleComponent::continue_(messages, tool_results, config)
+            }
+            Provider::OpenRouter => OpenRouterComponent::continue_(messages, tool_results, config),
+        }
+    }
+
+    pub fn stream(&self, messages: Vec<Message>, config: Config) -> ProviderChatStream {
+        match self {
+            Provider::Anthropic => ProviderChatStream::Anthropic(
+                AnthropicComponent::unwrapped_stream(messages, config),
+            ),
+            Provider::AzureOpenAI => ProviderChatStream::AzureOpenAI(
+                AzureOpenAIComponent::unwrapped_stream(messages, config),
+            ),
+            Provider::Bedrock => {
+                ProviderChatStream::Bedrock(BedrockComponent::unwrapped_stream(messages, config))
+            }
+            Provider::Gemini => {
+                ProviderChatStream::Gemini(GeminiComponent::unwrapped_stream(messages, config))
+            }
+            Provider::Grok => {
+                ProviderChatStream::Grok(GrokComponent::unwrapped_stream(messages, config))
+            }
+            Provider::Ollama => {
+                ProviderChatStream::Ollama(OllamaComponent::unwrapped_stream(messages, config))
+            }
+            Provider::OpenAI => {
+                ProviderChatStream::OpenAI(OpenAIComponent::unwrapped_stream(messages, config))
+            }
+            Provider::OpenAICompatible => ProviderChatStream::OpenAICompatible(
+                OpenAICompatibleComponent::unwrapped_stream(messages, config),
+            ),
+            Provider::OpenRouter => ProviderChatStream::OpenRouter(
+                OpenRouterComponent::unwrapped_stream(messages, config),
+            ),
+        }
+    }
+
+    pub fn list_models(&self) -> Result<Vec<ModelInfo>, Error> {
+        match self {
+            Provider::Anthropic => AnthropicComponent::list_models(),
+            Provider::AzureOpenAI => AzureOpenAIComponent::list_models(),
+            Provider::Bedrock => BedrockComponent::list_models(),
+            Provider::Gemini => GeminiComponent::list_models(),
+            Provider::Grok => GrokComponent::list_models(),
+            Provider::Ollama => OllamaComponent::list_models(),
+            Provider::OpenAI => OpenAIComponent::list_models(),
+            Provider::OpenAICompatible => OpenAICompatibleComponent::list_models(),
+            Provider::OpenRouter => OpenRouterComponent::list_models(),
+        }
+    }
+
+    pub fn count_tokens(
+        &self,
+        messages: Vec<Message>,
+        config: Config,
+    ) -> Result<TokenCount, Error> {
+        match self {
+            Provider::Anthropic => AnthropicComponent::count_tokens(messages, config),
+            Provider::AzureOpenAI => AzureOpenAIComponent::count_tokens(messages, config),
+            Provider::Bedrock => BedrockComponent::count_tokens(messages, config),
+            Provider::Gemini => GeminiComponent::count_tokens(messages, config),
+            Provider::Grok => GrokComponent::count_tokens(messages, config),
+            Provider::Ollama => OllamaComponent::count_tokens(messages, config),
+            Provider::OpenAI => OpenAIComponent::count_tokens(messages, config),
+            Provider::OpenAICompatible => OpenAICompatibleComponent::count_tokens(messages, config),
+            Provider::OpenRouter => OpenRouterComponent::count_tokens(messages, config),
+        }
+    }
+}
+
+/// The unwrapped chat stream of one of the supported providers
+pub enum ProviderChatStream {
+    Anthropic(<AnthropicComponent as Guest>::ChatStream),
+    AzureOpenAI(<AzureOpenAIComponent as Guest>::ChatStream),
+    Bedrock(<BedrockComponent as Guest>::ChatStream),
+    Gemini(<GeminiComponent as Guest>::ChatStream),
+    Grok(<GrokComponent as Guest>::ChatStream),
+    Ollama(<OllamaComponent as Guest>::ChatStream),
+    OpenAI(<OpenAIComponent as Guest>::ChatStream),
+    OpenAICompatible(<OpenAICompatibleComponent as Guest>::ChatStream),
+    OpenRouter(<OpenRouterComponent as Guest>::ChatStream),
+}
+
+impl ProviderChatStream {
+    pub fn get_next(&self) -> Option<Vec<StreamEvent>> {
+        match self {
+            ProviderChatStream::Anthropic(stream) => stream.get_next(),
+            ProviderChatStream::AzureOpenAI(stream) => stream.get_next(),
+            ProviderChatStream::Bedrock(stream) => stream.get_next(),
+            ProviderChatStream::Gemini(stream) => stream.get_next(),
+            ProviderChatStream::Grok(stream) => stream.get_next(),
+            ProviderChatStream::Ollama(stream) => stream.get_next(),
+            ProviderChatStream::OpenAI(stream) => stream.get_next(),
+            ProviderChatStream::OpenAICompatible(stream) => stream.get_next(),
+            ProviderChatStream::OpenRouter(stream) => stream.get_next(),
+        }
+    }
+
+    pub fn cancel(&self) {
+        match self {
+            ProviderChatStream::Anthropic(stream) => stream.cancel(),
+            ProviderChatStream::AzureOpenAI(stream) => stream.cancel(),
+            ProviderChatStream::Bedrock(stream) => stream.cancel(),
+            ProviderChatStream::Gemini(stream) => stream.cancel(),
+            ProviderChatStream::Grok(stream) => stream.cancel(),
+            ProviderChatStream::Ollama(stream) => stream.cancel(),
+            ProviderChatStream::OpenAI(stream) => stream.cancel(),
+            ProviderChatStream::OpenAICompatible(stream) => stream.cancel(),
+            ProviderChatStream::OpenRouter(stream) => stream.cancel(),
+        }
+    }
+
+    pub fn subscribe(&self) -> Pollable {
+        match self {
+            ProviderChatStream::Anthropic(stream) => AnthropicComponent::subscribe(stream),
+            ProviderChatStream::AzureOpenAI(stream) => AzureOpenAIComponent::subscribe(stream),
+            ProviderChatStream::Bedrock(stream) => BedrockComponent::subscribe(stream),
+            ProviderChatStream::Gemini(stream) => GeminiComponent::subscribe(stream),
+            ProviderChatStream::Grok(stream) => GrokComponent::subscribe(stream),
+            ProviderChatStream::Ollama(stream) => OllamaComponent::subscribe(stream),
+            ProviderChatStream::OpenAI(stream) => OpenAIComponent::subscribe(stream),
+            ProviderChatStream::OpenAICompatible(stream) => {
+                OpenAICompatibleComponent::subscribe(stream)
+            }
+            ProviderChatStream::OpenRouter(stream) => OpenRouterComponent::subscribe(stream),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::config::ProviderConfig;
+use crate::provider::ProviderChatStream;
+use crate::{is_fallback_error, route_metadata};
+use golem_llm::golem::llm::llm::{Config, Error, GuestChatStream, Message, StreamEvent};
+use golem_rust::bindings::wasi::clocks::monotonic_clock;
+use golem_rust::wasm_rpc::Pollable;
+use log::warn;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+/// A chat stream of the provider that was selected by the router.
+///
+/// The fallback to the next provider can only happen before anything was emitted. When the
+/// first events of a provider's stream are an error allowing the fallback, the stream of the
+/// next provider is started in its place, and the caller keeps polling for the first events.
+/// Failures after the first events are returned to the caller as they are.
+pub struct RouterChatStream {
+    messages: Vec<Message>,
+    config: Config,
+    /// The providers not tried yet, in the order of the router config
+    remaining_providers: RefCell<VecDeque<ProviderConfig>>,
+    current: RefCell<Option<RoutedStream>>,
+    /// The streams of the providers which failed before the fallback. They are kept alive until
+    /// the router stream is dropped, because the pollables created for them have to be dropped
+    /// first.
+    failed_streams: RefCell<Vec<RoutedStream>>,
+    /// Whether events of the current provider were emitted, after which there is no fallback
+    emitted: Cell<bool>,
+    /// The error of a stream failing without reaching any of the providers
+    failure: RefCell<Option<Error>>,
+}
+
+/// The stream of a provider, and the model it was asked for
+struct RoutedStream {
+    provider: &'static str,
+    model: String,
+    stream: ProviderChatStream,
+}
+
+impl RouterChatStream {
+    /// Starts streaming with the first of the providers
+    pub fn new(messages: Vec<Message>, config: Config, providers: Vec<ProviderConfig>) -> Self {
+        let stream = Self {
+            messages,
+            config,
+            remaining_providers: RefCell::new(providers.into()),
+            current: RefCell::new(None),
+            failed_streams: RefCell::new(Vec::new()),
+            emitted: Cell::new(false),
+            failure: RefCell::new(None),
+        };
+        stream.start_next_provider();
+        stream
+    }
+
+    /// A stream failing with `error` without reaching any of the providers
+    pub fn failed(messages: Vec<Message>, config: Config, error: Error) -> Self {
+        let stream = Self::new(messages, config, vec![]);
+        *stream.failure.borrow_mut() = Some(error);
+        stream
+    }
+
+    /// The pollable of the current provider's stream, which changes when the router falls
+    /// over to the next provider
+    pub fn subscribe(&self) -> Pollable {
+        match &*self.current.borrow() {
+            Some(routed) => routed.stream.subscribe(),
+            None => monotonic_clock::subscribe_duration(0),
+        }
+    }
+
+    fn start_next_provider(&self) {
+        let Some(provider_config) = self.remaining_providers.borrow_mut().pop_front() else {
+            return;
+        };
+        let config = Config {
+            model: provider_config.model(&self.config.model),
+            ..self.config.clone()
+        };
+        let model = config.model.clone();
+        let stream = provider_config
+            .provider
+            .stream(self.messages.clone(), config);
+        let failed_stream = self.current.borrow_mut().replace(RoutedStream {
+            provider: provider_config.provider.name(),
+            model,
+            stream,
+        });
+        self.failed_streams.borrow_mut().extend(failed_stream);
+    }
+
+    /// Whether the router falls over to the next provider after the current one emitted
+    /// `events`
+    fn falls_over(&self, events: &[StreamEvent]) -> bool {
+        match events.first() {
+            Some(StreamEvent::Error(error)) => {
+                !self.emitted.get()
+                    && is_fallback_error(error)
+                    && !self.remaining_providers.borrow().is_empty()
+            }
+            _ => false,
+        }
+    }
+
+    fn route_events(&self, events: Vec<StreamEvent>) -> Vec<StreamEvent> {
+        let current = self.current.borrow();
+        let Some(routed) = current.as_ref() else {
+            return events;
+        };
+        events
+            .into_iter()
+            .map(|event| match event {
+                StreamEvent::Finish(mut metadata) => {
+                    route_metadata(routed.provider, &routed.model, &mut metadata);
+                    StreamEvent::Finish(metadata)
+                }
+                other => other,
+            })
+            .collect()
+    }
+}
+
+impl GuestChatStream for RouterChatStream {
+    fn get_next(&self) -> Option<Vec<StreamEvent>> {
+        loop {
+            let events = match &*self.current.borrow() {
+                Some(routed) => routed.stream.get_next()?,
+                None => {
+                    return Some(
+                        self.failure
+                            .borrow_mut()
+                            .take()
+                            .map(StreamEvent::Error)
+                            .into_iter()
+                            .collect(),
+                    )
+                }
+            };
+
+            if self.falls_over(&events) {
+                if let (Some(StreamEvent::Error(error)), Some(routed)) =
+                    (events.first(), &*self.current.borrow())
+                {
+                    warn!(
+                        "Provider {} failed, falling over to the next provider: {}",
+                        routed.provider, error.message
+                    );
+                }
+                self.start_next_provider();
+                continue;
+            }
+
+            if !events.is_empty() {
+                self.emitted.set(true);
+            }
+            return Some(self.route_events(events));
+        }
+    }
+
+    fn blocking_get_next(&self) -> Vec<StreamEvent> {
+        loop {
+            // Subscribing again each time, as falling over changes the stream
+            self.subscribe().block();
+            if let Some(events) = self.get_next() {
+                break events;
+            }
+        }
+    }
+
+    fn cancel(&self) {
+        // Nothing else is tried after the cancellation
+        self.remaining_providers.borrow_mut().clear();
+        if let Some(routed) = &*self.current.borrow() {
+            routed.stream.cancel();
+        }
+    }
+}
@@ -23,6 +23,16 @@ const MODEL: &'static str = "grok-3-beta";
 const MODEL: &'static str = "openrouter/auto";
 #[cfg(feature = "ollama")]
 const MODEL: &'static str = "qwen3:1.7b";
+#[cfg(feature = "openai-compatible")]
+const MODEL: &'static str = "qwen3:1.7b";
+#[cfg(feature = "azure-openai")]
+const MODEL: &'static str = "gpt-4o-mini";
+#[cfg(feature = "gemini")]
+const MODEL: &'static str = "gemini-2.0-flash";
+#[cfg(feature = "mock")]
+const MODEL: &'static str = "mock";
+#[cfg(feature = "router")]
+const MODEL: &'static str = "claude-3-7-sonnet-20250219";
 
 #[cfg(feature = "openai")]
 const IMAGE_MODEL: &'static str = "gpt-4o-mini";
@@ -36,6 +46,39 @@ const IMAGE_MODEL: &'static str = "grok-2-vision-latest";
 const IMAGE_MODEL: &'static str = "openrouter/auto";
 #[cfg(feature = "ollama")]
 const IMAGE_MODEL: &'static str = "gemma3:4b";
+#[cfg(feature = "openai-compatible")]
+const IMAGE_MODEL: &'static str = "gemma3:4b";
+#[cfg(feature = "azure-openai")]
+const IMAGE_MODEL: &'static str = "gpt-4o-mini";
+#[cfg(feature = "gemini")]
+const IMAGE_MODEL: &'static str = "gemini-2.0-flash";
+#[cfg(feature = "mock")]
+const IMAGE_MODEL: &'static str = "mock";
+#[cfg(feature = "router")]
+const IMAGE_MODEL: &'static str = "claude-3-7-sonnet-20250219";
+
+#[cfg(feature = "openai")]
+const REASONING_MODEL: &'static str = "o4-mini";
+#[cfg(feature = "bedrock")]
+const REASONING_MODEL: &'static str = "us.anthropic.claude-3-7-sonnet-20250219-v1:0";
+#[cfg(feature = "anthropic")]
+const REASONING_MODEL: &'static str = "claude-3-7-sonnet-20250219";
+#[cfg(feature = "grok")]
+const REASONING_MODEL: &'static str = "grok-3-mini-beta";
+#[cfg(feature = "openrouter")]
+const REASONING_MODEL: &'static str = "deepseek/deepseek-r1";
+#[cfg(feature = "ollama")]
+const REASONING_MODEL: &'static str = "qwen3:1.7b";
+#[cfg(feature = "openai-compatible")]
+const REASONING_MODEL: &'static str = "qwen3:1.7b";
+#[cfg(feature = "azure-openai")]
+const REASONING_MODEL: &'static str = "o3-mini";
+#[cfg(feature = "gemini")]
+const REASONING_MODEL: &'static str = "gemini-2.5-flash";
+#[cfg(feature = "mock")]
+const REASONING_MODEL: &'static str = "mock";
+#[cfg(feature = "router")]
+const REASONING_MODEL: &'static str = "claude-3-7-sonnet-20250219";
 
 impl Guest for Component {
     /// test1 demonstrates a simple, non-streaming text question-answer interaction with the LLM.
@@ -46,8 +89,18 @@ impl Guest for Component {
             max_tokens: None,
             stop_sequences: None,
             tools: vec![],
+            builtin_tools: vec![],
             tool_choice: None,
             provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
         };
 
         println!("Sending request to LLM...");
@@ -59,6 +112,7 @@ impl Guest for Component {
                     "What is the usual weather on the Vršič pass in the beginning of May?"
                         .to_string(),
                 )],
+                cache_control: None,
             }],
             &config,
         );
@@ -81,6 +135,15 @@ impl Guest for Component {
                                     inline_data.mime_type
                                 ),
                             },
+                            llm::ContentPart::Reasoning(reasoning) =>
+                                format!("[REASONING: {}]", reasoning.text),
+                            llm::ContentPart::Document(document) =>
+                                format!("[DOCUMENT: {}]", document.mime_type),
+                            llm::ContentPart::Audio(audio) => format!(
+                                "[AUDIO: {} bytes, mime: {}]",
+                                audio.data.len(),
+                                audio.mime_type
+                            ),
                         })
                         .collect::<Vec<_>>()
                         .join(", ")
@@ -101,7 +164,8 @@ impl Guest for Component {
     }
 
     /// test2 demonstrates how to use tools with the LLM, including generating a tool response
-    /// and continuing the conversation with it.
+    /// and continuing the conversation with it. The tool calls are validated against the schema
+    /// of the tool, and the invalid ones are answered with their validation error.
     fn test2() -> String {
         let config = llm::Config {
             model: MODEL.to_string(),
@@ -126,8 +190,18 @@ impl Guest for Component {
                     }"#
                 .to_string(),
             }],
+            builtin_tools: vec![],
             tool_choice: Some("auto".to_string()),
             provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: Some(true),
         };
 
         let input = vec![
@@ -143,6 +217,7 @@ impl Guest for Component {
                 role: llm::Role::User,
                 name: Some("vigoo".to_string()),
                 content: input.clone(),
+                cache_control: None,
             }],
             &config,
         );
@@ -169,15 +244,19 @@ impl Guest for Component {
         if !tool_request.is_empty() {
             let mut calls = Vec::new();
             for call in tool_request {
-                calls.push((
-                    call.clone(),
-                    llm::ToolResult::Success(llm::ToolSuccess {
-                        id: call.id,
-                        name: call.name,
+                let result = match call.validation_error.clone() {
+                    Some(failure) => {
+                        println!("Invalid tool call: {:?}", failure);
+                        llm::ToolResult::Error(failure)
+                    }
+                    None => llm::ToolResult::Success(llm::ToolSuccess {
+                        id: call.id.clone(),
+                        name: call.name.clone(),
                         result_json: r#"{ "value": 6 }"#.to_string(),
                         execution_time_ms: None,
                     }),
-                ));
+                };
+                calls.push((call, result));
             }
 
             let response2 = llm::continue_(
@@ -185,6 +264,7 @@ impl Guest for Component {
                     role: llm::Role::User,
                     name: Some("vigoo".to_string()),
                     content: input.clone(),
+                    cache_control: None,
                 }],
                 &calls,
                 &config,
@@ -219,8 +299,18 @@ impl Guest for Component {
             max_tokens: None,
             stop_sequences: None,
             tools: vec![],
+            builtin_tools: vec![],
             tool_choice: None,
             provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
         };
 
         println!("Starting streaming request to LLM...");
@@ -232,6 +322,7 @@ impl Guest for Component {
                     "What is the usual weather on the Vršič pass in the beginning of May?"
                         .to_string(),
                 )],
+                cache_control: None,
             }],
             &config,
         );
@@ -294,8 +385,18 @@ impl Guest for Component {
                     }"#
                 .to_string(),
             }],
+            builtin_tools: vec![],
             tool_choice: Some("auto".to_string()),
             provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
         };
 
         let input = vec![
@@ -311,6 +412,7 @@ impl Guest for Component {
                 role: llm::Role::User,
                 name: Some("vigoo".to_string()),
                 content: input,
+                cache_control: None,
             }],
             &config,
         );
@@ -356,8 +458,18 @@ impl Guest for Component {
             max_tokens: None,
             stop_sequences: None,
             tools: vec![],
+            builtin_tools: vec![],
             tool_choice: None,
             provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
         };
 
         println!("Sending request to LLM...");
@@ -374,6 +486,7 @@ impl Guest for Component {
                             detail: Some(llm::ImageDetail::High),
                         })),
                     ],
+                    cache_control: None,
                 },
                 llm::Message {
                     role: llm::Role::System,
@@ -381,6 +494,7 @@ impl Guest for Component {
                     content: vec![llm::ContentPart::Text(
                         "Produce the output in both English and Hungarian".to_string(),
                     )],
+                    cache_control: None,
                 },
             ],
             &config,
@@ -404,6 +518,15 @@ impl Guest for Component {
                                     inline_data.mime_type
                                 ),
                             },
+                            llm::ContentPart::Reasoning(reasoning) =>
+                                format!("[REASONING: {}]", reasoning.text),
+                            llm::ContentPart::Document(document) =>
+                                format!("[DOCUMENT: {}]", document.mime_type),
+                            llm::ContentPart::Audio(audio) => format!(
+                                "[AUDIO: {} bytes, mime: {}]",
+                                audio.data.len(),
+                                audio.mime_type
+                            ),
                         })
                         .collect::<Vec<_>>()
                         .join(", ")
@@ -432,8 +555,18 @@ impl Guest for Component {
             max_tokens: None,
             stop_sequences: None,
             tools: vec![],
+            builtin_tools: vec![],
             tool_choice: None,
             provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
         };
 
         println!("Starting streaming request to LLM...");
@@ -445,6 +578,7 @@ impl Guest for Component {
                     "What is the usual weather on the Vršič pass in the beginning of May?"
                         .to_string(),
                 )],
+                cache_control: None,
             }],
             &config,
         );
@@ -486,6 +620,19 @@ impl Guest for Component {
                                         ));
                                     }
                                 },
+                                llm::ContentPart::Reasoning(reasoning) => {
+                                    result.push_str(&format!("REASONING: {}\n", reasoning.text));
+                                }
+                                llm::ContentPart::Document(document) => {
+                                    result.push_str(&format!("DOCUMENT: {}\n", document.mime_type));
+                                }
+                                llm::ContentPart::Audio(audio) => {
+                                    result.push_str(&format!(
+                                        "AUDIO: {} bytes, mime: {}\n",
+                                        audio.data.len(),
+                                        audio.mime_type
+                                    ));
+                                }
                             }
                         }
                     }
@@ -530,8 +677,18 @@ impl Guest for Component {
             max_tokens: None,
             stop_sequences: None,
             tools: vec![],
+            builtin_tools: vec![],
             tool_choice: None,
             provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
         };
 
         println!("Reading image from Initial File System...");
@@ -562,6 +719,7 @@ impl Guest for Component {
                         detail: None,
                     })),
                 ],
+                cache_control: None,
             }],
             &config,
         );
@@ -584,6 +742,15 @@ impl Guest for Component {
                                     inline_data.mime_type
                                 ),
                             },
+                            llm::ContentPart::Reasoning(reasoning) =>
+                                format!("[REASONING: {}]", reasoning.text),
+                            llm::ContentPart::Document(document) =>
+                                format!("[DOCUMENT: {}]", document.mime_type),
+                            llm::ContentPart::Audio(audio) => format!(
+                                "[AUDIO: {} bytes, mime: {}]",
+                                audio.data.len(),
+                                audio.mime_type
+                            ),
                         })
                         .collect::<Vec<_>>()
                         .join(", ")
@@ -609,8 +776,18 @@ impl Guest for Component {
             max_tokens: None,
             stop_sequences: None,
             tools: vec![],
+            builtin_tools: vec![],
             tool_choice: None,
             provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
         };
 
         let mut messages = vec![llm::Message {
@@ -619,6 +796,7 @@ impl Guest for Component {
             content: vec![llm::ContentPart::Text(
                 "Do you know what a haiku is?".to_string(),
             )],
+            cache_control: None,
         }];
 
         let stream = llm::stream(&messages, &config);
@@ -638,6 +816,7 @@ impl Guest for Component {
             role: llm::Role::Assistant,
             name: Some("assistant".to_string()),
             content: vec![llm::ContentPart::Text(result)],
+            cache_control: None,
         });
 
         messages.push(llm::Message {
@@ -646,6 +825,7 @@ impl Guest for Component {
             content: vec![llm::ContentPart::Text(
                 "Can you write one for me?".to_string(),
             )],
+            cache_control: None,
         });
 
         println!("Message: {messages:?}");
@@ -680,6 +860,1062 @@ impl Guest for Component {
 
         result
     }
+
+    /// test9 demonstrates requesting a structured response conforming to a JSON schema
+    fn test9() -> String {
+        let config = llm::Config {
+            model: MODEL.to_string(),
+            temperature: Some(0.2),
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: Some(llm::ResponseFormat::JsonSchema(llm::JsonSchemaFormat {
+                name: "mountain_pass".to_string(),
+                schema: r#"{
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "country": { "type": "string" },
+                            "elevation_meters": { "type": "number" }
+                        },
+                        "required": ["name", "country", "elevation_meters"],
+                        "additionalProperties": false
+                    }"#
+                .to_string(),
+                strict: Some(true),
+            })),
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        println!("Sending request to LLM...");
+        let response = llm::send(
+            &[llm::Message {
+                role: llm::Role::User,
+                name: Some("vigoo".to_string()),
+                content: vec![llm::ContentPart::Text(
+                    "Tell me about the Vršič pass.".to_string(),
+                )],
+                cache_control: None,
+            }],
+            &config,
+        );
+        println!("Response: {:?}", response);
+
+        match response {
+            llm::ChatEvent::Message(msg) => msg
+                .content
+                .into_iter()
+                .filter_map(|content| match content {
+                    llm::ContentPart::Text(txt) => Some(txt),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(""),
+            llm::ChatEvent::ToolRequest(request) => {
+                format!("Tool request: {:?}", request)
+            }
+            llm::ChatEvent::Error(error) => {
+                format!(
+                    "ERROR: {:?} {} ({})",
+                    error.code,
+                    error.message,
+                    error.provider_error_json.unwrap_or_default()
+                )
+            }
+        }
+    }
+
+    /// test10 demonstrates requesting the model's reasoning together with the answer
+    fn test10() -> String {
+        let config = llm::Config {
+            model: REASONING_MODEL.to_string(),
+            temperature: None,
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: Some(llm::ReasoningConfig {
+                effort: Some(llm::ReasoningEffort::Low),
+                budget_tokens: None,
+            }),
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        println!("Sending request to LLM...");
+        let response = llm::send(
+            &[llm::Message {
+                role: llm::Role::User,
+                name: Some("vigoo".to_string()),
+                content: vec![llm::ContentPart::Text(
+                    "A train leaves Ljubljana at 9:40 and arrives in Koper at 12:05. How long is the journey?".to_string(),
+                )],
+                cache_control: None,
+            }],
+            &config,
+        );
+        println!("Response: {:?}", response);
+
+        match response {
+            llm::ChatEvent::Message(msg) => msg
+                .content
+                .into_iter()
+                .filter_map(|content| match content {
+                    llm::ContentPart::Text(txt) => Some(txt),
+                    llm::ContentPart::Reasoning(reasoning) => Some(format!(
+                        "[REASONING: {}{}]",
+                        reasoning.text,
+                        if reasoning.signature.is_some() || reasoning.redacted_data.is_some() {
+                            " (signed)"
+                        } else {
+                            ""
+                        }
+                    )),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            llm::ChatEvent::ToolRequest(request) => {
+                format!("Tool request: {:?}", request)
+            }
+            llm::ChatEvent::Error(error) => {
+                format!(
+                    "ERROR: {:?} {} ({})",
+                    error.code,
+                    error.message,
+                    error.provider_error_json.unwrap_or_default()
+                )
+            }
+        }
+    }
+
+    /// test11 demonstrates caching a long system prompt between two requests
+    fn test11() -> String {
+        let config = llm::Config {
+            model: MODEL.to_string(),
+            temperature: Some(0.2),
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        // Prompt caching only applies to prompts longer than about a thousand tokens
+        let system_prompt = "You are a helpful travel guide for the Julian Alps. \
+            Answer every question with a short, friendly paragraph and mention one lesser known fact about the region. "
+            .repeat(100);
+
+        let mut result = String::new();
+        for question in [
+            "What is the highest mountain of Slovenia?",
+            "Where is Lake Bohinj?",
+        ] {
+            println!("Sending request to LLM...");
+            let response = llm::send(
+                &[
+                    llm::Message {
+                        role: llm::Role::System,
+                        name: None,
+                        content: vec![llm::ContentPart::Text(system_prompt.clone())],
+                        cache_control: Some(llm::CacheControl { ttl: None }),
+                    },
+                    llm::Message {
+                        role: llm::Role::User,
+                        name: Some("vigoo".to_string()),
+                        content: vec![llm::ContentPart::Text(question.to_string())],
+                        cache_control: None,
+                    },
+                ],
+                &config,
+            );
+            println!("Response: {:?}", response);
+
+            match response {
+                llm::ChatEvent::Message(msg) => {
+                    let usage = msg.metadata.usage.unwrap_or(llm::Usage {
+                        input_tokens: None,
+                        output_tokens: None,
+                        total_tokens: None,
+                        cache_read_tokens: None,
+                        cache_write_tokens: None,
+                        reasoning_tokens: None,
+                    });
+                    result.push_str(&format!(
+                        "{question}: input tokens: {:?}, cache read tokens: {:?}, cache write tokens: {:?}, cost: {:?}\n",
+                        usage.input_tokens, usage.cache_read_tokens, usage.cache_write_tokens, msg.metadata.cost.map(|cost| cost.total)
+                    ));
+                }
+                llm::ChatEvent::ToolRequest(request) => {
+                    result.push_str(&format!("Tool request: {:?}\n", request));
+                }
+                llm::ChatEvent::Error(error) => {
+                    result.push_str(&format!(
+                        "ERROR: {:?} {} ({})\n",
+                        error.code,
+                        error.message,
+                        error.provider_error_json.unwrap_or_default()
+                    ));
+                }
+            }
+        }
+
+        result
+    }
+
+    /// test12 demonstrates passing a PDF document by its URL in the prompt
+    fn test12() -> String {
+        let config = llm::Config {
+            model: IMAGE_MODEL.to_string(),
+            temperature: Some(0.2),
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        println!("Sending request to LLM...");
+        let response = llm::send(
+            &[llm::Message {
+                role: llm::Role::User,
+                name: Some("vigoo".to_string()),
+                content: vec![
+                    llm::ContentPart::Text("What is written in this document?".to_string()),
+                    llm::ContentPart::Document(llm::DocumentSource {
+                        data: llm::DocumentData::Url(
+                            "https://www.w3.org/WAI/ER/tests/xhtml/testfiles/resources/pdf/dummy.pdf"
+                                .to_string(),
+                        ),
+                        mime_type: "application/pdf".to_string(),
+                        title: Some("Dummy PDF".to_string()),
+                    }),
+                ],
+                cache_control: None,
+            }],
+            &config,
+        );
+        println!("Response: {:?}", response);
+
+        match response {
+            llm::ChatEvent::Message(msg) => msg
+                .content
+                .into_iter()
+                .filter_map(|content| match content {
+                    llm::ContentPart::Text(txt) => Some(txt),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(""),
+            llm::ChatEvent::ToolRequest(request) => {
+                format!("Tool request: {:?}", request)
+            }
+            llm::ChatEvent::Error(error) => {
+                format!(
+                    "ERROR: {:?} {} ({})",
+                    error.code,
+                    error.message,
+                    error.provider_error_json.unwrap_or_default()
+                )
+            }
+        }
+    }
+
+    /// test13 lists the models available from the provider together with their capabilities
+    fn test13() -> String {
+        println!("Listing models...");
+        match llm::list_models() {
+            Ok(models) => {
+                let mut result = String::new();
+                for model in &models {
+                    result.push_str(&format!(
+                        "{} (context window: {:?}, max output tokens: {:?}, tools: {}, vision: {}, reasoning: {}, json-schema: {})\n",
+                        model.id,
+                        model.context_window,
+                        model.max_output_tokens,
+                        model.capabilities.tools,
+                        model.capabilities.vision,
+                        model.capabilities.reasoning,
+                        model.capabilities.json_schema,
+                    ));
+                }
+                let listed = models.iter().any(|model| model.id == MODEL);
+                result.push_str(&format!("Default model {MODEL} listed: {listed}"));
+                result
+            }
+            Err(error) => format!(
+                "ERROR: {:?} {} ({})",
+                error.code,
+                error.message,
+                error.provider_error_json.unwrap_or_default()
+            ),
+        }
+    }
+
+    /// test14 counts the input tokens of a request before sending it, and compares it with the reported usage
+    fn test14() -> String {
+        let config = llm::Config {
+            model: MODEL.to_string(),
+            temperature: Some(0.2),
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        let messages = vec![llm::Message {
+            role: llm::Role::User,
+            name: Some("vigoo".to_string()),
+            content: vec![llm::ContentPart::Text(
+                "What is the usual weather on the Vršič pass in the beginning of May?".to_string(),
+            )],
+            cache_control: None,
+        }];
+
+        println!("Counting tokens...");
+        let token_count = match llm::count_tokens(&messages, &config) {
+            Ok(token_count) => token_count,
+            Err(error) => {
+                return format!(
+                    "ERROR: {:?} {} ({})",
+                    error.code,
+                    error.message,
+                    error.provider_error_json.unwrap_or_default()
+                )
+            }
+        };
+        println!("Token count: {:?}", token_count);
+
+        println!("Sending request to LLM...");
+        let response = llm::send(&messages, &config);
+        println!("Response: {:?}", response);
+
+        match response {
+            llm::ChatEvent::Message(msg) => {
+                let reported_input_tokens = msg.metadata.usage.and_then(|usage| usage.input_tokens);
+                format!(
+                    "Counted input tokens: {} (estimated: {}), reported input tokens: {:?}",
+                    token_count.input_tokens, token_count.estimated, reported_input_tokens
+                )
+            }
+            llm::ChatEvent::ToolRequest(request) => {
+                format!("Tool request: {:?}", request)
+            }
+            llm::ChatEvent::Error(error) => {
+                format!(
+                    "ERROR: {:?} {} ({})",
+                    error.code,
+                    error.message,
+                    error.provider_error_json.unwrap_or_default()
+                )
+            }
+        }
+    }
+
+    /// test15 cancels a streaming response after a few events, and simulates a crash after the
+    /// cancellation to demonstrate that the aborted generation is not restarted (with Golem only)
+    fn test15() -> String {
+        let config = llm::Config {
+            model: MODEL.to_string(),
+            temperature: Some(0.2),
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        println!("Starting streaming request to LLM...");
+        let stream = llm::stream(
+            &[llm::Message {
+                role: llm::Role::User,
+                name: Some("vigoo".to_string()),
+                content: vec![llm::ContentPart::Text(
+                    "Write a long essay about the history of the Vršič pass.".to_string(),
+                )],
+                cache_control: None,
+            }],
+            &config,
+        );
+
+        let mut result = String::new();
+
+        let name = std::env::var("GOLEM_WORKER_NAME").unwrap();
+        let mut round = 0;
+
+        loop {
+            let events = stream.blocking_get_next();
+            if events.is_empty() {
+                break;
+            }
+
+            for event in events {
+                println!("Received {event:?}");
+
+                match event {
+                    StreamEvent::Delta(delta) => {
+                        for content in delta.content.unwrap_or_default() {
+                            if let llm::ContentPart::Text(txt) = content {
+                                result.push_str(&txt);
+                            }
+                        }
+                    }
+                    StreamEvent::Finish(finish) => {
+                        result.push_str(&format!("\nFINISH: {:?}\n", finish.finish_reason));
+                    }
+                    StreamEvent::Error(error) => {
+                        result.push_str(&format!(
+                            "\nERROR: {:?} {} ({})\n",
+                            error.code,
+                            error.message,
+                            error.provider_error_json.unwrap_or_default()
+                        ));
+                    }
+                }
+            }
+
+            if round == 2 {
+                println!("Cancelling the stream...");
+                stream.cancel();
+
+                atomically(|| {
+                    let client = TestHelperApi::new(&name);
+                    let answer = client.blocking_inc_and_get();
+                    if answer == 1 {
+                        panic!("Simulating crash")
+                    }
+                });
+            }
+
+            round += 1;
+        }
+
+        result
+    }
+
+    /// test16 runs the tool-execution loop of test2 with the conversation resource
+    fn test16() -> String {
+        let config = llm::Config {
+            model: MODEL.to_string(),
+            temperature: Some(0.2),
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![llm::ToolDefinition {
+                name: "test-tool".to_string(),
+                description: Some("Test tool for generating test values".to_string()),
+                parameters_schema: r#"{
+                        "type": "object",
+                        "properties": {
+                            "maximum": {
+                                "type": "number",
+                                "description": "Upper bound for the test value"
+                            }
+                        },
+                        "required": [
+                            "maximum"
+                        ],
+                        "additionalProperties": false
+                    }"#
+                .to_string(),
+            }],
+            builtin_tools: vec![],
+            tool_choice: Some("auto".to_string()),
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        let conversation = llm::Conversation::new(
+            &[llm::Message {
+                role: llm::Role::User,
+                name: Some("vigoo".to_string()),
+                content: vec![
+                    llm::ContentPart::Text("Generate a random number between 1 and 10".to_string()),
+                    llm::ContentPart::Text(
+                        "then translate this number to German and output it as a text message."
+                            .to_string(),
+                    ),
+                ],
+                cache_control: None,
+            }],
+            &config,
+            llm::ConversationOptions {
+                max_iterations: Some(5),
+                max_context_tokens: None,
+                truncation: None,
+                server_side_state: None,
+            },
+        );
+
+        let mut tool_results = Vec::new();
+        loop {
+            println!("Sending conversation step to LLM...");
+            let tool_calls = match conversation.step(&tool_results) {
+                llm::ChatEvent::Message(msg) if msg.tool_calls.is_empty() => {
+                    break format!("Message: {:?}", msg);
+                }
+                llm::ChatEvent::Message(msg) => {
+                    println!("Message with tool calls: {:?}", msg);
+                    msg.tool_calls
+                }
+                llm::ChatEvent::ToolRequest(request) => {
+                    println!("Tool request: {:?}", request);
+                    request
+                }
+                llm::ChatEvent::Error(error) => {
+                    break format!(
+                        "ERROR: {:?} {} ({})",
+                        error.code,
+                        error.message,
+                        error.provider_error_json.unwrap_or_default()
+                    );
+                }
+            };
+
+            tool_results = tool_calls
+                .into_iter()
+                .map(|call| {
+                    (
+                        call.clone(),
+                        llm::ToolResult::Success(llm::ToolSuccess {
+                            id: call.id,
+                            name: call.name,
+                            result_json: r#"{ "value": 6 }"#.to_string(),
+                            execution_time_ms: None,
+                        }),
+                    )
+                })
+                .collect();
+        }
+    }
+
+    /// test17 demonstrates a multi-turn conversation where the conversation resource owns the
+    /// history, and summarizes the earlier messages when it outgrows the allowed context size
+    fn test17() -> String {
+        let config = llm::Config {
+            model: MODEL.to_string(),
+            temperature: Some(0.2),
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        let conversation = llm::Conversation::new(
+            &[llm::Message {
+                role: llm::Role::System,
+                name: None,
+                content: vec![llm::ContentPart::Text(
+                    "You are a helpful travel assistant. Keep your answers short.".to_string(),
+                )],
+                cache_control: None,
+            }],
+            &config,
+            llm::ConversationOptions {
+                max_iterations: None,
+                max_context_tokens: Some(300),
+                truncation: Some(llm::TruncationStrategy::Summarize),
+                server_side_state: None,
+            },
+        );
+
+        let questions = [
+            "What is the usual weather on the Vršič pass in the beginning of May?",
+            "Which town is the best starting point to drive over it?",
+            "And what should I visit on the other side of the pass?",
+        ];
+
+        let mut result = String::new();
+        for question in questions {
+            conversation.add_message(&llm::Message {
+                role: llm::Role::User,
+                name: Some("vigoo".to_string()),
+                content: vec![llm::ContentPart::Text(question.to_string())],
+                cache_control: None,
+            });
+
+            println!("Sending conversation step to LLM...");
+            match conversation.step(&[]) {
+                llm::ChatEvent::Message(msg) => {
+                    let answer = msg
+                        .content
+                        .into_iter()
+                        .filter_map(|content| match content {
+                            llm::ContentPart::Text(txt) => Some(txt),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join("");
+                    result.push_str(&format!("Q: {question}\nA: {answer}\n"));
+                }
+                llm::ChatEvent::ToolRequest(request) => {
+                    return format!("Tool request: {:?}", request);
+                }
+                llm::ChatEvent::Error(error) => {
+                    return format!(
+                        "ERROR: {:?} {} ({})",
+                        error.code,
+                        error.message,
+                        error.provider_error_json.unwrap_or_default()
+                    );
+                }
+            }
+        }
+
+        result.push_str("\nHistory:\n");
+        for message in conversation.history() {
+            result.push_str(&format!("{:?}: {:?}\n", message.role, message.content));
+        }
+        result
+    }
+
+    /// test18 generates a response as a background job, and simulates a crash while polling it,
+    /// but only the first time. After the automatic recovery it continues polling the same job.
+    fn test18() -> String {
+        let config = llm::Config {
+            model: REASONING_MODEL.to_string(),
+            temperature: None,
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        println!("Starting background job...");
+        let job = llm::ChatJob::new(
+            &[llm::Message {
+                role: llm::Role::User,
+                name: Some("vigoo".to_string()),
+                content: vec![llm::ContentPart::Text(
+                    "Plan a three day hiking trip in the Julian Alps, with the daily routes and \
+                     the huts to stay at."
+                        .to_string(),
+                )],
+                cache_control: None,
+            }],
+            &config,
+        );
+        println!("Job id: {:?}", job.id());
+
+        let name = std::env::var("GOLEM_WORKER_NAME").unwrap();
+        let mut round = 0;
+
+        let event = loop {
+            match job.poll() {
+                Ok(llm::JobStatus::Completed(event)) => break event,
+                Ok(llm::JobStatus::Cancelled) => return "Job was cancelled".to_string(),
+                Ok(status) => println!("Job status: {status:?}"),
+                Err(error) => println!("Failed to poll the job: {}", error.message),
+            }
+
+            if round == 1 {
+                atomically(|| {
+                    let client = TestHelperApi::new(&name);
+                    let answer = client.blocking_inc_and_get();
+                    if answer == 1 {
+                        panic!("Simulating crash")
+                    }
+                });
+            }
+
+            round += 1;
+            std::thread::sleep(std::time::Duration::from_secs(2));
+        };
+
+        match event {
+            llm::ChatEvent::Message(msg) => msg
+                .content
+                .into_iter()
+                .filter_map(|content| match content {
+                    llm::ContentPart::Text(txt) => Some(txt),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(""),
+            llm::ChatEvent::ToolRequest(request) => {
+                format!("Tool request: {:?}", request)
+            }
+            llm::ChatEvent::Error(error) => {
+                format!(
+                    "ERROR: {:?} {} ({})",
+                    error.code,
+                    error.message,
+                    error.provider_error_json.unwrap_or_default()
+                )
+            }
+        }
+    }
+
+    /// test19 demonstrates a multi-turn conversation chained on the responses stored by the
+    /// provider, so each turn only sends the new message instead of the whole history
+    fn test19() -> String {
+        let config = llm::Config {
+            model: MODEL.to_string(),
+            temperature: Some(0.2),
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        let conversation = llm::Conversation::new(
+            &[llm::Message {
+                role: llm::Role::System,
+                name: None,
+                content: vec![llm::ContentPart::Text(
+                    "You are a helpful travel assistant. Keep your answers short.".to_string(),
+                )],
+                cache_control: None,
+            }],
+            &config,
+            llm::ConversationOptions {
+                max_iterations: None,
+                max_context_tokens: None,
+                truncation: None,
+                server_side_state: Some(true),
+            },
+        );
+
+        let questions = [
+            "Which is the highest mountain of Slovenia?",
+            "How long does it take to climb it from the closest hut?",
+        ];
+
+        let mut result = String::new();
+        for question in questions {
+            conversation.add_message(&llm::Message {
+                role: llm::Role::User,
+                name: Some("vigoo".to_string()),
+                content: vec![llm::ContentPart::Text(question.to_string())],
+                cache_control: None,
+            });
+
+            println!("Sending conversation step to LLM...");
+            match conversation.step(&[]) {
+                llm::ChatEvent::Message(msg) => {
+                    result.push_str(&format!("{question}\nResponse {}: ", msg.id));
+                    for content in msg.content {
+                        if let llm::ContentPart::Text(txt) = content {
+                            result.push_str(&txt);
+                        }
+                    }
+                    result.push('\n');
+                }
+                llm::ChatEvent::ToolRequest(request) => {
+                    result.push_str(&format!("Tool request: {:?}\n", request));
+                }
+                llm::ChatEvent::Error(error) => {
+                    result.push_str(&format!(
+                        "ERROR: {:?} {} ({})\n",
+                        error.code,
+                        error.message,
+                        error.provider_error_json.unwrap_or_default()
+                    ));
+                    break;
+                }
+            }
+        }
+
+        result
+    }
+
+    /// test20 asks a question which needs a web search, done by the provider's built-in tool,
+    /// and prints the sources cited in the response
+    fn test20() -> String {
+        let config = llm::Config {
+            model: MODEL.to_string(),
+            temperature: None,
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![llm::BuiltinTool::WebSearch(llm::WebSearchOptions {
+                max_uses: Some(3),
+                allowed_domains: None,
+            })],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        println!("Sending request for LLM response...");
+        let response = llm::send(
+            &[llm::Message {
+                role: llm::Role::User,
+                name: Some("vigoo".to_string()),
+                content: vec![llm::ContentPart::Text(
+                    "What is the latest released version of the Rust compiler?".to_string(),
+                )],
+                cache_control: None,
+            }],
+            &config,
+        );
+
+        match response {
+            llm::ChatEvent::Message(msg) => {
+                let mut result = msg
+                    .content
+                    .into_iter()
+                    .filter_map(|content| match content {
+                        llm::ContentPart::Text(txt) => Some(txt),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("");
+                for builtin_tool_result in msg.builtin_tool_results {
+                    result.push_str(&format!("\n{builtin_tool_result:?}"));
+                }
+                result
+            }
+            llm::ChatEvent::ToolRequest(request) => {
+                format!("Tool request: {:?}", request)
+            }
+            llm::ChatEvent::Error(error) => {
+                format!(
+                    "ERROR: {:?} {} ({})",
+                    error.code,
+                    error.message,
+                    error.provider_error_json.unwrap_or_default()
+                )
+            }
+        }
+    }
+
+    /// test21 submits two requests as a batch and polls it until it finishes. Batches can take
+    /// a long time to be processed, the worker keeps polling the same batch after a restart.
+    fn test21() -> String {
+        let config = llm::Config {
+            model: MODEL.to_string(),
+            temperature: None,
+            max_tokens: Some(200),
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        let questions = [
+            ("capital", "What is the capital of Slovenia?"),
+            ("river", "What is the longest river of Hungary?"),
+        ];
+        let requests = questions
+            .iter()
+            .map(|(custom_id, question)| llm::BatchRequest {
+                custom_id: custom_id.to_string(),
+                messages: vec![llm::Message {
+                    role: llm::Role::User,
+                    name: Some("vigoo".to_string()),
+                    content: vec![llm::ContentPart::Text(question.to_string())],
+                    cache_control: None,
+                }],
+                config: config.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        println!("Submitting batch...");
+        let batch = llm::ChatBatch::new(&requests);
+        println!("Batch id: {:?}", batch.id());
+
+        let results = loop {
+            match batch.poll() {
+                Ok(llm::BatchStatus::Completed(results)) => break results,
+                Ok(llm::BatchStatus::Cancelled(_)) => return "Batch was cancelled".to_string(),
+                Ok(llm::BatchStatus::Failed(error)) => {
+                    return format!("ERROR: {:?} {}", error.code, error.message)
+                }
+                Ok(status) => println!("Batch status: {status:?}"),
+                Err(error) => {
+                    return format!(
+                        "ERROR: {:?} {} ({})",
+                        error.code,
+                        error.message,
+                        error.provider_error_json.unwrap_or_default()
+                    )
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_secs(30));
+        };
+
+        let mut result = String::new();
+        for item in results {
+            let answer = match item.event {
+                llm::ChatEvent::Message(msg) => msg
+                    .content
+                    .into_iter()
+                    .filter_map(|content| match content {
+                        llm::ContentPart::Text(txt) => Some(txt),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(""),
+                llm::ChatEvent::ToolRequest(request) => format!("Tool request: {:?}", request),
+                llm::ChatEvent::Error(error) => {
+                    format!("ERROR: {:?} {}", error.code, error.message)
+                }
+            };
+            result.push_str(&format!("{}: {answer}\n", item.custom_id));
+        }
+        result
+    }
+
+    /// test22 streams a response of the mock provider which crashes the worker after two chunks,
+    /// the `crash_after` rule of the mock script. After the automatic recovery the durable stream
+    /// continues with the retry prompt, answered by the `partial response` rule of the script.
+    fn test22() -> String {
+        let config = llm::Config {
+            model: MODEL.to_string(),
+            temperature: None,
+            max_tokens: None,
+            stop_sequences: None,
+            tools: vec![],
+            builtin_tools: vec![],
+            tool_choice: None,
+            provider_options: vec![],
+            response_format: None,
+            reasoning: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            user: None,
+            validate_tool_calls: None,
+        };
+
+        println!("Starting streaming request to LLM...");
+        let stream = llm::stream(
+            &[llm::Message {
+                role: llm::Role::User,
+                name: Some("vigoo".to_string()),
+                content: vec![llm::ContentPart::Text(
+                    "Describe the spring weather on the Vršič pass, before the crash.".to_string(),
+                )],
+                cache_control: None,
+            }],
+            &config,
+        );
+
+        let mut result = String::new();
+        while let Some(delta) = utils::consume_next_event(&stream) {
+            result.push_str(&delta);
+        }
+        result
+    }
 }
 
 bindings::export!(Component with_types_in bindings);
@@ -35,6 +35,19 @@ pub fn consume_next_event(stream: &llm::ChatStream) -> Option<String> {
                                 ));
                             }
                         },
+                        llm::ContentPart::Reasoning(reasoning) => {
+                            result.push_str(&format!("REASONING: {}\n", reasoning.text));
+                        }
+                        llm::ContentPart::Document(document) => {
+                            result.push_str(&format!("DOCUMENT: {}\n", document.mime_type));
+                        }
+                        llm::ContentPart::Audio(audio) => {
+                            result.push_str(&format!(
+                                "AUDIO: {} bytes, mime: {}\n",
+                                audio.data.len(),
+                                audio.mime_type
+                            ));
+                        }
                     }
                 }
             }
@@ -0,0 +1,214 @@
+#!/usr/bin/env python3
+"""A minimal local mock of the Azure OpenAI Chat Completions API, for running the test
+application with the `azure-openai-*` profiles without an Azure subscription.
+
+    python3 azure_openai_mock.py [--port 8765] [--api-key test] [--token test]
+
+Requests are accepted when authenticated either with the `api-key` header or with an
+`Authorization: Bearer` token matching the given values. When the request has tools and
+the conversation does not end with a tool result, the mock asks for calling the first tool,
+otherwise it answers with a text describing what it received.
+"""
+
+import argparse
+import json
+import time
+import uuid
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from urllib.parse import parse_qs, urlparse
+
+DEPLOYMENTS = [
+    {"id": "gpt-4o-mini", "model": "gpt-4o-mini", "status": "succeeded"},
+    {"id": "o3-mini", "model": "o3-mini", "status": "succeeded"},
+]
+
+
+def placeholder_arguments(schema):
+    arguments = {}
+    for name in schema.get("required", []):
+        typ = schema.get("properties", {}).get(name, {}).get("type")
+        if typ in ("integer", "number"):
+            arguments[name] = 1
+        elif typ == "boolean":
+            arguments[name] = True
+        elif typ == "array":
+            arguments[name] = []
+        elif typ == "object":
+            arguments[name] = {}
+        else:
+            arguments[name] = "mock"
+    return json.dumps(arguments)
+
+
+def describe(messages):
+    last = messages[-1] if messages else {}
+    content = last.get("content")
+    if isinstance(content, list):
+        texts = [part.get("text", "") for part in content if part.get("type") == "text"]
+        images = sum(1 for part in content if part.get("type") == "image_url")
+        return f"Received {' '.join(texts)!r} with {images} image(s)"
+    if last.get("role") == "tool":
+        return f"The tool returned {content}"
+    return f"Received {content!r}"
+
+
+def completion(request):
+    messages = request.get("messages", [])
+    tools = request.get("tools", [])
+    ends_with_tool_result = bool(messages) and messages[-1].get("role") == "tool"
+    if tools and not ends_with_tool_result:
+        function = tools[0]["function"]
+        tool_call = {
+            "id": f"call_{uuid.uuid4().hex[:24]}",
+            "type": "function",
+            "function": {
+                "name": function["name"],
+                "arguments": placeholder_arguments(function.get("parameters") or {}),
+            },
+        }
+        return None, tool_call
+    return describe(messages), None
+
+
+class Handler(BaseHTTPRequestHandler):
+    protocol_version = "HTTP/1.1"
+
+    def authorized(self):
+        api_key = self.headers.get("api-key")
+        authorization = self.headers.get("Authorization", "")
+        if api_key is not None:
+            return api_key == self.server.api_key
+        return authorization == f"Bearer {self.server.token}"
+
+    def send_json(self, status, body):
+        data = json.dumps(body).encode()
+        self.send_response(status)
+        self.send_header("Content-Type", "application/json")
+        self.send_header("Content-Length", str(len(data)))
+        self.end_headers()
+        self.wfile.write(data)
+
+    def send_error_json(self, status, code, message):
+        self.send_json(status, {"error": {"code": code, "message": message}})
+
+    def do_GET(self):
+        url = urlparse(self.path)
+        if not self.authorized():
+            return self.send_error_json(401, "401", "Access denied due to invalid credentials")
+        if url.path == "/openai/deployments":
+            return self.send_json(200, {"data": DEPLOYMENTS, "object": "list"})
+        self.send_error_json(404, "404", "Resource not found")
+
+    def do_POST(self):
+        url = urlparse(self.path)
+        parts = url.path.strip("/").split("/")
+        if len(parts) != 5 or parts[:2] != ["openai", "deployments"] or parts[3:] != [
+            "chat",
+            "completions",
+        ]:
+            return self.send_error_json(404, "404", "Resource not found")
+        if not self.authorized():
+            return self.send_error_json(401, "401", "Access denied due to invalid credentials")
+        if "api-version" not in parse_qs(url.query):
+            return self.send_error_json(400, "400", "Missing api-version query parameter")
+
+        deployment = parts[2]
+        if deployment not in (d["id"] for d in DEPLOYMENTS):
+            return self.send_error_json(404, "DeploymentNotFound", "Deployment not found")
+
+        length = int(self.headers.get("Content-Length", "0"))
+        request = json.loads(self.rfile.read(length) or b"{}")
+        text, tool_call = completion(request)
+        if request.get("stream"):
+            self.stream(deployment, request, text, tool_call)
+        else:
+            self.respond(deployment, text, tool_call)
+
+    def respond(self, deployment, text, tool_call):
+        message = {"role": "assistant", "content": text}
+        if tool_call:
+            message["tool_calls"] = [tool_call]
+        self.send_json(
+            200,
+            {
+                "id": f"chatcmpl-{uuid.uuid4().hex}",
+                "object": "chat.completion",
+                "created": int(time.time()),
+                "model": deployment,
+                "choices": [
+                    {
+                        "index": 0,
+                        "message": message,
+                        "finish_reason": "tool_calls" if tool_call else "stop",
+                    }
+                ],
+                "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15},
+            },
+        )
+
+    def stream(self, deployment, request, text, tool_call):
+        self.send_response(200)
+        self.send_header("Content-Type", "text/event-stream")
+        self.send_header("Connection", "close")
+        self.end_headers()
+
+        chunk_id = f"chatcmpl-{uuid.uuid4().hex}"
+        created = int(time.time())
+
+        def send(choices, usage=None):
+            chunk = {
+                "id": chunk_id,
+                "object": "chat.completion.chunk",
+                "created": created,
+                "model": deployment,
+                "choices": choices,
+            }
+            if usage is not None:
+                chunk["usage"] = usage
+            self.wfile.write(f"data: {json.dumps(chunk)}\n\n".encode())
+            self.wfile.flush()
+
+        # Azure sends the prompt filter results in a first chunk without choices
+        send([])
+        if tool_call:
+            arguments = tool_call["function"]["arguments"]
+            middle = len(arguments) // 2
+            fragments = [
+                {
+                    "index": 0,
+                    "id": tool_call["id"],
+                    "type": "function",
+                    "function": {"name": tool_call["function"]["name"], "arguments": ""},
+                },
+                {"index": 0, "function": {"arguments": arguments[:middle]}},
+                {"index": 0, "function": {"arguments": arguments[middle:]}},
+            ]
+            for fragment in fragments:
+                send([{"index": 0, "delta": {"tool_calls": [fragment]}, "finish_reason": None}])
+        else:
+            for word in text.split(" "):
+                send([{"index": 0, "delta": {"content": word + " "}, "finish_reason": None}])
+        send([{"index": 0, "delta": {}, "finish_reason": "tool_calls" if tool_call else "stop"}])
+        if (request.get("stream_options") or {}).get("include_usage"):
+            send([], {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15})
+        self.wfile.write(b"data: [DONE]\n\n")
+        self.wfile.flush()
+        self.close_connection = True
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--port", type=int, default=8765)
+    parser.add_argument("--api-key", default="test")
+    parser.add_argument("--token", default="test")
+    args = parser.parse_args()
+
+    server = ThreadingHTTPServer(("0.0.0.0", args.port), Handler)
+    server.api_key = args.api_key
+    server.token = args.token
+    print(f"Azure OpenAI mock listening on http://localhost:{args.port}")
+    server.serve_forever()
+
+
+if __name__ == "__main__":
+    main()